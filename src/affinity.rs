@@ -0,0 +1,44 @@
+//! Pinning the benchmark targets to particular CPU cores, so that the scheduler can't move them
+//! between cores in the middle of a measurement. This is only supported on Linux.
+
+use std::process::Command;
+
+/// The largest number of CPUs that the affinity mask can describe, matching glibc's `cpu_set_t`.
+pub const MAX_CPUS: usize = 1024;
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::os::raw::{c_int, c_ulong};
+
+    pub type CpuSet = [c_ulong; super::MAX_CPUS / (8 * std::mem::size_of::<c_ulong>())];
+
+    extern "C" {
+        pub fn sched_setaffinity(pid: c_int, cpusetsize: usize, mask: *const CpuSet) -> c_int;
+    }
+}
+
+/// Make the command's process run only on the given CPUs. Every CPU must be less than `MAX_CPUS`.
+#[cfg(target_os = "linux")]
+pub fn restrict_to_cpus(command: &mut Command, cpus: &[usize]) {
+    use std::os::unix::process::CommandExt;
+
+    let mut mask: sys::CpuSet = Default::default();
+    let bits = 8 * std::mem::size_of_val(&mask[0]);
+    for &cpu in cpus {
+        mask[cpu / bits] |= 1 << (cpu % bits);
+    }
+    // This runs in the child process after it's forked, so that every thread started by the
+    // benchmark inherits the affinity.
+    unsafe {
+        command.pre_exec(move || {
+            if sys::sched_setaffinity(0, std::mem::size_of::<sys::CpuSet>(), &mask) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn restrict_to_cpus(_command: &mut Command, _cpus: &[usize]) {}
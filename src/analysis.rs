@@ -1,3 +1,4 @@
+use crate::config::{BenchmarkOverride, CompareMetric, SignificanceTest};
 use crate::connection::{SamplingMethod, Throughput};
 use crate::estimate::{build_change_estimates, build_estimates, ConfidenceInterval, Estimate};
 use crate::estimate::{
@@ -10,6 +11,7 @@ use crate::stats::bivariate::Data;
 use crate::stats::univariate::outliers::tukey;
 use crate::stats::univariate::Sample;
 use crate::stats::{Distribution, Tails};
+use std::collections::HashMap;
 use std::time::Duration;
 
 #[derive(Debug, Clone)]
@@ -20,7 +22,54 @@ pub struct BenchmarkConfig {
     pub nresamples: usize,
     pub sample_size: usize,
     pub significance_level: f64,
-    pub warm_up_time: Duration,
+    pub significance_test: SignificanceTest,
+    pub compare_metric: CompareMetric,
+}
+impl Default for BenchmarkConfig {
+    /// The default settings of Criterion.rs, for analyzing stored measurements whose benchmark
+    /// configuration wasn't saved.
+    fn default() -> Self {
+        BenchmarkConfig {
+            confidence_level: 0.95,
+            measurement_time: Duration::from_secs(5),
+            noise_threshold: 0.01,
+            nresamples: 100_000,
+            sample_size: 100,
+            significance_level: 0.05,
+            significance_test: SignificanceTest::default(),
+            compare_metric: CompareMetric::default(),
+        }
+    }
+}
+impl BenchmarkConfig {
+    /// Apply the overrides from Criterion.toml which match the given benchmark ID. Later overrides
+    /// take precedence over earlier ones.
+    pub fn with_overrides(mut self, overrides: &[BenchmarkOverride], id: &str) -> BenchmarkConfig {
+        for benchmark_override in overrides.iter().filter(|o| o.matches(id)) {
+            if let Some(noise_threshold) = benchmark_override.noise_threshold {
+                self.noise_threshold = noise_threshold;
+            }
+            if let Some(significance_level) = benchmark_override.significance_level {
+                self.significance_level = significance_level;
+            }
+        }
+        self
+    }
+
+    /// Use the given test to decide whether a change is significant.
+    pub fn with_significance_test(
+        mut self,
+        significance_test: SignificanceTest,
+    ) -> BenchmarkConfig {
+        self.significance_test = significance_test;
+        self
+    }
+
+    /// Use the change in the given statistic to decide whether a benchmark improved or regressed.
+    pub fn with_compare_metric(mut self, compare_metric: CompareMetric) -> BenchmarkConfig {
+        self.compare_metric = compare_metric;
+        self
+    }
 }
 
 pub struct MeasuredValues<'a> {
@@ -29,7 +78,31 @@ pub struct MeasuredValues<'a> {
     pub avg_values: &'a [f64],
 }
 
-// Common analysis procedure
+/// Check that a set of measured values can be analyzed. The statistics need at least two samples,
+/// and every sample needs a finite average value. Returns a description of the problem if not.
+pub(crate) fn check_measured_values(values: &MeasuredValues) -> Result<(), String> {
+    let sample_count = values.sample_values.len();
+    if values.iteration_count.len() != sample_count || values.avg_values.len() != sample_count {
+        return Err(format!(
+            "the benchmark reported {} iteration counts but {} measurements",
+            values.iteration_count.len(),
+            sample_count
+        ));
+    }
+    if sample_count < 2 {
+        return Err(format!(
+            "the benchmark reported {} samples, but at least two are needed",
+            sample_count
+        ));
+    }
+    if values.avg_values.iter().any(|value| !value.is_finite()) {
+        return Err("at least one sample had an undefined time per iteration".to_owned());
+    }
+    Ok(())
+}
+
+// Common analysis procedure. The new sample must have been checked by `check_measured_values`.
+// If the old sample can't be analyzed, the new sample is not compared against it.
 pub(crate) fn analysis<'a>(
     config: &BenchmarkConfig,
     throughput: Option<Throughput>,
@@ -40,9 +113,19 @@ pub(crate) fn analysis<'a>(
     let iters = new_sample.iteration_count;
     let values = new_sample.sample_values;
 
-    let avg_values = Sample::new(&new_sample.avg_values);
-
-    let data = Data::new(&iters, &values);
+    let avg_values = Sample::new(new_sample.avg_values);
+    let old_sample = old_sample.filter(|(old_values, _)| match check_measured_values(old_values) {
+        Ok(()) => true,
+        Err(problem) => {
+            warn!(
+                "Unable to compare against the stored measurement because {}.",
+                problem
+            );
+            false
+        }
+    });
+
+    let data = Data::new(iters, values);
     let labeled_sample = tukey::classify(avg_values);
     let (mut distributions, mut estimates) = estimates(avg_values, config);
 
@@ -53,9 +136,22 @@ pub(crate) fn analysis<'a>(
     }
 
     let compare_data = if let Some((old_sample, old_estimates)) = old_sample {
-        let (t_value, t_distribution, relative_estimates, relative_distributions, base_avg_times) =
-            compare(avg_values, &old_sample, config);
-        let p_value = t_distribution.p_value(t_value, &Tails::Two);
+        // The slopes can only be compared if both measurements were taken with linear sampling.
+        let new_slope = (estimates.slope.as_ref())
+            .zip(distributions.slope.as_ref())
+            .filter(|_| old_estimates.slope.is_some());
+        let (
+            p_value,
+            t_value,
+            t_distribution,
+            relative_estimates,
+            relative_distributions,
+            base_avg_times,
+        ) = compare(avg_values, &old_sample, new_slope, config);
+        let compare_metric = match config.compare_metric {
+            CompareMetric::Slope if relative_estimates.slope.is_none() => CompareMetric::Mean,
+            compare_metric => compare_metric,
+        };
         Some(crate::report::ComparisonData {
             p_value,
             t_distribution,
@@ -64,25 +160,56 @@ pub(crate) fn analysis<'a>(
             relative_distributions,
             significance_threshold: config.significance_level,
             noise_threshold: config.noise_threshold,
-            base_iter_counts: old_sample.iteration_count.iter().copied().collect(),
-            base_sample_times: old_sample.sample_values.iter().copied().collect(),
+            compare_metric,
+            base_iter_counts: old_sample.iteration_count.to_vec(),
+            base_sample_times: old_sample.sample_values.to_vec(),
             base_avg_times,
             base_estimates: old_estimates.clone(),
+            baseline_label: None,
+            base_criterion_version: None,
+            throughput_mismatch: None,
         })
     } else {
         None
     };
 
     MeasurementData {
-        data: Data::new(&*iters, &*values),
+        data: Data::new(iters, values),
         avg_times: labeled_sample,
         absolute_estimates: estimates,
         distributions,
         comparison: compare_data,
         throughput,
+        peak_rss: None,
+        instructions_per_iteration: None,
+        allocations: None,
+        cpu_affinity: Vec::new(),
+        criterion_version: None,
+        bench_version: None,
+        bench_seed: None,
+        single_thread: false,
+        profile: None,
+        compile_time: None,
+        async_runtime: None,
+        effective_sample_count: effective_sample_count(iters, values),
+        low_confidence: false,
+        min_samples_enforced: false,
+        metadata: HashMap::new(),
+        budget: None,
+        sub_measurements: Vec::new(),
     }
 }
 
+/// Count the samples which can contribute to the estimates: those which ran at least one
+/// iteration and measured a finite, non-zero value.
+pub(crate) fn effective_sample_count(iteration_count: &[f64], sample_values: &[f64]) -> usize {
+    iteration_count
+        .iter()
+        .zip(sample_values)
+        .filter(|&(&iterations, &value)| iterations > 0.0 && value.is_finite() && value > 0.0)
+        .count()
+}
+
 // Performs a simple linear regression on the sample
 fn regression(
     data: &Data<'_, f64, f64>,
@@ -96,7 +223,7 @@ fn regression(
     )
     .0;
 
-    let point = Slope::fit(&data);
+    let point = Slope::fit(data);
     let (lb, ub) = distribution.confidence_interval(config.confidence_level);
     let se = distribution.std_dev(None);
 
@@ -154,13 +281,16 @@ fn estimates(avg_times: &Sample<f64>, config: &BenchmarkConfig) -> (Distribution
     (distributions, estimates)
 }
 
-// Common comparison procedure
-#[cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+// Common comparison procedure. The p-value comes from the test chosen by the config. The change in
+// the slope is only estimated when the config compares slopes and the new slope is given.
+#[allow(clippy::type_complexity)]
 pub(crate) fn compare(
     new_avg_times: &Sample<f64>,
     old_values: &MeasuredValues,
+    new_slope: Option<(&Estimate, &Distribution<f64>)>,
     config: &BenchmarkConfig,
 ) -> (
+    f64,
     f64,
     Distribution<f64>,
     ChangeEstimates,
@@ -178,10 +308,24 @@ pub(crate) fn compare(
 
     let (t_statistic, t_distribution) = t_test(new_avg_times, base_avg_value_sample, config);
 
+    let new_slope = new_slope.filter(|_| config.compare_metric == CompareMetric::Slope);
+    let old_data = Data::new(iters, values);
+    let slope_change = new_slope.map(|new_slope| slope_change(new_slope, &old_data, config));
     let (estimates, relative_distributions) =
-        difference_estimates(new_avg_times, base_avg_value_sample, config);
+        difference_estimates(new_avg_times, base_avg_value_sample, slope_change, config);
+
+    let p_value = match config.significance_test {
+        // The confidence interval excludes zero when too little of the distribution is on the
+        // other side of it.
+        SignificanceTest::Ci => relative_distributions
+            .compared(config.compare_metric)
+            .p_value(0.0, &Tails::Two),
+        SignificanceTest::Welch => t_distribution.p_value(t_statistic, &Tails::Two),
+        SignificanceTest::MannWhitney => mann_whitney_p_value(new_avg_times, base_avg_value_sample),
+    };
 
     (
+        p_value,
         t_statistic,
         t_distribution,
         estimates,
@@ -224,10 +368,91 @@ fn t_test(
     (t_statistic, t_distribution)
 }
 
-// Estimates the relative change in the statistics of the population
+/// The two-sided p-value of the Mann-Whitney U test of whether the values in `a` tend to be larger
+/// or smaller than those in `b`. This uses the normal approximation, corrected for ties and for
+/// continuity. The values must be finite.
+fn mann_whitney_p_value(a: &[f64], b: &[f64]) -> f64 {
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    let n = n_a + n_b;
+    let mut values: Vec<(f64, bool)> = (a.iter().map(|&value| (value, true)))
+        .chain(b.iter().map(|&value| (value, false)))
+        .collect();
+    values.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+    // Tied values share the average of the ranks they cover.
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < values.len() {
+        let ties = values[start..]
+            .iter()
+            .take_while(|value| value.0 == values[start].0)
+            .count();
+        let rank = start as f64 + (ties as f64 + 1.0) / 2.0;
+        let ties_in_a = values[start..start + ties].iter().filter(|v| v.1).count();
+        rank_sum_a += rank * ties_in_a as f64;
+        tie_correction += (ties * ties * ties - ties) as f64;
+        start += ties;
+    }
+
+    let u = rank_sum_a - n_a * (n_a + 1.0) / 2.0;
+    let mean = n_a * n_b / 2.0;
+    let variance = n_a * n_b / 12.0 * (n + 1.0 - tie_correction / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        // Every value is the same.
+        return 1.0;
+    }
+    let z = ((u - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+    erfc(z / std::f64::consts::SQRT_2).min(1.0)
+}
+
+/// The complementary error function, accurate to about 1e-7. From Numerical Recipes in C, 2nd
+/// edition, section 6.2.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = -1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let result = t * (-z * z + polynomial).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+// Estimates the relative change in the slope, from the bootstrapped slope of the new measurement and
+// a bootstrapped regression of the base measurement
+fn slope_change(
+    (new, new_distribution): (&Estimate, &Distribution<f64>),
+    base_data: &Data<'_, f64, f64>,
+    config: &BenchmarkConfig,
+) -> (f64, Distribution<f64>) {
+    let (base_distribution, base) = regression(base_data, config);
+    // The resamples of the two slopes are independent, so pairing them up in order resamples the
+    // ratio of the slopes.
+    let ratios: Vec<f64> = (new_distribution.iter())
+        .zip(base_distribution.iter())
+        .map(|(new, base)| new / base - 1.)
+        .collect();
+    (
+        new.point_estimate / base.point_estimate - 1.,
+        Distribution::from(ratios.into_boxed_slice()),
+    )
+}
+
+// Estimates the relative change in the statistics of the population. The change in the slope is
+// only included if it's given.
 fn difference_estimates(
     avg_times: &Sample<f64>,
     base_avg_times: &Sample<f64>,
+    slope_change: Option<(f64, Distribution<f64>)>,
     config: &BenchmarkConfig,
 ) -> (ChangeEstimates, ChangeDistributions) {
     fn stats(a: &Sample<f64>, b: &Sample<f64>) -> (f64, f64) {
@@ -245,15 +470,173 @@ fn difference_estimates(
         crate::stats::univariate::bootstrap(avg_times, base_avg_times, nresamples, stats)
     );
 
+    let (slope, dist_slope) = slope_change.unzip();
+
     let distributions = ChangeDistributions {
         mean: dist_mean,
         median: dist_median,
+        slope: dist_slope,
     };
 
     let (mean, median) = stats(avg_times, base_avg_times);
-    let points = ChangePointEstimates { mean, median };
+    let points = ChangePointEstimates {
+        mean,
+        median,
+        slope,
+    };
 
     let estimates = build_change_estimates(&distributions, &points, cl);
 
     (estimates, distributions)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> BenchmarkConfig {
+        BenchmarkConfig {
+            confidence_level: 0.95,
+            measurement_time: Duration::from_secs(1),
+            noise_threshold: 0.01,
+            nresamples: 10,
+            sample_size: 10,
+            significance_level: 0.05,
+            significance_test: SignificanceTest::Welch,
+            compare_metric: CompareMetric::Mean,
+        }
+    }
+
+    #[test]
+    fn test_mann_whitney_p_value() {
+        let low = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let high = [6.0, 7.0, 8.0, 9.0, 10.0];
+        assert!((mann_whitney_p_value(&low, &high) - 0.012_19).abs() < 1e-4);
+        assert!((mann_whitney_p_value(&high, &low) - 0.012_19).abs() < 1e-4);
+
+        // Interleaved and tied samples are indistinguishable.
+        let interleaved = [1.5, 2.5, 3.5, 4.5, 5.5];
+        assert!(mann_whitney_p_value(&low, &interleaved) > 0.5);
+        assert_eq!(1.0, mann_whitney_p_value(&[3.0, 3.0], &[3.0, 3.0, 3.0]));
+    }
+
+    #[test]
+    fn test_erfc() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-7);
+        assert!((erfc(1.0) - 0.157_299_2).abs() < 1e-7);
+        assert!((erfc(-1.0) - 1.842_700_8).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_empty_values_are_rejected() {
+        let empty = MeasuredValues {
+            iteration_count: &[],
+            sample_values: &[],
+            avg_values: &[],
+        };
+        assert!(check_measured_values(&empty).is_err());
+
+        let mismatched = MeasuredValues {
+            iteration_count: &[1.0, 2.0],
+            sample_values: &[],
+            avg_values: &[],
+        };
+        assert!(check_measured_values(&mismatched).is_err());
+    }
+
+    #[test]
+    fn test_effective_sample_count() {
+        assert_eq!(
+            3,
+            effective_sample_count(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0])
+        );
+        assert_eq!(
+            1,
+            effective_sample_count(&[0.0, 2.0, 3.0], &[1.0, f64::NAN, 3.0])
+        );
+        assert_eq!(0, effective_sample_count(&[1.0], &[0.0]));
+    }
+
+    #[test]
+    fn test_empty_old_sample_is_not_compared() {
+        let iters = [1.0, 2.0, 3.0, 4.0];
+        let values = [10.0, 21.0, 29.0, 41.0];
+        let avg_values = [10.0, 10.5, 9.667, 10.25];
+        let new_sample = || MeasuredValues {
+            iteration_count: &iters,
+            sample_values: &values,
+            avg_values: &avg_values,
+        };
+        assert!(check_measured_values(&new_sample()).is_ok());
+
+        let old_estimates =
+            analysis(&config(), None, new_sample(), None, SamplingMethod::Flat).absolute_estimates;
+        let old_sample = MeasuredValues {
+            iteration_count: &[],
+            sample_values: &[],
+            avg_values: &[],
+        };
+
+        let measurements = analysis(
+            &config(),
+            None,
+            new_sample(),
+            Some((old_sample, &old_estimates)),
+            SamplingMethod::Flat,
+        );
+        assert!(measurements.comparison.is_none());
+    }
+
+    #[test]
+    fn test_compare_slopes() {
+        let iters = [1.0, 2.0, 3.0, 4.0];
+        let old_values = [10.0, 21.0, 29.0, 41.0];
+        let old_avg_values = [10.0, 10.5, 9.667, 10.25];
+        let new_values = [20.0, 42.0, 58.0, 82.0];
+        let new_avg_values = [20.0, 21.0, 19.333, 20.5];
+        let config = config().with_compare_metric(CompareMetric::Slope);
+        let old_sample = || MeasuredValues {
+            iteration_count: &iters,
+            sample_values: &old_values,
+            avg_values: &old_avg_values,
+        };
+        let new_sample = || MeasuredValues {
+            iteration_count: &iters,
+            sample_values: &new_values,
+            avg_values: &new_avg_values,
+        };
+
+        let old_estimates =
+            analysis(&config, None, old_sample(), None, SamplingMethod::Linear).absolute_estimates;
+        let measurements = analysis(
+            &config,
+            None,
+            new_sample(),
+            Some((old_sample(), &old_estimates)),
+            SamplingMethod::Linear,
+        );
+        let comparison = measurements.comparison.unwrap();
+        assert_eq!(CompareMetric::Slope, comparison.compare_metric);
+        let slope = comparison.relative_estimates.slope.as_ref().unwrap();
+        assert!((slope.point_estimate - 1.0).abs() < 1e-9);
+        assert_eq!(slope, comparison.primary_change());
+
+        // Measurements taken with flat sampling have no slope, so their means are compared.
+        let old_estimates =
+            analysis(&config, None, old_sample(), None, SamplingMethod::Flat).absolute_estimates;
+        let measurements = analysis(
+            &config,
+            None,
+            new_sample(),
+            Some((old_sample(), &old_estimates)),
+            SamplingMethod::Linear,
+        );
+        let comparison = measurements.comparison.unwrap();
+        assert_eq!(CompareMetric::Mean, comparison.compare_metric);
+        assert!(comparison.relative_estimates.slope.is_none());
+        assert_eq!(
+            &comparison.relative_estimates.mean,
+            comparison.primary_change()
+        );
+    }
+}
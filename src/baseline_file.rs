@@ -0,0 +1,286 @@
+//! Baseline files: the estimates and samples of a set of measurements, written by
+//! `--export-baseline` to a TOML file which can be committed to version control, and read back by
+//! `--baseline-file` to compare new measurements against. Unlike the baselines saved with
+//! `--save-baseline`, these don't depend on the data directory of any one machine.
+
+use crate::connection::Throughput;
+use crate::estimate::{ConfidenceInterval, Estimate, Estimates};
+use crate::model::{Model, SavedStatistics};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+const HEADER: &str = "\
+# The baseline measurements of the benchmarks, exported by `cargo criterion --export-baseline`.
+# Compare against them with `cargo criterion --baseline-file FILE`. Times are in nanoseconds.
+";
+
+/// The group, function and value of a benchmark, which identify it in a baseline file.
+pub type BaselineKey = (String, Option<String>, Option<String>);
+
+#[derive(Serialize, Deserialize)]
+struct BaselineFile {
+    #[serde(default, rename = "benchmark")]
+    benchmarks: Vec<BaselineEntry>,
+}
+
+// The tables must come after all of the plain values for the entry to be written as TOML.
+#[derive(Serialize, Deserialize)]
+struct BaselineEntry {
+    group: String,
+    function: Option<String>,
+    value: Option<String>,
+    measured: DateTime<Utc>,
+    commit: Option<String>,
+    criterion_version: Option<String>,
+    bench_version: Option<String>,
+    bench_seed: Option<u64>,
+    profile: Option<String>,
+    async_runtime: Option<String>,
+    #[serde(default)]
+    single_thread: bool,
+    #[serde(default)]
+    features: Vec<String>,
+    confidence_level: f64,
+    // The raw samples, which comparing against the measurement needs.
+    #[serde(default)]
+    iterations: Vec<f64>,
+    #[serde(default)]
+    times: Vec<f64>,
+    throughput: Option<BaselineThroughput>,
+    mean: BaselineEstimate,
+    median: BaselineEstimate,
+    median_abs_dev: BaselineEstimate,
+    std_dev: BaselineEstimate,
+    slope: Option<BaselineEstimate>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BaselineEstimate {
+    estimate: f64,
+    lower: f64,
+    upper: f64,
+    #[serde(default)]
+    standard_error: f64,
+}
+impl BaselineEstimate {
+    fn new(estimate: &Estimate) -> BaselineEstimate {
+        BaselineEstimate {
+            estimate: estimate.point_estimate,
+            lower: estimate.confidence_interval.lower_bound,
+            upper: estimate.confidence_interval.upper_bound,
+            standard_error: estimate.standard_error,
+        }
+    }
+
+    fn to_estimate(&self, confidence_level: f64) -> Estimate {
+        Estimate {
+            confidence_interval: ConfidenceInterval {
+                confidence_level,
+                lower_bound: self.lower,
+                upper_bound: self.upper,
+            },
+            point_estimate: self.estimate,
+            standard_error: self.standard_error,
+        }
+    }
+}
+
+// TOML can't represent the enum directly, so the kind of throughput is stored as a string.
+#[derive(Serialize, Deserialize)]
+struct BaselineThroughput {
+    kind: String,
+    count: u64,
+    unit: Option<String>,
+}
+impl BaselineThroughput {
+    fn new(throughput: &Throughput) -> BaselineThroughput {
+        let (kind, count, unit) = match throughput {
+            Throughput::Bytes(count) => ("bytes", *count, None),
+            Throughput::Elements(count) => ("elements", *count, None),
+            Throughput::Custom { unit, count } => ("custom", *count, Some(unit.clone())),
+        };
+        BaselineThroughput {
+            kind: kind.to_owned(),
+            count,
+            unit,
+        }
+    }
+
+    fn into_throughput(self) -> Throughput {
+        match (self.kind.as_str(), self.unit) {
+            ("bytes", _) => Throughput::Bytes(self.count),
+            ("custom", Some(unit)) => Throughput::Custom {
+                unit,
+                count: self.count,
+            },
+            _ => Throughput::Elements(self.count),
+        }
+    }
+}
+
+impl BaselineEntry {
+    fn new(key: BaselineKey, stats: &SavedStatistics) -> BaselineEntry {
+        let (group, function, value) = key;
+        let estimates = &stats.estimates;
+        BaselineEntry {
+            group,
+            function,
+            value,
+            measured: stats.datetime,
+            commit: stats.history_id.clone(),
+            criterion_version: stats.criterion_version.clone(),
+            bench_version: stats.bench_version.clone(),
+            bench_seed: stats.bench_seed,
+            profile: stats.profile.clone(),
+            async_runtime: stats.async_runtime.clone(),
+            single_thread: stats.single_thread,
+            features: stats.features.clone(),
+            confidence_level: estimates.mean.confidence_interval.confidence_level,
+            iterations: stats.iterations.clone(),
+            times: stats.values.clone(),
+            throughput: stats.throughput.as_ref().map(BaselineThroughput::new),
+            mean: BaselineEstimate::new(&estimates.mean),
+            median: BaselineEstimate::new(&estimates.median),
+            median_abs_dev: BaselineEstimate::new(&estimates.median_abs_dev),
+            std_dev: BaselineEstimate::new(&estimates.std_dev),
+            slope: estimates.slope.as_ref().map(BaselineEstimate::new),
+        }
+    }
+
+    fn into_stats(self) -> (BaselineKey, SavedStatistics) {
+        let confidence_level = self.confidence_level;
+        let estimates = Estimates {
+            mean: self.mean.to_estimate(confidence_level),
+            median: self.median.to_estimate(confidence_level),
+            median_abs_dev: self.median_abs_dev.to_estimate(confidence_level),
+            slope: (self.slope.as_ref()).map(|slope| slope.to_estimate(confidence_level)),
+            std_dev: self.std_dev.to_estimate(confidence_level),
+        };
+        let avg_values = (self.iterations.iter())
+            .zip(&self.times)
+            .map(|(iterations, time)| time / iterations)
+            .collect();
+        let stats = SavedStatistics {
+            datetime: self.measured,
+            iterations: self.iterations,
+            values: self.times,
+            avg_values,
+            estimates,
+            throughput: self.throughput.map(BaselineThroughput::into_throughput),
+            changes: None,
+            change_direction: None,
+            p_value: None,
+            history_id: self.commit,
+            history_description: None,
+            features: self.features,
+            peak_rss: None,
+            instructions_per_iteration: None,
+            allocations: None,
+            cpu_affinity: vec![],
+            criterion_version: self.criterion_version,
+            bench_version: self.bench_version,
+            bench_seed: self.bench_seed,
+            single_thread: self.single_thread,
+            min_samples_enforced: false,
+            profile: self.profile,
+            async_runtime: self.async_runtime,
+            target: None,
+            target_fingerprint: None,
+            compile_time: None,
+            metadata: HashMap::new(),
+            sub_measurements: BTreeMap::new(),
+        };
+        ((self.group, self.function, self.value), stats)
+    }
+}
+
+/// Write the latest measurement of every benchmark in the model to a baseline file. Returns the
+/// number of benchmarks written.
+pub fn export(path: &Path, model: &Model) -> Result<usize> {
+    let mut benchmarks = Vec::new();
+    for (group_id, ids) in model.sorted_benchmarks(crate::config::SortOrder::Name) {
+        for id in ids {
+            let stats = model.groups[group_id].benchmarks[id].load_latest()?;
+            let key = (
+                id.group_id.clone(),
+                id.function_id.clone(),
+                id.value_str.clone(),
+            );
+            benchmarks.push(BaselineEntry::new(key, &stats));
+        }
+    }
+    let count = benchmarks.len();
+    let contents = toml::to_string(&BaselineFile { benchmarks })
+        .context("Failed to serialize the baseline file")?;
+    std::fs::write(path, format!("{}\n{}", HEADER, contents))
+        .with_context(|| format!("Failed to write baseline file {:?}", path))?;
+    Ok(count)
+}
+
+/// Read the measurements in a baseline file.
+pub fn load(path: &Path) -> Result<HashMap<BaselineKey, SavedStatistics>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read baseline file {:?}", path))?;
+    let file: BaselineFile = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse baseline file {:?}", path))?;
+    Ok(file
+        .benchmarks
+        .into_iter()
+        .map(BaselineEntry::into_stats)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let estimate = |point| BaselineEstimate {
+            estimate: point,
+            lower: point - 1.0,
+            upper: point + 1.0,
+            standard_error: 0.5,
+        };
+        let entry = BaselineEntry {
+            group: "grp".to_owned(),
+            function: Some("sum".to_owned()),
+            value: None,
+            measured: "2026-01-01T00:00:00Z".parse().unwrap(),
+            commit: Some("abc123".to_owned()),
+            criterion_version: None,
+            bench_version: None,
+            bench_seed: None,
+            profile: Some("bench".to_owned()),
+            async_runtime: None,
+            single_thread: false,
+            features: vec!["simd".to_owned()],
+            confidence_level: 0.95,
+            iterations: vec![1.0, 2.0],
+            times: vec![10.0, 22.0],
+            throughput: Some(BaselineThroughput::new(&Throughput::Elements(100))),
+            mean: estimate(10.5),
+            median: estimate(10.5),
+            median_abs_dev: estimate(0.5),
+            std_dev: estimate(0.7),
+            slope: Some(estimate(11.0)),
+        };
+        let (key, stats) = entry.into_stats();
+        let text = toml::to_string(&BaselineFile {
+            benchmarks: vec![BaselineEntry::new(key.clone(), &stats)],
+        })
+        .unwrap();
+        assert!(text.contains("[benchmark.mean]\nestimate = 10.5\nlower = 9.5\nupper = 11.5\n"));
+
+        let file: BaselineFile = toml::from_str(&text).unwrap();
+        let (read_key, read_stats) = file.benchmarks.into_iter().next().unwrap().into_stats();
+        assert_eq!(key, read_key);
+        assert_eq!(vec![10.0, 11.0], read_stats.avg_values);
+        assert_eq!(stats.estimates.slope, read_stats.estimates.slope);
+        assert_eq!(Some("abc123"), read_stats.history_id.as_deref());
+        assert_eq!(Some(Throughput::Elements(100)), read_stats.throughput);
+        assert_eq!(stats.datetime, read_stats.datetime);
+    }
+}
@@ -1,19 +1,122 @@
-use crate::connection::{AxisScale, Connection, IncomingMessage, PlotConfiguration};
-use crate::model::Model;
-use crate::report::{BenchmarkId, Report, ReportContext};
-use anyhow::{anyhow, Context, Result};
+use crate::config::{BenchmarkOverride, Budget, BudgetStatistic, CompareMetric, SignificanceTest};
+use crate::connection::{
+    AxisScale, Connection, IncomingMessage, OutgoingMessage, PlotConfiguration, PreviousEstimate,
+    SAMPLING_HINTS_VARIABLE,
+};
+use crate::error::RunError;
+use crate::model::{AllocStats, Model};
+use crate::perf::InstructionCounter;
+use crate::report::{BenchmarkId, BudgetCheck, Report, ReportContext, ThroughputMismatch};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
 use std::net::TcpListener;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 
+/// The smallest sample size that Criterion.rs allows. A `--min-samples` no larger than this never
+/// needs to be enforced.
+const CRITERION_MIN_SAMPLE_SIZE: usize = 10;
+
+/// The environment variables which limit the size of the worker thread pools of rayon, tokio's
+/// multi-threaded runtime and OpenMP. `--single-thread` sets each of them to 1.
+const SINGLE_THREAD_VARIABLES: [&str; 3] = [
+    "RAYON_NUM_THREADS",
+    "TOKIO_WORKER_THREADS",
+    "OMP_NUM_THREADS",
+];
+
 /// Structure representing a compiled benchmark executable.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BenchTarget {
     pub name: String,
     pub executable: PathBuf,
+    /// The kinds of Cargo target that this executable was built from, such as `bench` or `test`.
+    pub kind: Vec<String>,
+    /// The cargo features which were enabled when this target was compiled, in sorted order.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// The directory containing the manifest of the package that this target belongs to.
+    #[serde(default)]
+    pub package_directory: Option<PathBuf>,
+    /// The directory to run this target in, or None to run it in the current directory.
+    #[serde(skip)]
+    pub working_directory: Option<PathBuf>,
+    /// The fingerprint of the executable, once it has been computed by `compute_fingerprint`.
+    #[serde(skip)]
+    pub fingerprint: Option<String>,
+    /// The statistical settings from Criterion.toml which override the benchmarks' own.
+    #[serde(skip)]
+    pub benchmark_overrides: Vec<BenchmarkOverride>,
+    /// The CPUs to run this target on, or empty to let it run on any CPU.
+    #[serde(skip)]
+    pub cpu_affinity: Vec<usize>,
+    /// The tag given to this version of the benchmark code with `--bench-version`, if any.
+    #[serde(skip)]
+    pub bench_version: Option<String>,
+    /// The seed given with `--bench-seed` for the benchmarks to generate their inputs from, if any.
+    #[serde(skip)]
+    pub bench_seed: Option<u64>,
+    /// Should the worker thread pools of the benchmarks be limited to a single thread?
+    #[serde(skip)]
+    pub single_thread: bool,
+    /// The port to listen for the connection from this target on, or None to use any free port.
+    #[serde(skip)]
+    pub connect_port: Option<u16>,
+    /// The time budgets given with `--budget` or in Criterion.toml.
+    #[serde(skip)]
+    pub budgets: Vec<Budget>,
+    /// Which estimate of each benchmark's time is checked against its budget.
+    #[serde(skip)]
+    pub budget_statistic: BudgetStatistic,
+    /// The Cargo profile that this target was compiled with.
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// How long Cargo took to compile this target, in seconds, if it reported the timing of each
+    /// target.
+    #[serde(default)]
+    pub compile_time: Option<f64>,
+    /// Benchmarks with fewer usable samples than this are reported as low confidence.
+    #[serde(skip)]
+    pub min_samples: usize,
+    /// The test which decides whether a change in performance is significant.
+    #[serde(skip)]
+    pub significance_test: SignificanceTest,
+    /// The statistic whose change decides whether a benchmark improved or regressed.
+    #[serde(skip)]
+    pub compare_metric: CompareMetric,
+    /// Should the measurements of benchmarks with no stored measurement be thrown away?
+    #[serde(skip)]
+    pub reject_new_benchmarks: bool,
+    /// How many times to launch this target again if it fails before reporting any benchmark.
+    #[serde(skip)]
+    pub run_retries: usize,
 }
 impl BenchTarget {
+    /// Was this executable built from a benchmark target, rather than a test or library?
+    pub fn is_bench(&self) -> bool {
+        self.kind.iter().any(|kind| kind == "bench")
+    }
+
+    /// Computes a fingerprint of the compiled executable. The executable changes whenever the
+    /// benchmark's source or any of its dependencies change, so this is used to tell whether the
+    /// target needs to be run again. This is a 64-bit FNV-1a hash, which is quick to compute and
+    /// doesn't vary between versions of Rust.
+    pub fn compute_fingerprint(&self) -> Result<String> {
+        let contents = std::fs::read(&self.executable).with_context(|| {
+            format!(
+                "Failed to read benchmark executable {:?} to fingerprint it",
+                self.executable
+            )
+        })?;
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in contents {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(format!("{:016x}", hash))
+    }
+
     /// Launches this benchmark target with the given additional arguments.
     ///
     /// Opens a localhost socket on an arbitrary port. This port is passed to the target in an
@@ -25,17 +128,96 @@ impl BenchTarget {
     /// The report will be notified about important events happening with the benchmark and the
     /// model will be updated with the new benchmark IDs and measurements as we go. This function
     /// will block until the benchmark target terminates.
+    ///
+    /// If `remaining` is set, it is decremented for each benchmark run, and the target is stopped
+    /// once it reaches zero. If `adaptive_sampling` is set, the target is told the estimates from
+    /// the previous measurement of each benchmark, if it can use them.
+    ///
+    /// If the target fails to connect, or exits with an error before reporting any benchmark, it
+    /// is launched again up to `run_retries` times. Once it has reported a benchmark, a failure is
+    /// returned straight away, since running it again would repeat those benchmarks.
+    #[allow(clippy::too_many_arguments)]
     pub fn execute(
         &self,
         criterion_home: &PathBuf,
+        report_directory: &Path,
         additional_args: &[OsString],
         library_paths: &[PathBuf],
         report: &dyn Report,
         model: &mut Model,
         redirect_stdout: bool,
+        measure_memory: bool,
+        measure_instructions: bool,
+        adaptive_sampling: bool,
+        remaining: &mut Option<usize>,
     ) -> Result<()> {
-        let listener = TcpListener::bind("localhost:0")
-            .context("Unable to open socket to connect to Criterion.rs")?;
+        let mut retries = 0;
+        loop {
+            let mut reported_benchmark = false;
+            let result = self.launch(
+                criterion_home,
+                report_directory,
+                additional_args,
+                library_paths,
+                report,
+                model,
+                redirect_stdout,
+                measure_memory,
+                measure_instructions,
+                adaptive_sampling,
+                remaining,
+                &mut reported_benchmark,
+            );
+            match result {
+                Err(e) if !reported_benchmark && retries < self.run_retries && is_retryable(&e) => {
+                    retries += 1;
+                    warn!(
+                        target: "run-retry",
+                        "Benchmark target {} failed before reporting any benchmark: {}. Retrying \
+                        (attempt {} of {}).",
+                        self.name,
+                        e,
+                        retries,
+                        self.run_retries
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Launches this benchmark target once; see `execute`. `reported_benchmark` is set once the
+    /// target has reported that it is running or skipping a benchmark.
+    #[allow(clippy::too_many_arguments)]
+    fn launch(
+        &self,
+        criterion_home: &PathBuf,
+        report_directory: &Path,
+        additional_args: &[OsString],
+        library_paths: &[PathBuf],
+        report: &dyn Report,
+        model: &mut Model,
+        redirect_stdout: bool,
+        measure_memory: bool,
+        measure_instructions: bool,
+        adaptive_sampling: bool,
+        remaining: &mut Option<usize>,
+        reported_benchmark: &mut bool,
+    ) -> Result<()> {
+        let listener = match (
+            TcpListener::bind(("localhost", self.connect_port.unwrap_or(0))),
+            self.connect_port,
+        ) {
+            (Err(e), Some(port)) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                anyhow::bail!(
+                    "Port {} given with --connect-port is already in use, so Criterion.rs can't \
+                    connect on it. Choose another port, or leave out --connect-port to use any \
+                    free port.",
+                    port
+                );
+            }
+            (result, _) => result.context("Unable to open socket to connect to Criterion.rs")?,
+        };
         listener
             .set_nonblocking(true)
             .context("Unable to set socket to nonblocking")?;
@@ -45,13 +227,18 @@ impl BenchTarget {
             .context("Unable to get local address of socket")?;
         let port = addr.port();
 
+        // The target may run in another directory, so it needs the absolute path of the home.
+        let criterion_home = std::env::current_dir()
+            .context("Unable to get the current directory")?
+            .join(criterion_home);
         let mut command = Command::new(&self.executable);
         command
             .arg("--bench")
             .args(additional_args)
             .env(dylib_path_envvar(), dylib_search_path(library_paths)?)
-            .env("CRITERION_HOME", criterion_home)
-            .env("CARGO_CRITERION_PORT", &port.to_string())
+            .env("CRITERION_HOME", &criterion_home)
+            .env("CARGO_CRITERION_PORT", port.to_string())
+            .env_remove(SAMPLING_HINTS_VARIABLE)
             .stdin(Stdio::null())
             .stdout(if redirect_stdout {
                 // If we're printing machine-readable output to stdout, output from the target might
@@ -62,12 +249,56 @@ impl BenchTarget {
                 Stdio::inherit()
             })
             .stderr(Stdio::inherit());
+        if let Some(working_directory) = &self.working_directory {
+            command.current_dir(working_directory);
+        }
+        if let Some(seed) = self.bench_seed {
+            command.env("CARGO_CRITERION_BENCH_SEED", seed.to_string());
+        }
+        if self.sends_sampling_hints(adaptive_sampling) {
+            command.env(SAMPLING_HINTS_VARIABLE, "1");
+        }
+        if self.single_thread {
+            for variable in SINGLE_THREAD_VARIABLES {
+                command.env(variable, "1");
+            }
+        }
+        if !self.cpu_affinity.is_empty() {
+            crate::affinity::restrict_to_cpus(&mut command, &self.cpu_affinity);
+        }
 
         debug!("Running '{:?}'", command);
 
-        let mut child = command
-            .spawn()
-            .with_context(|| format!("Unable to launch bench target {}", self.name))?;
+        let mut child = command.spawn().with_context(|| {
+            if self.cpu_affinity.is_empty() {
+                format!("Unable to launch bench target {}", self.name)
+            } else {
+                format!(
+                    "Unable to launch bench target {} on CPUs {:?}",
+                    self.name, self.cpu_affinity
+                )
+            }
+        })?;
+
+        let instruction_counter = if measure_instructions {
+            match InstructionCounter::attach(child.id()) {
+                Ok(counter) => Some(counter),
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Unable to count the instructions executed by benchmark target {}. \
+                             This requires permission to use the performance counters; see the \
+                             help for --measure.",
+                            self.name
+                        )
+                    });
+                }
+            }
+        } else {
+            None
+        };
 
         if redirect_stdout {
             let mut stdout = child.stdout.take().unwrap();
@@ -77,10 +308,30 @@ impl BenchTarget {
         loop {
             match listener.accept() {
                 Ok((socket, _)) => {
-                    let conn = Connection::new(socket).with_context(|| {
-                        format!("Unable to open connection to bench target {}", self.name)
-                    })?;
-                    return self.communicate(&mut child, conn, report, criterion_home, model);
+                    let result = Connection::new(socket)
+                        .with_context(|| RunError::ConnectionFailed {
+                            target: self.name.clone(),
+                        })
+                        .and_then(|conn| {
+                            self.communicate(
+                                &mut child,
+                                conn,
+                                report,
+                                report_directory,
+                                model,
+                                measure_memory,
+                                instruction_counter.as_ref(),
+                                adaptive_sampling,
+                                remaining,
+                                reported_benchmark,
+                            )
+                        });
+                    if result.is_err() {
+                        // Don't leave the target running if we lost the connection to it.
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    return result;
                 }
                 Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
                     // No connection yet, try again in a bit.
@@ -101,11 +352,11 @@ impl BenchTarget {
                     if exit_status.success() {
                         return Ok(());
                     } else {
-                        return Err(anyhow!(
-                            "Non-Criterion.rs benchmark target {} exited with error code {:?}",
-                            self.name,
-                            exit_status.code()
-                        ));
+                        return Err(RunError::BenchmarkFailed {
+                            target: self.name.clone(),
+                            exit_code: exit_status.code(),
+                        }
+                        .into());
                     }
                 }
                 Ok(None) => (), // Child still running, keep trying.
@@ -116,33 +367,84 @@ impl BenchTarget {
         }
     }
 
+    /// Is there anything to tell the benchmarks in a `SamplingHint`? Without `--adaptive-sampling`
+    /// or a `--min-samples` above Criterion.rs's own minimum, there isn't, so the benchmarks aren't
+    /// offered any hints and sample as usual.
+    fn sends_sampling_hints(&self, adaptive_sampling: bool) -> bool {
+        adaptive_sampling || self.min_samples > CRITERION_MIN_SAMPLE_SIZE
+    }
+
     /// This function is called when a benchmark connects to the socket. It interacts with the
     /// benchmark target to receive information about the measurements and inform the report and
     /// model about the benchmarks. This function returns when the benchmark target terminates.
+    #[allow(clippy::too_many_arguments)]
     fn communicate(
         &self,
         child: &mut Child,
         mut conn: Connection,
         report: &dyn Report,
-        criterion_home: &std::path::Path,
+        report_directory: &Path,
         model: &mut Model,
+        measure_memory: bool,
+        instruction_counter: Option<&InstructionCounter>,
+        adaptive_sampling: bool,
+        remaining: &mut Option<usize>,
+        reported_benchmark: &mut bool,
     ) -> Result<()> {
+        // The process to read the peak memory usage of, if we're measuring it.
+        let memory_pid = if measure_memory {
+            Some(child.id())
+        } else {
+            None
+        };
         let mut context = ReportContext {
-            output_directory: criterion_home.join("reports"),
+            output_directory: report_directory.to_owned(),
             plot_config: PlotConfiguration {
                 summary_scale: AxisScale::Linear,
             },
         };
+        if adaptive_sampling && !conn.expects_sampling_hints() {
+            warn!(
+                target: "adaptive-sampling-unsupported",
+                "Benchmark target {} was built with Criterion.rs {}, which can't use previous \
+                estimates; it will be sampled as usual.",
+                self.name,
+                conn.criterion_rs_version()
+            );
+        }
+        if self.min_samples > CRITERION_MIN_SAMPLE_SIZE && !conn.expects_sampling_hints() {
+            warn!(
+                target: "min-samples-unsupported",
+                "Benchmark target {} was built with Criterion.rs {}, which can't be asked for more \
+                samples; --min-samples {} will only be used to flag low confidence benchmarks.",
+                self.name,
+                conn.criterion_rs_version(),
+                self.min_samples
+            );
+        }
         let mut any_from_group_executed = false;
         loop {
-            let message = conn.recv().with_context(|| {
-                format!(
-                    "Failed to receive message from Criterion.rs benchmark target {}",
-                    self.name
-                )
+            let message = conn.recv().with_context(|| RunError::ConnectionFailed {
+                target: self.name.clone(),
             })?;
             if message.is_none() {
-                return Ok(());
+                // The target closed the connection, so it should be about to exit. It may have
+                // crashed, though, so check how it exited.
+                let exit_status = child.wait().with_context(|| {
+                    format!(
+                        "Failed to wait for Criterion.rs child process {}",
+                        self.name
+                    )
+                })?;
+                if exit_status.success() {
+                    return Ok(());
+                } else {
+                    return Err(RunError::BenchmarkFailed {
+                        target: self.name.clone(),
+                        exit_code: exit_status.code(),
+                    }
+                    .into());
+                }
             }
             let message = message.unwrap();
             match message {
@@ -151,6 +453,17 @@ impl BenchTarget {
                     model.check_benchmark_group(&self.name, &group);
                 }
                 IncomingMessage::FinishedBenchmarkGroup { group } => {
+                    if let Some((previous, current, missing)) = model.check_group_members(&group) {
+                        warn!(
+                            target: "missing-benchmark",
+                            "Benchmark group {} has {} benchmark(s), but it had {} in its previous \
+                            run. Missing: {}",
+                            group,
+                            current,
+                            previous,
+                            missing.join(", ")
+                        );
+                    }
                     let benchmark_group = model.add_benchmark_group(&self.name, &group);
                     {
                         let formatter = crate::value_formatter::ValueFormatter::new(&mut conn);
@@ -162,13 +475,53 @@ impl BenchTarget {
                 }
                 IncomingMessage::BeginningBenchmark { id } => {
                     any_from_group_executed = true;
+                    *reported_benchmark = true;
                     let mut id = id.into();
                     model.add_benchmark_id(&self.name, &mut id);
-                    self.run_benchmark(&mut conn, report, model, id, &mut context)?;
+                    model.record_benchmark(&id, true);
+                    if self.sends_sampling_hints(adaptive_sampling) && conn.expects_sampling_hints()
+                    {
+                        let previous = if adaptive_sampling {
+                            model.get_last_sample(&id).map(|stats| PreviousEstimate {
+                                mean_ns: stats.estimates.mean.point_estimate,
+                                std_dev_ns: stats.estimates.std_dev.point_estimate,
+                            })
+                        } else {
+                            None
+                        };
+                        let min_samples = self.min_samples as u64;
+                        conn.send(&OutgoingMessage::SamplingHint {
+                            previous,
+                            min_samples,
+                        })
+                        .with_context(|| RunError::ConnectionFailed {
+                            target: self.name.clone(),
+                        })?;
+                    }
+                    self.run_benchmark(
+                        &mut conn,
+                        report,
+                        model,
+                        id,
+                        &mut context,
+                        memory_pid,
+                        instruction_counter,
+                    )?;
+                    if let Some(remaining) = remaining {
+                        *remaining -= 1;
+                        if *remaining == 0 {
+                            // The target may already have exited on its own.
+                            let _ = child.kill();
+                            let _ = child.wait();
+                            return Ok(());
+                        }
+                    }
                 }
                 IncomingMessage::SkippingBenchmark { id } => {
+                    *reported_benchmark = true;
                     let mut id = id.into();
                     model.add_benchmark_id(&self.name, &mut id);
+                    model.record_benchmark(&id, false);
                 }
                 other => panic!("Unexpected message {:?}", other),
             }
@@ -184,11 +537,11 @@ impl BenchTarget {
                     if exit_status.success() {
                         return Ok(());
                     } else {
-                        return Err(anyhow!(
-                            "Criterion.rs benchmark target {} exited with error code {:?}",
-                            self.name,
-                            exit_status.code()
-                        ));
+                        return Err(RunError::BenchmarkFailed {
+                            target: self.name.clone(),
+                            exit_code: exit_status.code(),
+                        }
+                        .into());
                     }
                 }
                 Ok(None) => continue,
@@ -197,6 +550,7 @@ impl BenchTarget {
     }
 
     /// Helper function for communicating with the benchmark target about a single benchmark.
+    #[allow(clippy::too_many_arguments)]
     fn run_benchmark(
         &self,
         conn: &mut Connection,
@@ -204,15 +558,19 @@ impl BenchTarget {
         model: &mut Model,
         id: BenchmarkId,
         context: &mut ReportContext,
+        memory_pid: Option<u32>,
+        instruction_counter: Option<&InstructionCounter>,
     ) -> Result<()> {
-        report.benchmark_start(&id, &context);
+        report.benchmark_start(&id, context);
+        let mut metadata = HashMap::new();
+        let mut allocation_counts = None;
+        let mut sub_measurements = BTreeMap::new();
+        // The instruction count when the measurement started, if we're counting instructions.
+        let mut instructions_at_start = None;
 
         loop {
-            let message = conn.recv().with_context(|| {
-                format!(
-                    "Failed to receive message from Criterion.rs benchmark {}",
-                    self.name
-                )
+            let message = conn.recv().with_context(|| RunError::ConnectionFailed {
+                target: self.name.clone(),
             })?;
             let message = match message {
                 Some(message) => message,
@@ -220,14 +578,26 @@ impl BenchTarget {
             };
             match message {
                 IncomingMessage::Warmup { nanos } => {
-                    report.warmup(&id, &context, nanos);
+                    report.warmup(&id, context, nanos);
                 }
                 IncomingMessage::MeasurementStart {
                     sample_count,
                     estimate_ns,
                     iter_count,
                 } => {
-                    report.measurement_start(&id, &context, sample_count, estimate_ns, iter_count);
+                    report.measurement_start(&id, context, sample_count, estimate_ns, iter_count);
+                    instructions_at_start = instruction_counter.and_then(read_instructions);
+                }
+                IncomingMessage::BenchmarkMetadata {
+                    metadata: new_metadata,
+                } => {
+                    metadata.extend(new_metadata);
+                }
+                IncomingMessage::AllocationCounts { allocations, bytes } => {
+                    allocation_counts = Some((allocations, bytes));
+                }
+                IncomingMessage::SubMeasurement { name, iters, times } => {
+                    sub_measurements.insert(name, (iters, times));
                 }
                 IncomingMessage::MeasurementComplete {
                     iters,
@@ -235,18 +605,67 @@ impl BenchTarget {
                     plot_config,
                     sampling_method,
                     benchmark_config,
+                    async_runtime,
                 } => {
+                    let instructions = instructions_at_start.and_then(|start| {
+                        let end = instruction_counter.and_then(read_instructions)?;
+                        Some(end.saturating_sub(start))
+                    });
                     context.plot_config = plot_config;
-                    report.analysis(&id, &context);
+
+                    if self.reject_new_benchmarks && model.get_last_sample(&id).is_none() {
+                        error!(
+                            target: "new-benchmark",
+                            "Benchmark {} has no stored measurement, and --no-new-benchmarks was \
+                             given; its measurement will not be saved.",
+                            id.as_title()
+                        );
+                        model.reject_new_benchmark(&id);
+                        // Create and drop a value formatter because the benchmark will be waiting
+                        // for that
+                        crate::value_formatter::ValueFormatter::new(conn);
+                        return Ok(());
+                    }
+
+                    report.analysis(&id, context);
 
                     let avg_values: Vec<f64> = iters
                         .iter()
                         .zip(times.iter())
-                        .map(|(iter, time)| *time / (*iter as f64))
+                        .map(|(iter, time)| *time / *iter)
                         .collect();
+                    let sub_avg_values: Vec<Vec<f64>> = sub_measurements
+                        .values()
+                        .map(|(iters, times): &(Vec<f64>, Vec<f64>)| {
+                            iters
+                                .iter()
+                                .zip(times)
+                                .map(|(iter, time)| time / iter)
+                                .collect()
+                        })
+                        .collect();
+
+                    if let Err(problem) =
+                        crate::analysis::check_measured_values(&crate::analysis::MeasuredValues {
+                            iteration_count: &iters,
+                            sample_values: &times,
+                            avg_values: &avg_values,
+                        })
+                    {
+                        warn!(
+                            target: "skipped-analysis",
+                            "Skipping analysis of benchmark {} because {}.",
+                            id.as_title(),
+                            problem
+                        );
+                        // Create and drop a value formatter because the benchmark will be waiting
+                        // for that
+                        crate::value_formatter::ValueFormatter::new(conn);
+                        return Ok(());
+                    }
 
-                    if times.iter().any(|&f| f == 0.0) {
-                        error!("At least one measurement of benchmark {} took zero time per \
+                    if times.contains(&0.0) {
+                        error!(target: "skipped-analysis", "At least one measurement of benchmark {} took zero time per \
                         iteration. This should not be possible. If using iter_custom, please verify \
                         that your routine is correctly measured.", id.as_title());
                         // Create and drop a value formatter because the benchmark will be waiting
@@ -255,12 +674,105 @@ impl BenchTarget {
                         return Ok(());
                     }
 
-                    let saved_stats = model.get_last_sample(&id).cloned();
+                    let saved_stats = model.get_comparison_sample(&id).filter(|stats| {
+                        if !stats.has_samples() {
+                            info!(
+                                "Not comparing benchmark {} because the stored measurement has no \
+                                 raw samples.",
+                                id.as_title()
+                            );
+                        }
+                        stats.has_samples()
+                    });
+                    let criterion_version = conn.criterion_rs_version();
+                    if let Some(saved_stats) = &saved_stats {
+                        if let Some(saved_version) = &saved_stats.criterion_version {
+                            if *saved_version != criterion_version {
+                                warn!(
+                                    target: "criterion-version-mismatch",
+                                    "Benchmark {} was built with Criterion.rs {} but is being \
+                                     compared to a measurement taken with Criterion.rs {}. Changes \
+                                     to the statistics between versions may affect the results.",
+                                    id.as_title(),
+                                    criterion_version,
+                                    saved_version
+                                );
+                            }
+                        }
+                        if features_differ(&saved_stats.features, &self.features) {
+                            warn!(
+                                target: "feature-mismatch",
+                                "Benchmark {} was built with features [{}] but is being compared \
+                                 to a measurement built with features [{}].",
+                                id.as_title(),
+                                self.features.join(", "),
+                                saved_stats.features.join(", ")
+                            );
+                        }
+                        if let (Some(saved_profile), Some(profile)) =
+                            (&saved_stats.profile, &self.profile)
+                        {
+                            if saved_profile != profile {
+                                warn!(
+                                    target: "profile-mismatch",
+                                    "Benchmark {} was built with the '{}' profile but is being \
+                                     compared to a measurement built with the '{}' profile.",
+                                    id.as_title(),
+                                    profile,
+                                    saved_profile
+                                );
+                            }
+                        }
+                        if saved_stats.async_runtime != async_runtime {
+                            warn!(
+                                target: "async-runtime-mismatch",
+                                "Benchmark {} ran {} but is being compared to a measurement which \
+                                 ran {}. Async benchmarks have different overheads.",
+                                id.as_title(),
+                                describe_async_runtime(&async_runtime),
+                                describe_async_runtime(&saved_stats.async_runtime)
+                            );
+                        }
+                        if saved_stats.bench_seed != self.bench_seed {
+                            warn!(
+                                target: "bench-seed-mismatch",
+                                "Benchmark {} was run with {} but is being compared to a \
+                                 measurement run with {}. The benchmark may have measured \
+                                 different inputs.",
+                                id.as_title(),
+                                describe_bench_seed(self.bench_seed),
+                                describe_bench_seed(saved_stats.bench_seed)
+                            );
+                        }
+                        if saved_stats.single_thread != self.single_thread {
+                            warn!(
+                                target: "single-thread-mismatch",
+                                "Benchmark {} was run {} but is being compared to a measurement \
+                                 which was run {}. Parallel code may perform differently.",
+                                id.as_title(),
+                                describe_single_thread(self.single_thread),
+                                describe_single_thread(saved_stats.single_thread)
+                            );
+                        }
+                        if saved_stats.bench_version != self.bench_version {
+                            warn!(
+                                target: "bench-version-mismatch",
+                                "Benchmark {} has bench version {} but is being compared to a \
+                                 measurement with bench version {}. The benchmark may not measure \
+                                 the same thing as it did then.",
+                                id.as_title(),
+                                describe_bench_version(&self.bench_version),
+                                describe_bench_version(&saved_stats.bench_version)
+                            );
+                        }
+                    }
 
-                    let benchmark_config: crate::analysis::BenchmarkConfig =
-                        benchmark_config.into();
+                    let benchmark_config = crate::analysis::BenchmarkConfig::from(benchmark_config)
+                        .with_overrides(&self.benchmark_overrides, id.as_title())
+                        .with_significance_test(self.significance_test)
+                        .with_compare_metric(self.compare_metric);
 
-                    let measured_data = crate::analysis::analysis(
+                    let mut measured_data = crate::analysis::analysis(
                         &benchmark_config,
                         id.throughput.clone(),
                         crate::analysis::MeasuredValues {
@@ -278,9 +790,139 @@ impl BenchTarget {
                         }),
                         sampling_method,
                     );
+                    measured_data.peak_rss = memory_pid.and_then(peak_rss);
+                    measured_data.cpu_affinity = self.cpu_affinity.clone();
+                    measured_data.instructions_per_iteration = instructions
+                        .map(|instructions| instructions as f64 / iters.iter().sum::<f64>());
+                    measured_data.allocations = allocation_counts.map(|(allocations, bytes)| {
+                        AllocStats::per_iteration(allocations, bytes, iters.iter().sum())
+                    });
+                    measured_data.criterion_version = Some(criterion_version);
+                    measured_data.bench_version = self.bench_version.clone();
+                    measured_data.bench_seed = self.bench_seed;
+                    measured_data.single_thread = self.single_thread;
+                    measured_data.async_runtime = async_runtime;
+                    measured_data.profile = self.profile.clone();
+                    measured_data.compile_time = self.compile_time;
+                    measured_data.low_confidence =
+                        measured_data.effective_sample_count < self.min_samples;
+                    measured_data.min_samples_enforced = iters.len() > benchmark_config.sample_size;
+                    if measured_data.min_samples_enforced {
+                        warn!(
+                            target: "min-samples-enforced",
+                            "Benchmark {} is configured to take {} samples but took {} to satisfy \
+                             --min-samples, so it may have run past its measurement time of {}.",
+                            id.as_title(),
+                            benchmark_config.sample_size,
+                            iters.len(),
+                            crate::format::time(benchmark_config.measurement_time.as_secs_f64() * 1e9)
+                                .trim()
+                        );
+                    }
+                    measured_data.metadata = metadata;
+                    // Each part is compared against the same part of the stored measurement.
+                    measured_data.sub_measurements = (sub_measurements.iter())
+                        .zip(&sub_avg_values)
+                        .filter_map(|((name, (iters, times)), avg_values)| {
+                            let values = crate::analysis::MeasuredValues {
+                                iteration_count: iters,
+                                sample_values: times,
+                                avg_values,
+                            };
+                            if let Err(problem) = crate::analysis::check_measured_values(&values) {
+                                warn!(
+                                    target: "skipped-analysis",
+                                    "Skipping analysis of part {} of benchmark {} because {}.",
+                                    name,
+                                    id.as_title(),
+                                    problem
+                                );
+                                return None;
+                            }
+                            let saved_part = (saved_stats.as_ref())
+                                .and_then(|stats| stats.sub_measurements.get(name))
+                                .filter(|stats| stats.has_samples());
+                            let sub_data = crate::analysis::analysis(
+                                &benchmark_config,
+                                None,
+                                values,
+                                saved_part.map(|stats| {
+                                    let measured_values = crate::analysis::MeasuredValues {
+                                        iteration_count: &stats.iterations,
+                                        sample_values: &stats.values,
+                                        avg_values: &stats.avg_values,
+                                    };
+                                    (measured_values, &stats.estimates)
+                                }),
+                                sampling_method,
+                            );
+                            Some((name.clone(), sub_data))
+                        })
+                        .collect();
+                    measured_data.budget = crate::config::budget_for(&self.budgets, id.as_title())
+                        .map(|budget| {
+                            BudgetCheck::new(
+                                budget.limit_ns,
+                                &measured_data.absolute_estimates,
+                                self.budget_statistic,
+                            )
+                        });
+                    if measured_data.budget.is_some_and(|budget| budget.exceeded()) {
+                        model.record_over_budget(&id);
+                    }
+                    if let Some(comparison) = &mut measured_data.comparison {
+                        comparison.baseline_label = model.comparison_label();
+                        comparison.base_criterion_version = saved_stats
+                            .as_ref()
+                            .and_then(|stats| stats.criterion_version.clone());
+                        comparison.throughput_mismatch = saved_stats.as_ref().and_then(|stats| {
+                            ThroughputMismatch::new(
+                                stats.throughput.as_ref(),
+                                id.throughput.as_ref(),
+                            )
+                        });
+                        if let Some(mismatch) = comparison.throughput_mismatch {
+                            warn!(
+                                target: "throughput-mismatch",
+                                "Benchmark {} {}, so the change in throughput can't be reported.",
+                                id.as_title(),
+                                mismatch
+                            );
+                        }
+                        if comparison.compare_metric != self.compare_metric {
+                            warn!(
+                                target: "compare-metric-unavailable",
+                                "Benchmark {} can't be compared by its slope because {} wasn't \
+                                 taken with linear sampling, so the means are compared instead.",
+                                id.as_title(),
+                                if measured_data.absolute_estimates.slope.is_none() {
+                                    "its measurement"
+                                } else {
+                                    "the measurement it is compared to"
+                                }
+                            );
+                        }
+                        if crate::report::has_regressed(comparison) {
+                            model.record_regression(&id);
+                        }
+                    }
+                    // A regression in any part of the benchmark is a regression of the benchmark.
+                    if (measured_data.sub_measurements.iter())
+                        .filter_map(|(_, sub)| sub.comparison.as_ref())
+                        .any(crate::report::has_regressed)
+                    {
+                        model.record_regression(&id);
+                    }
 
-                    if let Err(e) = model.benchmark_complete(&id, &measured_data) {
+                    if let Err(e) = model.benchmark_complete(
+                        &id,
+                        &measured_data,
+                        &self.features,
+                        &self.name,
+                        self.fingerprint.as_deref(),
+                    ) {
                         error!(
+                            target: "save-failure",
                             "Failed to save results for target {} benchmark {}: {}",
                             self.name,
                             id.as_title(),
@@ -290,11 +932,15 @@ impl BenchTarget {
 
                     {
                         let formatter = crate::value_formatter::ValueFormatter::new(conn);
-                        report.measurement_complete(&id, &context, &measured_data, &formatter);
+                        report.measurement_complete(&id, context, &measured_data, &formatter);
 
                         match model.load_history(&id) {
-                            Ok(history) => report.history(&context, &id, &history, &formatter),
-                            Err(e) => error!("Failed to load historical data: {:?}", e),
+                            Ok(history) => report.history(context, &id, &history, &formatter),
+                            Err(e) => error!(
+                                target: "load-failure",
+                                "Failed to load historical data: {:?}",
+                                e
+                            ),
                         }
                     }
                     return Ok(());
@@ -305,6 +951,82 @@ impl BenchTarget {
     }
 }
 
+/// Is this a failure that launching the target again might fix? Errors that cargo-criterion itself
+/// caused, such as failing to launch the executable at all, are not.
+fn is_retryable(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<RunError>(),
+        Some(RunError::ConnectionFailed { .. }) | Some(RunError::BenchmarkFailed { .. })
+    )
+}
+
+/// Describe a `--bench-version` tag in a warning.
+/// Were the stored measurement and the new one built with different features? Measurements stored
+/// before the features were recorded have an empty list, which says nothing about their features,
+/// so they never differ.
+fn features_differ(saved: &[String], current: &[String]) -> bool {
+    !saved.is_empty() && saved != current
+}
+
+fn describe_bench_version(bench_version: &Option<String>) -> String {
+    match bench_version {
+        Some(tag) => format!("'{}'", tag),
+        None => "(none)".to_owned(),
+    }
+}
+
+/// Describe a `--bench-seed` in a warning.
+fn describe_bench_seed(bench_seed: Option<u64>) -> String {
+    match bench_seed {
+        Some(seed) => format!("seed {}", seed),
+        None => "no seed".to_owned(),
+    }
+}
+
+/// Describe whether a benchmark was run with `--single-thread` in a warning.
+fn describe_single_thread(single_thread: bool) -> &'static str {
+    if single_thread {
+        "with --single-thread"
+    } else {
+        "without --single-thread"
+    }
+}
+
+/// Describe the async runtime that a benchmark ran on in a warning.
+fn describe_async_runtime(async_runtime: &Option<String>) -> String {
+    match async_runtime {
+        Some(runtime) => format!("on the {} async runtime", runtime),
+        None => "synchronously".to_owned(),
+    }
+}
+
+/// Read the peak resident set size, in bytes, of the process with the given ID. This is only
+/// supported on Linux, where it is read from `/proc/<pid>/status`.
+fn peak_rss(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid))
+        .map_err(|e| debug!("Unable to read memory usage of process {}: {}", pid, e))
+        .ok()?;
+    parse_vm_hwm(&status)
+}
+
+fn read_instructions(counter: &InstructionCounter) -> Option<u64> {
+    counter
+        .read()
+        .map_err(|e| debug!("Unable to read instruction count: {}", e))
+        .ok()
+}
+
+/// Parse the `VmHWM` (peak resident set size) line from the contents of `/proc/<pid>/status`.
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let mut fields = line["VmHWM:".len()..].split_whitespace();
+    let value: u64 = fields.next()?.parse().ok()?;
+    match fields.next() {
+        Some("kB") => Some(value * 1024),
+        _ => None,
+    }
+}
+
 // This dylib path logic is adapted from Cargo.
 pub fn dylib_path_envvar() -> &'static str {
     if cfg!(windows) {
@@ -337,3 +1059,46 @@ fn dylib_search_path(linked_paths: &[PathBuf]) -> Result<OsString> {
     std::env::join_paths(&dylib_path)
         .with_context(|| format!("Failed to join dynamic lib search paths together. Does {} have an unterminated quote character? Paths:\n{:?}", dylib_path_envvar(), &dylib_path))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_hwm() {
+        let status =
+            "Name:\tbench\nVmPeak:\t  20000 kB\nVmHWM:\t    1234 kB\nVmRSS:\t    1000 kB\n";
+        assert_eq!(parse_vm_hwm(status), Some(1234 * 1024));
+        assert_eq!(parse_vm_hwm("Name:\tbench\n"), None);
+    }
+
+    #[test]
+    fn test_features_differ() {
+        let features = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect();
+        let default: Vec<String> = features(&["default"]);
+        assert!(!features_differ(&default, &default));
+        assert!(features_differ(&default, &features(&["default", "simd"])));
+        assert!(features_differ(&default, &[]));
+        // An empty stored list is from before the features were recorded.
+        assert!(!features_differ(&[], &default));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let lost: anyhow::Error =
+            Err::<(), _>(std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+                .with_context(|| RunError::ConnectionFailed {
+                    target: "bench".to_owned(),
+                })
+                .unwrap_err();
+        assert!(is_retryable(&lost));
+        let crashed = RunError::BenchmarkFailed {
+            target: "bench".to_owned(),
+            exit_code: Some(101),
+        };
+        assert!(is_retryable(&crashed.into()));
+        assert!(!is_retryable(&anyhow::anyhow!(
+            "Unable to launch bench target"
+        )));
+    }
+}
@@ -0,0 +1,218 @@
+//! Reading and writing the single-file bundles made by `cargo criterion bundle`. A bundle is an
+//! uncompressed tar archive in the POSIX ustar format, so it can also be unpacked with `tar`. Only
+//! regular files are stored, and their names always use `/` as the separator.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const BLOCK_SIZE: usize = 512;
+const NAME_LEN: usize = 100;
+const PREFIX_LEN: usize = 155;
+
+/// A file in a bundle, named by its path within the bundle.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+/// Write the entries to a new bundle at the given path.
+pub fn write(path: &Path, entries: &[Entry]) -> Result<()> {
+    let mtime = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let mut archive = Vec::new();
+    for entry in entries {
+        archive.extend_from_slice(&header(entry, mtime)?);
+        archive.extend_from_slice(&entry.contents);
+        archive.resize(padded(archive.len()), 0);
+    }
+    // The end of the archive is marked by two empty blocks.
+    archive.resize(archive.len() + 2 * BLOCK_SIZE, 0);
+
+    let mut file =
+        File::create(path).with_context(|| format!("Failed to create bundle {:?}", path))?;
+    file.write_all(&archive)
+        .with_context(|| format!("Failed to write bundle {:?}", path))?;
+    Ok(())
+}
+
+/// Read every regular file from the bundle at the given path.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let mut archive = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut archive))
+        .with_context(|| format!("Failed to read bundle {:?}", path))?;
+    parse(&archive).with_context(|| format!("{:?} is not a valid bundle", path))
+}
+
+fn parse(archive: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + BLOCK_SIZE <= archive.len() {
+        let header = &archive[offset..offset + BLOCK_SIZE];
+        if header.iter().all(|&byte| byte == 0) {
+            return Ok(entries);
+        }
+        if &header[257..262] != b"ustar" {
+            anyhow::bail!("Entry at offset {} is not a ustar header", offset);
+        }
+        let expected = parse_octal(&header[148..156])?;
+        if checksum(header) != expected {
+            anyhow::bail!("Entry at offset {} has an invalid checksum", offset);
+        }
+        let size = parse_octal(&header[124..136])? as usize;
+        let start = offset + BLOCK_SIZE;
+        if start + size > archive.len() {
+            anyhow::bail!("Entry at offset {} is truncated", offset);
+        }
+        // Directories and links aren't needed to restore the data, so they're skipped.
+        if header[156] == b'0' || header[156] == 0 {
+            let name = field_str(&header[..NAME_LEN]);
+            let prefix = field_str(&header[345..345 + PREFIX_LEN]);
+            entries.push(Entry {
+                name: if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", prefix, name)
+                },
+                contents: archive[start..start + size].to_vec(),
+            });
+        }
+        offset = padded(start + size);
+    }
+    anyhow::bail!("The archive ends without an end-of-archive marker")
+}
+
+fn header(entry: &Entry, mtime: u64) -> Result<[u8; BLOCK_SIZE]> {
+    // Names too long for the name field are split at a slash, and the start goes in the prefix.
+    let (prefix, name) = if entry.name.len() <= NAME_LEN {
+        ("", entry.name.as_str())
+    } else {
+        // The split is at a slash, which is always a character boundary however the rest of the
+        // name is encoded.
+        let split = (entry.name.match_indices('/'))
+            .map(|(split, _)| split)
+            .rfind(|&split| split <= PREFIX_LEN && entry.name.len() - split - 1 <= NAME_LEN)
+            .with_context(|| format!("The file name {:?} is too long to bundle", entry.name))?;
+        (&entry.name[..split], &entry.name[split + 1..])
+    };
+
+    let mut header = [0u8; BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], entry.contents.len() as u64);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    let checksum = checksum(&header);
+    write_octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+    Ok(header)
+}
+
+/// The sum of the bytes of a header, counting the checksum field itself as spaces.
+fn checksum(header: &[u8]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| if (148..156).contains(&i) { b' ' } else { byte } as u64)
+        .sum()
+}
+
+/// Write a number as zero-padded octal digits followed by a NUL, filling the field.
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    let digits = field_str(field);
+    let digits = digits.trim();
+    u64::from_str_radix(digits, 8).with_context(|| format!("Invalid number {:?}", digits))
+}
+
+/// The text of a NUL-terminated header field.
+fn field_str(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Round a length up to a whole number of blocks.
+fn padded(len: usize) -> usize {
+    len.div_ceil(BLOCK_SIZE) * BLOCK_SIZE
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let long_name = format!("data/main/{}/benchmark.cbor", "x".repeat(120));
+        let entries = vec![
+            Entry {
+                name: "summary.txt".to_owned(),
+                contents: b"sum   time: [1 ns 2 ns 3 ns]\n".to_vec(),
+            },
+            Entry {
+                name: long_name,
+                contents: vec![7; 1000],
+            },
+            Entry {
+                name: "empty".to_owned(),
+                contents: vec![],
+            },
+        ];
+        let mut archive = Vec::new();
+        for entry in &entries {
+            archive.extend_from_slice(&header(entry, 0).unwrap());
+            archive.extend_from_slice(&entry.contents);
+            archive.resize(padded(archive.len()), 0);
+        }
+        archive.resize(archive.len() + 2 * BLOCK_SIZE, 0);
+        assert_eq!(0, archive.len() % BLOCK_SIZE);
+        assert_eq!(entries, parse(&archive).unwrap());
+
+        archive[20] ^= 1;
+        assert!(parse(&archive).is_err());
+    }
+
+    #[test]
+    fn test_long_non_ascii_names_are_split_at_a_slash() {
+        // The prefix field ends in the middle of a two-byte character.
+        let name = format!("data/x{}/{}", "é".repeat(50), "é".repeat(45));
+        let entry = Entry {
+            name,
+            contents: vec![1, 2, 3],
+        };
+        let header = header(&entry, 0).unwrap();
+        assert_eq!("é".repeat(45), field_str(&header[..NAME_LEN]));
+
+        let mut archive = header.to_vec();
+        archive.extend_from_slice(&entry.contents);
+        archive.resize(padded(archive.len()) + 2 * BLOCK_SIZE, 0);
+        assert_eq!(vec![entry], parse(&archive).unwrap());
+    }
+
+    #[test]
+    fn test_names_which_cannot_be_split_are_rejected() {
+        let entry = Entry {
+            name: "x".repeat(NAME_LEN + 1),
+            contents: vec![],
+        };
+        assert!(header(&entry, 0).is_err());
+    }
+}
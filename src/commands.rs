@@ -0,0 +1,1345 @@
+//! Commands which inspect or convert the stored benchmark data, or inspect the configuration,
+//! without compiling or running any benchmarks.
+
+use crate::analysis::{analysis, check_measured_values, BenchmarkConfig, MeasuredValues};
+use crate::bundle;
+use crate::config::{
+    pattern_matches, BenchmarkOverride, Budget, ComparisonFormat, ConfigFormat, FullConfig,
+    SignificanceTest, SortOrder, StorageFormat, MESSAGE_FORMATS, OUTPUT_FORMATS, PLOTTING_BACKENDS,
+};
+use crate::connection::{SamplingMethod, Throughput, PROTOCOL_VERSION};
+use crate::error::RunError;
+use crate::estimate::Statistic;
+use crate::format;
+use crate::markdown::escape_cell;
+use crate::model::{
+    check_stored_data, load_measurement, migrate_stored_data, pool_samples, Model, SavedStatistics,
+};
+use crate::report::{
+    compare_to_threshold, is_significant_change, latest_stats, write_stored_estimates,
+    ComparisonResult,
+};
+use crate::runner;
+use crate::stats::univariate::Sample;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::io::{stdout, Write};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Serialize)]
+struct GroupSummary<'a> {
+    name: &'a str,
+    benchmark_count: usize,
+}
+
+#[derive(Serialize)]
+struct Capabilities {
+    version: &'static str,
+    protocol_version: u16,
+    output_formats: Vec<Capability>,
+    plotting_backends: Vec<Capability>,
+    message_formats: &'static [&'static str],
+}
+
+#[derive(Serialize)]
+struct Capability {
+    name: &'static str,
+    usable: bool,
+    /// Why the capability can't be used here, if it can't.
+    unusable_reason: Option<String>,
+}
+impl Capability {
+    fn new(name: &'static str, available: Result<()>) -> Capability {
+        Capability {
+            name,
+            usable: available.is_ok(),
+            unusable_reason: available.err().map(|e| e.to_string()),
+        }
+    }
+}
+
+/// Print the output formats, plotting backends and message formats which this build supports,
+/// and whether each can be used in this environment.
+pub fn capabilities(json: bool) -> Result<()> {
+    let output_formats = OUTPUT_FORMATS
+        .iter()
+        .map(|&name| match name {
+            "pdf" => Capability::new(name, runner::pdf_available()),
+            _ => Capability::new(name, Ok(())),
+        })
+        .collect();
+    let plotting_backends = PLOTTING_BACKENDS
+        .iter()
+        .map(|&name| match name {
+            "gnuplot" => Capability::new(name, runner::gnuplot_available()),
+            "plotters" => Capability::new(name, runner::plotters_available()),
+            _ => Capability::new(name, Ok(())),
+        })
+        .collect();
+    let capabilities = Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        protocol_version: PROTOCOL_VERSION,
+        output_formats,
+        plotting_backends,
+        message_formats: MESSAGE_FORMATS,
+    };
+
+    let stdout = stdout();
+    let mut out = stdout.lock();
+    if json {
+        serde_json::to_writer(&mut out, &capabilities)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "cargo-criterion {} (protocol version {})",
+        capabilities.version, capabilities.protocol_version
+    )?;
+    for (heading, list) in [
+        ("Output formats", &capabilities.output_formats),
+        ("Plotting backends", &capabilities.plotting_backends),
+    ] {
+        writeln!(out, "{}:", heading)?;
+        for capability in list {
+            match &capability.unusable_reason {
+                None => writeln!(out, "  {}", capability.name)?,
+                Some(reason) => writeln!(out, "  {} (unusable: {})", capability.name, reason)?,
+            }
+        }
+    }
+    writeln!(out, "Message formats:")?;
+    for name in capabilities.message_formats {
+        writeln!(out, "  {}", name)?;
+    }
+    Ok(())
+}
+
+/// Collect the name and benchmark count of each group in the model, sorted by name.
+fn group_summaries(model: &Model) -> Vec<GroupSummary<'_>> {
+    let mut groups: Vec<GroupSummary> = model
+        .groups
+        .iter()
+        .map(|(name, group)| GroupSummary {
+            name,
+            benchmark_count: group.benchmarks.len(),
+        })
+        .collect();
+    groups.sort_by(|a, b| a.name.cmp(b.name));
+    groups
+}
+
+/// Print each benchmark group in the stored data along with the number of benchmarks in it.
+pub fn list_groups(model: &Model, json: bool) -> Result<()> {
+    let stdout = stdout();
+    write_groups(&mut stdout.lock(), &group_summaries(model), json)
+}
+
+fn write_groups(out: &mut dyn Write, groups: &[GroupSummary<'_>], json: bool) -> Result<()> {
+    if json {
+        serde_json::to_writer(&mut *out, groups)?;
+        writeln!(out)?;
+    } else {
+        for group in groups {
+            let plural = if group.benchmark_count == 1 { "" } else { "s" };
+            writeln!(
+                out,
+                "{} ({} benchmark{})",
+                group.name, group.benchmark_count, plural
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the latest measurement of each benchmark in the group, and its change from the
+/// measurement before it.
+pub fn report(
+    model: &Model,
+    group: &str,
+    sort_by: SortOrder,
+    change_ratio_above: Option<f64>,
+) -> Result<()> {
+    if !model.groups.contains_key(group) {
+        anyhow::bail!(
+            "No stored measurements were found for benchmark group {:?}",
+            group
+        );
+    }
+    let stdout = stdout();
+    let mut out = stdout.lock();
+    write_stored_estimates(
+        &mut out,
+        &latest_stats(model, sort_by),
+        true,
+        change_ratio_above,
+    )?;
+    Ok(())
+}
+
+/// The stored measurements recorded against one commit, ie. one `--history-id`.
+#[derive(Serialize, Debug)]
+struct CommitSummary {
+    commit: String,
+    benchmark_count: usize,
+    measurement_count: usize,
+    first_measured: DateTime<Utc>,
+    last_measured: DateTime<Utc>,
+}
+
+/// Collect a summary of each distinct commit, given the benchmark title, commit and time of each
+/// stored measurement. The summaries are ordered by when each commit was first measured.
+fn commit_summaries<'a>(
+    measurements: impl IntoIterator<Item = (&'a str, &'a str, DateTime<Utc>)>,
+) -> Vec<CommitSummary> {
+    let mut commits: HashMap<&str, (HashSet<&str>, CommitSummary)> = HashMap::new();
+    for (title, commit, datetime) in measurements {
+        let (benchmarks, summary) = commits.entry(commit).or_insert_with(|| {
+            let summary = CommitSummary {
+                commit: commit.to_owned(),
+                benchmark_count: 0,
+                measurement_count: 0,
+                first_measured: datetime,
+                last_measured: datetime,
+            };
+            (HashSet::new(), summary)
+        });
+        benchmarks.insert(title);
+        summary.benchmark_count = benchmarks.len();
+        summary.measurement_count += 1;
+        summary.first_measured = summary.first_measured.min(datetime);
+        summary.last_measured = summary.last_measured.max(datetime);
+    }
+    let mut summaries: Vec<CommitSummary> = commits
+        .into_iter()
+        .map(|(_, (_, summary))| summary)
+        .collect();
+    summaries.sort_by(|a, b| (a.first_measured, &a.commit).cmp(&(b.first_measured, &b.commit)));
+    summaries
+}
+
+/// Print each distinct commit (`--history-id`) in the stored history, with the number of
+/// benchmarks and measurements recorded against it and when they were measured.
+pub fn commits(model: &Model, json: bool) -> Result<()> {
+    let mut histories = Vec::new();
+    for group in model.groups.values() {
+        for id in group.benchmarks.keys() {
+            histories.push((id.as_title(), model.load_history(id)?));
+        }
+    }
+    // Measurements which don't record a commit are left out.
+    let summaries = commit_summaries(histories.iter().flat_map(|(title, history)| {
+        history.iter().filter_map(move |stats| {
+            let commit = stats.history_id.as_deref()?;
+            Some((*title, commit, stats.datetime))
+        })
+    }));
+
+    let stdout = stdout();
+    let mut out = stdout.lock();
+    if json {
+        serde_json::to_writer(&mut out, &summaries)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    if summaries.is_empty() {
+        eprintln!("No stored measurements record a commit. Record one with --history-id.");
+    }
+    for summary in summaries {
+        let time_format = "%Y-%m-%d %H:%M:%S";
+        writeln!(
+            out,
+            "{} ({} benchmarks, {} measurements, {} to {})",
+            summary.commit,
+            summary.benchmark_count,
+            summary.measurement_count,
+            summary.first_measured.format(time_format),
+            summary.last_measured.format(time_format)
+        )?;
+    }
+    Ok(())
+}
+
+/// Save the latest stored measurement of every benchmark whose title matches the pattern (or of
+/// every benchmark, if there is no pattern) as the named baseline.
+pub fn accept(model: &Model, baseline: &str, pattern: Option<&str>) -> Result<()> {
+    let mut accepted = 0;
+    for (_, ids) in model.sorted_benchmarks(SortOrder::Insertion) {
+        for id in ids {
+            if let Some(pattern) = pattern {
+                if !pattern_matches(pattern, id.as_title()) {
+                    continue;
+                }
+            }
+            model
+                .save_latest_as_baseline(id, baseline)
+                .with_context(|| {
+                    format!("Failed to accept the measurement of {}", id.as_title())
+                })?;
+            accepted += 1;
+        }
+    }
+    if accepted == 0 {
+        anyhow::bail!("No stored measurements were found to accept");
+    }
+    eprintln!(
+        "Saved the latest measurements of {} benchmark(s) as baseline {}",
+        accepted, baseline
+    );
+    Ok(())
+}
+
+/// Package the stored data of one benchmark, its HTML report and a summary of its measurement
+/// history into a single bundle, so that it can be shared and imported elsewhere.
+pub fn bundle(
+    model: &Model,
+    benchmark: &str,
+    out: &Path,
+    data_directory: &Path,
+    report_directory: &Path,
+) -> Result<()> {
+    let id = model
+        .groups
+        .values()
+        .flat_map(|group| group.benchmarks.keys())
+        .find(|id| id.as_title() == benchmark)
+        .with_context(|| {
+            format!(
+                "No stored measurements were found for benchmark {:?}",
+                benchmark
+            )
+        })?;
+
+    let mut summary = format!("{}\n\n", benchmark).into_bytes();
+    let history = model.load_history(id)?;
+    let dates: Vec<String> = history
+        .iter()
+        .map(|stats| stats.datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+        .collect();
+    let measurements: Vec<(&str, &SavedStatistics)> =
+        dates.iter().map(String::as_str).zip(&history).collect();
+    write_stored_estimates(&mut summary, &measurements, true, None)?;
+    let mut entries = vec![bundle::Entry {
+        name: "summary.txt".to_owned(),
+        contents: summary,
+    }];
+
+    // Only the files directly in the benchmark's directory are its own; its subdirectories may
+    // hold the data of other benchmarks.
+    let benchmark_directory = model.benchmark_directory(id);
+    bundle_directory(
+        &mut entries,
+        "data",
+        data_directory,
+        &benchmark_directory,
+        false,
+    )?;
+    let report = report_directory.join(id.as_directory_name());
+    if report.is_dir() {
+        bundle_directory(&mut entries, "reports", report_directory, &report, true)?;
+    }
+
+    bundle::write(out, &entries)?;
+    eprintln!(
+        "Bundled {} file(s) of benchmark {} into {:?}",
+        entries.len(),
+        benchmark,
+        out
+    );
+    Ok(())
+}
+
+/// Add the files in `directory` to the bundle, named by their path relative to `root` under
+/// `prefix`. If `recurse` is set, subdirectories are added too, except for those with their own
+/// `index.html`, which are the reports of other benchmarks.
+fn bundle_directory(
+    entries: &mut Vec<bundle::Entry>,
+    prefix: &str,
+    root: &Path,
+    directory: &Path,
+    recurse: bool,
+) -> Result<()> {
+    let relative = directory
+        .strip_prefix(root)
+        .with_context(|| format!("{:?} is not within {:?}", directory, root))?;
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {:?}", directory))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()
+        .with_context(|| format!("Failed to read directory {:?}", directory))?;
+    paths.sort();
+    for path in paths {
+        if path.is_file() {
+            let mut name = prefix.to_owned();
+            for component in relative.join(path.file_name().unwrap()).components() {
+                name.push('/');
+                name.push_str(&component.as_os_str().to_string_lossy());
+            }
+            let contents =
+                std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+            entries.push(bundle::Entry { name, contents });
+        } else if recurse && path.is_dir() && !path.join("index.html").exists() {
+            bundle_directory(entries, prefix, root, &path, recurse)?;
+        }
+    }
+    Ok(())
+}
+
+/// Unpack a bundle made by `bundle` into the data and report directories. Existing files are only
+/// replaced if `force` is set.
+pub fn import(
+    path: &Path,
+    data_directory: &Path,
+    report_directory: &Path,
+    force: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let mut files = Vec::new();
+    let mut summary = None;
+    for entry in bundle::read(path)? {
+        let mut components = entry.name.split('/');
+        let root = match components.next() {
+            Some("summary.txt") if entry.name == "summary.txt" => {
+                summary = Some(entry.contents);
+                continue;
+            }
+            Some("data") => data_directory,
+            Some("reports") => report_directory,
+            _ => anyhow::bail!("Unexpected file {:?} in bundle {:?}", entry.name, path),
+        };
+        // Every part of the name must be a plain file or directory name, so that nothing can be
+        // written outside of the directory.
+        let mut destination = root.to_owned();
+        for component in components {
+            let mut parts = Path::new(component).components();
+            if !matches!(
+                (parts.next(), parts.next()),
+                (Some(Component::Normal(_)), None)
+            ) {
+                anyhow::bail!("Unsafe file name {:?} in bundle {:?}", entry.name, path);
+            }
+            destination.push(component);
+        }
+        if destination.exists() && !force {
+            anyhow::bail!(
+                "{:?} already exists; pass --force to replace it with the file from the bundle",
+                destination
+            );
+        }
+        files.push((destination, entry.contents));
+    }
+
+    if let Some(summary) = summary {
+        eprint!("{}", String::from_utf8_lossy(&summary));
+    }
+    for (destination, contents) in &files {
+        if dry_run {
+            warn!(target: "dry-run", "Dry run: would write {:?}", destination);
+            continue;
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        std::fs::write(destination, contents)
+            .with_context(|| format!("Failed to write {:?}", destination))?;
+    }
+    eprintln!("Imported {} file(s) from {:?}", files.len(), path);
+    Ok(())
+}
+
+/// Pool the samples of the last `count` stored measurements of a benchmark, and store the result
+/// of analyzing them together as a new measurement of the benchmark.
+pub fn pool(
+    model: &mut Model,
+    benchmark: &str,
+    count: usize,
+    overrides: &[BenchmarkOverride],
+) -> Result<()> {
+    let id = model
+        .groups
+        .values()
+        .flat_map(|group| group.benchmarks.keys())
+        .find(|id| id.as_title() == benchmark)
+        .cloned()
+        .with_context(|| {
+            format!(
+                "No stored measurements were found for benchmark {:?}",
+                benchmark
+            )
+        })?;
+    let history = model.load_history(&id)?;
+    if history.len() < count {
+        anyhow::bail!(
+            "Benchmark {} has only {} stored measurements, so the last {} can't be pooled",
+            benchmark,
+            history.len(),
+            count
+        );
+    }
+    let measurements = &history[history.len() - count..];
+    let pooled = pool_samples(measurements)
+        .with_context(|| format!("Unable to pool the measurements of {}", benchmark))?;
+    let values = MeasuredValues {
+        iteration_count: &pooled.iterations,
+        sample_values: &pooled.values,
+        avg_values: &pooled.avg_values,
+    };
+    if let Err(problem) = check_measured_values(&values) {
+        anyhow::bail!(
+            "Unable to pool the measurements of {} because {}",
+            benchmark,
+            problem
+        );
+    }
+
+    // The statistical settings of the measurements aren't stored, so Criterion.rs' defaults are
+    // used apart from the confidence level.
+    let latest = &measurements[count - 1];
+    let mut config = BenchmarkConfig::default().with_overrides(overrides, benchmark);
+    config.confidence_level = latest.estimates.mean.confidence_interval.confidence_level;
+    let sampling_method = if pooled.linear {
+        SamplingMethod::Linear
+    } else {
+        SamplingMethod::Flat
+    };
+    let mut measured_data = analysis(
+        &config,
+        latest.throughput.clone(),
+        values,
+        None,
+        sampling_method,
+    );
+    measured_data.cpu_affinity = latest.cpu_affinity.clone();
+    measured_data.criterion_version = latest.criterion_version.clone();
+    measured_data.bench_version = latest.bench_version.clone();
+    measured_data.bench_seed = latest.bench_seed;
+    measured_data.single_thread = latest.single_thread;
+    measured_data.min_samples_enforced = latest.min_samples_enforced;
+    measured_data.profile = latest.profile.clone();
+    measured_data.compile_time = latest.compile_time;
+    measured_data.async_runtime = latest.async_runtime.clone();
+    measured_data.metadata = latest.metadata.clone();
+
+    let mean = &measured_data.absolute_estimates.mean;
+    eprintln!(
+        "Pooled {} measurements of {} ({} samples): mean [{} {} {}]",
+        count,
+        benchmark,
+        pooled.values.len(),
+        format::time(mean.confidence_interval.lower_bound),
+        format::time(mean.point_estimate),
+        format::time(mean.confidence_interval.upper_bound)
+    );
+    // The pooled samples didn't all come from one executable, so no fingerprint is recorded.
+    model.benchmark_complete(
+        &id,
+        &measured_data,
+        &latest.features,
+        latest.target.as_deref().unwrap_or_default(),
+        None,
+    )
+}
+
+/// Describe the problems with a measurement which would keep it from being reported or compared
+/// against, such as estimates which aren't numbers.
+fn measurement_problems(stats: &SavedStatistics) -> Vec<String> {
+    let mut problems = Vec::new();
+    if stats.has_samples() {
+        let values = MeasuredValues {
+            iteration_count: &stats.iterations,
+            sample_values: &stats.values,
+            avg_values: &stats.avg_values,
+        };
+        if let Err(problem) = check_measured_values(&values) {
+            problems.push(problem);
+        }
+    }
+    for &statistic in &[
+        Statistic::Mean,
+        Statistic::Median,
+        Statistic::MedianAbsDev,
+        Statistic::Slope,
+        Statistic::StdDev,
+    ] {
+        if let Some(estimate) = stats.estimates.get(statistic) {
+            let interval = &estimate.confidence_interval;
+            let values = [
+                interval.lower_bound,
+                estimate.point_estimate,
+                interval.upper_bound,
+            ];
+            if values.iter().any(|value| !value.is_finite()) {
+                problems.push(format!("the {} estimate is not a number", statistic));
+            } else if interval.lower_bound > interval.upper_bound {
+                problems.push(format!(
+                    "the confidence interval of the {} estimate is reversed",
+                    statistic
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Print the contents of a single measurement file, and check that it can be used.
+pub fn inspect(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        anyhow::bail!("Measurement file {:?} does not exist", path);
+    }
+    let stats = load_measurement(path).with_context(|| RunError::DataCorruption {
+        path: path.to_owned(),
+        problem_count: 1,
+    })?;
+    let stdout = stdout();
+    let mut out = stdout.lock();
+
+    writeln!(out, "Measurement file {:?}", path)?;
+    writeln!(out, "Measured at: {}", stats.datetime.to_rfc3339())?;
+    if stats.has_samples() {
+        writeln!(out, "Samples:     {}", stats.avg_values.len())?;
+    } else {
+        writeln!(out, "Samples:     not stored")?;
+    }
+    let throughput = match &stats.throughput {
+        Some(Throughput::Bytes(bytes)) => format!("{} bytes per iteration", bytes),
+        Some(Throughput::Elements(elements)) => format!("{} elements per iteration", elements),
+        Some(Throughput::Custom { unit, count }) => format!("{} {} per iteration", count, unit),
+        None => "none".to_owned(),
+    };
+    writeln!(out, "Throughput:  {}", throughput)?;
+    writeln!(out, "Estimates:")?;
+    for &statistic in &[
+        Statistic::Slope,
+        Statistic::Mean,
+        Statistic::Median,
+        Statistic::StdDev,
+        Statistic::MedianAbsDev,
+    ] {
+        if let Some(estimate) = stats.estimates.get(statistic) {
+            writeln!(
+                out,
+                "  {:<8}[{} {} {}]",
+                statistic,
+                format::time(estimate.confidence_interval.lower_bound),
+                format::time(estimate.point_estimate),
+                format::time(estimate.confidence_interval.upper_bound),
+            )?;
+        }
+    }
+    if let Some(changes) = &stats.changes {
+        writeln!(out, "Changes:")?;
+        for &statistic in &[Statistic::Mean, Statistic::Median] {
+            let estimate = changes.get(statistic);
+            writeln!(
+                out,
+                "  {:<8}[{} {} {}]",
+                statistic,
+                format::change(estimate.confidence_interval.lower_bound, true),
+                format::change(estimate.point_estimate, true),
+                format::change(estimate.confidence_interval.upper_bound, true),
+            )?;
+        }
+    }
+
+    let problems = measurement_problems(&stats);
+    for problem in &problems {
+        writeln!(out, "error: {}", problem)?;
+    }
+    if !problems.is_empty() {
+        return Err(RunError::DataCorruption {
+            path: path.to_owned(),
+            problem_count: problems.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// The settings printed by `--dump-config`. The benchmark overrides come last because TOML
+/// requires tables to follow plain values.
+#[derive(Serialize)]
+struct EffectiveConfig<'a> {
+    criterion_home: &'a Path,
+    data_directory: &'a Path,
+    report_directory: &'a Path,
+    manifest_directory: &'a Path,
+    timeline: &'a str,
+    compare_against: String,
+    save_baseline: Option<&'a str>,
+    output_format: String,
+    text_color: String,
+    plotting_backend: String,
+    message_format: Option<String>,
+    message_path: Option<&'a Path>,
+    upload_url: Option<&'a str>,
+    // Only the names of the headers, since their values may be secrets.
+    upload_headers: Vec<&'a str>,
+    upload_required: bool,
+    log_format: String,
+    storage_format: String,
+    sample_storage: String,
+    dir_name_strategy: String,
+    significance_test: String,
+    compare_metric: String,
+    measure: String,
+    sort_by: String,
+    report_title: Option<&'a str>,
+    report_description: Option<&'a str>,
+    time_unit: String,
+    debug_build: bool,
+    profile: &'a str,
+    bench_seed: Option<u64>,
+    single_thread: bool,
+    connect_port: Option<u16>,
+    fail_fast: bool,
+    run_retries: usize,
+    deny_duplicate_ids: bool,
+    allow_duplicate_ids: &'a [String],
+    no_missing_benchmarks: bool,
+    budget_statistic: String,
+    cargo_args: Vec<String>,
+    additional_args: Vec<String>,
+    benchmark_overrides: &'a [BenchmarkOverride],
+    budgets: &'a [Budget],
+}
+
+/// The name of a setting which is written on the command line as its variant name in lowercase.
+fn setting_name(value: &dyn std::fmt::Debug) -> String {
+    format!("{:?}", value).to_lowercase()
+}
+
+/// Print the fully-resolved configuration in the given format.
+pub fn dump_config(config: &FullConfig, format: ConfigFormat) -> Result<()> {
+    let self_config = &config.self_config;
+    let to_strings = |args: &[std::ffi::OsString]| -> Vec<String> {
+        args.iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    };
+    let effective = EffectiveConfig {
+        criterion_home: &self_config.criterion_home,
+        data_directory: &self_config.data_directory,
+        report_directory: &self_config.report_directory,
+        manifest_directory: &self_config.manifest_directory,
+        timeline: &self_config.timeline,
+        compare_against: self_config.compare_against.to_string(),
+        save_baseline: self_config.save_baseline.as_deref(),
+        output_format: self_config.output_format.to_string(),
+        text_color: setting_name(&self_config.text_color),
+        plotting_backend: setting_name(&self_config.plotting_backend),
+        message_format: (self_config.message_format.as_ref()).map(ToString::to_string),
+        message_path: self_config.message_path.as_deref(),
+        upload_url: self_config.upload_url.as_deref(),
+        upload_headers: (self_config.upload_headers.iter())
+            .map(|(name, _)| name.as_str())
+            .collect(),
+        upload_required: self_config.upload_required,
+        log_format: setting_name(&self_config.log_format),
+        storage_format: self_config.storage_format.to_string(),
+        sample_storage: setting_name(&self_config.sample_storage),
+        dir_name_strategy: setting_name(&self_config.dir_name_strategy),
+        significance_test: self_config.significance_test.to_string(),
+        compare_metric: self_config.compare_metric.to_string(),
+        measure: setting_name(&self_config.measure),
+        sort_by: setting_name(&self_config.sort_by),
+        report_title: self_config.report_title.as_deref(),
+        report_description: self_config.report_description.as_deref(),
+        time_unit: self_config.time_unit.to_string(),
+        debug_build: self_config.debug_build,
+        profile: &self_config.profile,
+        bench_seed: self_config.bench_seed,
+        single_thread: self_config.single_thread,
+        connect_port: self_config.connect_port,
+        fail_fast: self_config.do_fail_fast,
+        run_retries: self_config.run_retries,
+        deny_duplicate_ids: self_config.deny_duplicate_ids,
+        allow_duplicate_ids: &self_config.allow_duplicate_ids,
+        no_missing_benchmarks: self_config.no_missing_benchmarks,
+        budget_statistic: self_config.budget_statistic.to_string(),
+        cargo_args: to_strings(&config.cargo_args),
+        additional_args: to_strings(&config.additional_args),
+        benchmark_overrides: &self_config.benchmark_overrides,
+        budgets: &self_config.budgets,
+    };
+
+    let stdout = stdout();
+    let mut out = stdout.lock();
+    match format {
+        ConfigFormat::Toml => write!(out, "{}", toml::to_string_pretty(&effective)?)?,
+        ConfigFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &effective)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check all of the stored data in the data directory and print a report of the problems found.
+/// Returns an error if any of the data is corrupt.
+pub fn doctor(data_directory: &Path) -> Result<()> {
+    let health = check_stored_data(data_directory);
+    let stdout = stdout();
+    let mut out = stdout.lock();
+
+    writeln!(
+        out,
+        "Checked {} benchmarks and {} measurements in {:?}",
+        health.benchmark_count, health.measurement_count, data_directory
+    )?;
+    for problem in &health.problems {
+        let severity = if problem.is_corruption() {
+            "error"
+        } else {
+            "warning"
+        };
+        writeln!(out, "{}: {}", severity, problem)?;
+    }
+
+    let corrupt_count = health
+        .problems
+        .iter()
+        .filter(|problem| problem.is_corruption())
+        .count();
+    if corrupt_count > 0 {
+        return Err(RunError::DataCorruption {
+            path: data_directory.to_owned(),
+            problem_count: corrupt_count,
+        }
+        .into());
+    }
+    writeln!(out, "No problems found with the stored data")?;
+    Ok(())
+}
+
+/// Convert all of the stored data in the data directory to the given storage format and print how
+/// much was converted. With `dry_run`, only prints how much would be converted.
+pub fn migrate(data_directory: &Path, to: StorageFormat, dry_run: bool) -> Result<()> {
+    let summary = migrate_stored_data(data_directory, to, dry_run)?;
+    let action = if dry_run {
+        "Would convert"
+    } else {
+        "Converted"
+    };
+    println!(
+        "{} {} measurements of {} benchmarks in {:?}; {} benchmarks were already stored as {}",
+        action,
+        summary.converted_measurements,
+        summary.migrated_benchmarks,
+        data_directory,
+        summary.unchanged_benchmarks,
+        to
+    );
+    Ok(())
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum ComparisonStatus {
+    /// The benchmark has both a baseline and a new measurement.
+    Compared,
+    /// The benchmark only has a new measurement.
+    Added,
+    /// The benchmark only has a baseline.
+    Removed,
+}
+
+#[derive(Serialize, Debug)]
+struct ChangeInterval {
+    lower: f64,
+    point: f64,
+    upper: f64,
+}
+
+#[derive(Serialize, Debug)]
+struct Change {
+    /// The relative change in the mean time, as a fraction.
+    mean: ChangeInterval,
+    p_value: f64,
+    significance_level: f64,
+    result: &'static str,
+    /// True if the confidence intervals of the baseline and new times don't overlap.
+    significant: bool,
+}
+
+/// The comparison of one benchmark between the baseline and the new measurements. Times are the
+/// typical estimates, in nanoseconds.
+#[derive(Serialize, Debug)]
+struct BaselineComparison {
+    id: String,
+    status: ComparisonStatus,
+    baseline_time: Option<f64>,
+    new_time: Option<f64>,
+    /// The change in performance. Missing if either measurement was stored without its samples.
+    change: Option<Change>,
+}
+
+/// Compare two stored measurements using the default Criterion.rs settings, since the settings the
+/// benchmarks were measured with aren't stored. Any matching overrides from Criterion.toml are
+/// applied on top of the defaults.
+fn compare_measurements(
+    id: &str,
+    new: &SavedStatistics,
+    old: &SavedStatistics,
+    overrides: &[BenchmarkOverride],
+    significance_test: SignificanceTest,
+) -> Option<Change> {
+    fn measured_values(stats: &SavedStatistics) -> MeasuredValues<'_> {
+        MeasuredValues {
+            iteration_count: &stats.iterations,
+            sample_values: &stats.values,
+            avg_values: &stats.avg_values,
+        }
+    }
+    let (new_values, old_values) = (measured_values(new), measured_values(old));
+    if check_measured_values(&new_values).is_err() || check_measured_values(&old_values).is_err() {
+        return None;
+    }
+
+    let config = BenchmarkConfig::default()
+        .with_overrides(overrides, id)
+        .with_significance_test(significance_test);
+    let (p_value, _, _, relative_estimates, _, _) = crate::analysis::compare(
+        Sample::new(new_values.avg_values),
+        &old_values,
+        None,
+        &config,
+    );
+    let mean = &relative_estimates.mean;
+    let result = if p_value < config.significance_level {
+        match compare_to_threshold(mean, config.noise_threshold) {
+            ComparisonResult::Improved => "Improved",
+            ComparisonResult::Regressed => "Regressed",
+            ComparisonResult::NonSignificant => "Within noise",
+        }
+    } else {
+        "No change"
+    };
+
+    Some(Change {
+        mean: ChangeInterval {
+            lower: mean.confidence_interval.lower_bound,
+            point: mean.point_estimate,
+            upper: mean.confidence_interval.upper_bound,
+        },
+        p_value,
+        significance_level: config.significance_level,
+        result,
+        significant: is_significant_change(&old.estimates, &new.estimates),
+    })
+}
+
+/// Compare the baseline and new measurements of one benchmark, either of which may be missing.
+fn compare_pair(
+    id: &str,
+    baseline: Option<&SavedStatistics>,
+    new: Option<&SavedStatistics>,
+    overrides: &[BenchmarkOverride],
+    significance_test: SignificanceTest,
+) -> BaselineComparison {
+    let typical = |stats: Option<&SavedStatistics>| {
+        stats.map(|stats| stats.estimates.typical().point_estimate)
+    };
+    BaselineComparison {
+        id: id.to_owned(),
+        status: match (baseline, new) {
+            (Some(_), Some(_)) => ComparisonStatus::Compared,
+            (None, _) => ComparisonStatus::Added,
+            (_, None) => ComparisonStatus::Removed,
+        },
+        baseline_time: typical(baseline),
+        new_time: typical(new),
+        change: match (baseline, new) {
+            (Some(baseline), Some(new)) => {
+                compare_measurements(id, new, baseline, overrides, significance_test)
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Pair up the latest measurement of each benchmark in the new model with its baseline in the
+/// baseline model, which must have been loaded to compare against that baseline.
+fn compare_models(
+    baseline: &Model,
+    new: &Model,
+    overrides: &[BenchmarkOverride],
+    significance_test: SignificanceTest,
+) -> Vec<BaselineComparison> {
+    let mut comparisons = Vec::new();
+    let mut seen = HashSet::new();
+    for (group_id, ids) in new.sorted_benchmarks(SortOrder::Name) {
+        for id in ids {
+            let benchmark = &new.groups[group_id].benchmarks[id];
+            // The samples are only needed for this comparison, so load them one at a time.
+            let new_stats = match benchmark.load_latest() {
+                Ok(stats) => stats,
+                Err(e) => {
+                    error!("{:?}", e);
+                    benchmark.latest_stats.clone()
+                }
+            };
+            seen.insert(id.as_title().to_owned());
+            let baseline_stats = baseline.get_comparison_sample(id);
+            comparisons.push(compare_pair(
+                id.as_title(),
+                baseline_stats.as_ref(),
+                Some(&new_stats),
+                overrides,
+                significance_test,
+            ));
+        }
+    }
+
+    for (_, ids) in baseline.sorted_benchmarks(SortOrder::Name) {
+        for id in ids {
+            if seen.contains(id.as_title()) {
+                continue;
+            }
+            if let Some(baseline_stats) = baseline.get_comparison_sample(id) {
+                comparisons.push(compare_pair(
+                    id.as_title(),
+                    Some(&baseline_stats),
+                    None,
+                    overrides,
+                    significance_test,
+                ));
+            }
+        }
+    }
+    comparisons
+}
+
+/// Marks a change whose confidence intervals don't overlap.
+fn significance_note(change: &Change) -> &'static str {
+    if change.significant {
+        " (significant)"
+    } else {
+        ""
+    }
+}
+
+fn comparison_markdown(
+    comparisons: &[BaselineComparison],
+    change_ratio_above: Option<f64>,
+) -> String {
+    let time = |time: Option<f64>| time.map(|t| format::time(t).trim().to_owned());
+    let mut table = String::new();
+    table.push_str("| Benchmark | Baseline | New | Change | Result |\n");
+    table.push_str("|-----------|----------|-----|--------|--------|\n");
+    for comparison in comparisons {
+        let (change, result) = match (&comparison.status, &comparison.change) {
+            (ComparisonStatus::Added, _) => (String::new(), "Added".to_owned()),
+            (ComparisonStatus::Removed, _) => (String::new(), "Removed".to_owned()),
+            (ComparisonStatus::Compared, Some(change)) => (
+                format::time_change(change.mean.point, change_ratio_above)
+                    .trim()
+                    .to_owned(),
+                format!("{}{}", change.result, significance_note(change)),
+            ),
+            (ComparisonStatus::Compared, None) => (String::new(), "Not comparable".to_owned()),
+        };
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            escape_cell(&comparison.id),
+            time(comparison.baseline_time).unwrap_or_default(),
+            time(comparison.new_time).unwrap_or_default(),
+            change,
+            result
+        ));
+    }
+    table
+}
+
+fn write_comparison_text(
+    out: &mut dyn Write,
+    comparisons: &[BaselineComparison],
+    change_ratio_above: Option<f64>,
+) -> Result<()> {
+    if comparisons.is_empty() {
+        writeln!(out, "No stored measurements or baselines to compare")?;
+    }
+    for comparison in comparisons {
+        match (&comparison.status, &comparison.change) {
+            (ComparisonStatus::Added, _) => writeln!(out, "{:<24}added", comparison.id)?,
+            (ComparisonStatus::Removed, _) => writeln!(out, "{:<24}removed", comparison.id)?,
+            (ComparisonStatus::Compared, change) => {
+                writeln!(
+                    out,
+                    "{:<24}time:   {} -> {}",
+                    comparison.id,
+                    format::time(comparison.baseline_time.unwrap_or_default()),
+                    format::time(comparison.new_time.unwrap_or_default()),
+                )?;
+                match change {
+                    Some(change) => writeln!(
+                        out,
+                        "{}change: [{} {} {}] (p = {:.2} {} {:.2}) {}{}",
+                        " ".repeat(24),
+                        format::time_change(change.mean.lower, change_ratio_above),
+                        format::time_change(change.mean.point, change_ratio_above),
+                        format::time_change(change.mean.upper, change_ratio_above),
+                        change.p_value,
+                        if change.p_value < change.significance_level {
+                            "<"
+                        } else {
+                            ">"
+                        },
+                        change.significance_level,
+                        change.result,
+                        significance_note(change)
+                    )?,
+                    None => writeln!(
+                        out,
+                        "{}change: not comparable; a measurement has no raw samples",
+                        " ".repeat(24)
+                    )?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print a comparison of the latest measurements in the new model against the baseline that the
+/// baseline model was loaded with.
+pub fn compare_baseline(
+    baseline: &Model,
+    new: &Model,
+    format: ComparisonFormat,
+    overrides: &[BenchmarkOverride],
+    significance_test: SignificanceTest,
+    change_ratio_above: Option<f64>,
+) -> Result<()> {
+    let comparisons = compare_models(baseline, new, overrides, significance_test);
+    print_comparisons(&comparisons, format, change_ratio_above)
+}
+
+/// Print a comparison of the measurements of each benchmark recorded against two commits (ie.
+/// `--history-id`s). Each commit is matched by prefix, as with `--compare-against commit:SHA`, and
+/// if a benchmark was measured more than once against a commit, its most recent measurement there
+/// is used. Benchmarks which were only measured against one of the commits are listed as added
+/// (only measured at the head) or removed (only measured at the base).
+pub fn compare_commits(
+    model: &Model,
+    base: &str,
+    head: &str,
+    format: ComparisonFormat,
+    overrides: &[BenchmarkOverride],
+    significance_test: SignificanceTest,
+    change_ratio_above: Option<f64>,
+) -> Result<()> {
+    let at_commit = |history: &[SavedStatistics], commit: &str| -> Option<SavedStatistics> {
+        history
+            .iter()
+            .rev()
+            .find(|stats| matches!(&stats.history_id, Some(id) if id.starts_with(commit)))
+            .cloned()
+    };
+    let (mut found_base, mut found_head) = (false, false);
+    let mut comparisons = Vec::new();
+    for (_, ids) in model.sorted_benchmarks(SortOrder::Name) {
+        for id in ids {
+            let history = model.load_history(id)?;
+            let base_stats = at_commit(&history, base);
+            let head_stats = at_commit(&history, head);
+            found_base |= base_stats.is_some();
+            found_head |= head_stats.is_some();
+            if base_stats.is_none() && head_stats.is_none() {
+                continue;
+            }
+            comparisons.push(compare_pair(
+                id.as_title(),
+                base_stats.as_ref(),
+                head_stats.as_ref(),
+                overrides,
+                significance_test,
+            ));
+        }
+    }
+    for (found, commit) in &[(found_base, base), (found_head, head)] {
+        if !found {
+            anyhow::bail!(
+                "No stored measurements were recorded against commit {:?}. Run `cargo criterion \
+                commits` to list the commits in the stored history.",
+                commit
+            );
+        }
+    }
+    print_comparisons(&comparisons, format, change_ratio_above)
+}
+
+/// Print the comparisons to stdout in the given format.
+fn print_comparisons(
+    comparisons: &[BaselineComparison],
+    format: ComparisonFormat,
+    change_ratio_above: Option<f64>,
+) -> Result<()> {
+    let stdout = stdout();
+    let mut out = stdout.lock();
+    match format {
+        ComparisonFormat::Text => write_comparison_text(&mut out, comparisons, change_ratio_above)?,
+        ComparisonFormat::Markdown => write!(
+            out,
+            "{}",
+            comparison_markdown(comparisons, change_ratio_above)
+        )?,
+        ComparisonFormat::Json => {
+            serde_json::to_writer(&mut out, comparisons)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_commit_summaries() {
+        let measurements = vec![
+            ("a", "def", "2026-01-03T00:00:00Z"),
+            ("a", "abc", "2026-01-01T00:00:00Z"),
+            ("b", "abc", "2026-01-02T00:00:00Z"),
+            ("b", "abc", "2026-01-02T12:00:00Z"),
+        ];
+        let summaries = commit_summaries(
+            measurements
+                .into_iter()
+                .map(|(title, commit, datetime)| (title, commit, datetime.parse().unwrap())),
+        );
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].commit, "abc");
+        assert_eq!(summaries[0].benchmark_count, 2);
+        assert_eq!(summaries[0].measurement_count, 3);
+        assert_eq!(
+            summaries[0].first_measured.to_rfc3339(),
+            "2026-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            summaries[0].last_measured.to_rfc3339(),
+            "2026-01-02T12:00:00+00:00"
+        );
+        assert_eq!(summaries[1].commit, "def");
+        assert_eq!(summaries[1].benchmark_count, 1);
+    }
+
+    #[test]
+    fn test_write_groups() {
+        let groups = [
+            GroupSummary {
+                name: "parsing",
+                benchmark_count: 1,
+            },
+            GroupSummary {
+                name: "sum",
+                benchmark_count: 3,
+            },
+        ];
+        let mut text = Vec::new();
+        write_groups(&mut text, &groups, false).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "parsing (1 benchmark)\nsum (3 benchmarks)\n"
+        );
+        let mut json = Vec::new();
+        write_groups(&mut json, &groups, true).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[{\"name\":\"parsing\",\"benchmark_count\":1},\
+             {\"name\":\"sum\",\"benchmark_count\":3}]\n"
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_names_outside_of_the_data() {
+        let directory = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-import",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let (data_directory, report_directory) =
+            (directory.join("data"), directory.join("reports"));
+        let import_entry = |name: &str| {
+            let bundle_path = directory.join("bundle.tar");
+            let entry = bundle::Entry {
+                name: name.to_owned(),
+                contents: b"contents".to_vec(),
+            };
+            bundle::write(&bundle_path, &[entry]).unwrap();
+            import(
+                &bundle_path,
+                &data_directory,
+                &report_directory,
+                false,
+                false,
+            )
+        };
+
+        for name in [
+            "data/../escaped",
+            "data/main/../../escaped",
+            "reports/..",
+            "/escaped",
+            "data//escaped",
+            "escaped",
+        ] {
+            assert!(import_entry(name).is_err(), "{:?} was imported", name);
+        }
+        assert!(!directory.join("escaped").exists());
+        assert!(!data_directory.exists());
+
+        import_entry("data/main/sum/benchmark.cbor").unwrap();
+        assert!(path!(&data_directory, "main", "sum", "benchmark.cbor").is_file());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_comparison_markdown_lists_added_and_removed() {
+        let comparisons = [
+            BaselineComparison {
+                id: "a".to_owned(),
+                status: ComparisonStatus::Compared,
+                baseline_time: Some(2.0),
+                new_time: Some(1.0),
+                change: Some(Change {
+                    mean: ChangeInterval {
+                        lower: -0.6,
+                        point: -0.5,
+                        upper: -0.4,
+                    },
+                    p_value: 0.01,
+                    significance_level: 0.05,
+                    result: "Improved",
+                    significant: true,
+                }),
+            },
+            BaselineComparison {
+                id: "b".to_owned(),
+                status: ComparisonStatus::Added,
+                baseline_time: None,
+                new_time: Some(1.0),
+                change: None,
+            },
+            BaselineComparison {
+                id: "c".to_owned(),
+                status: ComparisonStatus::Removed,
+                baseline_time: Some(1.0),
+                new_time: None,
+                change: None,
+            },
+        ];
+        assert_eq!(
+            "| Benchmark | Baseline | New | Change | Result |\n\
+             |-----------|----------|-----|--------|--------|\n\
+             | a | 2.0000 ns | 1.0000 ns | -50.000% | Improved (significant) |\n\
+             | b |  | 1.0000 ns |  | Added |\n\
+             | c | 1.0000 ns |  |  | Removed |\n",
+            comparison_markdown(&comparisons, None)
+        );
+    }
+}
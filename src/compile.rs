@@ -3,14 +3,24 @@
 //! emits.
 
 use crate::bench_target::BenchTarget;
+use crate::config::{cargo_program, BudgetStatistic, CompareMetric, SignificanceTest};
+use crate::error::RunError;
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
 
 #[derive(Debug)]
 /// Enum representing the different ways calling Cargo might fail
 pub enum CompileError {
     CompileFailed(ExitStatus),
+    BuildScriptFailed {
+        exit_status: ExitStatus,
+        packages: Vec<String>,
+    },
 }
 impl std::fmt::Display for CompileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -20,6 +30,16 @@ impl std::fmt::Display for CompileError {
                 "'cargo bench' returned an error ({}); unable to continue.",
                 exit_status
             ),
+            CompileError::BuildScriptFailed {
+                exit_status,
+                packages,
+            } => write!(
+                f,
+                "'cargo bench' returned an error ({}) because the build script for {} failed; \
+                see the build script output above. Unable to continue.",
+                exit_status,
+                packages.join(", ")
+            ),
         }
     }
 }
@@ -27,96 +47,184 @@ impl std::error::Error for CompileError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             CompileError::CompileFailed(_) => None,
+            CompileError::BuildScriptFailed { .. } => None,
         }
     }
 }
 
 // These structs match the parts of Cargo's message format that we care about.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 struct Target {
     name: String,
     kind: Vec<String>,
 }
 
-/// Enum listing out the different types of messages that Cargo can send. We only care about the
-/// compiler-artifact message.
+#[derive(Serialize, Deserialize, Debug)]
+struct Diagnostic {
+    level: String,
+    #[serde(default)]
+    rendered: Option<String>,
+}
+
+/// Enum listing out the different types of messages that Cargo can send. We mostly care about the
+/// compiler-artifact message, but the others are used to work out why a build failed.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "reason")]
 #[allow(clippy::enum_variant_names)]
 enum Message {
     #[serde(rename = "compiler-artifact")]
     CompilerArtifact {
+        package_id: String,
         target: Target,
         executable: Option<PathBuf>,
+        #[serde(default)]
+        features: Vec<String>,
+        #[serde(default)]
+        manifest_path: Option<PathBuf>,
     },
 
     // TODO: Delete these and replace with a #[serde(other)] variant
     // See https://github.com/serde-rs/serde/issues/912
     #[serde(rename = "compiler-message")]
-    CompilerMessage {},
+    CompilerMessage { message: Diagnostic },
 
     #[serde(rename = "build-script-executed")]
-    BuildScriptExecuted { linked_paths: Vec<String> },
+    BuildScriptExecuted {
+        package_id: String,
+        linked_paths: Vec<String>,
+    },
 
     #[serde(rename = "build-finished")]
     BuildFinished {},
-}
 
-#[derive(Debug)]
-pub struct CompiledBenchmarks {
-    pub targets: Vec<BenchTarget>,
-    pub library_paths: Vec<PathBuf>,
+    /// Only sent with `-Zunstable-options --timings=json`.
+    #[serde(rename = "timing-info")]
+    TimingInfo {
+        package_id: String,
+        target: Target,
+        mode: String,
+        duration: f64,
+    },
 }
 
-/// Launches `cargo bench` with the given additional arguments, with some additional arguments to
-/// list out the benchmarks and their executables and parses that information. This compiles the
-/// benchmarks but doesn't run them. Returns information on the compiled benchmarks that we can use
-/// to run them directly.
-pub fn compile(debug_build: bool, cargo_args: &[std::ffi::OsString]) -> Result<CompiledBenchmarks> {
-    let subcommand: &[&'static str] = if debug_build {
-        &["test", "--benches"]
-    } else {
-        &["bench"]
-    };
+/// The information collected from Cargo's message stream.
+#[derive(Debug, Default)]
+struct CollectedMessages {
+    targets: Vec<BenchTarget>,
+    library_paths: Vec<PathBuf>,
+    /// Packages whose build script was compiled but never reported as executed.
+    unexecuted_build_scripts: Vec<String>,
+    /// Did the compiler report any errors?
+    saw_compiler_error: bool,
+}
 
-    let mut cargo = Command::new("cargo")
-        .args(subcommand)
-        .args(cargo_args)
-        .args(&["--no-run", "--message-format", "json-render-diagnostics"])
-        .stdin(Stdio::null())
-        .stderr(Stdio::inherit()) // Cargo writes its normal compile output to stderr
-        .stdout(Stdio::piped()) // Capture the JSON messages on stdout
-        .spawn()?;
+/// Identifies a compiled target, to match up Cargo's timing messages with its artifacts.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct UnitKey {
+    package_id: String,
+    target: Target,
+}
+impl CollectedMessages {
+    /// Work out the most specific error to report for a failed build.
+    fn failure(self, exit_status: ExitStatus) -> CompileError {
+        if !self.saw_compiler_error && !self.unexecuted_build_scripts.is_empty() {
+            CompileError::BuildScriptFailed {
+                exit_status,
+                packages: self.unexecuted_build_scripts,
+            }
+        } else {
+            CompileError::CompileFailed(exit_status)
+        }
+    }
+}
 
-    // Build a message stream reading from the child process
-    let cargo_stdout = cargo
-        .stdout
-        .take()
-        .expect("Child process doesn't have a stdout handle");
-    let stream = serde_json::Deserializer::from_reader(cargo_stdout).into_iter::<Message>();
+/// Read Cargo's JSON messages from the given stream and collect the parts we care about. If
+/// log_diagnostics is true, the compiler's warnings and errors are passed on to the logger.
+fn collect_messages<R: std::io::Read>(
+    reader: R,
+    log_diagnostics: bool,
+) -> Result<CollectedMessages> {
+    let stream = serde_json::Deserializer::from_reader(reader).into_iter::<Message>();
 
-    // Collect the benchmark artifacts from the message stream
-    let mut targets = vec![];
-    let mut library_paths = vec![];
+    let mut collected = CollectedMessages::default();
+    let mut artifact_keys = Vec::new();
+    let mut compile_times = HashMap::new();
     for message in stream {
         let message = message.context("Failed to parse message from cargo")?;
         match message {
-            Message::CompilerArtifact { target, executable } => {
-                if target
+            Message::CompilerArtifact {
+                package_id,
+                target,
+                executable,
+                mut features,
+                manifest_path,
+            } => {
+                if target.kind.iter().any(|kind| kind == "custom-build") {
+                    collected.unexecuted_build_scripts.push(package_id);
+                } else if target
                     .kind
                     .iter()
                     // Benchmarks and tests have executables. Libraries might, if they expose tests.
                     .any(|kind| kind == "bench" || kind == "test" || kind == "lib")
                 {
                     if let Some(executable) = executable {
-                        targets.push(BenchTarget {
+                        artifact_keys.push(UnitKey {
+                            package_id,
+                            target: target.clone(),
+                        });
+                        features.sort();
+                        collected.targets.push(BenchTarget {
                             name: target.name,
                             executable,
+                            kind: target.kind,
+                            features,
+                            package_directory: manifest_path
+                                .as_deref()
+                                .and_then(Path::parent)
+                                .map(ToOwned::to_owned),
+                            working_directory: None,
+                            fingerprint: None,
+                            benchmark_overrides: Vec::new(),
+                            cpu_affinity: Vec::new(),
+                            bench_version: None,
+                            bench_seed: None,
+                            single_thread: false,
+                            connect_port: None,
+                            budgets: Vec::new(),
+                            budget_statistic: BudgetStatistic::default(),
+                            profile: None,
+                            compile_time: None,
+                            min_samples: 0,
+                            significance_test: SignificanceTest::default(),
+                            compare_metric: CompareMetric::default(),
+                            reject_new_benchmarks: false,
+                            run_retries: 0,
                         });
                     }
                 }
             }
-            Message::BuildScriptExecuted { linked_paths } => {
+            Message::CompilerMessage { message } => {
+                if message.level == "error" {
+                    collected.saw_compiler_error = true;
+                }
+                if let (true, Some(rendered)) = (log_diagnostics, &message.rendered) {
+                    let rendered = rendered.trim_end();
+                    match message.level.as_str() {
+                        "error" | "error: internal compiler error" => {
+                            error!(target: "compiler", "{}", rendered)
+                        }
+                        "warning" => warn!(target: "compiler", "{}", rendered),
+                        _ => info!("{}", rendered),
+                    }
+                }
+            }
+            Message::BuildScriptExecuted {
+                package_id,
+                linked_paths,
+            } => {
+                collected
+                    .unexecuted_build_scripts
+                    .retain(|id| id != &package_id);
                 for path in linked_paths {
                     let path = path
                         .replace("dependency=", "")
@@ -125,24 +233,381 @@ pub fn compile(debug_build: bool, cargo_args: &[std::ffi::OsString]) -> Result<C
                         .replace("framework=", "")
                         .replace("all=", "");
                     let path = PathBuf::from(path);
-                    library_paths.push(path);
+                    collected.library_paths.push(path);
                 }
             }
+            // Libraries are also built in "build" mode to link the benchmarks against, but only the
+            // test harness has an executable.
+            Message::TimingInfo {
+                package_id,
+                target,
+                mode,
+                duration,
+            } if mode == "bench" || mode == "test" => {
+                compile_times.insert(UnitKey { package_id, target }, duration);
+            }
             _ => (),
         }
     }
 
-    targets.sort_by(|target1, target2| (target1.name).cmp(&target2.name));
+    for (target, key) in collected.targets.iter_mut().zip(&artifact_keys) {
+        target.compile_time = compile_times.get(key).copied();
+    }
+    collected
+        .targets
+        .sort_by(|target1, target2| (target1.name).cmp(&target2.name));
+    Ok(collected)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompiledBenchmarks {
+    pub targets: Vec<BenchTarget>,
+    pub library_paths: Vec<PathBuf>,
+    /// How long `cargo bench --no-run` took, or None if the benchmarks weren't compiled during
+    /// this run.
+    #[serde(skip)]
+    pub compile_time: Option<Duration>,
+}
+
+/// Constructs the `cargo bench` command used to compile the benchmarks. All of the user's cargo
+/// arguments (including `--offline`, `--frozen` and `--locked`) are passed through unchanged. If
+/// log_diagnostics is true, Cargo is asked not to render the compiler's diagnostics so that they
+/// can be logged instead.
+fn compile_command(
+    debug_build: bool,
+    log_diagnostics: bool,
+    cargo_args: &[std::ffi::OsString],
+) -> Command {
+    let subcommand: &[&'static str] = if debug_build {
+        &["test", "--benches"]
+    } else {
+        &["bench"]
+    };
+
+    let mut command = Command::new(cargo_program());
+    command
+        .args(subcommand)
+        .args(cargo_args)
+        .args(["--no-run", "--message-format"])
+        .arg(if log_diagnostics {
+            "json"
+        } else {
+            "json-render-diagnostics"
+        });
+    command
+}
+
+/// Launches `cargo bench` with the given additional arguments, with some additional arguments to
+/// list out the benchmarks and their executables and parses that information. This compiles the
+/// benchmarks but doesn't run them. Returns information on the compiled benchmarks that we can use
+/// to run them directly. If log_diagnostics is true, the compiler's warnings and errors are
+/// printed through the logger rather than by Cargo. Either way the errors have been shown by the
+/// time a failed build returns, so there's no need to run Cargo again to display them.
+pub fn compile(
+    debug_build: bool,
+    log_diagnostics: bool,
+    cargo_args: &[std::ffi::OsString],
+) -> Result<CompiledBenchmarks> {
+    let started = Instant::now();
+    let mut cargo = compile_command(debug_build, log_diagnostics, cargo_args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit()) // Cargo writes its normal compile output to stderr
+        .stdout(Stdio::piped()) // Capture the JSON messages on stdout
+        .spawn()?;
+
+    // Collect the benchmark artifacts from the message stream
+    let cargo_stdout = cargo
+        .stdout
+        .take()
+        .expect("Child process doesn't have a stdout handle");
+    let collected = collect_messages(cargo_stdout, log_diagnostics)?;
 
     let exit_status = cargo
         .wait()
         .context("Cargo compilation failed in an unexpected way")?;
     if !(exit_status.success()) {
-        Err(CompileError::CompileFailed(exit_status).into())
+        Err(RunError::CompileFailed(collected.failure(exit_status)).into())
     } else {
         Ok(CompiledBenchmarks {
-            targets,
-            library_paths,
+            targets: collected.targets,
+            library_paths: collected.library_paths,
+            compile_time: Some(started.elapsed()),
         })
     }
 }
+
+/// The compiled benchmark list saved by `--reuse-compile`, along with the information needed to
+/// tell whether it's still valid.
+#[derive(Debug, Serialize, Deserialize)]
+struct CompileCache<B> {
+    debug_build: bool,
+    cargo_args: Vec<String>,
+    /// The time at which the compile that produced this list was started.
+    compiled_at: SystemTime,
+    benchmarks: B,
+}
+
+fn cargo_args_key(cargo_args: &[std::ffi::OsString]) -> Vec<String> {
+    cargo_args
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Find the most recent modification time of any Rust source file or Cargo manifest under the
+/// given directory, skipping target directories and hidden directories.
+fn newest_source_mtime(source_root: &Path) -> Option<SystemTime> {
+    WalkDir::new(source_root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name == "target" || name.starts_with('.'))
+        })
+        // Ignore errors.
+        .filter_map(::std::result::Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.file_type().is_file()
+                && (name.ends_with(".rs") || name == "Cargo.toml" || name == "Cargo.lock")
+        })
+        .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+        .max()
+}
+
+/// Load the benchmark list saved by a previous compile, if the cache exists and is still valid
+/// for these arguments and sources. Returns None if the benchmarks need to be recompiled.
+pub fn load_cached(
+    cache_path: &Path,
+    source_root: &Path,
+    debug_build: bool,
+    cargo_args: &[std::ffi::OsString],
+) -> Option<CompiledBenchmarks> {
+    let cache: CompileCache<CompiledBenchmarks> = File::open(cache_path)
+        .ok()
+        .and_then(|mut file| serde_cbor::from_reader(&mut file).ok())?;
+
+    if cache.debug_build != debug_build || cache.cargo_args != cargo_args_key(cargo_args) {
+        info!("Compile cache was created with different arguments; recompiling.");
+        return None;
+    }
+    match newest_source_mtime(source_root) {
+        Some(mtime) if mtime <= cache.compiled_at => (),
+        _ => {
+            info!("Sources have changed since the compile cache was created; recompiling.");
+            return None;
+        }
+    }
+    if !cache
+        .benchmarks
+        .targets
+        .iter()
+        .all(|target| target.executable.is_file())
+    {
+        info!("Cached benchmark executables are missing; recompiling.");
+        return None;
+    }
+    Some(cache.benchmarks)
+}
+
+/// Save the benchmark list so that a later run with `--reuse-compile` can skip compiling.
+/// `compiled_at` should be the time at which the compile was started.
+pub fn save_cached(
+    cache_path: &Path,
+    compiled_at: SystemTime,
+    debug_build: bool,
+    cargo_args: &[std::ffi::OsString],
+    benchmarks: &CompiledBenchmarks,
+) -> Result<()> {
+    let cache = CompileCache {
+        debug_build,
+        cargo_args: cargo_args_key(cargo_args),
+        compiled_at,
+        benchmarks,
+    };
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    let mut cache_file = File::create(cache_path)
+        .with_context(|| format!("Failed to create compile cache file {:?}", cache_path))?;
+    serde_cbor::to_writer(&mut cache_file, &cache)
+        .with_context(|| format!("Failed to save compile cache file {:?}", cache_path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compile_command_forwards_network_args() {
+        let cargo_args: Vec<std::ffi::OsString> =
+            vec!["--offline".into(), "--frozen".into(), "--locked".into()];
+        let command = compile_command(false, false, &cargo_args);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "bench",
+                "--offline",
+                "--frozen",
+                "--locked",
+                "--no-run",
+                "--message-format",
+                "json-render-diagnostics"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_command_forwards_profile() {
+        let cargo_args: Vec<std::ffi::OsString> = vec!["--profile".into(), "release-lto".into()];
+        let command = compile_command(false, false, &cargo_args);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "bench",
+                "--profile",
+                "release-lto",
+                "--no-run",
+                "--message-format",
+                "json-render-diagnostics"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_command_leaves_diagnostics_to_logger() {
+        let command = compile_command(true, true, &[]);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["test", "--benches", "--no-run", "--message-format", "json"]
+        );
+    }
+
+    #[test]
+    fn test_unexecuted_build_script_is_reported() {
+        let messages = r#"
+            {"reason":"compiler-artifact","package_id":"dep 0.1.0","target":{"name":"build-script-build","kind":["custom-build"]},"executable":null}
+            {"reason":"compiler-artifact","package_id":"other 0.1.0","target":{"name":"build-script-build","kind":["custom-build"]},"executable":null}
+            {"reason":"build-script-executed","package_id":"other 0.1.0","linked_paths":["native=/some/path"]}
+            {"reason":"build-finished","success":false}
+        "#;
+        let collected = collect_messages(messages.as_bytes(), false).unwrap();
+        assert_eq!(collected.library_paths, [PathBuf::from("/some/path")]);
+        match collected.failure(ExitStatus::default()) {
+            CompileError::BuildScriptFailed { packages, .. } => assert_eq!(packages, ["dep 0.1.0"]),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_artifact_features_are_recorded() {
+        let messages = r#"
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","target":{"name":"bench","kind":["bench"]},"features":["simd","default"],"executable":"/target/bench"}
+            {"reason":"build-finished","success":true}
+        "#;
+        let collected = collect_messages(messages.as_bytes(), false).unwrap();
+        assert_eq!(collected.targets.len(), 1);
+        assert_eq!(collected.targets[0].features, ["default", "simd"]);
+    }
+
+    #[test]
+    fn test_artifact_kind_is_recorded() {
+        let messages = r#"
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","target":{"name":"bench","kind":["bench"]},"executable":"/target/bench"}
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","target":{"name":"pkg","kind":["lib"]},"executable":"/target/pkg"}
+            {"reason":"build-finished","success":true}
+        "#;
+        let collected = collect_messages(messages.as_bytes(), false).unwrap();
+        let benches: Vec<_> = collected
+            .targets
+            .iter()
+            .filter(|target| target.is_bench())
+            .map(|target| target.name.as_str())
+            .collect();
+        assert_eq!(benches, ["bench"]);
+    }
+
+    #[test]
+    fn test_timing_info_is_attributed_to_targets() {
+        let messages = r#"
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","target":{"name":"pkg","kind":["lib"]},"executable":null}
+            {"reason":"timing-info","package_id":"pkg 0.1.0","target":{"name":"pkg","kind":["lib"]},"mode":"build","duration":1.5,"rmeta_time":0.5}
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","target":{"name":"pkg","kind":["lib"]},"executable":"/target/pkg"}
+            {"reason":"timing-info","package_id":"pkg 0.1.0","target":{"name":"pkg","kind":["lib"]},"mode":"bench","duration":2.0}
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","target":{"name":"bench","kind":["bench"]},"executable":"/target/bench"}
+            {"reason":"build-finished","success":true}
+        "#;
+        let collected = collect_messages(messages.as_bytes(), false).unwrap();
+        let times: Vec<_> = collected
+            .targets
+            .iter()
+            .map(|target| (target.name.as_str(), target.compile_time))
+            .collect();
+        assert_eq!(times, [("bench", None), ("pkg", Some(2.0))]);
+    }
+
+    #[test]
+    fn test_artifact_package_directory_is_recorded() {
+        let messages = r#"
+            {"reason":"compiler-artifact","package_id":"pkg 0.1.0","manifest_path":"/work/pkg/Cargo.toml","target":{"name":"bench","kind":["bench"]},"executable":"/target/bench"}
+            {"reason":"build-finished","success":true}
+        "#;
+        let collected = collect_messages(messages.as_bytes(), false).unwrap();
+        assert_eq!(
+            collected.targets[0].package_directory,
+            Some(PathBuf::from("/work/pkg"))
+        );
+    }
+
+    #[test]
+    fn test_compiler_error_is_not_blamed_on_build_script() {
+        let messages = r#"
+            {"reason":"compiler-artifact","package_id":"dep 0.1.0","target":{"name":"build-script-build","kind":["custom-build"]},"executable":null}
+            {"reason":"compiler-message","message":{"level":"error","rendered":"error: oops"}}
+            {"reason":"build-finished","success":false}
+        "#;
+        let collected = collect_messages(messages.as_bytes(), false).unwrap();
+        match collected.failure(ExitStatus::default()) {
+            CompileError::CompileFailed(_) => (),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compile_cache_is_invalidated() {
+        let root = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-compile-cache",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "").unwrap();
+        let source_mtime = newest_source_mtime(&root).unwrap();
+        let cache_path = root.join("target/criterion/compile_cache.cbor");
+        let cargo_args: Vec<std::ffi::OsString> = vec!["--bench".into(), "parsing".into()];
+        let save = |compiled_at: SystemTime| {
+            let benchmarks = CompiledBenchmarks {
+                targets: vec![],
+                library_paths: vec![PathBuf::from("deps")],
+                compile_time: None,
+            };
+            save_cached(&cache_path, compiled_at, false, &cargo_args, &benchmarks).unwrap();
+        };
+
+        save(source_mtime);
+        let cached = load_cached(&cache_path, &root, false, &cargo_args).unwrap();
+        assert_eq!(cached.library_paths, [PathBuf::from("deps")]);
+        // Compiling in another mode or with other arguments needs a new build.
+        assert!(load_cached(&cache_path, &root, true, &cargo_args).is_none());
+        assert!(load_cached(&cache_path, &root, false, &["--bench".into()]).is_none());
+        // So does a source file changed after the compile started. The target directory doesn't
+        // count as a source.
+        save(source_mtime - Duration::from_secs(1));
+        assert!(load_cached(&cache_path, &root, false, &cargo_args).is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
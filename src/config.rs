@@ -1,4 +1,6 @@
+use crate::message_formats::{parse_header, UploadUrl};
 use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
 use std::borrow::ToOwned;
 use std::ffi::OsString;
 use std::fs::File;
@@ -62,7 +64,7 @@ impl Default for Colors {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 #[serde(default)]
 /// Struct to hold the various configuration settings that we can read from the TOML config file.
 struct TomlConfig {
@@ -72,28 +74,148 @@ struct TomlConfig {
     pub output_format: Option<String>,
     /// Plotting backend
     pub plotting_backend: Option<String>,
+    /// Storage format for new measurements
+    pub storage_format: Option<String>,
+    /// Statistical settings for the benchmarks matching a pattern
+    pub benchmark_overrides: Vec<BenchmarkOverride>,
+    /// Time budgets for the benchmarks matching a pattern
+    pub budgets: Vec<TomlBudget>,
+    /// Patterns matching the benchmark and group IDs which may be encountered more than once
+    pub allow_duplicate_ids: Vec<String>,
+    /// Title of the HTML index and the PDF report
+    pub report_title: Option<String>,
+    /// Description shown under the title of the HTML index and the PDF report
+    pub report_description: Option<String>,
 
     /// The colors used for the charts. Users may wish to override this to accommodate
     /// colorblindness, or just to make things look prettier.
     pub colors: Colors,
 }
-impl Default for TomlConfig {
-    fn default() -> Self {
-        TomlConfig {
-            criterion_home: None,
-            output_format: None,
-            plotting_backend: None,
-            colors: Default::default(),
+
+/// Statistical settings which override those set in the benchmark code, for the benchmarks whose
+/// IDs match a pattern. These are read from `[[benchmark_overrides]]` tables in Criterion.toml.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchmarkOverride {
+    /// The benchmark IDs to apply to. `*` matches any sequence of characters, including `/`.
+    pub benchmarks: String,
+    pub noise_threshold: Option<f64>,
+    pub significance_level: Option<f64>,
+}
+impl BenchmarkOverride {
+    pub fn matches(&self, id: &str) -> bool {
+        pattern_matches(&self.benchmarks, id)
+    }
+}
+
+/// A time budget read from a `[[budgets]]` table in Criterion.toml, before its limit is parsed.
+#[derive(Deserialize, Debug)]
+struct TomlBudget {
+    benchmarks: String,
+    limit: String,
+}
+
+/// The most time per iteration that the benchmarks whose IDs match a pattern may take. These are
+/// given with `--budget` or read from `[[budgets]]` tables in Criterion.toml.
+#[derive(Serialize, Debug, Clone)]
+pub struct Budget {
+    /// The benchmark IDs to apply to. `*` matches any sequence of characters, including `/`.
+    pub benchmarks: String,
+    /// The limit, in nanoseconds.
+    pub limit_ns: f64,
+}
+impl Budget {
+    /// Parse a budget given on the command line as `ID=DURATION`.
+    fn from_arg(s: &str) -> Result<Budget, String> {
+        let split = s
+            .rfind('=')
+            .ok_or_else(|| format!("'{}' is not of the form ID=DURATION", s))?;
+        Ok(Budget {
+            benchmarks: s[..split].to_owned(),
+            limit_ns: parse_duration(&s[split + 1..])?,
+        })
+    }
+}
+
+/// Find the budget of the benchmark with the given ID. If several budgets match, the last one
+/// given applies.
+pub fn budget_for<'a>(budgets: &'a [Budget], id: &str) -> Option<&'a Budget> {
+    budgets
+        .iter()
+        .rev()
+        .find(|budget| pattern_matches(&budget.benchmarks, id))
+}
+
+/// Which estimate of a benchmark's time is checked against its budget.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BudgetStatistic {
+    /// The mean. This is the default.
+    #[default]
+    Mean,
+    /// The upper bound of the confidence interval of the mean, so that a benchmark only passes if
+    /// it is confidently within its budget.
+    UpperBound,
+}
+impl BudgetStatistic {
+    fn from_str(s: &str) -> BudgetStatistic {
+        match s {
+            "mean" => BudgetStatistic::Mean,
+            "upper-bound" => BudgetStatistic::UpperBound,
+            other => panic!("Unknown budget statistic string: {}", other),
+        }
+    }
+}
+impl std::fmt::Display for BudgetStatistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetStatistic::Mean => write!(f, "mean"),
+            BudgetStatistic::UpperBound => write!(f, "upper-bound"),
+        }
+    }
+}
+
+/// Returns true if the benchmark ID matches the pattern, where `*` matches any sequence of
+/// characters, including `/`.
+pub fn pattern_matches(pattern: &str, id: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // There is always at least one part, even if the pattern is empty.
+    let first = parts.next().unwrap();
+    if !id.starts_with(first) {
+        return false;
+    }
+    let mut rest = &id[first.len()..];
+    let mut parts: Vec<&str> = parts.collect();
+    let last = match parts.pop() {
+        Some(last) => last,
+        // No wildcards, so the pattern must match the whole ID.
+        None => return rest.is_empty(),
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
         }
     }
+    rest.ends_with(last)
 }
 
+/// The names accepted by `--output-format`.
+pub const OUTPUT_FORMATS: &[&str] = &[
+    "criterion",
+    "quiet",
+    "verbose",
+    "bencher",
+    "stable-text",
+    "pdf",
+];
+
 #[derive(Debug)]
 pub enum OutputFormat {
     Criterion,
     Quiet,
     Verbose,
     Bencher,
+    StableText,
+    Pdf,
 }
 impl OutputFormat {
     fn from_str(s: &str) -> OutputFormat {
@@ -102,10 +224,24 @@ impl OutputFormat {
             "quiet" => OutputFormat::Quiet,
             "verbose" => OutputFormat::Verbose,
             "bencher" => OutputFormat::Bencher,
+            "stable-text" => OutputFormat::StableText,
+            "pdf" => OutputFormat::Pdf,
             other => panic!("Unknown output format string: {}", other),
         }
     }
 }
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Criterion => write!(f, "criterion"),
+            OutputFormat::Quiet => write!(f, "quiet"),
+            OutputFormat::Verbose => write!(f, "verbose"),
+            OutputFormat::Bencher => write!(f, "bencher"),
+            OutputFormat::StableText => write!(f, "stable-text"),
+            OutputFormat::Pdf => write!(f, "pdf"),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum TextColor {
@@ -124,6 +260,9 @@ impl TextColor {
     }
 }
 
+/// The names accepted by `--plotting-backend`.
+pub const PLOTTING_BACKENDS: &[&str] = &["gnuplot", "plotters", "disabled"];
+
 #[derive(Debug)]
 pub enum PlottingBackend {
     Gnuplot,
@@ -143,26 +282,493 @@ impl PlottingBackend {
     }
 }
 
+/// The names accepted by `--message-format`.
+pub const MESSAGE_FORMATS: &[&str] = &["json", "json-v2"];
+
 #[derive(Debug)]
 pub enum MessageFormat {
     Json,
+    /// The JSON messages, with each estimate written as a `{point, lower, upper, unit}` object.
+    JsonV2,
 }
 impl MessageFormat {
     fn from_str(s: &str) -> MessageFormat {
         match s {
             "json" => MessageFormat::Json,
+            "json-v2" => MessageFormat::JsonV2,
             other => panic!("Unknown message format: {}", other),
         }
     }
 }
+impl std::fmt::Display for MessageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageFormat::Json => write!(f, "json"),
+            MessageFormat::JsonV2 => write!(f, "json-v2"),
+        }
+    }
+}
+
+/// How cargo-criterion's own log messages (errors, warnings and so on) are printed.
+#[derive(Debug, Clone, Copy)]
+pub enum LogFormat {
+    /// Human-readable text. The default.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+impl LogFormat {
+    fn from_str(s: &str) -> LogFormat {
+        match s {
+            "text" => LogFormat::Text,
+            "json" => LogFormat::Json,
+            other => panic!("Unknown log format: {}", other),
+        }
+    }
+}
+
+/// How the `compare-baseline` command prints its report.
+#[derive(Debug, Clone, Copy)]
+pub enum ComparisonFormat {
+    Text,
+    Markdown,
+    Json,
+}
+impl ComparisonFormat {
+    fn from_str(s: &str) -> ComparisonFormat {
+        match s {
+            "text" => ComparisonFormat::Text,
+            "markdown" => ComparisonFormat::Markdown,
+            "json" => ComparisonFormat::Json,
+            other => panic!("Unknown comparison format: {}", other),
+        }
+    }
+}
+
+/// How `--dump-config` prints the configuration.
+#[derive(Debug, Clone, Copy)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+impl ConfigFormat {
+    fn from_str(s: &str) -> ConfigFormat {
+        match s {
+            "toml" => ConfigFormat::Toml,
+            "json" => ConfigFormat::Json,
+            other => panic!("Unknown config format: {}", other),
+        }
+    }
+}
+
+/// How measurements are written to disk.
+#[derive(Debug, Clone, Copy)]
+pub enum StorageFormat {
+    /// Each measurement is written to its own file. This is the default.
+    Files,
+    /// Measurements are appended to a single log file per benchmark.
+    Log,
+}
+impl StorageFormat {
+    fn from_str(s: &str) -> StorageFormat {
+        match s {
+            "files" => StorageFormat::Files,
+            "log" => StorageFormat::Log,
+            other => panic!("Unknown storage format string: {}", other),
+        }
+    }
+}
+impl std::fmt::Display for StorageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageFormat::Files => write!(f, "files"),
+            StorageFormat::Log => write!(f, "log"),
+        }
+    }
+}
+
+/// How much of each new measurement is written to disk.
+#[derive(Debug, Clone, Copy)]
+pub enum SampleStorage {
+    /// The raw samples and the estimates. This is the default.
+    Full,
+    /// Only the estimates. Measurements stored this way can't be compared against.
+    Summary,
+}
+impl SampleStorage {
+    fn from_str(s: &str) -> SampleStorage {
+        match s {
+            "full" => SampleStorage::Full,
+            "summary" => SampleStorage::Summary,
+            other => panic!("Unknown sample storage string: {}", other),
+        }
+    }
+}
+
+/// How the directories that new benchmarks' measurements are stored in are named.
+#[derive(Debug, Clone, Copy)]
+pub enum DirNameStrategy {
+    /// Nested directories named after the group, function and value. This is the default.
+    Readable,
+    /// A directory named after the group, containing one named with a hash of the benchmark ID.
+    Hashed,
+}
+impl DirNameStrategy {
+    fn from_str(s: &str) -> DirNameStrategy {
+        match s {
+            "readable" => DirNameStrategy::Readable,
+            "hashed" => DirNameStrategy::Hashed,
+            other => panic!("Unknown directory name strategy string: {}", other),
+        }
+    }
+}
+
+/// The statistical test which decides whether a benchmark's performance has changed significantly
+/// since the measurement it's compared against.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum SignificanceTest {
+    /// Whether the bootstrapped confidence interval of the change in the mean excludes zero.
+    Ci,
+    /// Welch's t-test, with a bootstrapped distribution of the t statistic. This is the default.
+    #[default]
+    Welch,
+    /// The Mann-Whitney U test, which compares the ranks of the samples instead of their values.
+    MannWhitney,
+}
+impl SignificanceTest {
+    fn from_str(s: &str) -> SignificanceTest {
+        match s {
+            "ci" => SignificanceTest::Ci,
+            "welch" => SignificanceTest::Welch,
+            "mann-whitney" => SignificanceTest::MannWhitney,
+            other => panic!("Unknown significance test string: {}", other),
+        }
+    }
+}
+impl std::fmt::Display for SignificanceTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignificanceTest::Ci => write!(f, "ci"),
+            SignificanceTest::Welch => write!(f, "welch"),
+            SignificanceTest::MannWhitney => write!(f, "mann-whitney"),
+        }
+    }
+}
+
+/// The statistic whose change decides whether a benchmark has improved or regressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CompareMetric {
+    /// The mean time per iteration. This is the default.
+    #[default]
+    Mean,
+    /// The median time per iteration.
+    Median,
+    /// The slope of the linear regression of the sample times on the iteration counts, which only
+    /// measurements taken with linear sampling have.
+    Slope,
+}
+impl CompareMetric {
+    fn from_str(s: &str) -> CompareMetric {
+        match s {
+            "mean" => CompareMetric::Mean,
+            "median" => CompareMetric::Median,
+            "slope" => CompareMetric::Slope,
+            other => panic!("Unknown compare metric string: {}", other),
+        }
+    }
+}
+impl std::fmt::Display for CompareMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompareMetric::Mean => write!(f, "mean"),
+            CompareMetric::Median => write!(f, "median"),
+            CompareMetric::Slope => write!(f, "slope"),
+        }
+    }
+}
+
+/// Which stored measurements are deleted after the benchmarks have run.
+#[derive(Debug, Clone, Copy)]
+pub enum RetentionPolicy {
+    /// Every measurement is kept. This is the default.
+    KeepAll,
+    /// The oldest measurements are deleted until the stored data takes up no more than this many
+    /// bytes.
+    MaxBytes(u64),
+}
+
+/// What is measured for each benchmark.
+#[derive(Debug, Clone, Copy)]
+pub enum Measure {
+    /// Only the time taken by each iteration. This is the default.
+    Time,
+    /// The number of instructions executed per iteration, as well as the time.
+    Instructions,
+}
+impl Measure {
+    fn from_str(s: &str) -> Measure {
+        match s {
+            "time" => Measure::Time,
+            "instructions" => Measure::Instructions,
+            other => panic!("Unknown measure string: {}", other),
+        }
+    }
+}
+
+/// The order in which the summary reports list benchmark groups and benchmarks.
+#[derive(Debug, Clone, Copy)]
+pub enum SortOrder {
+    /// The order in which the benchmarks were run. This is the default.
+    Insertion,
+    /// Alphabetically by name.
+    Name,
+    /// Slowest first.
+    Time,
+    /// Biggest regression first.
+    Change,
+}
+impl SortOrder {
+    fn from_str(s: &str) -> SortOrder {
+        match s {
+            "insertion" => SortOrder::Insertion,
+            "name" => SortOrder::Name,
+            "time" => SortOrder::Time,
+            "change" => SortOrder::Change,
+            other => panic!("Unknown sort order string: {}", other),
+        }
+    }
+}
+
+/// The unit that times are shown in.
+#[derive(Debug, Clone, Copy)]
+pub enum TimeUnit {
+    /// Choose a unit to suit each value. This is the default.
+    Auto,
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+}
+impl TimeUnit {
+    fn from_str(s: &str) -> TimeUnit {
+        match s {
+            "auto" => TimeUnit::Auto,
+            "ns" => TimeUnit::Nanoseconds,
+            "us" => TimeUnit::Microseconds,
+            "ms" => TimeUnit::Milliseconds,
+            "s" => TimeUnit::Seconds,
+            other => panic!("Unknown time unit string: {}", other),
+        }
+    }
+
+    /// The number of nanoseconds in this unit and its name, or None if the unit is chosen to suit
+    /// each value.
+    pub fn scale(self) -> Option<(f64, &'static str)> {
+        match self {
+            TimeUnit::Auto => None,
+            TimeUnit::Nanoseconds => Some((1.0, "ns")),
+            TimeUnit::Microseconds => Some((1e3, "us")),
+            TimeUnit::Milliseconds => Some((1e6, "ms")),
+            TimeUnit::Seconds => Some((1e9, "s")),
+        }
+    }
+}
+impl std::fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.scale() {
+            Some((_, name)) => write!(f, "{}", name),
+            None => write!(f, "auto"),
+        }
+    }
+}
+
+/// Selects which stored measurement new measurements are compared against.
+#[derive(Debug, Clone)]
+pub enum CompareAgainst {
+    /// The most recent measurement. This is the default.
+    Previous,
+    /// The measurement saved under the given baseline name with `--save-baseline`.
+    Baseline(String),
+    /// The most recent measurement taken at or before the given time.
+    Datetime(DateTime<Utc>),
+    /// The most recent measurement whose history ID starts with the given commit ID.
+    Commit(String),
+    /// The average of the given number of most recent measurements.
+    Rolling(usize),
+    /// The measurements in the given baseline file. Set by `--baseline-file`.
+    File(PathBuf),
+    /// Nothing; every benchmark is treated as a fresh baseline. Set by `--baseline-only`.
+    Nothing,
+}
+impl CompareAgainst {
+    fn from_str(s: &str) -> Result<CompareAgainst, String> {
+        if s == "previous" {
+            return Ok(CompareAgainst::Previous);
+        }
+        match s.split_at(s.find(':').unwrap_or(s.len())) {
+            ("baseline", name) if name.len() > 1 => Ok(CompareAgainst::Baseline(name[1..].to_owned())),
+            ("commit", commit) if commit.len() > 1 => Ok(CompareAgainst::Commit(commit[1..].to_owned())),
+            ("datetime", datetime) if datetime.len() > 1 => {
+                parse_datetime(&datetime[1..]).map(CompareAgainst::Datetime)
+            }
+            ("rolling", count) if count.len() > 1 => match count[1..].parse::<usize>() {
+                Ok(count) if count > 0 => Ok(CompareAgainst::Rolling(count)),
+                _ => Err(format!(
+                    "Invalid rolling average size '{}'. Expected a positive integer.",
+                    &count[1..]
+                )),
+            },
+            _ => Err(format!(
+                "Unknown comparison selector '{}'. Expected one of previous, baseline:NAME, datetime:TIMESTAMP, commit:SHA or rolling:N.",
+                s
+            )),
+        }
+    }
+}
+impl std::fmt::Display for CompareAgainst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompareAgainst::Previous => write!(f, "previous"),
+            CompareAgainst::Baseline(name) => write!(f, "baseline:{}", name),
+            CompareAgainst::Datetime(datetime) => write!(f, "datetime:{}", datetime.to_rfc3339()),
+            CompareAgainst::Commit(commit) => write!(f, "commit:{}", commit),
+            CompareAgainst::Rolling(count) => write!(f, "rolling:{}", count),
+            CompareAgainst::File(path) => write!(f, "file:{}", path.display()),
+            CompareAgainst::Nothing => write!(f, "nothing"),
+        }
+    }
+}
+
+/// Parse a timestamp given on the command line. This may be an RFC 3339 timestamp, or a date in
+/// YYYY-MM-DD format, which is taken to mean the end of that day in the local timezone.
+fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .and_then(|datetime| Local.from_local_datetime(&datetime).earliest())
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .ok_or_else(|| {
+            format!(
+                "Unable to parse '{}' as a timestamp. Expected an RFC 3339 timestamp or a date in YYYY-MM-DD format.",
+                s
+            )
+        })
+}
+
+/// Parse a list of CPUs given on the command line, such as `0,2-3`. Returns the CPUs in ascending
+/// order without duplicates.
+fn parse_cpu_list(s: &str) -> Result<Vec<usize>, String> {
+    let parse_cpu = |cpu: &str| -> Result<usize, String> {
+        let cpu: usize = cpu
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a valid CPU number", cpu.trim()))?;
+        if cpu >= crate::affinity::MAX_CPUS {
+            return Err(format!(
+                "CPU {} is too large; CPUs must be less than {}",
+                cpu,
+                crate::affinity::MAX_CPUS
+            ));
+        }
+        Ok(cpu)
+    };
+
+    let mut cpus = Vec::new();
+    for item in s.split(',') {
+        match item.find('-') {
+            Some(dash) => {
+                let (first, last) = (parse_cpu(&item[..dash])?, parse_cpu(&item[dash + 1..])?);
+                if first > last {
+                    return Err(format!("'{}' is not a valid range of CPUs", item.trim()));
+                }
+                cpus.extend(first..=last);
+            }
+            None => cpus.push(parse_cpu(item)?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Parse a size given on the command line, such as `500MB` or `2GiB`, as a number of bytes.
+/// Decimal units (KB, MB, GB) are powers of 1000 and binary units (KiB, MiB, GiB) are powers of
+/// 1024. A number with no unit is a number of bytes.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "kib" => 1024,
+        "mib" => 1024u64.pow(2),
+        "gib" => 1024u64.pow(3),
+        other => {
+            return Err(format!(
+                "'{}' is not a known unit; expected one of B, KB, MB, GB, KiB, MiB, GiB",
+                other
+            ))
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid size", s))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration given on the command line, such as `5ms` or `1.5s`, as a number of
+/// nanoseconds. The unit is required and may be ns, us (or µs), ms or s.
+fn parse_duration(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let multiplier = match unit.trim() {
+        "ns" => 1.0,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        "" => {
+            return Err(format!(
+                "'{}' has no unit; expected one of ns, us, ms, s",
+                s
+            ))
+        }
+        other => {
+            return Err(format!(
+                "'{}' is not a known unit; expected one of ns, us, ms, s",
+                other
+            ))
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid duration", s))?;
+    Ok(number * multiplier)
+}
 
 /// Struct to hold the various configuration settings for cargo-criterion itself.
 #[derive(Debug)]
 pub struct SelfConfig {
     /// The path to the output directory
     pub criterion_home: PathBuf,
+    /// The directory that measurements are stored in. Defaults to `data` under criterion_home.
+    pub data_directory: PathBuf,
+    /// The directory that reports are written to. Defaults to `reports` under criterion_home.
+    pub report_directory: PathBuf,
     /// Should we run the benchmarks?
     pub do_run: bool,
+    /// Should files be left unchanged, and the changes which would be made printed instead?
+    pub dry_run: bool,
     /// Should we fail immediately if a benchmark target fails, or continue with the others?
     pub do_fail_fast: bool,
     /// How should the CLI output be formatted
@@ -173,14 +779,247 @@ pub struct SelfConfig {
     pub plotting_backend: PlottingBackend,
     /// Should we compile the benchmarks in debug mode (true) or release mode (false, default)
     pub debug_build: bool,
+    /// The Cargo profile that the benchmarks are compiled with.
+    pub profile: String,
+    /// Should we reuse the benchmarks compiled by the last run if the sources haven't changed?
+    pub reuse_compile: bool,
+    /// The directory containing the Cargo manifest.
+    pub manifest_directory: PathBuf,
     /// Should we print machine-readable output, and if so, in what format?
     pub message_format: Option<MessageFormat>,
+    /// The file to write the machine-readable output to, instead of stdout.
+    pub message_path: Option<PathBuf>,
+    /// The URL of the collector to post the JSON messages to, if any.
+    pub upload_url: Option<String>,
+    /// Extra headers to send with each message posted to the collector.
+    pub upload_headers: Vec<(String, String)>,
+    /// Should the run fail if any message can't be posted to the collector?
+    pub upload_required: bool,
+    /// How should log messages be printed?
+    pub log_format: LogFormat,
     /// The colors to use for charts.
     pub colors: Colors,
     // An optional identifier used to identify this run in the history reports.
     pub history_id: Option<String>,
     // An optional description used to describe this run in the history reports.
     pub history_description: Option<String>,
+    /// The GitHub Actions job summary file to append a Markdown summary to, if requested.
+    pub github_summary: Option<PathBuf>,
+    /// Which stored measurement should new measurements be compared against?
+    pub compare_against: CompareAgainst,
+    /// If set, new measurements are also saved as the baseline with this name.
+    pub save_baseline: Option<String>,
+    /// If set, the latest measurement of every benchmark is written to this baseline file after
+    /// the run.
+    pub export_baseline: Option<PathBuf>,
+    /// The name of the timeline to load and store measurements in.
+    pub timeline: String,
+    /// Should we record the peak memory usage of the benchmark targets?
+    pub measure_memory: bool,
+    /// What to measure for each benchmark.
+    pub measure: Measure,
+    /// The CPUs to run the benchmark targets on, or empty to let them run on any CPU.
+    pub cpu_affinity: Vec<usize>,
+    /// The directory to run the benchmark targets in. If not set, each target is run in the
+    /// directory of its package's manifest.
+    pub bench_cwd: Option<PathBuf>,
+    /// The tag to store with the measurements, identifying this version of the benchmark code.
+    pub bench_version: Option<String>,
+    /// The seed passed to the benchmark targets for generating their inputs, if any.
+    pub bench_seed: Option<u64>,
+    /// Should the benchmark targets be asked to run their worker thread pools on a single thread?
+    pub single_thread: bool,
+    /// The port to listen for connections from the benchmark targets on, or None to use any free
+    /// port.
+    pub connect_port: Option<u16>,
+    /// Benchmarks with fewer usable samples than this are reported as low confidence.
+    pub min_samples: usize,
+    /// The test which decides whether a change in performance is significant.
+    pub significance_test: SignificanceTest,
+    /// The statistic whose change decides whether a benchmark has improved or regressed.
+    pub compare_metric: CompareMetric,
+    /// Should benchmarks with no stored measurement be rejected?
+    pub no_new_benchmarks: bool,
+    /// How many times to launch a benchmark target again if it fails before reporting any
+    /// benchmark.
+    pub run_retries: usize,
+    /// Should the run fail if any benchmark has regressed?
+    pub fail_on_regression: bool,
+    /// Patterns matching the benchmarks whose regressions shouldn't fail the run.
+    pub ignore_regression: Vec<String>,
+    /// Patterns matching the benchmark and group IDs which may be encountered more than once
+    /// without a warning, from Criterion.toml.
+    pub allow_duplicate_ids: Vec<String>,
+    /// Should the run fail if any benchmark or group ID is encountered more than once, even one
+    /// which is allowed to be?
+    pub deny_duplicate_ids: bool,
+    /// Should the run fail if any benchmark group has fewer benchmarks than in its previous run?
+    pub no_missing_benchmarks: bool,
+    /// The time budgets of the benchmarks, from Criterion.toml followed by those given with
+    /// `--budget`. Benchmarks over their budget fail the run.
+    pub budgets: Vec<Budget>,
+    /// Which estimate of each benchmark's time is checked against its budget.
+    pub budget_statistic: BudgetStatistic,
+    /// Should the benchmark targets be told the previous estimates, so that they can spend less
+    /// time sampling benchmarks whose timing is stable?
+    pub adaptive_sampling: bool,
+    /// Should benchmark targets which haven't changed since they were last run be skipped?
+    pub changed_only: bool,
+    /// Should only the targets which Cargo reports as benchmarks be run, leaving out test and
+    /// library executables?
+    pub bench_only: bool,
+    /// If set, stop after running this many benchmarks.
+    pub take: Option<usize>,
+    /// How many times to run all of the benchmarks.
+    pub repeat: usize,
+    /// A shell command to run after the benchmarks have run and the reports are written.
+    pub post_run_hook: Option<String>,
+    /// Should a plot which can't be drawn abort the run rather than being left out of the report?
+    pub strict_plots: bool,
+    /// Should the run fail if no plotting backend is usable, instead of leaving the plots out of
+    /// the HTML report?
+    pub require_plots: bool,
+    /// If set, the benchmarks are run under Criterion.rs' profiling mode for this many seconds
+    /// each instead of being measured.
+    pub profile_time: Option<u64>,
+    /// Should the benchmarks only be run briefly to check that they work, without keeping the
+    /// results?
+    pub check: bool,
+    /// The order in which the summary reports list benchmarks.
+    pub sort_by: SortOrder,
+    /// The title of the HTML index and the PDF report, if not the default.
+    pub report_title: Option<String>,
+    /// A description to show under the title of the HTML index and the PDF report.
+    pub report_description: Option<String>,
+    /// The unit that times are shown in.
+    pub time_unit: TimeUnit,
+    /// If set, changes in time of at least this factor are shown as ratios ("2.40x slower")
+    /// rather than as percentages.
+    pub change_ratio_above: Option<f64>,
+    /// How new measurements are written to disk.
+    pub storage_format: StorageFormat,
+    /// How much of each new measurement to store.
+    pub sample_storage: SampleStorage,
+    /// The SQLite database to also insert new measurements into, if any.
+    pub db: Option<PathBuf>,
+    /// How to name the directories that new benchmarks are stored in.
+    pub dir_name_strategy: DirNameStrategy,
+    /// Which stored measurements to delete after the benchmarks have run.
+    pub retention: RetentionPolicy,
+    /// Should stored measurements which don't record when they were taken be dated by the
+    /// modification time of their files?
+    pub datetime_from_mtime: bool,
+    /// How many files loading or checking the stored data may keep open at once.
+    pub max_open_files: usize,
+    /// Statistical settings which override the benchmarks' own, from Criterion.toml.
+    pub benchmark_overrides: Vec<BenchmarkOverride>,
+}
+
+/// Which command cargo-criterion was asked to perform.
+#[derive(Debug)]
+pub enum Action {
+    /// Compile and run the benchmarks. This is the default.
+    Run,
+    /// Print the benchmark groups found in the stored data, without running anything.
+    ListGroups {
+        /// Print the groups as JSON rather than as text.
+        json: bool,
+    },
+    /// Print the distinct commits (history IDs) in the stored history, without running anything.
+    Commits {
+        /// Print the commits as JSON rather than as text.
+        json: bool,
+    },
+    /// Analyze the samples of the last few stored measurements of a benchmark together, and store
+    /// the result as a new measurement, without running anything.
+    Pool {
+        /// The title of the benchmark to pool the measurements of.
+        benchmark: String,
+        /// How many of the benchmark's latest measurements to pool.
+        count: usize,
+    },
+    /// Save the latest stored measurement of each benchmark as a named baseline, without running
+    /// anything.
+    Accept {
+        /// The name of the baseline to save the measurements as.
+        baseline: String,
+        /// Only accept the benchmarks whose titles match this pattern, if given.
+        benchmarks: Option<String>,
+    },
+    /// Package the stored data and report of one benchmark into a single file, without running
+    /// anything.
+    Bundle {
+        /// The title of the benchmark to bundle.
+        benchmark: String,
+        /// The file to write the bundle to.
+        out: PathBuf,
+    },
+    /// Unpack a bundle into the data and report directories, without running anything.
+    Import {
+        /// The bundle to unpack.
+        path: PathBuf,
+        /// Whether existing files may be replaced by the files in the bundle.
+        force: bool,
+    },
+    /// Print the latest stored measurements of one benchmark group, without running anything.
+    Report {
+        /// The name of the group to report on.
+        group: String,
+    },
+    /// Print the contents of a single measurement file, without running anything.
+    Inspect {
+        /// The measurement file to print.
+        path: PathBuf,
+    },
+    /// Check the stored data for corrupt or missing files, without running anything.
+    Doctor,
+    /// Check that the environment is ready for a run, without running anything.
+    Preflight,
+    /// Print the output formats and plotting backends this build supports, without running
+    /// anything.
+    Capabilities {
+        /// Print the capabilities as JSON rather than as text.
+        json: bool,
+    },
+    /// Compare the measurements recorded against two commits, without running anything.
+    CompareCommits {
+        /// The commit to compare against.
+        base: String,
+        /// The commit to compare.
+        head: String,
+        /// How the comparison should be printed.
+        format: ComparisonFormat,
+    },
+    /// Compare the latest measurements in another data directory against a named baseline,
+    /// without running anything.
+    CompareBaseline {
+        /// The name of the baseline to compare against.
+        baseline: String,
+        /// The criterion home directory holding the measurements to compare.
+        from: PathBuf,
+        /// How the comparison should be printed.
+        format: ComparisonFormat,
+    },
+    /// Rewrite the stored data in another storage format, without running anything.
+    Migrate {
+        /// The storage format to convert the data to.
+        to: StorageFormat,
+    },
+    /// Print the configuration after applying the config file, environment variables and
+    /// command-line arguments, without running anything.
+    DumpConfig {
+        /// How to print the configuration.
+        format: ConfigFormat,
+    },
+    /// Serve the generated reports over HTTP, without running anything.
+    Serve {
+        /// The report directory to serve.
+        from: PathBuf,
+        /// The address to listen on.
+        host: String,
+        /// The port to listen on.
+        port: u16,
+    },
 }
 
 /// Overall struct that represents all of the configuration data for this run.
@@ -192,13 +1031,34 @@ pub struct FullConfig {
     pub cargo_args: Vec<OsString>,
     /// The additional arguments we pass through to the benchmark executables
     pub additional_args: Vec<OsString>,
+    /// The command to perform
+    pub action: Action,
+}
+
+/// Cargo arguments which control network and lockfile access. These must be passed along to every
+/// Cargo command we run, not just the compile step, or Cargo may try to touch the network anyway.
+const NETWORK_ARGS: [&str; 3] = ["--frozen", "--locked", "--offline"];
+
+/// The Cargo executable to run: the one in `$CARGO` if it's set, as Cargo does when it runs
+/// `cargo criterion`, or else `cargo` from the PATH.
+pub fn cargo_program() -> OsString {
+    std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"))
+}
+
+/// Construct the `cargo metadata` command, passing along any network-related Cargo arguments.
+fn metadata_command(cargo_args: &[OsString]) -> Command {
+    let mut command = Command::new(cargo_program());
+    command.args(["metadata", "--format-version", "1"]).args(
+        cargo_args
+            .iter()
+            .filter(|arg| NETWORK_ARGS.iter().any(|network_arg| arg == network_arg)),
+    );
+    command
 }
 
-/// Call `cargo criterion` and parse the output to get the path to the target directory.
-fn get_target_directory_from_metadata() -> Result<PathBuf> {
-    let out = Command::new("cargo")
-        .args(&["metadata", "--format-version", "1"])
-        .output()?;
+/// Call `cargo metadata` and parse the output to get the path to the target directory.
+fn get_target_directory_from_metadata(cargo_args: &[OsString]) -> Result<PathBuf> {
+    let out = metadata_command(cargo_args).output()?;
 
     #[derive(Deserialize)]
     struct MetadataMessage {
@@ -210,13 +1070,60 @@ fn get_target_directory_from_metadata() -> Result<PathBuf> {
     Ok(path)
 }
 
-/// Parse the command-line arguments, load the Criterion.toml config file, and generate a
-/// configuration object used for the rest of the run.
-#[cfg_attr(feature = "cargo-clippy", allow(clippy::or_fun_call))]
-pub fn configure() -> Result<FullConfig, anyhow::Error> {
-    use clap::{App, AppSettings, Arg};
+/// Ask git for the name of the branch checked out in the given directory.
+fn current_git_branch(directory: &Path) -> Result<String> {
+    let out = Command::new("git")
+        .args(["symbolic-ref", "--short", "HEAD"])
+        .current_dir(directory)
+        .output()
+        .context("Failed to run git")?;
+    if !out.status.success() {
+        anyhow::bail!(
+            "git symbolic-ref failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    let branch = String::from_utf8(out.stdout)
+        .context("git printed a branch name that is not valid UTF-8")?
+        .trim()
+        .to_owned();
+    Ok(branch)
+}
+
+/// Replace each argument of the form `@path` with the arguments read from that file, one per line,
+/// as cargo and rustc do. Blank lines are ignored and the files are not expanded recursively.
+/// Arguments after `--` are passed to the benchmarks untouched.
+fn expand_arg_files<I: IntoIterator<Item = OsString>>(args: I) -> Result<Vec<OsString>> {
+    let mut expanded = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+        match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+            Some(path) if !path.is_empty() => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read argument file {:?}", path))?;
+                expanded.extend(
+                    contents
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(OsString::from),
+                );
+            }
+            _ => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
 
-    let matches = App::new("cargo-criterion")
+/// The command-line interface of cargo-criterion.
+fn app() -> clap::App<'static, 'static> {
+    use clap::{App, AppSettings, Arg, SubCommand};
+
+    App::new("cargo-criterion")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Execute, analyze and report on benchmarks of a local package")
         .bin_name("cargo criterion")
@@ -292,6 +1199,19 @@ pub fn configure() -> Result<FullConfig, anyhow::Error> {
                 .long("--no-run")
                 .help("Compile, but don't run benchmarks"),
         )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("--dry-run")
+                .global(true)
+                .help("Print what would be written or deleted instead of changing any files.")
+                .long_help(
+"Print what would be written or deleted instead of changing any files. The benchmarks are still \
+run and reported on the command line, but their measurements aren't stored, no reports are written \
+and --history-max-size doesn't delete anything. With the migrate command, prints what would be \
+converted without converting it. To see which benchmarks would run without running them, pass \
+--list to the benchmarks instead (`cargo criterion -- --list`).
+"),
+        )
         .arg(
             Arg::with_name("package")
                 .long("--package")
@@ -352,6 +1272,20 @@ pub fn configure() -> Result<FullConfig, anyhow::Error> {
                 .value_name("TRIPLE")
                 .help("Build for the target triple"),
         )
+        .arg(
+            Arg::with_name("data-dir")
+                .long("--data-dir")
+                .takes_value(true)
+                .value_name("DIRECTORY")
+                .help("Directory to load and store measurements in. Defaults to 'data' under the criterion home directory."),
+        )
+        .arg(
+            Arg::with_name("report-dir")
+                .long("--report-dir")
+                .takes_value(true)
+                .value_name("DIRECTORY")
+                .help("Directory to write reports to. Defaults to 'reports' under the criterion home directory."),
+        )
         .arg(
             Arg::with_name("target-dir")
                 .long("--target-dir")
@@ -378,6 +1312,21 @@ pub fn configure() -> Result<FullConfig, anyhow::Error> {
                 .long("--no-fail-fast")
                 .help("Run all benchmarks regardless of failure"),
         )
+        .arg(
+            Arg::with_name("run-retries")
+                .long("--run-retries")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Launch a benchmark target up to N more times if it fails before reporting any benchmark.")
+                .long_help(
+"Launch a benchmark target up to N more times if it fails to connect to cargo-criterion, or exits \
+with an error before reporting any benchmark. This works around flaky CI machines, where a target \
+occasionally fails to start. Each retry is logged. A target that fails after it has started \
+running benchmarks is not retried, and a target which crashes on every attempt still fails once \
+the retries are used up. Defaults to 0.
+")
+        )
         .arg(
             Arg::with_name("debug")
                 .long("--debug")
@@ -389,19 +1338,47 @@ different language (eg. external C modules).
 
 Note however that it will tend to increase the measurement overhead, as the measurement loops 
 in the benchmark will not be optimized either. This may result in less-accurate measurements.
+")
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("--profile")
+                .takes_value(true)
+                .value_name("PROFILE-NAME")
+                .conflicts_with("debug")
+                .help("Build the benchmarks with the specified profile")
+                .long_help(
+"Build the benchmarks with the given Cargo profile instead of the 'bench' profile, eg. a custom \
+profile which enables link-time optimization. The name of the profile is stored with each \
+measurement, and a warning is printed when a benchmark is compared against a measurement that was \
+built with a different profile.
+")
+        )
+        .arg(
+            Arg::with_name("reuse-compile")
+                .long("--reuse-compile")
+                .help("Skip compiling the benchmarks if nothing has changed since the last run.")
+                .long_help(
+"Skip compiling the benchmarks if nothing has changed since the last run.
+
+cargo-criterion will remember the list of compiled benchmarks and reuse it on later runs with the
+same Cargo arguments, so long as no Rust source file, Cargo.toml or Cargo.lock under the package
+directory has been modified since. Changes that Cargo would notice but this check does not (eg.
+changes to environment variables, or to path dependencies outside the package directory) will not
+cause a recompile, so the benchmarks may be stale.
 ")
         )
         .arg(
             Arg::with_name("output-format")
                 .long("output-format")
                 .takes_value(true)
-                .possible_values(&["criterion", "quiet", "verbose", "bencher"])
+                .possible_values(OUTPUT_FORMATS)
                 .default_value("criterion")
                 .hide_default_value(true)
                 .hide_possible_values(true)
-                .help("Change the CLI output format. Possible values are criterion, quiet, verbose, bencher.")
+                .help("Change the CLI output format. Possible values are criterion, quiet, verbose, bencher, stable-text, pdf.")
                 .long_help(
-"Change the CLI output format. Possible values are [criterion, quiet, verbose, bencher].
+"Change the CLI output format. Possible values are [criterion, quiet, verbose, bencher, stable-text, pdf].
 
 criterion: Prints confidence intervals for measurement and throughput, and indicates whether a \
 change was detected from the previous run. The default.
@@ -412,80 +1389,892 @@ eg. on a library's README.
 verbose: Like criterion, but prints additional statistics.
 
 bencher: Emulates the output format of the bencher crate and nightly-only libtest benchmarks.
+
+stable-text: Prints nothing while the benchmarks run, then prints the estimates of every stored \
+benchmark to stdout, sorted by name. Nothing else is printed - no timestamps, changes or machine \
+details - so the output can be checked into version control, and it only changes when the \
+estimates do.
+
+pdf: Like criterion, but also writes report.pdf to the report directory once the benchmarks are \
+complete, with a summary page and a page for each stored benchmark with its estimates and a plot of \
+its samples. Only available if cargo-criterion was installed with the 'pdf_report' feature.
 ")
         )
         .arg(
             Arg::with_name("plotting-backend")
                 .long("plotting-backend")
                 .takes_value(true)
-                .possible_values(&["gnuplot", "plotters", "disabled"])
+                .possible_values(PLOTTING_BACKENDS)
                 .help("Set the plotting backend. By default, cargo-criterion will use the gnuplot backend if gnuplot is available, or the plotters backend if it isn't. If set to 'disabled', plot generation will be disabled."))
+            .arg(Arg::with_name("post-run-hook")
+                .long("--post-run-hook")
+                .takes_value(true)
+                .value_name("CMD")
+                .help("Run CMD with the shell once the benchmarks have run and the reports are written.")
+                .long_help(
+"Run CMD with the shell (sh -c, or cmd /C on Windows) once the benchmarks have run and the reports \
+have been written, for example to upload the results. The command is run even if the benchmarks \
+failed. It is given these environment variables:
+
+CARGO_CRITERION_HOME: The criterion home directory.
+CARGO_CRITERION_STATUS: 0 if the run succeeded, 1 if it failed.
+
+If the command fails, cargo-criterion exits with an error.
+"))
+            .arg(Arg::with_name("strict-plots")
+                .long("--strict-plots")
+                .help("Exit with an error once the report is generated if gnuplot failed to draw a plot, instead of only leaving that plot out of the report."))
+            .arg(Arg::with_name("require-plots")
+                .long("--require-plots")
+                .help("Stop with an error if no plotting backend is usable, instead of generating the HTML report without plots."))
+            .arg(Arg::with_name("dump-config")
+                .long("--dump-config")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["toml", "json"])
+                .help("Print the effective configuration as TOML or JSON, then exit without running anything.")
+                .long_help(
+"Print the configuration that would be used, after combining Criterion.toml, the environment \
+variables and the command-line arguments, as TOML or JSON. Nothing is compiled or run. This is \
+useful for finding out why a setting differs between machines.
+")
+            )
             .arg(Arg::with_name("message-format")
                 .long("message-format")
                 .takes_value(true)
-                .possible_values(&["json"])
+                .possible_values(MESSAGE_FORMATS)
                 .help("If set, machine-readable output of the requested format will be printed to stdout.")
                 .long_help(
-"Change the machine-readable output format. Possible values are [json].
+"Change the machine-readable output format. Possible values are [json, json-v2].
+
+Machine-readable information on the benchmarks will be printed in the requested format to stdout, \
+or to the file given by --message-path. All of cargo-criterion's other output will be printed to \
+stderr.
 
-Machine-readable information on the benchmarks will be printed in the requested format to stdout.
-All of cargo-criterion's other output will be printed to stderr.
+json-v2 prints the same messages as json, except that each estimate is written as a {point, lower, \
+upper, unit} object, and the relative changes are written as percentages rather than fractions.
 
 See the documentation for details on the data printed by each format.
 ")
         )
         .arg(
-            Arg::with_name("history_id")
-                .long("--history-id")
+            Arg::with_name("message-path")
+                .long("--message-path")
                 .takes_value(true)
-                .help("An optional identifier string such as a commit ID that will be shown in the history reports to identify this run.")
+                .value_name("FILE")
+                .requires("message-format")
+                .help("Write the machine-readable output to FILE instead of stdout. Each message is written as soon as it is available.")
         )
         .arg(
-            Arg::with_name("history_description")
-                .long("--history-description")
+            Arg::with_name("upload-url")
+                .long("--upload-url")
                 .takes_value(true)
-                .help("An optional description string such as a commit message that will be shown in the history reports to describe this run.")
-        )
-        .arg(
-            Arg::with_name("verbose")
-                .long("--verbose")
-                .short("v")
-                .multiple(true)
-                .help("Use verbose output (-vv very verbose/build.rs output). Only used for Cargo builds; see also --output-format"),
+                .value_name("URL")
+                .validator(|s| UploadUrl::parse(&s).map(|_| ()))
+                .help("POST each result to URL as JSON, as it becomes available.")
+                .long_help(
+"POST each result to URL as it becomes available, as the same JSON messages that --message-format \
+json prints: one request for each benchmark when it completes, then one for each group and one at \
+the end of the run. Only http:// URLs are supported. A result which can't be posted is warned \
+about, and the run carries on; see --upload-required. Nothing is posted in a dry run.
+")
         )
         .arg(
-            Arg::with_name("color")
-                .long("--color")
+            Arg::with_name("upload-header")
+                .long("--upload-header")
                 .takes_value(true)
-                .possible_values(&["auto", "always", "never"])
-                .help("Coloring: auto, always, never"),
+                .value_name("NAME: VALUE")
+                .multiple(true)
+                .number_of_values(1)
+                .requires("upload-url")
+                .validator(|s| parse_header(&s).map(|_| ()))
+                .help("Send the header with each request made for --upload-url. Can be given more than once.")
         )
         .arg(
-            Arg::with_name("frozen")
-                .long("--frozen")
-                .help("Require Cargo.lock and cache are up to date"),
+            Arg::with_name("upload-required")
+                .long("--upload-required")
+                .requires("upload-url")
+                .help("Fail the run if any result couldn't be posted to the --upload-url.")
         )
         .arg(
-            Arg::with_name("locked")
-                .long("--locked")
-                .help("Require Cargo.lock is up to date"),
+            Arg::with_name("log-format")
+                .long("--log-format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .hide_default_value(true)
+                .hide_possible_values(true)
+                .help("Change the format of cargo-criterion's log messages. Possible values are text, json.")
+                .long_help(
+"Change the format of cargo-criterion's log messages, such as errors and warnings. Possible values \
+are [text, json].
+
+text: Print log messages as human-readable text. The default.
+
+json: Print each log message to stderr as a JSON object on its own line, with level, timestamp, \
+target and message fields. Compiler warnings and errors are printed the same way. This does not \
+affect the benchmark results; see --message-format for those.
+")
         )
         .arg(
-            Arg::with_name("offline")
-                .long("--offline")
-                .help("Run without accessing the network"),
+            Arg::with_name("history_id")
+                .long("--history-id")
+                .takes_value(true)
+                .help("An optional identifier string such as a commit ID that will be shown in the history reports to identify this run.")
         )
         .arg(
-            Arg::with_name("unstable_flags")
-                .short("Z")
+            Arg::with_name("history_description")
+                .long("--history-description")
                 .takes_value(true)
-                .value_name("FLAG")
-                .multiple(true)
-                .help("Unstable (nightly-only) flags to Cargo, see 'cargo -Z help' for details"),
+                .help("An optional description string such as a commit message that will be shown in the history reports to describe this run.")
         )
         .arg(
-            Arg::with_name("SUBCOMMAND")
-                .hidden(true)
+            Arg::with_name("compare-against")
+                .long("--compare-against")
+                .takes_value(true)
+                .value_name("SELECTOR")
+                .validator(|s| CompareAgainst::from_str(&s).map(|_| ()))
+                .help("Select which stored measurement to compare against. Possible values are previous, baseline:NAME, datetime:TIMESTAMP, commit:SHA, rolling:N.")
+                .long_help(
+"Select which stored measurement each benchmark is compared against.
+
+previous: Compare against the most recent stored measurement. The default.
+
+baseline:NAME: Compare against the measurement saved with `--save-baseline NAME`.
+
+datetime:TIMESTAMP: Compare against the most recent measurement taken at or before TIMESTAMP, which \
+may be an RFC 3339 timestamp or a YYYY-MM-DD date.
+
+commit:SHA: Compare against the most recent measurement whose `--history-id` starts with SHA.
+
+rolling:N: Compare against the average of the N most recent stored measurements. This smooths \
+out the run-to-run noise of a single previous measurement.
+")
+        )
+        .arg(
+            Arg::with_name("baseline-only")
+                .long("--baseline-only")
+                .conflicts_with("compare-against")
+                .help("Record measurements without comparing them to any stored measurement.")
+                .long_help(
+"Record measurements without comparing them to any stored measurement. Every benchmark is \
+treated as a fresh baseline, so no change or regression is reported. This is useful when \
+bootstrapping a new timeline or running on a fresh machine.
+")
+        )
+        .arg(
+            Arg::with_name("save-baseline")
+                .long("--save-baseline")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Save the measurements from this run as the baseline with the given name, for use with --compare-against baseline:NAME.")
+        )
+        .arg(
+            Arg::with_name("baseline-file")
+                .long("--baseline-file")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with_all(&["compare-against", "baseline-only"])
+                .help("Compare against the measurements in a baseline file written by --export-baseline, instead of the stored measurements.")
+                .long_help(
+"Compare against the measurements in a baseline file written by --export-baseline, instead of the \
+stored measurements. A baseline file can be committed to version control, so that every developer's \
+runs are compared against the same reference, whatever is in their own data directory. Benchmarks \
+which aren't in the file are not compared.
+")
+        )
+        .arg(
+            Arg::with_name("export-baseline")
+                .long("--export-baseline")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("After the run, write the latest measurement of every benchmark to a baseline file, for use with --baseline-file.")
+                .long_help(
+"After the run, write the latest measurement of every benchmark in the timeline to FILE, for use \
+with --baseline-file. The file is TOML, with the group, function and value of each benchmark \
+followed by its estimates and their confidence intervals in nanoseconds. It also holds the raw \
+samples, which comparing against the measurements needs. The file is replaced if it exists.
+")
+        )
+        .arg(
+            Arg::with_name("timeline")
+                .long("--timeline")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Load and store measurements in the timeline with the given name. Defaults to $CARGO_CRITERION_TIMELINE, or 'main'.")
+        )
+        .arg(
+            Arg::with_name("timeline-from-branch")
+                .long("--timeline-from-branch")
+                .help("If no timeline is given, name the timeline after the current git branch.")
+        )
+        .arg(
+            Arg::with_name("timeline-date")
+                .long("--timeline-date")
+                .conflicts_with("timeline-from-branch")
+                .help("If no timeline is given, name the timeline after today's date (YYYY-MM-DD), so that each day's measurements are kept separately.")
+        )
+        .arg(
+            Arg::with_name("repeat")
+                .long("--repeat")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(0) => Err("N must be at least 1".to_owned()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                })
+                .help("Run all of the benchmarks N times, storing each run as a separate measurement. This multiplies the run time by N.")
+                .long_help(
+"Run all of the benchmarks N times, storing each run as a separate measurement in the history. \
+This multiplies the run time by N. Each run is compared against the measurement before it, as if \
+cargo-criterion had been run N times. With --take, each run stops after the first N benchmarks.
+
+To compare later runs against the average of these measurements, use `--compare-against rolling:N`.
+")
+        )
+        .arg(
+            Arg::with_name("take")
+                .long("--take")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(0) => Err("N must be at least 1".to_owned()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                })
+                .help("Stop after running the first N benchmarks, counted across all benchmark targets.")
+                .long_help(
+"Stop after running the first N benchmarks, counted across all benchmark targets in the order \
+they are run. The benchmark target running the Nth benchmark is stopped once it finishes, and the \
+remaining targets aren't run. The stored results of the benchmarks which weren't run are still \
+included in the reports.
+")
+        )
+        .arg(
+            Arg::with_name("changed-only")
+                .long("--changed-only")
+                .help("Only run the benchmark targets which have changed since they were last run.")
+                .long_help(
+"Only run the benchmark targets which have changed since they were last run. The stored results of \
+the skipped targets are still included in the reports.
+
+A target counts as changed if its compiled executable differs from the one which produced its latest \
+stored measurement, so changes to the benchmark's source or to any of its dependencies are noticed \
+as long as Cargo rebuilds the executable. Changes which don't affect the executable are not noticed: \
+different arguments to the benchmarks (such as a filter or a new measurement time), environment \
+variables, input files read at run time, and the machine the benchmarks run on. A target that was \
+last run with a filter counts as unchanged, even though some of its benchmarks weren't run.
+")
+        )
+        .arg(
+            Arg::with_name("bench-only")
+                .long("--bench-only")
+                .help("Only run the benchmark targets, leaving out test and library executables.")
+                .long_help(
+"Only run the targets which Cargo reports as benchmarks (those in benches/ or declared with \
+[[bench]]), leaving out test and library executables.
+
+By default, cargo-criterion also runs the test and library executables that Cargo builds, in case \
+they contain benchmarks. Those that don't still have to be launched and asked for their benchmarks, \
+which takes time and can fill the output with noise. Benchmarks which are only reachable through \
+a test or library executable, for example ones run from a #[test] function or defined in a \
+library's unit tests, are not run when this is given.
+")
+        )
+        .arg(
+            Arg::with_name("measure-memory")
+                .long("--measure-memory")
+                .help("Record the peak memory usage (resident set size) of the benchmark targets. Linux only.")
+                .long_help(
+"Record the peak memory usage (resident set size) of the benchmark targets. This is only supported \
+on Linux, where it is read from /proc.
+
+The peak is measured for the whole benchmark process, not for each benchmark individually. Each \
+benchmark reports the highest memory usage of its process so far, which includes the memory used by \
+Criterion.rs itself and by any benchmarks run earlier in the same target.
+
+To record how many allocations each benchmark makes, and how many bytes it allocates, the benchmark \
+crate must install a counting global allocator which reports its counts to cargo-criterion. This \
+doesn't need this option; the counts are shown whenever a benchmark reports them.
+")
+        )
+        .arg(
+            Arg::with_name("bench-cwd")
+                .long("--bench-cwd")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("Run the benchmark targets in the given directory. Defaults to the directory of each target's package.")
+                .long_help(
+"Run the benchmark targets in the given directory. By default, each target runs in the directory \
+containing its package's Cargo.toml, as it does with `cargo bench`, so that benchmarks can load \
+files relative to the crate root.
+")
+        )
+        .arg(
+            Arg::with_name("cpu-affinity")
+                .long("--cpu-affinity")
+                .takes_value(true)
+                .value_name("LIST")
+                .validator(|s| parse_cpu_list(&s).map(|_| ()))
+                .help("Run the benchmark targets only on the given CPUs, such as 0,2-3. Linux only.")
+                .long_help(
+"Run the benchmark targets only on the given CPUs, so that the scheduler doesn't move them between \
+cores in the middle of a measurement. LIST is a comma-separated list of CPU numbers and ranges, such \
+as 0,2-3. This is only supported on Linux. The CPUs are stored with each measurement.
+")
+        )
+        .arg(
+            Arg::with_name("bench-version")
+                .long("--bench-version")
+                .takes_value(true)
+                .value_name("TAG")
+                .help("Tag the measurements with the given version of the benchmark code, and warn when comparing against a different version.")
+                .long_help(
+"Tag the measurements with the given version of the benchmark code. Change the tag whenever you \
+change what a benchmark measures without renaming it. A warning is printed when a benchmark is \
+compared against a measurement with a different tag, or with no tag, since the numbers may not be \
+comparable.
+")
+        )
+        .arg(
+            Arg::with_name("bench-seed")
+                .long("--bench-seed")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Pass the seed N to the benchmark targets in the CARGO_CRITERION_BENCH_SEED environment variable, and record it with the measurements.")
+                .long_help(
+"Pass the seed N to the benchmark targets in the CARGO_CRITERION_BENCH_SEED environment variable, \
+and record it with the measurements. Benchmarks which generate random inputs can seed their random \
+number generator from this variable, so that every run measures the same inputs and the variance \
+between runs comes from the code rather than from the inputs. cargo-criterion only passes the seed \
+on; it has no effect on benchmarks which don't read it.
+
+A warning is printed when a benchmark is compared against a measurement taken with a different \
+seed, or without one, since the benchmark may have measured different inputs.
+")
+        )
+        .arg(
+            Arg::with_name("single-thread")
+                .long("--single-thread")
+                .help("Ask the benchmark targets to run their worker thread pools on a single thread, and record this with the measurements.")
+                .long_help(
+"Ask the benchmark targets to run their worker thread pools on a single thread, by setting the \
+RAYON_NUM_THREADS, TOKIO_WORKER_THREADS and OMP_NUM_THREADS environment variables to 1, and record \
+this with the measurements. This removes the noise that parallelism adds to the measurements of \
+code that uses rayon, a multi-threaded tokio runtime or OpenMP.
+
+This only affects libraries which read those variables; threads started directly with \
+std::thread, or by thread pools configured in code, are unaffected. It is independent of \
+--cpu-affinity, which restricts the CPUs that the threads may run on but not how many threads \
+there are. A warning is printed when a benchmark is compared against a measurement which was taken \
+with a different setting.
+")
+        )
+        .arg(
+            Arg::with_name("connect-port")
+                .long("--connect-port")
+                .takes_value(true)
+                .value_name("PORT")
+                .validator(|value| match value.parse::<u16>() {
+                    Ok(0) | Err(_) => Err(format!("'{}' is not a valid port number", value)),
+                    Ok(_) => Ok(()),
+                })
+                .help("Listen for the connections from the benchmark targets on PORT instead of on any free port.")
+                .long_help(
+"Listen for the connections from the benchmark targets on PORT of localhost instead of on any free \
+port. The port is passed to the targets as usual, in the CARGO_CRITERION_PORT environment variable. \
+This is useful in sandboxes and restrictive network namespaces where only certain ports can be \
+used. The run fails with an error if the port is already in use.
+")
+        )
+        .arg(
+            Arg::with_name("min-samples")
+                .long("--min-samples")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Make the benchmarks take at least N samples, and report benchmarks with fewer than N usable samples as low confidence. Defaults to 10.")
+                .long_help(
+"Make the benchmarks take at least N samples, and report benchmarks with fewer than N usable \
+samples as low confidence. Defaults to 10, the smallest sample size that Criterion.rs allows.
+
+A benchmark configured with a smaller sample size takes N samples instead, even if that runs past \
+its measurement time, which gives more reliable estimates for slow benchmarks at the cost of a \
+longer run. A warning is printed when this happens, and it is recorded with the measurement. Only \
+benchmarks built with a version of Criterion.rs which accepts sampling hints can be asked for more \
+samples; the others are sampled as usual.
+
+A benchmark can also end up with fewer samples than it asked for if some of them couldn't be used, \
+and the confidence intervals of estimates based on so few samples are too wide to trust, so a \
+change in their performance may just be noise. Low confidence benchmarks are flagged in the \
+command-line output, the HTML reports and the JSON messages.
+")
+        )
+        .arg(
+            Arg::with_name("significance-test")
+                .long("--significance-test")
+                .takes_value(true)
+                .possible_values(&["ci", "welch", "mann-whitney"])
+                .hide_possible_values(true)
+                .help("Choose the test which decides whether a change in performance is significant. Possible values are ci, welch, mann-whitney.")
+                .long_help(
+"Choose the statistical test which decides whether the performance of a benchmark has changed \
+significantly since the measurement it's compared against. A change is significant when the test's \
+p-value is below the significance level, which is 0.05 unless the benchmark sets another. Every test \
+is applied to the time per iteration of each sample, which is read from the raw samples of both \
+measurements. Possible values are [ci, welch, mann-whitney].
+
+ci: Bootstrap the relative change in the mean time per iteration, and check whether its confidence \
+interval excludes zero. This makes no assumptions about how the samples are distributed, but since \
+it compares means, a few outliers can hide a change or make one up. With the default significance \
+level, it agrees with the 95% confidence interval of the change that is reported for the benchmark.
+
+welch: Welch's t-test, which compares the means and allows the two measurements to have different \
+variances. The distribution of the t statistic is bootstrapped from the samples instead of assuming \
+that they're normally distributed, but the test is still sensitive to outliers. The default, and the \
+test that Criterion.rs uses.
+
+mann-whitney: The Mann-Whitney U test, which compares the ranks of the samples instead of their \
+values, so it's robust against outliers and skewed, non-normal timings. It detects whether the times \
+of one measurement tend to be larger than those of the other, which isn't quite the same as a change \
+in the mean. It assumes that the samples are independent of each other, and the p-value uses the \
+normal approximation, which is accurate for sample sizes of 10 or more.
+")
+        )
+        .arg(
+            Arg::with_name("compare-metric")
+                .long("--compare-metric")
+                .takes_value(true)
+                .possible_values(&["mean", "median", "slope"])
+                .hide_possible_values(true)
+                .help("Choose the statistic whose change decides whether a benchmark improved or regressed. Possible values are mean, median, slope. Defaults to mean.")
+                .long_help(
+"Choose the statistic whose change decides whether a benchmark has improved or regressed. The change \
+in this statistic is the one that is highlighted in the reports, recorded as the benchmark's change, \
+and checked by --fail-on-regression. Possible values are [mean, median, slope].
+
+mean: The mean time per iteration. The default.
+
+median: The median time per iteration, which a few outliers don't move.
+
+slope: The slope of the linear regression of the sample times on the iteration counts, which is the \
+estimate Criterion.rs reports as the time per iteration. Only measurements taken with linear \
+sampling have a slope. If either of the compared measurements doesn't, a warning is printed and the \
+means are compared instead.
+
+With --significance-test ci, the confidence interval of the change in this statistic decides whether \
+the change is significant.
+")
+        )
+        .arg(
+            Arg::with_name("no-new-benchmarks")
+                .long("--no-new-benchmarks")
+                .help("Fail if any benchmark has no stored measurement, such as a benchmark with a misspelled name.")
+                .long_help(
+"Fail if any benchmark that is run has no stored measurement in the current timeline, such as a \
+new benchmark or one whose name was misspelled. The measurements of such benchmarks are not saved, \
+so they stay new until the stored data is updated without this option. The other benchmarks are \
+run as usual, and cargo-criterion exits with code 6 at the end.
+")
+        )
+        .arg(
+            Arg::with_name("fail-on-regression")
+                .long("--fail-on-regression")
+                .help("Fail if the performance of any benchmark has regressed.")
+                .long_help(
+"Fail if the performance of any benchmark has regressed compared to the stored measurement, using \
+the same significance level and noise threshold as the report. The other benchmarks are run as \
+usual, and cargo-criterion exits with code 7 at the end.
+")
+        )
+        .arg(
+            Arg::with_name("ignore-regression")
+                .long("--ignore-regression")
+                .takes_value(true)
+                .value_name("GLOB")
+                .multiple(true)
+                .number_of_values(1)
+                .requires("fail-on-regression")
+                .help("Don't fail on regressions of the benchmarks matching GLOB. Can be given more than once.")
+                .long_help(
+"Don't let regressions of the benchmarks whose IDs match GLOB fail the run, for benchmarks which \
+are known to be noisy. In GLOB, `*` matches any sequence of characters, including `/`. The matching \
+benchmarks are still run, and their changes are still reported. Can be given more than once.
+")
+        )
+        .arg(
+            Arg::with_name("deny-duplicate-ids")
+                .long("--deny-duplicate-ids")
+                .help("Fail if any benchmark or group ID is encountered more than once.")
+                .long_help(
+"Fail if any benchmark or group ID is encountered more than once, including the IDs which \
+`allow_duplicate_ids` in Criterion.toml allows to be. By default, a duplicate ID is only warned \
+about. The other benchmarks are run as usual, and cargo-criterion exits with code 9 at the end.
+")
+        )
+        .arg(
+            Arg::with_name("no-missing-benchmarks")
+                .long("--no-missing-benchmarks")
+                .help("Fail if any benchmark group has fewer benchmarks than in its previous run.")
+                .long_help(
+"Fail if any benchmark group has fewer benchmarks than in its previous run, such as when a \
+benchmark was deleted or renamed by mistake. Benchmarks which are filtered out still count as part \
+of their group. By default, a missing benchmark is only warned about. With this option, the previous \
+benchmarks of the group stay on record, so that the next run fails again until it's run once \
+without the option. The other benchmarks are run as usual, and cargo-criterion exits with code 10 \
+at the end.
+")
+        )
+        .arg(
+            Arg::with_name("budget")
+                .long("--budget")
+                .takes_value(true)
+                .value_name("ID=DURATION")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|s| Budget::from_arg(&s).map(|_| ()))
+                .help("Fail the run if the benchmarks matching ID take longer than DURATION per iteration. Can be given more than once.")
+                .long_help(
+"Fail the run if the benchmarks whose IDs match ID take longer than DURATION per iteration, such as \
+`--budget my_group/parse=5ms`. In ID, `*` matches any sequence of characters, including `/`. \
+DURATION is a number followed by one of the units ns, us, ms or s. Can be given more than once; if \
+several budgets match a benchmark, the last one applies. Budgets can also be given in Criterion.toml \
+as [[budgets]] tables with `benchmarks` and `limit` keys, which come before those given on the \
+command line.
+
+Budgets are checked against each new measurement on its own, regardless of any earlier \
+measurements, and the reports show whether each benchmark passed. Benchmarks without a budget are \
+unaffected. A run with benchmarks over their budget exits with code 8.
+")
+        )
+        .arg(
+            Arg::with_name("budget-statistic")
+                .long("--budget-statistic")
+                .takes_value(true)
+                .possible_values(&["mean", "upper-bound"])
+                .hide_possible_values(true)
+                .help("Choose the estimate which is checked against the budgets. Possible values are mean, upper-bound.")
+                .long_help(
+"Choose the estimate of each benchmark's time which is checked against its --budget. Possible \
+values are [mean, upper-bound].
+
+mean: The mean time per iteration. The default.
+
+upper-bound: The upper bound of the confidence interval of the mean. A benchmark only passes if it \
+is confidently within its budget, which leaves room for noise.
+")
+        )
+        .arg(
+            Arg::with_name("adaptive-sampling")
+                .long("--adaptive-sampling")
+                .help("Let the benchmarks use the previous estimates to spend less time warming up and sampling.")
+                .long_help(
+"Tell each benchmark the mean and standard deviation from its previous measurement, so that it can \
+shorten its warm-up and measurement when the previous estimate is stable. This makes reruns faster \
+at the cost of some accuracy, so it is best suited to quick local iteration rather than to \
+measurements you intend to keep as a reference.
+
+This requires a version of Criterion.rs which can use the previous estimates. Benchmarks built with \
+older versions are sampled as usual, with a warning.
+")
+        )
+        .arg(
+            Arg::with_name("measure")
+                .long("--measure")
+                .takes_value(true)
+                .possible_values(&["time", "instructions"])
+                .hide_possible_values(true)
+                .help("Change what is measured for each benchmark. Possible values are time, instructions.")
+                .long_help(
+"Change what is measured for each benchmark. Possible values are [time, instructions].
+
+time: Measure the time taken by each iteration. The default.
+
+instructions: Also count the user-space instructions executed per iteration, which is much less \
+affected by other activity on the machine than the time. This is only supported on Linux, using the \
+perf_event interface, and requires permission to read the performance counters of your own \
+processes. That's allowed when the kernel.perf_event_paranoid sysctl is 2 or lower, which is the \
+default on most distributions; containers may also need to allow the perf_event_open system call.
+
+The count includes the overhead of Criterion.rs's measurement loop, and the instructions executed \
+by any threads the benchmark starts, so it is best used to compare runs of the same benchmark.
+")
+        )
+        .arg(
+            Arg::with_name("profile-time")
+                .long("--profile-time")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Iterate each benchmark for approximately the given number of seconds so it can be profiled, instead of measuring it.")
+                .long_help(
+"Iterate each benchmark for approximately the given number of seconds so that it can be profiled \
+with an external profiler or a Criterion.rs profiler hook, instead of measuring it. No measurements \
+are recorded and no reports are generated.
+
+Any output written by a profiler hook is moved into the benchmark's data directory, next to its \
+stored measurements, in a `profile` subdirectory named after the time of the run.
+")
+        )
+        .arg(
+            Arg::with_name("check")
+                .long("--check")
+                .conflicts_with("profile-time")
+                .help("Run each benchmark as briefly as possible to check that it works, without keeping the results.")
+                .long_help(
+"Run each benchmark as briefly as possible to check that it compiles, connects to cargo-criterion \
+and runs without panicking. The benchmarks are run with the smallest sample size, warm-up time and \
+measurement time that Criterion.rs accepts (10 samples, 1 second and 1 second), so the estimates \
+they produce are not meaningful. Settings made in the benchmark code take precedence over these.
+
+The results are kept in a separate `check` directory under the criterion home, which is cleared \
+at the start of each check run. They aren't compared against or saved into the stored \
+measurements, and no HTML report is generated.
+")
+        )
+        .arg(
+            Arg::with_name("sort-by")
+                .long("--sort-by")
+                .takes_value(true)
+                .possible_values(&["insertion", "name", "time", "change"])
+                .hide_possible_values(true)
+                .help("Change the order of the benchmarks in the summary reports. Possible values are insertion, name, time, change.")
+                .long_help(
+"Change the order in which the HTML index and the GitHub job summary list benchmark groups and \
+benchmarks. Possible values are [insertion, name, time, change]. The command-line output is printed \
+as the benchmarks run, so it is not affected.
+
+insertion: The order in which the benchmarks were run. The default.
+
+name: Alphabetically by name.
+
+time: Slowest first.
+
+change: Biggest regression first. Benchmarks without a comparison are listed last.
+")
+        )
+        .arg(
+            Arg::with_name("report-title")
+                .long("--report-title")
+                .takes_value(true)
+                .value_name("TEXT")
+                .help("Use TEXT as the title of the HTML index and the PDF report.")
+                .long_help(
+"Use TEXT as the title of the HTML index and the PDF report, instead of the generic one. This helps \
+to tell apart reports archived for particular releases or machines. This can also be set with \
+`report_title` in Criterion.toml.
+")
+        )
+        .arg(
+            Arg::with_name("report-description")
+                .long("--report-description")
+                .takes_value(true)
+                .value_name("TEXT")
+                .help("Show TEXT under the title of the HTML index and the PDF report.")
+                .long_help(
+"Show TEXT under the title of the HTML index and the PDF report, for example to describe the \
+release or the hardware that the benchmarks were run on. This can also be set with \
+`report_description` in Criterion.toml.
+")
+        )
+        .arg(
+            Arg::with_name("time-unit")
+                .long("--time-unit")
+                .takes_value(true)
+                .possible_values(&["auto", "ns", "us", "ms", "s"])
+                .hide_possible_values(true)
+                .help("Show every time in the given unit. Possible values are auto, ns, us, ms, s.")
+                .long_help(
+"Show every time in the given unit, which makes the times of benchmarks of very different speeds \
+easy to compare. Possible values are [auto, ns, us, ms, s]. This applies to the command-line output, \
+the reports and the axes of the plots, but not to machine-readable output, which is always in \
+nanoseconds. The default, auto, chooses a unit to suit each value. Only use this with benchmarks \
+which measure wall-clock time, as Criterion.rs does by default.
+")
+        )
+        .arg(
+            Arg::with_name("change-ratio-above")
+                .long("--change-ratio-above")
+                .takes_value(true)
+                .value_name("FACTOR")
+                .validator(|s| match s.parse::<f64>() {
+                    Ok(factor) if factor > 1.0 => Ok(()),
+                    _ => Err(format!("'{}' is not a number greater than 1", s)),
+                })
+                .help("Show changes in time of at least FACTOR times as ratios, such as '2.40x slower', instead of percentages.")
+                .long_help(
+"Show changes in time of at least FACTOR times in either direction as ratios, such as '2.40x \
+slower' or '3.00x faster', instead of percentages. Large changes are easier to read this way: a \
++140% change is 2.40x slower, and a -75% change is 4.00x faster. Smaller changes are still shown as \
+percentages. This applies to the command-line output, the HTML reports, the GitHub job summary and \
+the compare-baseline command. Changes in throughput are always shown as percentages.
+")
+        )
+        .arg(
+            Arg::with_name("storage-format")
+                .long("--storage-format")
+                .takes_value(true)
+                .possible_values(&["files", "log"])
+                .hide_possible_values(true)
+                .help("Change how new measurements are stored. Possible values are files, log.")
+                .long_help(
+"Change how new measurements are written to disk. Possible values are [files, log]. Measurements \
+stored in either format are always loaded.
+
+files: Write each measurement to its own file. The default.
+
+log: Append each measurement to a single measurements.log file per benchmark. This uses far fewer \
+files for suites with many benchmarks.
+")
+        )
+        .arg(
+            Arg::with_name("store")
+                .long("--store")
+                .takes_value(true)
+                .possible_values(&["full", "summary"])
+                .hide_possible_values(true)
+                .help("Change how much of each new measurement is stored. Possible values are full, summary.")
+                .long_help(
+"Change how much of each new measurement is written to disk. Possible values are [full, summary].
+
+full: Store the raw samples along with the statistical estimates. The default.
+
+summary: Store only the statistical estimates, which uses much less space. The history reports are \
+still generated, but later runs can't be compared against these measurements, because the \
+comparison needs the raw samples.
+")
+        )
+        .arg(
+            Arg::with_name("db")
+                .long("--db")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("Also insert each new measurement into the SQLite database at PATH, and read the stored measurements from it in the list-groups and report commands.")
+                .long_help(
+"Also insert each new measurement into the SQLite database at PATH, which is created if it doesn't \
+exist. The measurements are still saved in the data directory as usual. The list-groups and report \
+commands read the latest measurements from the database instead of the data directory.
+
+Each measurement is a row of the `measurements` table, with the columns group_id, function_id, \
+value_str, datetime, mean, ci_lower, ci_upper (in nanoseconds), throughput, throughput_unit, \
+commit (the --history-id), target and stats (the whole measurement, encoded as CBOR). This makes \
+it easy to analyze the history of many runs with standard tools. Only available if cargo-criterion \
+was installed with the 'sqlite' feature, which links against the system's libsqlite3.
+")
+        )
+        .arg(
+            Arg::with_name("dir-name-strategy")
+                .long("--dir-name-strategy")
+                .takes_value(true)
+                .possible_values(&["readable", "hashed"])
+                .hide_possible_values(true)
+                .help("Change how the directories of new benchmarks are named. Possible values are readable, hashed.")
+                .long_help(
+"Change how the directories that the measurements of new benchmarks are stored in are named. \
+Possible values are [readable, hashed]. Benchmarks which already have stored measurements keep \
+using their existing directories, and benchmarks stored either way are always loaded.
+
+readable: Store each benchmark in nested directories named after its group, function and value. \
+The default.
+
+hashed: Store each benchmark in a directory named after its group, containing a directory named \
+with a short hash of the full benchmark ID. This keeps paths short when benchmarks have long \
+function names or values, which can otherwise exceed the path length limit on Windows.
+")
+        )
+        .arg(
+            Arg::with_name("datetime-from-mtime")
+                .long("--datetime-from-mtime")
+                .help("Date stored measurements which don't record when they were taken by their file's modification time.")
+                .long_help(
+"Date stored measurements which don't record when they were taken by the modification time of the \
+file they're stored in. Measurement files imported from other tools may not record a timestamp, \
+which otherwise sorts them before every other measurement in the history reports. Measurements in \
+the same measurement log share its modification time, so they stay in the order they were appended.
+")
+        )
+        .arg(
+            Arg::with_name("max-open-files")
+                .long("--max-open-files")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(0) | Ok(1) => Err("N must be at least 2".to_owned()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                })
+                .help("Keep at most N files open at once while loading the stored data. Defaults to 10.")
+                .long_help(
+"Keep at most N files open at once while loading or checking the stored data. Each stored file is \
+closed as soon as it has been read, so this mostly limits how many of the directories being searched \
+are kept open; deeper directories are read into memory instead. Lower it on systems with a low limit \
+on open files, where loading a large tree of stored data would otherwise fail with \"too many open \
+files\" errors. Defaults to 10.
+")
+        )
+        .arg(
+            Arg::with_name("history-max-size")
+                .long("--history-max-size")
+                .takes_value(true)
+                .value_name("SIZE")
+                .validator(|s| parse_size(&s).map(|_| ()))
+                .help("After the run, delete the oldest measurements until the stored data fits in SIZE, such as 500MB.")
+                .long_help(
+"After the benchmarks have run, delete the oldest stored measurements until the data directory \
+takes up no more than SIZE. Sizes may be given in bytes or with a unit: KB, MB and GB are powers of \
+1000, and KiB, MiB and GiB are powers of 1024. The budget covers every benchmark in every timeline, \
+so the oldest measurements are deleted first whichever benchmark they belong to. The latest \
+measurement and the named baselines of each benchmark are never deleted. Measurements stored with \
+--storage-format log count towards the budget but can't be deleted one at a time, so they are kept.
+")
+        )
+        .arg(
+            Arg::with_name("github-summary")
+                .long("--github-summary")
+                .help("Append a Markdown table of the results to the GitHub Actions job summary (the file named by $GITHUB_STEP_SUMMARY).")
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("--verbose")
+                .short("v")
+                .multiple(true)
+                .help("Use verbose output (-vv very verbose/build.rs output). Only used for Cargo builds; see also --output-format"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("--color")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .help("Coloring: auto, always, never"),
+        )
+        .arg(
+            Arg::with_name("frozen")
+                .long("--frozen")
+                .help("Require Cargo.lock and cache are up to date"),
+        )
+        .arg(
+            Arg::with_name("locked")
+                .long("--locked")
+                .help("Require Cargo.lock is up to date"),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .long("--offline")
+                .help("Run without accessing the network"),
+        )
+        .arg(
+            Arg::with_name("unstable_flags")
+                .short("Z")
+                .takes_value(true)
+                .value_name("FLAG")
+                .multiple(true)
+                .help("Unstable (nightly-only) flags to Cargo, see 'cargo -Z help' for details"),
+        )
+        .arg(
+            Arg::with_name("SUBCOMMAND")
+                .hidden(true)
                 .help("Cargo passes the name of the subcommand as the first param, so ignore it."),
         )
         .arg(
@@ -498,6 +2287,343 @@ See the documentation for details on the data printed by each format.
                 .multiple(true)
                 .help("Arguments for the bench binary"),
         )
+        .subcommand(
+            SubCommand::with_name("list-groups")
+                .about("List the benchmark groups in the stored data without running anything")
+                .arg(
+                    Arg::with_name("json")
+                        .long("--json")
+                        .help("Print the groups as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("commits")
+                .about("List the distinct commits in the stored history without running anything")
+                .long_about(
+"List the distinct commits in the stored history without running anything. A measurement's commit \
+is the --history-id given when it was taken. Each commit is printed with the number of benchmarks \
+and measurements recorded against it and the range of times they were measured, which helps to \
+choose a commit to compare against with `--compare-against commit:SHA`. Measurements which don't \
+record a commit are left out.",
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("--json")
+                        .help("Print the commits as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pool")
+                .about("Analyze the last few measurements of a benchmark together without running anything")
+                .long_about(
+"Analyze the samples of the last N stored measurements of a benchmark together, and store the \
+result as a new measurement of the benchmark, without running anything. This gives estimates with \
+narrower confidence intervals when the same benchmark has been run several times, for example with \
+--repeat. Later runs are compared against the pooled measurement.
+
+Every sample keeps its own iteration count, so measurements with different iteration counts can be \
+pooled, and each sample counts once however many iterations it ran. The slope is only estimated if \
+all of the measurements used linear sampling. The measurements must have the same throughput and \
+must store their samples, so those saved with `--store summary` can't be pooled. The \
+statistical settings that the measurements were taken with aren't stored, so the estimates use \
+Criterion.rs' defaults along with any overrides in Criterion.toml.",
+                )
+                .arg(
+                    Arg::with_name("benchmark")
+                        .long("--benchmark")
+                        .takes_value(true)
+                        .value_name("ID")
+                        .required(true)
+                        .help("The full title of the benchmark, such as my_group/my_function/10"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .long("--count")
+                        .takes_value(true)
+                        .value_name("N")
+                        .required(true)
+                        .validator(|s| match s.parse::<usize>() {
+                            Ok(0) | Ok(1) => Err("N must be at least 2".to_owned()),
+                            Ok(_) => Ok(()),
+                            Err(e) => Err(e.to_string()),
+                        })
+                        .help("How many of the benchmark's latest measurements to pool"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("accept")
+                .about("Save the latest stored measurements as a named baseline without running anything")
+                .long_about(
+"Save the latest stored measurement of each benchmark as the baseline with the given name, without \
+running anything. This approves the numbers from a run after they have been reviewed, so that later \
+runs can be compared against them with `--compare-against baseline:NAME`. It has the same effect as \
+having given `--save-baseline NAME` to that run. Any earlier baseline with the same name is \
+replaced. Only the stored data in the timeline selected by `--timeline` is changed.",
+                )
+                .arg(
+                    Arg::with_name("baseline")
+                        .long("--baseline")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .required(true)
+                        .help("The name of the baseline to save the measurements as"),
+                )
+                .arg(
+                    Arg::with_name("benchmarks")
+                        .long("--benchmarks")
+                        .takes_value(true)
+                        .value_name("PATTERN")
+                        .help("Only accept the benchmarks whose IDs match PATTERN, where `*` matches any sequence of characters"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bundle")
+                .about("Package one benchmark's stored data and report into a single file")
+                .long_about(
+"Package the stored data of one benchmark into a single file, without running anything. The bundle \
+holds the benchmark's benchmark.cbor, all of its stored measurements, its HTML report (if one has been \
+generated) and a text summary of its measurement history. It is a tar archive, and can be unpacked into \
+another data directory with `cargo criterion import`, for instance to share a regression with someone \
+who can't reproduce it.",
+                )
+                .arg(
+                    Arg::with_name("benchmark")
+                        .long("--benchmark")
+                        .takes_value(true)
+                        .value_name("ID")
+                        .required(true)
+                        .help("The ID of the benchmark to bundle"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("--out")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .required(true)
+                        .help("The file to write the bundle to"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Unpack a bundle made by `cargo criterion bundle`")
+                .long_about(
+"Unpack a bundle made by `cargo criterion bundle` into the data and report directories under the \
+current criterion home, without running anything, and print the summary of the bundled benchmark's \
+measurement history. The stored data keeps the timeline it was bundled from. It is an error for the \
+bundle to contain a file which already exists, unless `--force` is given.",
+                )
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .help("The bundle to unpack"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("--force")
+                        .help("Replace existing files with the files in the bundle"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Print the latest stored measurements of a benchmark group without running anything")
+                .long_about(
+"Print the latest stored measurements of a benchmark group without running anything, along with \
+their change from the measurements before them. Only the stored data of the given group is read, so \
+this is fast even when there are many benchmarks.",
+                )
+                .arg(
+                    Arg::with_name("group")
+                        .long("--group")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .required(true)
+                        .help("The name of the benchmark group to report on"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Print the estimates stored in a single measurement file without running anything")
+                .long_about(
+"Print the estimates, throughput, sample count and date stored in a single measurement file (such \
+as target/criterion/data/main/my_group/measurement_<date>.cbor) without running anything. Exits with \
+an error if the file can't be read, or if its contents couldn't be reported or compared against.",
+                )
+                .arg(
+                    Arg::with_name("PATH")
+                        .required(true)
+                        .help("The measurement file to print"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Check the stored data for corrupt or missing files without running anything")
+                .long_about(
+"Check the stored data for corrupt or missing files without running anything. Every benchmark and \
+measurement file in every timeline is loaded, and any problems are reported. Exits with an error if \
+any stored data is damaged.",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("preflight")
+                .about("Check that the environment is ready for a run without running anything")
+                .long_about(
+"Check that the environment is ready for a run without running anything, and print a table of the \
+results. The checks are: that Cargo can be run (the one in $CARGO if it's set, or else the one on \
+the PATH), that the criterion home directory is writable, that the plotting backend is usable, that \
+the CPUs use the performance frequency governor, and that there is enough free space for the stored \
+data to grow to its --history-max-size, or at least 1 GiB free without one.
+
+Each check passes, warns or fails. Only problems which would make the run fail are failures: Cargo \
+or the criterion home being unusable, or no plotting backend being usable with --require-plots. \
+Exits with an error if any check fails, so this can be run as its own step in CI.",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("capabilities")
+                .about("List the supported output formats and plotting backends without running anything")
+                .long_about(
+"List the output formats, plotting backends and message formats which this build of cargo-criterion \
+supports, and the newest version of the benchmark protocol it understands, without running \
+anything. Each output format and plotting backend is listed with whether it can be used in this \
+environment; a plotting backend may be unusable because it wasn't compiled in, or because gnuplot \
+isn't installed. This is meant for tools which wrap cargo-criterion.",
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("--json")
+                        .help("Print the capabilities as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare-baseline")
+                .about("Compare stored measurements against a named baseline without running anything")
+                .long_about(
+"Compare the latest measurements stored under another criterion home directory against a baseline \
+saved with `--save-baseline`, without running anything. The baseline is read from this project's \
+stored data, in the timeline selected by `--timeline`. Nothing is written.
+
+Benchmarks which only have a measurement in the other directory are reported as added, and \
+benchmarks which only have a baseline are reported as removed.",
+                )
+                .arg(
+                    Arg::with_name("baseline")
+                        .long("--baseline")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .required(true)
+                        .help("The name of the baseline to compare against"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("--from")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .required(true)
+                        .help("The criterion home directory (such as target/criterion) containing the measurements to compare"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("--format")
+                        .takes_value(true)
+                        .possible_values(&["text", "markdown", "json"])
+                        .default_value("text")
+                        .help("How to print the comparison"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare-commits")
+                .about("Compare the measurements recorded against two commits without running anything")
+                .long_about(
+"Compare the stored measurements recorded against two commits, ie. two values of `--history-id`, \
+without running anything. Each commit may be given as a prefix of its SHA, as with \
+`--compare-against commit:SHA`, and if a benchmark was measured more than once against a commit, its \
+most recent measurement there is used. Only the stored data in the timeline selected by `--timeline` \
+is read, and nothing is written. `cargo criterion commits` lists the commits in the stored history.
+
+Benchmarks which were only measured against the head commit are reported as added, and benchmarks \
+which were only measured against the base commit are reported as removed.",
+                )
+                .arg(
+                    Arg::with_name("base")
+                        .long("--base")
+                        .takes_value(true)
+                        .value_name("SHA")
+                        .required(true)
+                        .help("The commit to compare against"),
+                )
+                .arg(
+                    Arg::with_name("head")
+                        .long("--head")
+                        .takes_value(true)
+                        .value_name("SHA")
+                        .required(true)
+                        .help("The commit to compare"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("--format")
+                        .takes_value(true)
+                        .possible_values(&["text", "markdown", "json"])
+                        .default_value("text")
+                        .help("How to print the comparison"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Convert the stored data to another storage format without running anything")
+                .long_about(
+"Convert the stored data to another storage format without running anything. The measurements of \
+every benchmark in every timeline are rewritten in the given format, keeping their history and named \
+baselines. Benchmarks which are already stored in that format are left alone, so this can safely be \
+run again. Refuses to run while benchmarks are running.",
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("--to")
+                        .takes_value(true)
+                        .possible_values(&["files", "log"])
+                        .required(true)
+                        .help("The storage format to convert to (see --storage-format)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serve the generated reports over HTTP without running anything")
+                .long_about(
+"Serve the generated reports over HTTP without running anything, so that they can be browsed from \
+another machine. The server is read-only and only serves files from the report directory. It listens \
+on localhost unless `--host` is given.",
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("--from")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .help("The report directory to serve. Defaults to the reports directory in the criterion home directory"),
+                )
+                .arg(
+                    Arg::with_name("host")
+                        .long("--host")
+                        .takes_value(true)
+                        .value_name("HOST")
+                        .default_value("localhost")
+                        .help("The address to listen on, such as 0.0.0.0 to accept connections from other machines"),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .long("--port")
+                        .takes_value(true)
+                        .value_name("PORT")
+                        .default_value("8000")
+                        .validator(|value| {
+                            value
+                                .parse::<u16>()
+                                .map(|_| ())
+                                .map_err(|_| format!("'{}' is not a valid port number", value))
+                        })
+                        .help("The port to listen on"),
+                ),
+        )
         .after_help(
             "\
 The benchmark filtering argument BENCHNAME and all the arguments following the
@@ -518,11 +2644,80 @@ Note that `--exclude` has to be specified in conjunction with the `--workspace`
 The `--jobs` argument affects the building of the benchmark executable but does
 not affect how many jobs are used when running the benchmarks.
 
+An argument of the form `@FILE` before the two dashes is replaced by the arguments
+listed in FILE, one per line.
+
 Compilation can be customized with the `bench` profile in the manifest.
+
+cargo-criterion exits with code 2 if the benchmarks fail to compile, 3 if a benchmark
+target exits with an error, 4 if the connection to a benchmark target fails, 5 if
+`cargo criterion doctor` finds corrupt data, 6 if --no-new-benchmarks rejects a
+benchmark, 7 if --fail-on-regression finds a regression, 8 if a benchmark is over its
+budget, 9 if --deny-duplicate-ids finds a duplicate ID, 10 if --no-missing-benchmarks finds
+a group with fewer benchmarks than before, and 1 for any other error.
 ",
         )
-        .get_matches();
+}
+
+/// Parse the command-line arguments, load the Criterion.toml config file, and generate a
+/// configuration object used for the rest of the run. If the arguments are invalid, the usage
+/// message is printed and the process exits.
+pub fn configure<I: IntoIterator<Item = OsString>>(args: I) -> Result<FullConfig, anyhow::Error> {
+    let matches = app().get_matches_from(expand_arg_files(args)?);
+    configure_from_matches(matches, None)
+}
+
+/// Like `configure`, but invalid arguments are returned as an error instead, and `criterion_home`
+/// (if given) takes precedence over every other way of choosing the Criterion home directory.
+pub fn try_configure<I: IntoIterator<Item = OsString>>(
+    args: I,
+    criterion_home: Option<PathBuf>,
+) -> Result<FullConfig, anyhow::Error> {
+    let matches = app().get_matches_from_safe(expand_arg_files(args)?)?;
+    configure_from_matches(matches, criterion_home)
+}
 
+/// Choose the timeline to load and store measurements in, given the value of
+/// `$CARGO_CRITERION_TIMELINE`. The name is made safe to use as the timeline's directory name.
+fn select_timeline(
+    matches: &clap::ArgMatches<'static>,
+    env_timeline: Option<OsString>,
+    manifest_directory: &Path,
+) -> String {
+    // Set the timeline to (in descending order of preference):
+    // - The value of --timeline
+    // - $CARGO_CRITERION_TIMELINE
+    // - The current git branch, if --timeline-from-branch was given
+    // - Today's date, if --timeline-date was given
+    // - main
+    let timeline = if let Some(value) = matches.value_of("timeline") {
+        value.to_owned()
+    } else if let Some(value) = env_timeline {
+        value.to_string_lossy().into_owned()
+    } else if matches.is_present("timeline-from-branch") {
+        match current_git_branch(manifest_directory) {
+            Ok(branch) => branch,
+            Err(e) => {
+                warn!(
+                    "Unable to determine the current git branch, using the main timeline: {:#}",
+                    e
+                );
+                "main".to_owned()
+            }
+        }
+    } else if matches.is_present("timeline-date") {
+        Local::now().format("%Y-%m-%d").to_string()
+    } else {
+        "main".to_owned()
+    };
+    crate::report::make_filename_safe(&timeline)
+}
+
+#[allow(clippy::or_fun_call)]
+fn configure_from_matches(
+    matches: clap::ArgMatches<'static>,
+    criterion_home: Option<PathBuf>,
+) -> Result<FullConfig, anyhow::Error> {
     // Load the config file.
     let criterion_manifest_file: PathBuf = matches
         .value_of_os("criterion-manifest-file")
@@ -609,6 +2804,10 @@ Compilation can be customized with the `bench` profile in the manifest.
         cargo_args.push("--manifest-path".into());
         cargo_args.push(value.to_owned());
     }
+    if let Some(value) = matches.value_of_os("profile") {
+        cargo_args.push("--profile".into());
+        cargo_args.push(value.to_owned());
+    }
     for _ in 0..matches.occurrences_of("verbose") {
         cargo_args.push("--verbose".into());
     }
@@ -631,60 +2830,368 @@ Compilation can be customized with the `bench` profile in the manifest.
     }
 
     // Set criterion home to (in descending order of preference):
+    // - The directory given to `try_configure`
     // - $CRITERION_HOME
     // - The value from the config file
     // - ${--target-dir}/criterion
     // - ${target directory from `cargo metadata`}/criterion
     // - ./target/criterion
-    let criterion_home = if let Some(value) = std::env::var_os("CRITERION_HOME") {
+    let criterion_home = if let Some(home) = criterion_home {
+        home
+    } else if let Some(value) = std::env::var_os("CRITERION_HOME") {
         PathBuf::from(value)
     } else if let Some(home) = toml_config.criterion_home {
         home
     } else if let Some(value) = matches.value_of_os("target-dir") {
         PathBuf::from(value).join("criterion")
-    } else if let Ok(mut target_path) = get_target_directory_from_metadata() {
+    } else if let Ok(mut target_path) = get_target_directory_from_metadata(&cargo_args) {
         target_path.push("criterion");
         target_path
     } else {
         PathBuf::from("target/criterion")
     };
 
+    let github_summary = if matches.is_present("github-summary") {
+        let path = std::env::var_os("GITHUB_STEP_SUMMARY").map(PathBuf::from);
+        if path.is_none() {
+            warn!("--github-summary was given but GITHUB_STEP_SUMMARY is not set; no job summary will be written.");
+        }
+        path
+    } else {
+        None
+    };
+
+    let manifest_directory = matches
+        .value_of_os("manifest-path")
+        .and_then(|path| Path::new(path).parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.to_owned())
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let timeline = select_timeline(
+        &matches,
+        std::env::var_os("CARGO_CRITERION_TIMELINE"),
+        &manifest_directory,
+    );
+
+    // In check mode the results go to a throwaway directory, so that they are neither compared
+    // against nor mixed into the real measurements.
+    let check = matches.is_present("check");
+    let (data_directory, report_directory) = if check {
+        let check_home = criterion_home.join("check");
+        (check_home.join("data"), check_home.join("reports"))
+    } else {
+        (
+            matches
+                .value_of_os("data-dir")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| criterion_home.join("data")),
+            matches
+                .value_of_os("report-dir")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| criterion_home.join("reports")),
+        )
+    };
+
+    let measure_memory = matches.is_present("measure-memory");
+    if measure_memory && !cfg!(target_os = "linux") {
+        warn!("--measure-memory is only supported on Linux; memory usage will not be recorded.");
+    }
+
+    let measure = (matches.value_of("measure"))
+        .map(Measure::from_str)
+        .unwrap_or(Measure::Time);
+    if matches!(measure, Measure::Instructions) && !cfg!(target_os = "linux") {
+        warn!(
+            "--measure instructions is only supported on Linux; instructions will not be counted."
+        );
+    }
+
+    let cpu_affinity = matches
+        .value_of("cpu-affinity")
+        .map(|list| parse_cpu_list(list).unwrap())
+        .unwrap_or_default();
+    if !cpu_affinity.is_empty() && !cfg!(target_os = "linux") {
+        warn!("--cpu-affinity is only supported on Linux; the benchmarks may run on any CPU.");
+    }
+
+    let bench_cwd = match matches.value_of_os("bench-cwd") {
+        Some(path) => Some(
+            std::env::current_dir()
+                .context("Unable to get the current directory")?
+                .join(path),
+        ),
+        None => None,
+    };
+
+    let mut budgets = Vec::new();
+    for budget in &toml_config.budgets {
+        let limit_ns = parse_duration(&budget.limit).map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid limit for the budget of {} in the config file: {}",
+                budget.benchmarks,
+                e
+            )
+        })?;
+        budgets.push(Budget {
+            benchmarks: budget.benchmarks.clone(),
+            limit_ns,
+        });
+    }
+    if let Some(values) = matches.values_of("budget") {
+        budgets.extend(values.map(|s| Budget::from_arg(s).unwrap()));
+    }
+
     let self_config = SelfConfig {
         output_format: (matches.value_of("output-format"))
             .or(toml_config.output_format.as_deref())
             .map(OutputFormat::from_str)
             .unwrap_or(OutputFormat::Criterion),
+        data_directory,
+        report_directory,
         criterion_home,
         do_run: !matches.is_present("no-run"),
+        dry_run: matches.is_present("dry-run"),
         do_fail_fast: !matches.is_present("no-fail-fast"),
         text_color: (matches.value_of("color"))
             .map(TextColor::from_str)
             .unwrap_or(TextColor::Auto),
-        plotting_backend: (matches.value_of("plotting-backend"))
-            .or(toml_config.plotting_backend.as_deref())
-            .map(PlottingBackend::from_str)
-            .unwrap_or(PlottingBackend::Auto),
+        plotting_backend: if check {
+            PlottingBackend::Disabled
+        } else {
+            (matches.value_of("plotting-backend"))
+                .or(toml_config.plotting_backend.as_deref())
+                .map(PlottingBackend::from_str)
+                .unwrap_or(PlottingBackend::Auto)
+        },
         debug_build: matches.is_present("debug"),
+        profile: match matches.value_of("profile") {
+            Some(profile) => profile.to_owned(),
+            None if matches.is_present("debug") => "test".to_owned(),
+            None => "bench".to_owned(),
+        },
+        reuse_compile: matches.is_present("reuse-compile"),
+        manifest_directory,
         message_format: (matches.value_of("message-format")).map(MessageFormat::from_str),
+        message_path: matches.value_of_os("message-path").map(PathBuf::from),
+        upload_url: matches.value_of("upload-url").map(ToOwned::to_owned),
+        upload_headers: matches
+            .values_of("upload-header")
+            .map(|values| values.map(|s| parse_header(s).unwrap()).collect())
+            .unwrap_or_default(),
+        upload_required: matches.is_present("upload-required"),
+        log_format: LogFormat::from_str(matches.value_of("log-format").unwrap()),
         colors: toml_config.colors,
         history_id: matches.value_of("history_id").map(|s| s.to_owned()),
         history_description: matches
             .value_of("history_description")
             .map(|s| s.to_owned()),
+        github_summary,
+        compare_against: if matches.is_present("baseline-only") {
+            CompareAgainst::Nothing
+        } else if let Some(path) = matches.value_of_os("baseline-file") {
+            CompareAgainst::File(PathBuf::from(path))
+        } else {
+            matches
+                .value_of("compare-against")
+                .map(|s| CompareAgainst::from_str(s).unwrap())
+                .unwrap_or(CompareAgainst::Previous)
+        },
+        save_baseline: matches.value_of("save-baseline").map(|s| s.to_owned()),
+        export_baseline: matches.value_of_os("export-baseline").map(PathBuf::from),
+        timeline,
+        measure_memory: measure_memory && cfg!(target_os = "linux"),
+        cpu_affinity: if cfg!(target_os = "linux") {
+            cpu_affinity
+        } else {
+            Vec::new()
+        },
+        adaptive_sampling: matches.is_present("adaptive-sampling"),
+        bench_cwd,
+        bench_version: matches.value_of("bench-version").map(|s| s.to_owned()),
+        bench_seed: matches.value_of("bench-seed").map(|s| s.parse().unwrap()),
+        single_thread: matches.is_present("single-thread"),
+        connect_port: matches.value_of("connect-port").map(|s| s.parse().unwrap()),
+        min_samples: matches
+            .value_of("min-samples")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or(10),
+        significance_test: matches
+            .value_of("significance-test")
+            .map(SignificanceTest::from_str)
+            .unwrap_or_default(),
+        compare_metric: matches
+            .value_of("compare-metric")
+            .map(CompareMetric::from_str)
+            .unwrap_or_default(),
+        no_new_benchmarks: matches.is_present("no-new-benchmarks"),
+        run_retries: matches
+            .value_of("run-retries")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or(0),
+        fail_on_regression: matches.is_present("fail-on-regression"),
+        ignore_regression: matches
+            .values_of("ignore-regression")
+            .map(|values| values.map(|s| s.to_owned()).collect())
+            .unwrap_or_default(),
+        allow_duplicate_ids: toml_config.allow_duplicate_ids,
+        deny_duplicate_ids: matches.is_present("deny-duplicate-ids"),
+        no_missing_benchmarks: matches.is_present("no-missing-benchmarks"),
+        budgets,
+        budget_statistic: matches
+            .value_of("budget-statistic")
+            .map(BudgetStatistic::from_str)
+            .unwrap_or_default(),
+        measure: if cfg!(target_os = "linux") {
+            measure
+        } else {
+            Measure::Time
+        },
+        changed_only: matches.is_present("changed-only"),
+        bench_only: matches.is_present("bench-only"),
+        take: matches.value_of("take").map(|s| s.parse().unwrap()),
+        repeat: matches
+            .value_of("repeat")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or(1),
+        strict_plots: matches.is_present("strict-plots"),
+        require_plots: matches.is_present("require-plots"),
+        post_run_hook: matches.value_of("post-run-hook").map(|s| s.to_owned()),
+        profile_time: matches.value_of("profile-time").map(|s| s.parse().unwrap()),
+        check,
+        change_ratio_above: matches
+            .value_of("change-ratio-above")
+            .map(|s| s.parse().unwrap()),
+        sort_by: matches
+            .value_of("sort-by")
+            .map(SortOrder::from_str)
+            .unwrap_or(SortOrder::Insertion),
+        report_title: (matches.value_of("report-title"))
+            .or(toml_config.report_title.as_deref())
+            .map(ToOwned::to_owned),
+        report_description: (matches.value_of("report-description"))
+            .or(toml_config.report_description.as_deref())
+            .map(ToOwned::to_owned),
+        time_unit: matches
+            .value_of("time-unit")
+            .map(TimeUnit::from_str)
+            .unwrap_or(TimeUnit::Auto),
+        storage_format: (matches.value_of("storage-format"))
+            .or(toml_config.storage_format.as_deref())
+            .map(StorageFormat::from_str)
+            .unwrap_or(StorageFormat::Files),
+        sample_storage: (matches.value_of("store"))
+            .map(SampleStorage::from_str)
+            .unwrap_or(SampleStorage::Full),
+        db: matches.value_of_os("db").map(PathBuf::from),
+        datetime_from_mtime: matches.is_present("datetime-from-mtime"),
+        max_open_files: matches
+            .value_of("max-open-files")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or(crate::model::DEFAULT_MAX_OPEN_FILES),
+        dir_name_strategy: matches
+            .value_of("dir-name-strategy")
+            .map(DirNameStrategy::from_str)
+            .unwrap_or(DirNameStrategy::Readable),
+        retention: match matches.value_of("history-max-size") {
+            Some(size) => RetentionPolicy::MaxBytes(parse_size(size).unwrap()),
+            None => RetentionPolicy::KeepAll,
+        },
+        benchmark_overrides: toml_config.benchmark_overrides,
     };
 
     // These are the extra arguments to be passed to the benchmark targets.
     let mut additional_args: Vec<OsString> = vec![];
     additional_args.extend(matches.value_of_os("BENCHNAME").map(ToOwned::to_owned));
 
+    if let Some(profile_time) = self_config.profile_time {
+        additional_args.push("--profile-time".into());
+        additional_args.push(profile_time.to_string().into());
+    }
+
+    if self_config.check {
+        for arg in &[
+            "--sample-size",
+            "10",
+            "--warm-up-time",
+            "1",
+            "--measurement-time",
+            "1",
+        ] {
+            additional_args.push(arg.into());
+        }
+    }
+
     if let Some(args) = matches.values_of_os("args") {
         additional_args.extend(args.map(ToOwned::to_owned));
     }
 
+    let action = match matches.subcommand() {
+        _ if matches.is_present("dump-config") => Action::DumpConfig {
+            format: ConfigFormat::from_str(matches.value_of("dump-config").unwrap()),
+        },
+        ("list-groups", Some(sub_matches)) => Action::ListGroups {
+            json: sub_matches.is_present("json"),
+        },
+        ("commits", Some(sub_matches)) => Action::Commits {
+            json: sub_matches.is_present("json"),
+        },
+        ("pool", Some(sub_matches)) => Action::Pool {
+            benchmark: sub_matches.value_of("benchmark").unwrap().to_owned(),
+            count: sub_matches.value_of("count").unwrap().parse().unwrap(),
+        },
+        ("accept", Some(sub_matches)) => Action::Accept {
+            baseline: sub_matches.value_of("baseline").unwrap().to_owned(),
+            benchmarks: sub_matches.value_of("benchmarks").map(ToOwned::to_owned),
+        },
+        ("bundle", Some(sub_matches)) => Action::Bundle {
+            benchmark: sub_matches.value_of("benchmark").unwrap().to_owned(),
+            out: PathBuf::from(sub_matches.value_of_os("out").unwrap()),
+        },
+        ("import", Some(sub_matches)) => Action::Import {
+            path: PathBuf::from(sub_matches.value_of_os("FILE").unwrap()),
+            force: sub_matches.is_present("force"),
+        },
+        ("report", Some(sub_matches)) => Action::Report {
+            group: sub_matches.value_of("group").unwrap().to_owned(),
+        },
+        ("inspect", Some(sub_matches)) => Action::Inspect {
+            path: PathBuf::from(sub_matches.value_of_os("PATH").unwrap()),
+        },
+        ("doctor", Some(_)) => Action::Doctor,
+        ("preflight", Some(_)) => Action::Preflight,
+        ("capabilities", Some(sub_matches)) => Action::Capabilities {
+            json: sub_matches.is_present("json"),
+        },
+        ("compare-commits", Some(sub_matches)) => Action::CompareCommits {
+            base: sub_matches.value_of("base").unwrap().to_owned(),
+            head: sub_matches.value_of("head").unwrap().to_owned(),
+            format: ComparisonFormat::from_str(sub_matches.value_of("format").unwrap()),
+        },
+        ("compare-baseline", Some(sub_matches)) => Action::CompareBaseline {
+            baseline: sub_matches.value_of("baseline").unwrap().to_owned(),
+            from: PathBuf::from(sub_matches.value_of_os("from").unwrap()),
+            format: ComparisonFormat::from_str(sub_matches.value_of("format").unwrap()),
+        },
+        ("migrate", Some(sub_matches)) => Action::Migrate {
+            to: StorageFormat::from_str(sub_matches.value_of("to").unwrap()),
+        },
+        ("serve", Some(sub_matches)) => Action::Serve {
+            from: sub_matches
+                .value_of_os("from")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| self_config.report_directory.clone()),
+            host: sub_matches.value_of("host").unwrap().to_owned(),
+            port: sub_matches.value_of("port").unwrap().parse().unwrap(),
+        },
+        _ => Action::Run,
+    };
+
     let configuration = FullConfig {
         self_config,
         cargo_args,
         additional_args,
+        action,
     };
     Ok(configuration)
 }
@@ -704,5 +3211,207 @@ fn load_toml_file(toml_path: &Path) -> Result<TomlConfig, anyhow::Error> {
 
     let config: TomlConfig = toml::from_str(&str_buf)
         .with_context(|| format!("Failed to parse config file {:?}", toml_path))?;
+    for benchmark_override in &config.benchmark_overrides {
+        if let Some(level) = benchmark_override.significance_level {
+            if level <= 0.0 || level >= 1.0 {
+                anyhow::bail!(
+                    "Invalid significance_level {} for benchmarks '{}' in config file {:?}. It must be between 0 and 1.",
+                    level,
+                    benchmark_override.benchmarks,
+                    toml_path
+                );
+            }
+        }
+        if let Some(threshold) = benchmark_override.noise_threshold {
+            if threshold < 0.0 {
+                anyhow::bail!(
+                    "Invalid noise_threshold {} for benchmarks '{}' in config file {:?}. It must not be negative.",
+                    threshold,
+                    benchmark_override.benchmarks,
+                    toml_path
+                );
+            }
+        }
+    }
     Ok(config)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(Ok(vec![0]), parse_cpu_list("0"));
+        assert_eq!(Ok(vec![0, 2, 3, 4, 7]), parse_cpu_list("7,2-4,0,3"));
+        assert!(parse_cpu_list("3-1").is_err());
+        assert!(parse_cpu_list("1,,2").is_err());
+        assert!(parse_cpu_list("x").is_err());
+        assert!(parse_cpu_list("1024").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(Ok(1234), parse_size("1234"));
+        assert_eq!(Ok(500_000_000), parse_size("500MB"));
+        assert_eq!(Ok(2 * 1024 * 1024 * 1024), parse_size("2 GiB"));
+        assert_eq!(Ok(1536), parse_size("1.5kib"));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Ok(250.0), parse_duration("250ns"));
+        assert_eq!(Ok(5_000_000.0), parse_duration("5ms"));
+        assert_eq!(Ok(1_500.0), parse_duration("1.5 µs"));
+        assert_eq!(Ok(2e9), parse_duration("2s"));
+        assert!(parse_duration("100").is_err());
+        assert!(parse_duration("ms").is_err());
+        assert!(parse_duration("3 fortnights").is_err());
+    }
+
+    #[test]
+    fn test_budget_for() {
+        let budgets = vec![
+            Budget::from_arg("group/*=10ms").unwrap(),
+            Budget::from_arg("group/fast=1us").unwrap(),
+        ];
+        assert_eq!(
+            1_000.0,
+            budget_for(&budgets, "group/fast").unwrap().limit_ns
+        );
+        assert_eq!(1e7, budget_for(&budgets, "group/slow").unwrap().limit_ns);
+        assert!(budget_for(&budgets, "other").is_none());
+        assert!(Budget::from_arg("group").is_err());
+    }
+
+    #[test]
+    fn test_benchmark_override_matches() {
+        let pattern = |benchmarks: &str| BenchmarkOverride {
+            benchmarks: benchmarks.to_owned(),
+            noise_threshold: None,
+            significance_level: None,
+        };
+        assert!(pattern("group/fn").matches("group/fn"));
+        assert!(!pattern("group/fn").matches("group/fn/10"));
+        assert!(pattern("group/*").matches("group/fn/10"));
+        assert!(!pattern("group/*").matches("other/fn"));
+        assert!(pattern("*/10").matches("group/fn/10"));
+        assert!(pattern("*fn*").matches("group/fn/10"));
+        assert!(pattern("a*b*a").matches("abba"));
+        assert!(!pattern("ab*ba").matches("aba"));
+        assert!(pattern("*").matches(""));
+    }
+
+    #[test]
+    fn test_compare_against_from_str() {
+        assert!(matches!(
+            CompareAgainst::from_str("previous"),
+            Ok(CompareAgainst::Previous)
+        ));
+        assert!(matches!(
+            CompareAgainst::from_str("baseline:main"),
+            Ok(CompareAgainst::Baseline(name)) if name == "main"
+        ));
+        assert!(matches!(
+            CompareAgainst::from_str("commit:abc123"),
+            Ok(CompareAgainst::Commit(commit)) if commit == "abc123"
+        ));
+        assert!(matches!(
+            CompareAgainst::from_str("datetime:2020-07-18T12:00:00Z"),
+            Ok(CompareAgainst::Datetime(datetime)) if datetime.to_rfc3339() == "2020-07-18T12:00:00+00:00"
+        ));
+        assert!(matches!(
+            CompareAgainst::from_str("rolling:5"),
+            Ok(CompareAgainst::Rolling(5))
+        ));
+        assert!(CompareAgainst::from_str("rolling:0").is_err());
+        assert!(CompareAgainst::from_str("rolling:many").is_err());
+        assert!(CompareAgainst::from_str("datetime:2020-07-18").is_ok());
+        assert!(CompareAgainst::from_str("datetime:yesterday").is_err());
+        assert!(CompareAgainst::from_str("baseline:").is_err());
+        assert!(CompareAgainst::from_str("latest").is_err());
+    }
+
+    #[test]
+    fn test_select_timeline() {
+        let timeline = |args: &[&str], env_timeline: Option<&str>| {
+            let matches = app().get_matches_from(["cargo-criterion"].iter().chain(args));
+            select_timeline(&matches, env_timeline.map(OsString::from), Path::new("."))
+        };
+        let today = Local::now().format("%Y-%m-%d").to_string();
+
+        assert_eq!("main", timeline(&[], None));
+        assert_eq!(today, timeline(&["--timeline-date"], None));
+        // An explicitly chosen timeline takes precedence over the date.
+        assert_eq!("ci", timeline(&["--timeline-date"], Some("ci")));
+        assert_eq!(
+            "nightly",
+            timeline(&["--timeline", "nightly", "--timeline-date"], Some("ci"))
+        );
+        // Timelines are directories, so their names can't nest.
+        assert_eq!("feature_x", timeline(&["--timeline", "feature/x"], None));
+        let error = app().get_matches_from_safe(vec![
+            "cargo-criterion",
+            "--timeline-date",
+            "--timeline-from-branch",
+        ]);
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_metadata_command_forwards_network_args() {
+        let cargo_args: Vec<OsString> = vec![
+            "--offline".into(),
+            "--bench".into(),
+            "my_bench".into(),
+            "--frozen".into(),
+            "--locked".into(),
+        ];
+        let command = metadata_command(&cargo_args);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "metadata",
+                "--format-version",
+                "1",
+                "--offline",
+                "--frozen",
+                "--locked"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_arg_files() {
+        let path = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-args.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, "--features\nfoo bar\n\n--no-default-features\n").unwrap();
+        let arg_file = format!("@{}", path.display());
+
+        let args: Vec<OsString> = vec![
+            "cargo-criterion".into(),
+            arg_file.clone().into(),
+            "--".into(),
+            arg_file.clone().into(),
+        ];
+        let expanded = expand_arg_files(args);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            expanded.unwrap(),
+            [
+                "cargo-criterion",
+                "--features",
+                "foo bar",
+                "--no-default-features",
+                "--",
+                arg_file.as_str()
+            ]
+        );
+        assert!(expand_arg_files(vec![OsString::from("@missing-file")]).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::{ErrorKind, Read, Write};
 use std::mem::size_of;
@@ -29,12 +30,12 @@ impl std::error::Error for ConnectionError {
 #[derive(Debug)]
 #[repr(u16)]
 enum ProtocolFormat {
-    CBOR = 1,
+    Cbor = 1,
 }
 impl ProtocolFormat {
     fn from_u16(format: u16) -> Result<Self, ConnectionError> {
         match format {
-            1 => Ok(ProtocolFormat::CBOR),
+            1 => Ok(ProtocolFormat::Cbor),
             _ => Err(ConnectionError::HelloFailed("Unknown format value sent by Criterion.rs benchmark; please update cargo-criterion.")),
         }
     }
@@ -45,6 +46,17 @@ const RUNNER_HELLO_SIZE: usize = RUNNER_MAGIC_NUMBER.len() // magic number
     + (size_of::<u8>() * 3); // version number
 
 const BENCHMARK_MAGIC_NUMBER: &str = "Criterion";
+/// The newest protocol version which this version of cargo-criterion understands.
+pub const PROTOCOL_VERSION: u16 = 2;
+/// The first protocol version in which the benchmark waits for a `SamplingHint` after each
+/// `BeginningBenchmark` message. This has to be negotiated: cargo-criterion only sets
+/// `SAMPLING_HINTS_VARIABLE` when it has hints to send, and a benchmark should only speak this
+/// version when that variable is set. Benchmarks which don't know about it keep speaking
+/// version 1 and are never sent hints.
+const SAMPLING_HINT_PROTOCOL_VERSION: u16 = 2;
+/// The environment variable which tells the benchmark that cargo-criterion can send it a
+/// `SamplingHint` for each benchmark, if it speaks protocol version 2.
+pub const SAMPLING_HINTS_VARIABLE: &str = "CARGO_CRITERION_SAMPLING_HINTS";
 const BENCHMARK_HELLO_SIZE: usize = BENCHMARK_MAGIC_NUMBER.len() // magic number
     + (size_of::<u8>() * 3) // version number
     + size_of::<u16>() // protocol version
@@ -63,7 +75,6 @@ pub struct Connection {
 
     criterion_rs_version: [u8; 3],
     protocol_version: u16,
-    protocol_format: ProtocolFormat,
 }
 impl Connection {
     /// Perform the connection handshake and wrap the TCP stream in a Connection object if successful.
@@ -106,10 +117,21 @@ impl Connection {
 
             criterion_rs_version,
             protocol_version,
-            protocol_format,
         })
     }
 
+    /// The version of Criterion.rs that the benchmark was built with, such as "0.3.6".
+    pub fn criterion_rs_version(&self) -> String {
+        let [major, minor, patch] = self.criterion_rs_version;
+        format!("{}.{}.{}", major, minor, patch)
+    }
+
+    /// Does the benchmark expect a `SamplingHint` in reply to each `BeginningBenchmark` message?
+    /// This is only the case if it was launched with `SAMPLING_HINTS_VARIABLE` set.
+    pub fn expects_sampling_hints(&self) -> bool {
+        self.protocol_version >= SAMPLING_HINT_PROTOCOL_VERSION
+    }
+
     /// Receive a message from the benchmark. If the benchmark has closed the connection, returns
     /// Ok(None).
     pub fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
@@ -173,12 +195,38 @@ pub enum IncomingMessage {
         estimate_ns: f64,
         iter_count: u64,
     },
+    // User-provided key/value annotations for the current benchmark. This may be sent any number
+    // of times before MeasurementComplete; later values replace earlier ones with the same key.
+    BenchmarkMetadata {
+        metadata: HashMap<String, String>,
+    },
+    // The allocations made while the current benchmark was being measured, sent before
+    // MeasurementComplete by benchmarks which install a counting global allocator. Other
+    // benchmarks never send this. The counts are totals over every iteration of the measurement,
+    // not including the warm-up.
+    AllocationCounts {
+        allocations: u64,
+        bytes: u64,
+    },
+    // A named part of the current benchmark, such as one phase of it, which the benchmark timed
+    // separately in each of its samples. This may be sent once per part before
+    // MeasurementComplete; later samples replace earlier ones with the same name. The parts are
+    // analyzed and compared like benchmarks of their own.
+    SubMeasurement {
+        name: String,
+        iters: Vec<f64>,
+        times: Vec<f64>,
+    },
     MeasurementComplete {
         iters: Vec<f64>,
         times: Vec<f64>,
         plot_config: PlotConfiguration,
         sampling_method: SamplingMethod,
         benchmark_config: BenchmarkConfig,
+        // The async runtime that the benchmark was run on (such as "tokio"), or None if it wasn't
+        // an async benchmark. Older versions of Criterion.rs don't send this.
+        #[serde(default)]
+        async_runtime: Option<String>,
     },
     // Value formatting responses
     FormattedValue {
@@ -213,6 +261,21 @@ pub enum OutgoingMessage<'a> {
         values: &'a [f64],
     },
     Continue,
+    // Sent in reply to BeginningBenchmark, only to benchmarks which were offered hints through
+    // SAMPLING_HINTS_VARIABLE and accepted them by speaking protocol version 2. The benchmark may
+    // use the previous estimate to shorten its warm-up and measurement. It takes at least
+    // min_samples samples, even if that runs past its measurement time.
+    SamplingHint {
+        previous: Option<PreviousEstimate>,
+        min_samples: u64,
+    },
+}
+
+/// The estimates from the previous measurement of a benchmark, per iteration.
+#[derive(Debug, Serialize)]
+pub struct PreviousEstimate {
+    pub mean_ns: f64,
+    pub std_dev_ns: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -248,19 +311,18 @@ pub struct PlotConfiguration {
 pub enum Throughput {
     Bytes(u64),
     Elements(u64),
+    // A count of some other unit, such as transactions or pixels, processed in each iteration.
+    Custom { unit: String, count: u64 },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, Copy)]
 pub enum SamplingMethod {
     Linear,
     Flat,
 }
 impl SamplingMethod {
     pub fn is_linear(&self) -> bool {
-        match self {
-            SamplingMethod::Linear => true,
-            _ => false,
-        }
+        matches!(self, SamplingMethod::Linear)
     }
 }
 
@@ -277,7 +339,6 @@ pub struct BenchmarkConfig {
     nresamples: usize,
     sample_size: usize,
     significance_level: f64,
-    warm_up_time: Duration,
 }
 impl From<BenchmarkConfig> for crate::analysis::BenchmarkConfig {
     fn from(other: BenchmarkConfig) -> Self {
@@ -291,10 +352,8 @@ impl From<BenchmarkConfig> for crate::analysis::BenchmarkConfig {
             nresamples: other.nresamples,
             sample_size: other.sample_size,
             significance_level: other.significance_level,
-            warm_up_time: std::time::Duration::new(
-                other.warm_up_time.secs,
-                other.warm_up_time.nanos,
-            ),
+            significance_test: crate::config::SignificanceTest::default(),
+            compare_metric: crate::config::CompareMetric::default(),
         }
     }
 }
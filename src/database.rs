@@ -0,0 +1,136 @@
+//! Storing measurements in an SQLite database, so that their history can be queried with SQL. This
+//! is only available with the 'sqlite' feature, which uses `rusqlite` to link against the system's
+//! libsqlite3.
+//!
+//! Each measurement is a row of the `measurements` table. The columns hold the benchmark ID and
+//! the estimates that are most often queried, in nanoseconds, and the `stats` column holds the
+//! whole measurement in the same CBOR encoding as the measurement files, so that the model can be
+//! loaded back from the database.
+
+use crate::connection::Throughput;
+use crate::model::SavedStatistics;
+use crate::report::BenchmarkId;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS measurements (
+    id INTEGER PRIMARY KEY,
+    group_id TEXT NOT NULL,
+    function_id TEXT,
+    value_str TEXT,
+    datetime TEXT NOT NULL,
+    mean REAL NOT NULL,
+    ci_lower REAL NOT NULL,
+    ci_upper REAL NOT NULL,
+    throughput INTEGER,
+    throughput_unit TEXT,
+    \"commit\" TEXT,
+    target TEXT,
+    stats BLOB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS measurements_by_benchmark
+    ON measurements (group_id, function_id, value_str, datetime);
+";
+
+/// An open database file.
+#[derive(Debug)]
+pub struct Database {
+    path: PathBuf,
+    connection: Connection,
+}
+impl Database {
+    /// Open the database at the given path, creating it and its tables if necessary.
+    pub fn open(path: &Path) -> Result<Database> {
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+        )
+        .with_context(|| format!("Failed to open database {:?}", path))?;
+        // Let concurrent runs writing to the same database wait for each other.
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .with_context(|| format!("Failed to open database {:?}", path))?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to create the tables of database {:?}", path))?;
+        Ok(Database {
+            path: path.to_owned(),
+            connection,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Insert a row for a new measurement of the given benchmark.
+    pub fn insert(&self, id: &BenchmarkId, stats: &SavedStatistics) -> Result<()> {
+        let mean = &stats.estimates.mean;
+        let (throughput, throughput_unit) = match &stats.throughput {
+            Some(Throughput::Bytes(bytes)) => (Some(*bytes as i64), Some("bytes")),
+            Some(Throughput::Elements(elements)) => (Some(*elements as i64), Some("elements")),
+            Some(Throughput::Custom { unit, count }) => (Some(*count as i64), Some(unit.as_str())),
+            None => (None, None),
+        };
+        let datetime = stats.datetime.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string();
+        let encoded = serde_cbor::to_vec(stats).context("Failed to serialize measurement")?;
+        self.connection
+            .execute(
+                "INSERT INTO measurements (group_id, function_id, value_str, datetime, mean, \
+                 ci_lower, ci_upper, throughput, throughput_unit, \"commit\", target, stats) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    id.group_id,
+                    id.function_id,
+                    id.value_str,
+                    datetime,
+                    mean.point_estimate,
+                    mean.confidence_interval.lower_bound,
+                    mean.confidence_interval.upper_bound,
+                    throughput,
+                    throughput_unit,
+                    stats.history_id,
+                    stats.target,
+                    encoded,
+                ],
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to insert the measurement of {} into database {:?}",
+                    id.as_title(),
+                    self.path
+                )
+            })?;
+        Ok(())
+    }
+
+    /// Read the latest measurement of each benchmark, or of each benchmark in the given group, in
+    /// the order that they were inserted.
+    pub fn load_latest(&self, group: Option<&str>) -> Result<Vec<(BenchmarkId, SavedStatistics)>> {
+        let mut statement = self.connection.prepare(
+            "SELECT group_id, function_id, value_str, stats FROM measurements WHERE id IN \
+             (SELECT max(id) FROM measurements WHERE ?1 IS NULL OR group_id = ?1 \
+             GROUP BY group_id, function_id, value_str) \
+             ORDER BY id",
+        )?;
+        let mut rows = statement.query(params![group])?;
+        let mut latest = Vec::new();
+        while let Some(row) = rows.next()? {
+            let encoded: Vec<u8> = row.get(3)?;
+            let stats: SavedStatistics = serde_cbor::from_slice(&encoded).with_context(|| {
+                format!("Failed to read a measurement from database {:?}", self.path)
+            })?;
+            let id = BenchmarkId::new(
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                stats.throughput.clone(),
+            );
+            latest.push((id, stats));
+        }
+        Ok(latest)
+    }
+}
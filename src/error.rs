@@ -0,0 +1,210 @@
+//! The ways that a run of cargo-criterion can fail, beyond the general errors reported with
+//! `anyhow`. Each kind of failure has its own exit code, so that scripts can tell them apart.
+
+use crate::compile::CompileError;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+/// Enum representing the failures that callers might want to handle differently.
+pub enum RunError {
+    /// Cargo failed to compile the benchmarks.
+    CompileFailed(CompileError),
+    /// A benchmark target exited with an error.
+    BenchmarkFailed {
+        target: String,
+        exit_code: Option<i32>,
+    },
+    /// The connection to a benchmark target failed partway through.
+    ConnectionFailed { target: String },
+    /// Benchmarks with no stored measurement were run, and `--no-new-benchmarks` was given.
+    NewBenchmarks { ids: Vec<String> },
+    /// Benchmarks regressed, and `--fail-on-regression` was given.
+    RegressionDetected { ids: Vec<String> },
+    /// Benchmarks took longer than their `--budget`.
+    OverBudget { ids: Vec<String> },
+    /// Benchmark or group IDs were encountered more than once, and `--deny-duplicate-ids` was
+    /// given.
+    DuplicateIds { ids: Vec<String> },
+    /// Benchmark groups had fewer benchmarks than in their previous run, and
+    /// `--no-missing-benchmarks` was given.
+    MissingBenchmarks { ids: Vec<String> },
+    /// `cargo criterion doctor` found corrupt files in the data directory.
+    DataCorruption { path: PathBuf, problem_count: usize },
+    /// Any other failure, such as an I/O error or an invalid argument.
+    Other(anyhow::Error),
+}
+impl RunError {
+    /// The code that cargo-criterion exits with when the run fails with this error. Any other
+    /// error exits with code 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunError::CompileFailed(_) => 2,
+            RunError::BenchmarkFailed { .. } => 3,
+            RunError::ConnectionFailed { .. } => 4,
+            RunError::DataCorruption { .. } => 5,
+            RunError::NewBenchmarks { .. } => 6,
+            RunError::RegressionDetected { .. } => 7,
+            RunError::OverBudget { .. } => 8,
+            RunError::DuplicateIds { .. } => 9,
+            RunError::MissingBenchmarks { .. } => 10,
+            RunError::Other(_) => 1,
+        }
+    }
+}
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::CompileFailed(error) => write!(f, "{}", error),
+            RunError::BenchmarkFailed { target, exit_code } => write!(
+                f,
+                "Benchmark target {} exited with error code {:?}",
+                target, exit_code
+            ),
+            RunError::ConnectionFailed { target } => write!(
+                f,
+                "Failed to communicate with Criterion.rs benchmark target {}",
+                target
+            ),
+            RunError::NewBenchmarks { ids } => write!(
+                f,
+                "Found {} benchmark(s) with no stored measurement: {}",
+                ids.len(),
+                ids.join(", ")
+            ),
+            RunError::RegressionDetected { ids } => write!(
+                f,
+                "The performance of {} benchmark(s) has regressed: {}",
+                ids.len(),
+                ids.join(", ")
+            ),
+            RunError::OverBudget { ids } => write!(
+                f,
+                "{} benchmark(s) took longer than their budget: {}",
+                ids.len(),
+                ids.join(", ")
+            ),
+            RunError::DuplicateIds { ids } => write!(
+                f,
+                "Found {} benchmark or group ID(s) which were encountered more than once: {}",
+                ids.len(),
+                ids.join(", ")
+            ),
+            RunError::MissingBenchmarks { ids } => write!(
+                f,
+                "{} benchmark(s) from the previous run are missing from their group: {}",
+                ids.len(),
+                ids.join(", ")
+            ),
+            RunError::DataCorruption {
+                path,
+                problem_count,
+            } => write!(
+                f,
+                "Found {} problem(s) with the stored data in {:?}",
+                problem_count, path
+            ),
+            RunError::Other(error) => write!(f, "{}", error),
+        }
+    }
+}
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::CompileFailed(error) => std::error::Error::source(error),
+            RunError::Other(error) => error.source(),
+            _ => None,
+        }
+    }
+}
+impl From<anyhow::Error> for RunError {
+    /// Errors which were raised as a `RunError` are taken back out of the `anyhow::Error`.
+    fn from(error: anyhow::Error) -> RunError {
+        match error.downcast::<RunError>() {
+            Ok(error) => error,
+            Err(error) => RunError::Other(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes() {
+        let ids = || vec!["group/benchmark".to_owned()];
+        let errors = [
+            (
+                RunError::CompileFailed(CompileError::CompileFailed(Default::default())),
+                2,
+            ),
+            (
+                RunError::BenchmarkFailed {
+                    target: "bench".to_owned(),
+                    exit_code: Some(101),
+                },
+                3,
+            ),
+            (
+                RunError::ConnectionFailed {
+                    target: "bench".to_owned(),
+                },
+                4,
+            ),
+            (
+                RunError::DataCorruption {
+                    path: PathBuf::from("target/criterion/data"),
+                    problem_count: 1,
+                },
+                5,
+            ),
+            (RunError::NewBenchmarks { ids: ids() }, 6),
+            (RunError::RegressionDetected { ids: ids() }, 7),
+            (RunError::OverBudget { ids: ids() }, 8),
+            (RunError::DuplicateIds { ids: ids() }, 9),
+            (RunError::MissingBenchmarks { ids: ids() }, 10),
+            (RunError::Other(anyhow::anyhow!("failed")), 1),
+        ];
+        for (error, exit_code) in errors {
+            assert_eq!(exit_code, error.exit_code(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_from_anyhow_error_finds_the_run_error() {
+        let io_error = || std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "closed");
+
+        // Raised directly.
+        let error = RunError::from(anyhow::Error::from(RunError::OverBudget { ids: vec![] }));
+        assert_eq!(8, error.exit_code());
+
+        // Added as the context of another error, as the benchmark targets do.
+        let error = RunError::from(
+            Err::<(), _>(io_error())
+                .with_context(|| RunError::ConnectionFailed {
+                    target: "bench".to_owned(),
+                })
+                .unwrap_err(),
+        );
+        match error {
+            RunError::ConnectionFailed { target } => assert_eq!("bench", target),
+            other => panic!("{:?}", other),
+        }
+
+        // Wrapped in more context.
+        let error = RunError::from(
+            anyhow::Error::from(RunError::BenchmarkFailed {
+                target: "bench".to_owned(),
+                exit_code: None,
+            })
+            .context("Failed to run the benchmarks"),
+        );
+        assert_eq!(3, error.exit_code());
+
+        // Anything else is kept as it is.
+        let error = RunError::from(anyhow::Error::from(io_error()).context("Failed to read"));
+        assert_eq!(1, error.exit_code());
+        assert_eq!("Failed to read", error.to_string());
+    }
+}
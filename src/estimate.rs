@@ -1,5 +1,6 @@
 use std::fmt;
 
+use crate::config::CompareMetric;
 use crate::stats::Distribution;
 
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Deserialize, Serialize, Debug)]
@@ -36,7 +37,7 @@ pub struct ConfidenceInterval {
 pub struct Estimate {
     /// The confidence interval for this estimate
     pub confidence_interval: ConfidenceInterval,
-    ///
+    /// The most likely value for this estimate
     pub point_estimate: f64,
     /// The standard error of this estimate
     pub standard_error: f64,
@@ -92,6 +93,9 @@ pub fn build_change_estimates(
     ChangeEstimates {
         mean: to_estimate(points.mean, &distributions.mean),
         median: to_estimate(points.median, &distributions.median),
+        slope: (points.slope)
+            .zip(distributions.slope.as_ref())
+            .map(|(point, distribution)| to_estimate(point, distribution)),
     }
 }
 
@@ -152,12 +156,16 @@ impl Distributions {
 pub struct ChangePointEstimates {
     pub mean: f64,
     pub median: f64,
+    pub slope: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChangeEstimates {
     pub mean: Estimate,
     pub median: Estimate,
+    // Only estimated when comparing slopes with `--compare-metric slope`.
+    #[serde(default)]
+    pub slope: Option<Estimate>,
 }
 impl ChangeEstimates {
     pub fn get(&self, stat: Statistic) -> &Estimate {
@@ -167,11 +175,20 @@ impl ChangeEstimates {
             _ => panic!("Unexpected statistic"),
         }
     }
+    /// The change in the given statistic, or in the mean if the slope wasn't estimated.
+    pub fn compared(&self, metric: CompareMetric) -> &Estimate {
+        match metric {
+            CompareMetric::Mean => &self.mean,
+            CompareMetric::Median => &self.median,
+            CompareMetric::Slope => self.slope.as_ref().unwrap_or(&self.mean),
+        }
+    }
 }
 
 pub struct ChangeDistributions {
     pub mean: Distribution<f64>,
     pub median: Distribution<f64>,
+    pub slope: Option<Distribution<f64>>,
 }
 impl ChangeDistributions {
     pub fn get(&self, stat: Statistic) -> &Distribution<f64> {
@@ -181,4 +198,13 @@ impl ChangeDistributions {
             _ => panic!("Unexpected statistic"),
         }
     }
+    /// The distribution of the change in the given statistic, or in the mean if the slope wasn't
+    /// estimated.
+    pub fn compared(&self, metric: CompareMetric) -> &Distribution<f64> {
+        match metric {
+            CompareMetric::Mean => &self.mean,
+            CompareMetric::Median => &self.median,
+            CompareMetric::Slope => self.slope.as_ref().unwrap_or(&self.mean),
+        }
+    }
 }
@@ -1,3 +1,11 @@
+use crate::config::TimeUnit;
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref TIME_UNIT: RwLock<TimeUnit> = RwLock::new(TimeUnit::Auto);
+}
+
 pub fn change(pct: f64, signed: bool) -> String {
     if signed {
         format!("{:>+6}%", signed_short(pct * 1e2))
@@ -6,20 +14,51 @@ pub fn change(pct: f64, signed: bool) -> String {
     }
 }
 
-pub fn time(ns: f64) -> String {
-    if ns < 1.0 {
-        format!("{:>6} ps", short(ns * 1e3))
-    } else if ns < 10f64.powi(3) {
-        format!("{:>6} ns", short(ns))
-    } else if ns < 10f64.powi(6) {
-        format!("{:>6} us", short(ns / 1e3))
-    } else if ns < 10f64.powi(9) {
-        format!("{:>6} ms", short(ns / 1e6))
-    } else {
-        format!("{:>6} s", short(ns / 1e9))
+/// Format a relative change in time. If `ratio_above` is set and the time has changed by at least
+/// that factor in either direction, the change is written as a ratio ("2.40x slower"), which is
+/// easier to read than a percentage for large changes.
+pub fn time_change(pct: f64, ratio_above: Option<f64>) -> String {
+    let ratio = 1.0 + pct;
+    match ratio_above {
+        Some(factor) if ratio >= factor => format!("{:.2}x slower", ratio),
+        Some(factor) if ratio > 0.0 && ratio <= 1.0 / factor => {
+            format!("{:.2}x faster", 1.0 / ratio)
+        }
+        _ => change(pct, true),
     }
 }
 
+/// Show every time in the given unit from now on, instead of choosing a unit to suit each value.
+pub fn set_time_unit(unit: TimeUnit) {
+    *TIME_UNIT.write().unwrap() = unit;
+}
+
+/// The number of nanoseconds in the unit that every time is shown in and its name, if one was set
+/// with `set_time_unit`.
+pub fn fixed_time_unit() -> Option<(f64, &'static str)> {
+    TIME_UNIT.read().unwrap().scale()
+}
+
+/// Choose the unit to show a time in, as the number of nanoseconds in that unit and its name.
+pub fn time_unit(ns: f64) -> (f64, &'static str) {
+    if let Some(unit) = fixed_time_unit() {
+        return unit;
+    }
+    // Use the smallest unit in which the value stays below 1000 once it's rounded, so that a time
+    // just below a boundary is shown as "1.0000 us" rather than "1000.00 ns".
+    for &(unit_ns, unit) in &[(1e-3, "ps"), (1.0, "ns"), (1e3, "us"), (1e6, "ms")] {
+        if matches!(short(ns / unit_ns).parse::<f64>(), Ok(n) if n < 1000.0) {
+            return (unit_ns, unit);
+        }
+    }
+    (1e9, "s")
+}
+
+pub fn time(ns: f64) -> String {
+    let (unit_ns, unit) = time_unit(ns);
+    format!("{:>6} {}", short(ns / unit_ns), unit)
+}
+
 pub fn short(n: f64) -> String {
     if n < 10.0 {
         format!("{:.4}", n)
@@ -76,6 +115,41 @@ pub fn integer(n: f64) -> String {
     format!("{}", n as u64)
 }
 
+pub fn bytes(n: u64) -> String {
+    let n = n as f64;
+    if n < 1024.0 {
+        format!("{:>6} B", short(n))
+    } else if n < 1024f64.powi(2) {
+        format!("{:>6} KiB", short(n / 1024.0))
+    } else if n < 1024f64.powi(3) {
+        format!("{:>6} MiB", short(n / 1024f64.powi(2)))
+    } else {
+        format!("{:>6} GiB", short(n / 1024f64.powi(3)))
+    }
+}
+
+pub fn instructions(n: f64) -> String {
+    if n < 1e3 {
+        format!("{} instructions", short(n))
+    } else if n < 1e6 {
+        format!("{}K instructions", short(n / 1e3))
+    } else if n < 1e9 {
+        format!("{}M instructions", short(n / 1e6))
+    } else {
+        format!("{}G instructions", short(n / 1e9))
+    }
+}
+
+pub fn allocations(n: f64) -> String {
+    if n < 1e3 {
+        format!("{} allocations", short(n))
+    } else if n < 1e6 {
+        format!("{}K allocations", short(n / 1e3))
+    } else {
+        format!("{}M allocations", short(n / 1e6))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -101,4 +175,37 @@ mod test {
             float *= 2.0;
         }
     }
+
+    #[test]
+    fn time_change_ratios() {
+        assert_eq!(time_change(0.5, None), "+50.000%");
+        assert_eq!(time_change(0.5, Some(2.0)), "+50.000%");
+        assert_eq!(time_change(1.4, Some(2.0)), "2.40x slower");
+        assert_eq!(time_change(-0.75, Some(2.0)), "4.00x faster");
+        assert_eq!(time_change(-0.25, Some(2.0)), "-25.000%");
+    }
+
+    #[test]
+    fn time_units_at_boundaries() {
+        assert_eq!(time(0.5), "500.00 ps");
+        assert_eq!(time(999.9), "999.90 ns");
+        assert_eq!(time(999.999), "1.0000 us");
+        assert_eq!(time(999_999.0), "1.0000 ms");
+        assert_eq!(time(2.5e10), "25.000 s");
+    }
+
+    #[test]
+    fn bytes_units() {
+        assert_eq!(bytes(512), "512.00 B");
+        assert_eq!(bytes(2048), "2.0000 KiB");
+        assert_eq!(bytes(5 * 1024 * 1024), "5.0000 MiB");
+        assert_eq!(bytes(3 * 1024 * 1024 * 1024), "3.0000 GiB");
+    }
+
+    #[test]
+    fn instructions_units() {
+        assert_eq!(instructions(12.5), "12.500 instructions");
+        assert_eq!(instructions(2500.0), "2.5000K instructions");
+        assert_eq!(instructions(3e7), "30.000M instructions");
+    }
 }
@@ -1,30 +1,31 @@
+use crate::config::SortOrder;
 use crate::estimate::Estimate;
 use crate::format;
 use crate::model::{
     Benchmark as BenchmarkModel, BenchmarkGroup as GroupModel, ChangeDirection, Model,
     SavedStatistics,
 };
-use crate::plot::{PlotContext, Plotter, Size};
+use crate::plot::{element_count, HistoryExtremes, NoPlots, PlotContext, Plotter, Size};
 use crate::report::{
     compare_to_threshold, make_filename_safe, BenchmarkId, ComparisonResult, MeasurementData,
     Report, ReportContext,
 };
 use crate::stats::bivariate::regression::Slope;
-use crate::stats::univariate::Sample;
 use crate::value_formatter::ValueFormatter;
 use anyhow::{Context as AnyhowContext, Result};
 use linked_hash_set::LinkedHashSet;
 use serde::Serialize;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use tinytemplate::TinyTemplate;
 
 const THUMBNAIL_SIZE: Option<Size> = Some(Size(450, 300));
 
-const COMMON_CSS: &'static str = include_str!("common.css");
+const COMMON_CSS: &str = include_str!("common.css");
 
 fn save<D, P>(data: &D, path: &P) -> Result<()>
 where
@@ -40,8 +41,6 @@ fn save_string<P>(data: &str, path: &P) -> Result<()>
 where
     P: AsRef<Path> + Debug,
 {
-    use std::io::Write;
-
     File::create(path)
         .and_then(|mut f| f.write_all(data.as_bytes()))
         .with_context(|| format!("Unable to save file {:?}", path))?;
@@ -87,7 +86,21 @@ struct Context {
     median: ConfidenceInterval,
     mad: ConfidenceInterval,
     throughput: Option<ConfidenceInterval>,
-
+    time_per_element: Option<ConfidenceInterval>,
+    peak_rss: Option<String>,
+    instructions: Option<String>,
+    allocations: Option<String>,
+    allocated_bytes: Option<String>,
+    // The number of usable samples, if it was too few to trust the estimates. This is a string
+    // because the templates treat the number zero as true.
+    low_confidence_samples: Option<String>,
+    criterion_version: Option<String>,
+    async_runtime: Option<String>,
+    single_thread: bool,
+    metadata: Vec<Metadatum>,
+
+    // Were the plots drawn? If not, the report leaves them out.
+    plots: bool,
     additional_plots: Vec<Plot>,
 
     comparison: Option<Comparison>,
@@ -96,7 +109,7 @@ struct Context {
 #[derive(Serialize, Debug)]
 struct IndividualBenchmark {
     name: String,
-    path: PathBuf,
+    path: String,
     regression_exists: bool,
 }
 impl IndividualBenchmark {
@@ -109,7 +122,7 @@ impl IndividualBenchmark {
 
         IndividualBenchmark {
             name: id.as_title().to_owned(),
-            path: path!(path_prefix, id.as_directory_name()),
+            path: relative_url(&path!(path_prefix, id.as_directory_name())),
             regression_exists: regression_path.exists(),
         }
     }
@@ -126,7 +139,9 @@ struct SummaryContext {
 
     violin_plot: Option<String>,
     line_chart: Option<String>,
+    per_element_chart: Option<String>,
 
+    plots: bool,
     benchmarks: Vec<IndividualBenchmark>,
 }
 
@@ -151,26 +166,63 @@ impl Plot {
     }
 }
 
+#[derive(Serialize, Debug)]
+struct Metadatum {
+    key: String,
+    value: String,
+}
+
 #[derive(Serialize, Debug)]
 struct Comparison {
     p_value: String,
     inequality: String,
     significance_level: String,
     explanation: String,
+    baseline_label: Option<String>,
+    base_criterion_version: Option<String>,
+    throughput_mismatch: Option<String>,
 
     change: ConfidenceInterval,
     thrpt_change: Option<ConfidenceInterval>,
     additional_plots: Vec<Plot>,
 }
 
+/// Turn a path relative to the page that links to it into a URL. The separators are always `/`,
+/// so that the reports work wherever they are copied to, whatever platform made them.
+fn relative_url(path: &Path) -> String {
+    debug_assert!(path.is_relative(), "{:?} should be relative", path);
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The URL of the report in the given directory, relative to the output directory, if it exists.
 fn if_exists(output_directory: &Path, path: &Path) -> Option<String> {
     let report_path = path.join("index.html");
     if PathBuf::from(output_directory).join(&report_path).is_file() {
-        Some(report_path.to_string_lossy().to_string())
+        Some(relative_url(&report_path))
     } else {
         None
     }
 }
+
+/// The name of a plot written next to the page that shows it, to use as its URL.
+fn plot_file_name(path: PathBuf) -> String {
+    path.file_name()
+        .expect("Plot paths end with a file name")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The time that an entry in a benchmark's history was measured, as shown in the history report.
+fn format_history_datetime(stats: &SavedStatistics) -> String {
+    stats
+        .datetime
+        .with_timezone(&chrono::Local)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
 #[derive(Serialize, Debug)]
 struct ReportLink<'a> {
     name: &'a str,
@@ -235,15 +287,15 @@ impl<'a> BenchmarkGroup<'a> {
     fn new(
         output_directory: &Path,
         group_id: &'a str,
-        group: &'a GroupModel,
+        benchmarks: &[&'a BenchmarkId],
     ) -> BenchmarkGroup<'a> {
         let group_report = ReportLink::group(output_directory, group_id);
 
         let mut function_ids = LinkedHashSet::new();
         let mut values = LinkedHashSet::new();
-        let mut individual_links = HashMap::with_capacity(group.benchmarks.len());
+        let mut individual_links = HashMap::with_capacity(benchmarks.len());
 
-        for id in group.benchmarks.keys() {
+        for id in benchmarks.iter().copied() {
             let function_id = id.function_id.as_deref();
             let value = id.value_str.as_deref();
 
@@ -285,10 +337,22 @@ impl<'a> BenchmarkGroup<'a> {
     }
 }
 
+/// A row of the table of totals over each group on the index page.
+#[derive(Serialize, Debug)]
+struct GroupSummaryRow<'a> {
+    group_report: ReportLink<'a>,
+    benchmark_count: usize,
+    total_time: String,
+    regressed_count: usize,
+    improved_count: usize,
+}
+
 #[derive(Serialize, Debug)]
 struct IndexContext<'a> {
     common_css: &'static str,
-    groups: Vec<BenchmarkGroup<'a>>,
+    title: Option<&'a str>,
+    description: Option<&'a str>,
+    group_summaries: Vec<GroupSummaryRow<'a>>,
 }
 
 #[derive(Serialize, Debug)]
@@ -306,22 +370,50 @@ struct HistoryEntry<'a> {
     is_no_change: bool,
     change_value: Option<ConfidenceInterval>,
     change_throughput: Option<ConfidenceInterval>,
+    change_p_value: Option<String>,
     change_class: &'static str,
 }
 
+/// The fastest or slowest entry in a benchmark's history.
+#[derive(Serialize, Debug)]
+struct HistoryExtreme<'a> {
+    number: usize,
+    id: Option<&'a str>,
+    datetime: String,
+    mean: String,
+}
+
 #[derive(Serialize, Debug)]
 struct HistoryContext<'a> {
     common_css: &'static str,
     title: &'a str,
+    fastest: HistoryExtreme<'a>,
+    slowest: HistoryExtreme<'a>,
+    // How the mean of the latest entry compares to the fastest, unless it is the fastest.
+    latest_vs_fastest: Option<String>,
+    plots: bool,
     history: Vec<HistoryEntry<'a>>,
 }
 
 pub struct Html {
     templates: TinyTemplate<'static>,
     plotter: RefCell<Box<dyn Plotter>>,
+    // Is there a plotting backend? If not, the reports are generated without plots.
+    plots: bool,
+    sort_by: SortOrder,
+    change_ratio_above: Option<f64>,
+    // The title and description of the index page, if given by the user.
+    title: Option<String>,
+    description: Option<String>,
 }
 impl Html {
-    pub(crate) fn new(plotter: Box<dyn Plotter>) -> Html {
+    pub(crate) fn new(
+        plotter: Option<Box<dyn Plotter>>,
+        sort_by: SortOrder,
+        change_ratio_above: Option<f64>,
+        title: Option<String>,
+        description: Option<String>,
+    ) -> Html {
         let mut templates = TinyTemplate::new();
         templates
             .add_template("report_link", include_str!("report_link.html.tt"))
@@ -329,6 +421,12 @@ impl Html {
         templates
             .add_template("index", include_str!("index.html.tt"))
             .expect("Unable to parse index template.");
+        templates
+            .add_template("index_group", include_str!("index_group.html.tt"))
+            .expect("Unable to parse index_group template.");
+        templates
+            .add_template("index_footer", include_str!("index_footer.html.tt"))
+            .expect("Unable to parse index_footer template.");
         templates
             .add_template("benchmark_report", include_str!("benchmark_report.html.tt"))
             .expect("Unable to parse benchmark_report template");
@@ -339,8 +437,22 @@ impl Html {
             .add_template("history_report", include_str!("history_report.html.tt"))
             .expect("Unable to parse history_report template");
 
-        let plotter = RefCell::new(plotter);
-        Html { templates, plotter }
+        let plots = plotter.is_some();
+        let plotter = RefCell::new(plotter.unwrap_or_else(|| Box::new(NoPlots)));
+        Html {
+            templates,
+            plotter,
+            plots,
+            sort_by,
+            change_ratio_above,
+            title,
+            description,
+        }
+    }
+
+    /// The plots which couldn't be drawn, if the plotting backend treats that as an error.
+    pub(crate) fn failed_plots(&self) -> Vec<PathBuf> {
+        self.plotter.borrow().failed_plots()
     }
 }
 impl Report for Html {
@@ -394,6 +506,19 @@ impl Report for Html {
         if measurements.absolute_estimates.slope.is_some() {
             additional_plots.push(Plot::new("Slope", "slope.svg"));
         }
+        if !self.plots {
+            additional_plots.clear();
+        }
+
+        let mut metadata: Vec<Metadatum> = measurements
+            .metadata
+            .iter()
+            .map(|(key, value)| Metadatum {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        metadata.sort_by(|a, b| a.key.cmp(&b.key));
 
         let context = Context {
             common_css: COMMON_CSS,
@@ -417,6 +542,26 @@ impl Report for Html {
             mad: time_interval(&measurements.absolute_estimates.median_abs_dev),
             std_dev: time_interval(&measurements.absolute_estimates.std_dev),
             throughput,
+            time_per_element: measurements.time_per_element().as_ref().map(time_interval),
+            peak_rss: measurements.peak_rss.map(format::bytes),
+            instructions: measurements
+                .instructions_per_iteration
+                .map(format::instructions),
+            allocations: measurements
+                .allocations
+                .map(|allocations| format::allocations(allocations.allocations_per_iteration)),
+            allocated_bytes: measurements
+                .allocations
+                .map(|allocations| format::bytes(allocations.bytes_per_iteration.round() as u64)),
+            low_confidence_samples: if measurements.low_confidence {
+                Some(measurements.effective_sample_count.to_string())
+            } else {
+                None
+            },
+            criterion_version: measurements.criterion_version.clone(),
+            async_runtime: measurements.async_runtime.clone(),
+            single_thread: measurements.single_thread,
+            metadata,
 
             r2: ConfidenceInterval {
                 lower: format!(
@@ -433,6 +578,7 @@ impl Report for Html {
                 ),
             },
 
+            plots: self.plots,
             additional_plots,
 
             comparison: self.comparison(measurements),
@@ -478,7 +624,7 @@ impl Report for Html {
             let samples_with_function: Vec<_> = benchmark_group
                 .benchmarks
                 .iter()
-                .filter(|(ref id, _)| id.function_id.as_ref() == Some(&function_id))
+                .filter(|(id, _)| id.function_id.as_ref() == Some(function_id))
                 .collect();
 
             if samples_with_function.len() > 1 {
@@ -487,7 +633,7 @@ impl Report for Html {
 
                 self.generate_summary(
                     &subgroup_id,
-                    &*samples_with_function,
+                    &samples_with_function,
                     context,
                     formatter,
                     false,
@@ -500,20 +646,14 @@ impl Report for Html {
                 .benchmarks
                 .iter()
                 .by_ref()
-                .filter(|(ref id, _)| id.value_str.as_ref() == Some(&value_str))
+                .filter(|(id, _)| id.value_str.as_ref() == Some(value_str))
                 .collect();
 
             if samples_with_value.len() > 1 {
                 let subgroup_id =
                     BenchmarkId::new(group_id.to_owned(), None, Some(value_str.clone()), None);
 
-                self.generate_summary(
-                    &subgroup_id,
-                    &*samples_with_value,
-                    context,
-                    formatter,
-                    false,
-                );
+                self.generate_summary(&subgroup_id, &samples_with_value, context, formatter, false);
             }
         }
 
@@ -521,7 +661,7 @@ impl Report for Html {
 
         self.generate_summary(
             &BenchmarkId::new(group_id.to_owned(), None, None, None),
-            &*(all_data),
+            &all_data,
             context,
             formatter,
             true,
@@ -532,11 +672,20 @@ impl Report for Html {
     fn final_summary(&self, report_context: &ReportContext, model: &Model) {
         let output_directory = &report_context.output_directory;
 
-        let groups = model
-            .groups
+        let sorted_benchmarks = model.sorted_benchmarks(self.sort_by);
+        let group_summaries = sorted_benchmarks
             .iter()
-            .map(|(id, group)| BenchmarkGroup::new(output_directory, id, &group))
-            .collect::<Vec<BenchmarkGroup<'_>>>();
+            .map(|(id, _)| {
+                let summary = model.groups[*id].summary();
+                GroupSummaryRow {
+                    group_report: ReportLink::group(output_directory, id),
+                    benchmark_count: summary.benchmark_count,
+                    total_time: format::time(summary.total_time),
+                    regressed_count: summary.regressed_count,
+                    improved_count: summary.improved_count,
+                }
+            })
+            .collect();
 
         try_else_return!(mkdirp(&output_directory));
 
@@ -544,16 +693,41 @@ impl Report for Html {
 
         let context = IndexContext {
             common_css: COMMON_CSS,
-            groups,
+            title: self.title.as_deref(),
+            description: self.description.as_deref(),
+            group_summaries,
         };
 
         debug_context(&report_path, &context);
 
-        let text = self
-            .templates
-            .render("index", &context)
-            .expect("Failed to render index template");
-        try_else_return!(save_string(&text, &report_path,));
+        // The links to every benchmark would make a large page for a large suite, so it's written
+        // out one group at a time rather than rendered in one piece.
+        let write_index = || -> Result<()> {
+            let mut file = BufWriter::new(File::create(&report_path)?);
+            let header = self
+                .templates
+                .render("index", &context)
+                .expect("Failed to render index template");
+            file.write_all(header.as_bytes())?;
+            for (id, benchmarks) in sorted_benchmarks {
+                let group = BenchmarkGroup::new(output_directory, id, &benchmarks);
+                let text = self
+                    .templates
+                    .render("index_group", &group)
+                    .expect("Failed to render index_group template");
+                file.write_all(text.as_bytes())?;
+            }
+            let footer = self
+                .templates
+                .render("index_footer", &())
+                .expect("Failed to render index_footer template");
+            file.write_all(footer.as_bytes())?;
+            file.flush()?;
+            Ok(())
+        };
+        try_else_return!(
+            write_index().with_context(|| format!("Unable to save file {:?}", report_path))
+        );
     }
 
     fn history(
@@ -563,12 +737,33 @@ impl Report for Html {
         history: &[SavedStatistics],
         formatter: &ValueFormatter,
     ) {
+        if history.is_empty() {
+            return;
+        }
         let ids: Vec<_> = history
             .iter()
             .enumerate()
             .map(|(i, _)| format!("#{}", i))
             .collect();
 
+        // The fastest and slowest entries are picked by their mean, whichever estimate is plotted.
+        let means: Vec<f64> = history
+            .iter()
+            .map(|stats| stats.estimates.mean.point_estimate)
+            .collect();
+        let mut extremes = HistoryExtremes {
+            fastest: 0,
+            slowest: 0,
+        };
+        for (i, &mean) in means.iter().enumerate() {
+            if mean < means[extremes.fastest] {
+                extremes.fastest = i;
+            }
+            if mean > means[extremes.slowest] {
+                extremes.slowest = i;
+            }
+        }
+
         let typicals: Vec<_> = history
             .iter()
             .map(|stats| stats.estimates.typical())
@@ -590,7 +785,8 @@ impl Report for Html {
             .map(|est| est.confidence_interval.lower_bound)
             .collect();
 
-        let typical = Sample::new(&point_estimates).max();
+        // A benchmark's history may only have one entry, which is too short to be a Sample.
+        let typical = point_estimates.iter().copied().fold(f64::NAN, f64::max);
 
         let latest_throughput = history.last().and_then(|s| s.throughput.as_ref());
         let throughput_intervals = if let Some(throughput) = latest_throughput {
@@ -604,7 +800,7 @@ impl Report for Html {
 
             point_estimates
                 .into_iter()
-                .zip(upper_bounds.into_iter().zip(lower_bounds.into_iter()))
+                .zip(upper_bounds.into_iter().zip(lower_bounds))
                 .map(|(point, (upper, lower))| {
                     Some(ConfidenceInterval {
                         lower: format!("{:5.2}{}", lower, unit),
@@ -625,7 +821,6 @@ impl Report for Html {
             id,
             context: report_context,
             size: Some(Size(960, 640)),
-            is_thumbnail: false,
         };
 
         self.plotter.borrow_mut().history(
@@ -634,19 +829,36 @@ impl Report for Html {
             &point_estimates,
             &lower_bounds,
             &ids,
+            extremes,
             &unit,
         );
         self.plotter.borrow_mut().wait();
 
         let intervals = point_estimates
             .into_iter()
-            .zip(upper_bounds.into_iter().zip(lower_bounds.into_iter()))
+            .zip(upper_bounds.into_iter().zip(lower_bounds))
             .map(|(point, (upper, lower))| ConfidenceInterval {
                 lower: format!("{:5.2}{}", lower, unit),
                 point: format!("{:5.2}{}", point, unit),
                 upper: format!("{:5.2}{}", upper, unit),
             });
 
+        let extreme = |i: usize| HistoryExtreme {
+            number: i,
+            id: history[i].history_id.as_deref(),
+            datetime: format_history_datetime(&history[i]),
+            mean: formatter.format_value(means[i]),
+        };
+        let latest = history.len() - 1;
+        let latest_vs_fastest = if latest == extremes.fastest {
+            None
+        } else {
+            Some(format::time_change(
+                means[latest] / means[extremes.fastest] - 1.0,
+                self.change_ratio_above,
+            ))
+        };
+
         let mut history_entries: Vec<HistoryEntry> = history
             .iter()
             .zip(intervals)
@@ -657,15 +869,18 @@ impl Report for Html {
                 value,
                 throughput,
                 id: stats.history_id.as_deref(),
-                datetime: stats
-                    .datetime
-                    .with_timezone(&chrono::Local)
-                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                datetime: format_history_datetime(stats),
                 description: stats.history_description.as_deref(),
                 change_value: stats.changes.as_ref().map(|c| ConfidenceInterval {
-                    point: format::change(c.mean.point_estimate, true),
-                    lower: format::change(c.mean.confidence_interval.lower_bound, true),
-                    upper: format::change(c.mean.confidence_interval.upper_bound, true),
+                    point: format::time_change(c.mean.point_estimate, self.change_ratio_above),
+                    lower: format::time_change(
+                        c.mean.confidence_interval.lower_bound,
+                        self.change_ratio_above,
+                    ),
+                    upper: format::time_change(
+                        c.mean.confidence_interval.upper_bound,
+                        self.change_ratio_above,
+                    ),
                 }),
                 change_throughput: match (stats.changes.as_ref(), latest_throughput) {
                     (Some(c), Some(_)) => {
@@ -685,6 +900,7 @@ impl Report for Html {
                     }
                     _ => None,
                 },
+                change_p_value: stats.p_value.map(|p| format!("{:.2}", p)),
                 change_class: match stats.change_direction {
                     Some(ChangeDirection::Improved) => "improved",
                     Some(ChangeDirection::Regressed) => "regressed",
@@ -706,6 +922,10 @@ impl Report for Html {
         let context = HistoryContext {
             common_css: COMMON_CSS,
             title: id.as_title(),
+            fastest: extreme(extremes.fastest),
+            slowest: extreme(extremes.slowest),
+            latest_vs_fastest,
+            plots: self.plots,
             history: history_entries,
         };
 
@@ -726,13 +946,13 @@ impl Html {
     fn comparison(&self, measurements: &MeasurementData<'_>) -> Option<Comparison> {
         if let Some(ref comp) = measurements.comparison {
             let different_mean = comp.p_value < comp.significance_threshold;
-            let mean_est = &comp.relative_estimates.mean;
+            let mean_est = comp.primary_change();
             let explanation_str: String;
 
             if !different_mean {
                 explanation_str = "No change in performance detected.".to_owned();
             } else {
-                let comparison = compare_to_threshold(&mean_est, comp.noise_threshold);
+                let comparison = compare_to_threshold(mean_est, comp.noise_threshold);
                 match comparison {
                     ComparisonResult::Improved => {
                         explanation_str = "Performance has improved.".to_owned();
@@ -751,33 +971,54 @@ impl Html {
                 inequality: (if different_mean { "<" } else { ">" }).to_owned(),
                 significance_level: format!("{:.2}", comp.significance_threshold),
                 explanation: explanation_str,
+                baseline_label: comp.baseline_label.clone(),
+                // Only mention the version of the base samples if it differs from this run's.
+                base_criterion_version: comp
+                    .base_criterion_version
+                    .clone()
+                    .filter(|version| Some(version) != measurements.criterion_version.as_ref()),
+                throughput_mismatch: comp
+                    .throughput_mismatch
+                    .map(|mismatch| mismatch.to_string()),
 
                 change: ConfidenceInterval {
-                    point: format::change(mean_est.point_estimate, true),
-                    lower: format::change(mean_est.confidence_interval.lower_bound, true),
-                    upper: format::change(mean_est.confidence_interval.upper_bound, true),
+                    point: format::time_change(mean_est.point_estimate, self.change_ratio_above),
+                    lower: format::time_change(
+                        mean_est.confidence_interval.lower_bound,
+                        self.change_ratio_above,
+                    ),
+                    upper: format::time_change(
+                        mean_est.confidence_interval.upper_bound,
+                        self.change_ratio_above,
+                    ),
                 },
 
-                thrpt_change: measurements.throughput.as_ref().map(|_| {
-                    let to_thrpt_estimate = |ratio: f64| 1.0 / (1.0 + ratio) - 1.0;
-                    ConfidenceInterval {
-                        point: format::change(to_thrpt_estimate(mean_est.point_estimate), true),
-                        lower: format::change(
-                            to_thrpt_estimate(mean_est.confidence_interval.lower_bound),
-                            true,
-                        ),
-                        upper: format::change(
-                            to_thrpt_estimate(mean_est.confidence_interval.upper_bound),
-                            true,
-                        ),
-                    }
-                }),
-
-                additional_plots: vec![
-                    Plot::new("Change in mean", "change/mean.svg"),
-                    Plot::new("Change in median", "change/median.svg"),
-                    Plot::new("T-Test", "change/t-test.svg"),
-                ],
+                thrpt_change: (measurements.throughput.as_ref())
+                    .filter(|_| comp.throughput_mismatch.is_none())
+                    .map(|_| {
+                        let to_thrpt_estimate = |ratio: f64| 1.0 / (1.0 + ratio) - 1.0;
+                        ConfidenceInterval {
+                            point: format::change(to_thrpt_estimate(mean_est.point_estimate), true),
+                            lower: format::change(
+                                to_thrpt_estimate(mean_est.confidence_interval.lower_bound),
+                                true,
+                            ),
+                            upper: format::change(
+                                to_thrpt_estimate(mean_est.confidence_interval.upper_bound),
+                                true,
+                            ),
+                        }
+                    }),
+
+                additional_plots: if self.plots {
+                    vec![
+                        Plot::new("Change in mean", "change/mean.svg"),
+                        Plot::new("Change in median", "change/median.svg"),
+                        Plot::new("T-Test", "change/t-test.svg"),
+                    ]
+                } else {
+                    Vec::new()
+                },
             };
             Some(comp)
         } else {
@@ -796,11 +1037,9 @@ impl Html {
             id,
             context,
             size: None,
-            is_thumbnail: false,
         };
 
         let plot_ctx_small = PlotContext {
-            is_thumbnail: true,
             size: THUMBNAIL_SIZE,
             ..plot_ctx
         };
@@ -905,7 +1144,6 @@ impl Html {
             id,
             context: report_context,
             size: None,
-            is_thumbnail: false,
         };
 
         try_else_return!(
@@ -916,14 +1154,34 @@ impl Html {
             || {}
         );
 
-        self.plotter.borrow_mut().violin(plot_ctx, formatter, data);
+        // The model doesn't keep the samples in memory, so they're loaded one benchmark at a time
+        // as the plot is drawn. Measurements stored with `--store summary` have no samples to draw.
+        let mut violin_samples = data
+            .iter()
+            .filter_map(|(id, benchmark)| match benchmark.load_latest() {
+                Ok(stats) if stats.has_samples() => Some((*id, stats.avg_values)),
+                Ok(_) => None,
+                Err(e) => {
+                    error!("{:?}", e);
+                    None
+                }
+            })
+            .peekable();
+        let violin_path = if violin_samples.peek().is_none() {
+            None
+        } else {
+            self.plotter
+                .borrow_mut()
+                .violin(plot_ctx, formatter, &mut violin_samples);
+            Some(plot_ctx.violin_path())
+        };
 
-        let value_types: Vec<_> = data.iter().map(|(ref id, _)| id.value_type()).collect();
+        let value_types: Vec<_> = data.iter().map(|(id, _)| id.value_type()).collect();
         let mut line_path = None;
 
         if value_types.iter().all(|x| x == &value_types[0]) {
             if let Some(value_type) = value_types[0] {
-                let values: Vec<_> = data.iter().map(|(ref id, _)| id.as_number()).collect();
+                let values: Vec<_> = data.iter().map(|(id, _)| id.as_number()).collect();
                 if values.iter().any(|x| x != &values[0]) {
                     self.plotter
                         .borrow_mut()
@@ -933,10 +1191,30 @@ impl Html {
             }
         }
 
+        // Only draw the time per element if it shows how the cost of at least one function
+        // changes with the size of its input.
+        let mut element_counts: HashMap<&Option<String>, HashSet<u64>> = HashMap::new();
+        for (id, benchmark) in data {
+            if let Some(elements) = element_count(&benchmark.latest_stats.throughput) {
+                element_counts
+                    .entry(&id.function_id)
+                    .or_default()
+                    .insert(elements);
+            }
+        }
+        let per_element_path = if element_counts.values().any(|counts| counts.len() > 1) {
+            self.plotter
+                .borrow_mut()
+                .per_element_comparison(plot_ctx, formatter, data);
+            Some(plot_ctx.per_element_path())
+        } else {
+            None
+        };
+
         let path_prefix = if full_summary { ".." } else { "../.." };
         let benchmarks = data
             .iter()
-            .map(|(ref id, _)| {
+            .map(|(id, _)| {
                 IndividualBenchmark::from_id(&report_context.output_directory, path_prefix, id)
             })
             .collect();
@@ -948,9 +1226,11 @@ impl Html {
             thumbnail_width: THUMBNAIL_SIZE.unwrap().0,
             thumbnail_height: THUMBNAIL_SIZE.unwrap().1,
 
-            violin_plot: Some(plot_ctx.violin_path().to_string_lossy().into_owned()),
-            line_chart: line_path.map(|p| p.to_string_lossy().into_owned()),
+            violin_plot: violin_path.filter(|_| self.plots).map(plot_file_name),
+            line_chart: line_path.filter(|_| self.plots).map(plot_file_name),
+            per_element_chart: per_element_path.filter(|_| self.plots).map(plot_file_name),
 
+            plots: self.plots,
             benchmarks,
         };
 
@@ -968,3 +1248,75 @@ impl Html {
         try_else_return!(save_string(&text, &report_path,), || {});
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reports_link_with_relative_paths() {
+        let output_directory = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-relative-links",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&output_directory);
+        let ids = [
+            BenchmarkId::new("group".to_owned(), Some("a".to_owned()), None, None),
+            BenchmarkId::new("group".to_owned(), Some("b".to_owned()), None, None),
+        ];
+        for directory in &["group", "group/a", "group/b"] {
+            let directory = output_directory.join(directory);
+            std::fs::create_dir_all(&directory).unwrap();
+            std::fs::write(directory.join("index.html"), "").unwrap();
+        }
+
+        let html = Html::new(None, SortOrder::Insertion, None, None, None);
+        let index = IndexContext {
+            common_css: "",
+            title: None,
+            description: None,
+            group_summaries: vec![],
+        };
+        let group =
+            BenchmarkGroup::new(&output_directory, "group", &ids.iter().collect::<Vec<_>>());
+        let summary = SummaryContext {
+            common_css: "",
+            group_id: "group".to_owned(),
+            thumbnail_width: 450,
+            thumbnail_height: 300,
+            violin_plot: Some(plot_file_name(output_directory.join("group/violin.svg"))),
+            line_chart: None,
+            per_element_chart: None,
+            plots: true,
+            benchmarks: ids
+                .iter()
+                .map(|id| IndividualBenchmark::from_id(&output_directory, "..", id))
+                .collect(),
+        };
+        let pages = [
+            html.templates.render("index", &index).unwrap(),
+            html.templates.render("index_group", &group).unwrap(),
+            html.templates.render("summary_report", &summary).unwrap(),
+        ];
+
+        let absolute = output_directory.to_string_lossy().into_owned();
+        for page in &pages {
+            assert!(!page.contains(&absolute), "{}", page);
+            for attribute in &["href=\"", "src=\""] {
+                for link in page.split(attribute).skip(1) {
+                    let link = &link[..link.find('"').unwrap()];
+                    let relative = !(link.starts_with('/') || link.contains('\\'));
+                    assert!(
+                        link.starts_with("https://") || relative,
+                        "{} is not a relative URL",
+                        link
+                    );
+                }
+            }
+        }
+        assert!(pages[1].contains("href=\"group/a/index.html\""));
+        assert!(pages[2].contains("href=\"../group/a/index.html\""));
+        assert!(pages[2].contains("src=\"violin.svg\""));
+        let _ = std::fs::remove_dir_all(&output_directory);
+    }
+}
@@ -0,0 +1,301 @@
+//! A Cargo extension for running [Criterion.rs] benchmarks and reporting the results.
+//!
+//! This crate is a Cargo extension which can be used as a replacement for `cargo bench` when
+//! running [Criterion.rs] benchmarks. It can also be used as a library, to run the benchmarks
+//! from a build script or a custom benchmark harness; see `Runner`.
+
+#![allow(
+    clippy::just_underscores_and_digits, // Used in the stats code
+    clippy::transmute_ptr_to_ptr, // Used in the stats code
+)]
+
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+mod macros_private;
+
+#[allow(clippy::too_many_arguments)]
+#[macro_use]
+mod plot;
+
+mod affinity;
+mod analysis;
+mod baseline_file;
+mod bench_target;
+mod bundle;
+mod commands;
+mod compile;
+mod config;
+mod connection;
+#[cfg(feature = "sqlite")]
+mod database;
+mod error;
+mod estimate;
+mod format;
+mod html;
+mod kde;
+mod logger;
+mod markdown;
+mod measurement_log;
+mod message_formats;
+mod model;
+#[cfg(feature = "pdf_report")]
+mod pdf;
+mod perf;
+mod preflight;
+mod report;
+mod runner;
+mod serve;
+mod stats;
+mod value_formatter;
+
+pub use crate::compile::CompileError;
+pub use crate::config::OutputFormat;
+pub use crate::error::RunError;
+pub use crate::runner::{RunSummary, Runner};
+
+use crate::config::{Action, CompareAgainst, LogFormat, SelfConfig};
+use anyhow::Error;
+use lazy_static::lazy_static;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+lazy_static! {
+    static ref DEBUG_ENABLED: bool = std::env::var_os("CRITERION_DEBUG").is_some();
+}
+
+fn debug_enabled() -> bool {
+    *DEBUG_ENABLED
+}
+
+/// Load the stored measurements of the timeline being used, to be compared against the given
+/// stored measurements.
+fn load_model(self_config: &SelfConfig, compare_against: CompareAgainst) -> model::Model {
+    load_model_from(
+        self_config,
+        self_config.data_directory.clone(),
+        compare_against,
+    )
+}
+
+/// Load the stored measurements of the timeline being used from another data directory. New
+/// measurements added to the model are stored with the history ID, baseline, format and so on
+/// given in the configuration.
+fn load_model_from(
+    self_config: &SelfConfig,
+    data_directory: PathBuf,
+    compare_against: CompareAgainst,
+) -> model::Model {
+    model::Model::load(
+        data_directory,
+        self_config.timeline.clone().into(),
+        self_config.history_id.clone(),
+        self_config.history_description.clone(),
+        compare_against,
+        self_config.save_baseline.clone(),
+        self_config.storage_format,
+        self_config.sample_storage,
+        self_config.dir_name_strategy,
+        self_config.dry_run,
+        self_config.datetime_from_mtime,
+    )
+}
+
+/// Configures the logger based on the debug environment variable. The log format isn't known
+/// until the configuration has been loaded, so messages are held until `logger::set_format`.
+fn configure_log() {
+    let filter = if debug_enabled() {
+        log::LevelFilter::max()
+    } else {
+        log::LevelFilter::Warn
+    };
+    logger::init(filter);
+}
+
+// TODO: Write unit tests for serialization.
+
+/// Run cargo-criterion with the given command-line arguments, starting with the program name, as
+/// the `cargo criterion` command does. This sets up cargo-criterion's logger, so it panics if
+/// another logger has already been set.
+pub fn run_cli<I: IntoIterator<Item = OsString>>(args: I) -> Result<(), Error> {
+    configure_log();
+
+    // First, load the config file and parse the command-line args.
+    let configuration = match config::configure(args) {
+        Ok(configuration) => configuration,
+        Err(e) => {
+            // Make sure that anything logged while loading the configuration is printed.
+            logger::set_format(LogFormat::Text);
+            return Err(e);
+        }
+    };
+    let self_config = &configuration.self_config;
+    logger::set_format(self_config.log_format);
+    format::set_time_unit(self_config.time_unit);
+    model::set_max_open_files(self_config.max_open_files);
+
+    if let Action::DumpConfig { format } = configuration.action {
+        commands::dump_config(&configuration, format)?;
+        return Ok(());
+    }
+    if let Action::ListGroups { json } = configuration.action {
+        let model = match &self_config.db {
+            Some(db) => model::Model::load_from_database(db, None)?,
+            None => load_model(self_config, CompareAgainst::Nothing),
+        };
+        commands::list_groups(&model, json)?;
+        return Ok(());
+    }
+    if let Action::Commits { json } = configuration.action {
+        let model = load_model(self_config, CompareAgainst::Nothing);
+        commands::commits(&model, json)?;
+        return Ok(());
+    }
+    if let Action::Pool { benchmark, count } = &configuration.action {
+        let mut model = load_model(self_config, CompareAgainst::Nothing);
+        let _data_lock = if self_config.dry_run {
+            None
+        } else {
+            Some(model::DataLock::acquire(&self_config.data_directory)?)
+        };
+        commands::pool(
+            &mut model,
+            benchmark,
+            *count,
+            &self_config.benchmark_overrides,
+        )?;
+        return Ok(());
+    }
+    if let Action::Accept {
+        baseline,
+        benchmarks,
+    } = &configuration.action
+    {
+        let model = load_model(self_config, CompareAgainst::Nothing);
+        let _data_lock = if self_config.dry_run {
+            None
+        } else {
+            Some(model::DataLock::acquire(&self_config.data_directory)?)
+        };
+        commands::accept(&model, baseline, benchmarks.as_deref())?;
+        return Ok(());
+    }
+    if let Action::Bundle { benchmark, out } = &configuration.action {
+        let model = load_model(self_config, CompareAgainst::Nothing);
+        commands::bundle(
+            &model,
+            benchmark,
+            out,
+            &self_config.data_directory,
+            &self_config.report_directory,
+        )?;
+        return Ok(());
+    }
+    if let Action::Import { path, force } = &configuration.action {
+        let _data_lock = if self_config.dry_run {
+            None
+        } else {
+            Some(model::DataLock::acquire(&self_config.data_directory)?)
+        };
+        commands::import(
+            path,
+            &self_config.data_directory,
+            &self_config.report_directory,
+            *force,
+            self_config.dry_run,
+        )?;
+        return Ok(());
+    }
+    if let Action::Report { group } = &configuration.action {
+        let model = match &self_config.db {
+            Some(db) => model::Model::load_from_database(db, Some(group))?,
+            None => model::Model::load_group(
+                self_config.data_directory.clone(),
+                self_config.timeline.clone().into(),
+                group,
+            ),
+        };
+        commands::report(
+            &model,
+            group,
+            self_config.sort_by,
+            self_config.change_ratio_above,
+        )?;
+        return Ok(());
+    }
+    if let Action::CompareBaseline {
+        baseline,
+        from,
+        format,
+    } = &configuration.action
+    {
+        let baseline_model = load_model(self_config, CompareAgainst::Baseline(baseline.clone()));
+        let new_model = load_model_from(self_config, from.join("data"), CompareAgainst::Nothing);
+        commands::compare_baseline(
+            &baseline_model,
+            &new_model,
+            *format,
+            &self_config.benchmark_overrides,
+            self_config.significance_test,
+            self_config.change_ratio_above,
+        )?;
+        return Ok(());
+    }
+    if let Action::CompareCommits { base, head, format } = &configuration.action {
+        let model = load_model(self_config, CompareAgainst::Nothing);
+        commands::compare_commits(
+            &model,
+            base,
+            head,
+            *format,
+            &self_config.benchmark_overrides,
+            self_config.significance_test,
+            self_config.change_ratio_above,
+        )?;
+        return Ok(());
+    }
+    if let Action::Inspect { path } = &configuration.action {
+        commands::inspect(path)?;
+        return Ok(());
+    }
+    if let Action::Capabilities { json } = configuration.action {
+        commands::capabilities(json)?;
+        return Ok(());
+    }
+    if let Action::Doctor = configuration.action {
+        commands::doctor(&self_config.data_directory)?;
+        return Ok(());
+    }
+    if let Action::Preflight = configuration.action {
+        preflight::preflight(self_config)?;
+        return Ok(());
+    }
+    if let Action::Migrate { to } = configuration.action {
+        commands::migrate(&self_config.data_directory, to, self_config.dry_run)?;
+        return Ok(());
+    }
+    if let Action::Serve { from, host, port } = &configuration.action {
+        serve::serve(from, host, *port)?;
+        return Ok(());
+    }
+
+    runner::run(&configuration, &mut |message| eprintln!("{}", message))?;
+    Ok(())
+}
+
+/// Helper trait which adds a function for converting Duration to nanoseconds.
+trait DurationExt {
+    fn to_nanos(&self) -> u64;
+}
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+impl DurationExt for std::time::Duration {
+    fn to_nanos(&self) -> u64 {
+        self.as_secs() * NANOS_PER_SEC + u64::from(self.subsec_nanos())
+    }
+}
@@ -0,0 +1,165 @@
+//! The logger for cargo-criterion's own messages (errors, warnings and so on).
+//!
+//! The log format is selected on the command line, but messages may be logged while the command
+//! line is still being parsed. Until the format is known, the logger holds on to every message
+//! and prints them all once `set_format` is called.
+//!
+//! Warnings and errors are also kept as diagnostics, so that they can be included in the
+//! machine-readable output. A diagnostic's kind is the target it was logged with, if that was
+//! given explicitly (eg. `warn!(target: "duplicate-benchmark-id", ...)`).
+
+use crate::config::LogFormat;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use simplelog::{TermLogger, TerminalMode};
+use std::io::Write;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref LOGGER: Logger = Logger {
+        state: Mutex::new(State::Pending(Vec::new())),
+        diagnostics: Mutex::new(Vec::new()),
+    };
+}
+
+/// A warning or error which hasn't been included in the machine-readable output yet.
+pub struct Diagnostic {
+    pub level: Level,
+    pub kind: String,
+    pub message: String,
+}
+impl Diagnostic {
+    fn new(record: &Record<'_>) -> Diagnostic {
+        // Targets which weren't given explicitly are module paths.
+        let kind = if record.target().contains("::") {
+            "other"
+        } else {
+            record.target()
+        };
+        Diagnostic {
+            level: record.level(),
+            kind: kind.to_owned(),
+            message: record.args().to_string(),
+        }
+    }
+}
+
+/// A log message which has been captured so that it can be printed later.
+struct SavedRecord {
+    level: Level,
+    target: String,
+    timestamp: DateTime<Utc>,
+    message: String,
+}
+impl SavedRecord {
+    fn new(record: &Record<'_>) -> SavedRecord {
+        SavedRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            timestamp: Utc::now(),
+            message: record.args().to_string(),
+        }
+    }
+}
+
+/// The fields of one line of JSON log output.
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    level: String,
+    timestamp: String,
+    target: &'a str,
+    message: &'a str,
+}
+
+fn print_json(record: &SavedRecord) {
+    let json = JsonRecord {
+        level: record.level.to_string().to_lowercase(),
+        timestamp: record.timestamp.to_rfc3339(),
+        target: &record.target,
+        message: &record.message,
+    };
+    if let Ok(line) = serde_json::to_string(&json) {
+        // There's nowhere left to report a failure to write a log message.
+        let _ = writeln!(std::io::stderr(), "{}", line);
+    }
+}
+
+enum State {
+    /// The log format isn't known yet. Messages are saved until it is.
+    Pending(Vec<SavedRecord>),
+    Text(Box<TermLogger>),
+    Json,
+}
+
+struct Logger {
+    state: Mutex<State>,
+    diagnostics: Mutex<Vec<Diagnostic>>,
+}
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if record.level() <= Level::Warn {
+            self.diagnostics
+                .lock()
+                .unwrap()
+                .push(Diagnostic::new(record));
+        }
+        match &mut *self.state.lock().unwrap() {
+            State::Pending(saved) => saved.push(SavedRecord::new(record)),
+            State::Text(logger) => logger.log(record),
+            State::Json => print_json(&SavedRecord::new(record)),
+        }
+    }
+
+    fn flush(&self) {
+        if let State::Text(logger) = &*self.state.lock().unwrap() {
+            logger.flush();
+        }
+    }
+}
+
+/// Install the logger, showing messages up to the given level.
+pub fn init(filter: LevelFilter) {
+    log::set_logger(&*LOGGER).unwrap();
+    log::set_max_level(filter);
+}
+
+/// Returns the warnings and errors logged since the last call.
+pub fn take_diagnostics() -> Vec<Diagnostic> {
+    std::mem::take(&mut *LOGGER.diagnostics.lock().unwrap())
+}
+
+/// Select the log format and print any messages logged before it was known.
+pub fn set_format(format: LogFormat) {
+    let mut state = LOGGER.state.lock().unwrap();
+    let new_state = match format {
+        LogFormat::Text => State::Text(TermLogger::new(
+            log::max_level(),
+            Default::default(),
+            TerminalMode::Stderr,
+        )),
+        LogFormat::Json => State::Json,
+    };
+
+    if let State::Pending(saved) = std::mem::replace(&mut *state, new_state) {
+        for record in &saved {
+            match &*state {
+                State::Text(logger) => logger.log(
+                    &Record::builder()
+                        .level(record.level)
+                        .target(&record.target)
+                        .args(format_args!("{}", record.message))
+                        .build(),
+                ),
+                _ => print_json(record),
+            }
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//! Module for rendering benchmark results as Markdown tables, such as the job summary shown by
+//! GitHub Actions.
+
+use crate::config::SortOrder;
+use crate::format;
+use crate::model::{AllocStats, Model};
+use crate::report::{
+    compare_to_threshold, is_significant_change, BenchmarkId, BudgetCheck, ComparisonResult,
+    MeasurementData, Report, ReportContext,
+};
+use crate::value_formatter::ValueFormatter;
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One row of the summary table, describing a single benchmark.
+struct SummaryRow {
+    name: String,
+    time: String,
+    change: String,
+    result: &'static str,
+    // True if the confidence intervals of the old and new times don't overlap.
+    significant: bool,
+    peak_rss: Option<u64>,
+    allocations: Option<AllocStats>,
+    budget: Option<BudgetCheck>,
+    // The rows of the parts of the benchmark which it timed separately, listed after its own row.
+    parts: Vec<SummaryRow>,
+}
+impl SummaryRow {
+    fn new(
+        name: String,
+        meas: &MeasurementData<'_>,
+        formatter: &ValueFormatter,
+        change_ratio_above: Option<f64>,
+    ) -> SummaryRow {
+        let typical_estimate = meas.absolute_estimates.typical();
+        let time = format!(
+            "[{} {} {}]",
+            formatter.format_value(typical_estimate.confidence_interval.lower_bound),
+            formatter.format_value(typical_estimate.point_estimate),
+            formatter.format_value(typical_estimate.confidence_interval.upper_bound),
+        );
+
+        let (change, result, significant) = match &meas.comparison {
+            Some(comp) => {
+                let mean_est = comp.primary_change();
+                let result = if comp.p_value < comp.significance_threshold {
+                    match compare_to_threshold(mean_est, comp.noise_threshold) {
+                        ComparisonResult::Improved => "Improved",
+                        ComparisonResult::Regressed => "Regressed",
+                        ComparisonResult::NonSignificant => "Within noise",
+                    }
+                } else {
+                    "No change"
+                };
+                (
+                    format::time_change(mean_est.point_estimate, change_ratio_above)
+                        .trim()
+                        .to_owned(),
+                    result,
+                    is_significant_change(&comp.base_estimates, &meas.absolute_estimates),
+                )
+            }
+            None => (String::new(), "New", false),
+        };
+
+        let parts = (meas.sub_measurements.iter())
+            .map(|(part, sub)| {
+                SummaryRow::new(
+                    format!("{}/{}", name, part),
+                    sub,
+                    formatter,
+                    change_ratio_above,
+                )
+            })
+            .collect();
+        SummaryRow {
+            name,
+            time,
+            change,
+            result,
+            significant,
+            peak_rss: meas.peak_rss,
+            allocations: meas.allocations,
+            budget: meas.budget,
+            parts,
+        }
+    }
+}
+
+/// Escape the characters which would otherwise break a Markdown table cell.
+pub(crate) fn escape_cell(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Render the rows as a Markdown table. The peak RSS column is only included if memory usage was
+/// measured for at least one benchmark, and the allocation columns only if at least one benchmark
+/// reported its allocations. Likewise, the budget column is only included if at least one benchmark
+/// has a budget. The rows of the parts of a benchmark follow the row of the benchmark.
+fn summary_table(rows: &[SummaryRow]) -> String {
+    let show_memory = rows.iter().any(|row| row.peak_rss.is_some());
+    let show_allocations = rows.iter().any(|row| row.allocations.is_some());
+    let show_budget = rows.iter().any(|row| row.budget.is_some());
+    let mut table = String::from("| Benchmark | Time | Change | Result |");
+    let mut separator = String::from("|-----------|------|--------|--------|");
+    if show_memory {
+        table.push_str(" Peak RSS |");
+        separator.push_str("----------|");
+    }
+    if show_allocations {
+        table.push_str(" Allocations | Allocated |");
+        separator.push_str("-------------|-----------|");
+    }
+    if show_budget {
+        table.push_str(" Budget |");
+        separator.push_str("--------|");
+    }
+    table.push('\n');
+    table.push_str(&separator);
+    table.push('\n');
+    let all_rows = (rows.iter()).flat_map(|row| std::iter::once(row).chain(&row.parts));
+    for row in all_rows {
+        table.push_str(&format!(
+            "| {} | {} | {} | {}{} |",
+            escape_cell(&row.name),
+            escape_cell(&row.time),
+            escape_cell(&row.change),
+            row.result,
+            if row.significant {
+                " (significant)"
+            } else {
+                ""
+            }
+        ));
+        if show_memory {
+            let peak_rss = row.peak_rss.map(format::bytes).unwrap_or_default();
+            table.push_str(&format!(" {} |", peak_rss.trim()));
+        }
+        if show_allocations {
+            let (allocations, bytes) = match &row.allocations {
+                Some(allocations) => (
+                    format::allocations(allocations.allocations_per_iteration),
+                    format::bytes(allocations.bytes_per_iteration.round() as u64),
+                ),
+                None => (String::new(), String::new()),
+            };
+            table.push_str(&format!(" {} | {} |", allocations, bytes.trim()));
+        }
+        if show_budget {
+            let budget = match &row.budget {
+                Some(budget) => format!(
+                    "{} {}",
+                    format::time(budget.limit_ns).trim(),
+                    if budget.exceeded() {
+                        "(fail)"
+                    } else {
+                        "(pass)"
+                    }
+                ),
+                None => String::new(),
+            };
+            table.push_str(&format!(" {} |", budget));
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// Report which collects a row for each completed benchmark and appends a Markdown summary table
+/// to the GitHub Actions job summary file once all of the benchmarks have finished.
+pub struct GithubSummaryReport {
+    path: PathBuf,
+    sort_by: SortOrder,
+    change_ratio_above: Option<f64>,
+    rows: RefCell<Vec<SummaryRow>>,
+}
+impl GithubSummaryReport {
+    pub fn new(
+        path: PathBuf,
+        sort_by: SortOrder,
+        change_ratio_above: Option<f64>,
+    ) -> GithubSummaryReport {
+        GithubSummaryReport {
+            path,
+            sort_by,
+            change_ratio_above,
+            rows: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Reorder the collected rows to match the order in which the model lists the benchmarks.
+    fn sort_rows(&self, model: &Model) {
+        let order: HashMap<&str, usize> = model
+            .sorted_benchmarks(self.sort_by)
+            .into_iter()
+            .flat_map(|(_, ids)| ids)
+            .enumerate()
+            .map(|(i, id)| (id.as_title(), i))
+            .collect();
+        self.rows
+            .borrow_mut()
+            .sort_by_key(|row| order.get(row.name.as_str()).copied().unwrap_or(usize::MAX));
+    }
+
+    fn write_summary(&self, path: &Path) -> Result<()> {
+        let rows = self.rows.borrow();
+        let text = format!("## Benchmark Results\n\n{}\n", summary_table(&rows));
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open job summary file {:?}", path))?;
+        file.write_all(text.as_bytes())
+            .with_context(|| format!("Failed to write job summary file {:?}", path))?;
+        Ok(())
+    }
+}
+impl Report for GithubSummaryReport {
+    fn measurement_complete(
+        &self,
+        id: &BenchmarkId,
+        _context: &ReportContext,
+        measurements: &MeasurementData<'_>,
+        formatter: &ValueFormatter,
+    ) {
+        self.rows.borrow_mut().push(SummaryRow::new(
+            id.as_title().to_owned(),
+            measurements,
+            formatter,
+            self.change_ratio_above,
+        ));
+    }
+
+    fn final_summary(&self, _context: &ReportContext, model: &Model) {
+        if self.rows.borrow().is_empty() {
+            return;
+        }
+        self.sort_rows(model);
+        if let Err(e) = self.write_summary(&self.path) {
+            error!("Failed to write GitHub job summary: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary_table_escapes_pipes() {
+        let rows = vec![SummaryRow {
+            name: "group/a|b".to_owned(),
+            time: "[1.0 ns 2.0 ns 3.0 ns]".to_owned(),
+            change: "+1.0000%".to_owned(),
+            result: "No change",
+            significant: false,
+            peak_rss: None,
+            allocations: None,
+            budget: None,
+            parts: vec![],
+        }];
+        let table = summary_table(&rows);
+        assert_eq!(
+            "| Benchmark | Time | Change | Result |\n\
+             |-----------|------|--------|--------|\n\
+             | group/a\\|b | [1.0 ns 2.0 ns 3.0 ns] | +1.0000% | No change |\n",
+            table
+        );
+    }
+
+    #[test]
+    fn test_summary_table_peak_rss_column() {
+        let row = |name: &str, peak_rss| SummaryRow {
+            name: name.to_owned(),
+            time: "[1.0 ns 2.0 ns 3.0 ns]".to_owned(),
+            change: String::new(),
+            result: "New",
+            significant: false,
+            peak_rss,
+            allocations: None,
+            budget: None,
+            parts: vec![],
+        };
+        let table = summary_table(&[row("a", Some(2048)), row("b", None)]);
+        assert_eq!(
+            "| Benchmark | Time | Change | Result | Peak RSS |\n\
+             |-----------|------|--------|--------|----------|\n\
+             | a | [1.0 ns 2.0 ns 3.0 ns] |  | New | 2.0000 KiB |\n\
+             | b | [1.0 ns 2.0 ns 3.0 ns] |  | New |  |\n",
+            table
+        );
+    }
+
+    #[test]
+    fn test_summary_table_allocation_columns() {
+        let row = |name: &str, allocations| SummaryRow {
+            name: name.to_owned(),
+            time: "[1.0 ns 2.0 ns 3.0 ns]".to_owned(),
+            change: String::new(),
+            result: "New",
+            significant: false,
+            peak_rss: None,
+            allocations,
+            budget: None,
+            parts: vec![],
+        };
+        let counted = AllocStats::per_iteration(30, 3072, 10.0);
+        let table = summary_table(&[row("a", Some(counted)), row("b", None)]);
+        assert_eq!(
+            "| Benchmark | Time | Change | Result | Allocations | Allocated |\n\
+             |-----------|------|--------|--------|-------------|-----------|\n\
+             | a | [1.0 ns 2.0 ns 3.0 ns] |  | New | 3.0000 allocations | 307.00 B |\n\
+             | b | [1.0 ns 2.0 ns 3.0 ns] |  | New |  |  |\n",
+            table
+        );
+    }
+
+    #[test]
+    fn test_summary_table_lists_parts_after_their_benchmark() {
+        let row = |name: &str, parts| SummaryRow {
+            name: name.to_owned(),
+            time: "[1.0 ns 2.0 ns 3.0 ns]".to_owned(),
+            change: String::new(),
+            result: "New",
+            significant: false,
+            peak_rss: None,
+            allocations: None,
+            budget: None,
+            parts,
+        };
+        let rows = vec![
+            row("a", vec![row("a/parse", vec![]), row("a/render", vec![])]),
+            row("b", vec![]),
+        ];
+        assert_eq!(
+            "| Benchmark | Time | Change | Result |\n\
+             |-----------|------|--------|--------|\n\
+             | a | [1.0 ns 2.0 ns 3.0 ns] |  | New |\n\
+             | a/parse | [1.0 ns 2.0 ns 3.0 ns] |  | New |\n\
+             | a/render | [1.0 ns 2.0 ns 3.0 ns] |  | New |\n\
+             | b | [1.0 ns 2.0 ns 3.0 ns] |  | New |\n",
+            summary_table(&rows)
+        );
+    }
+
+    #[test]
+    fn test_summary_table_marks_significant_changes() {
+        let rows = vec![SummaryRow {
+            name: "a".to_owned(),
+            time: "[1.0 ns 2.0 ns 3.0 ns]".to_owned(),
+            change: "+50.000%".to_owned(),
+            result: "Regressed",
+            significant: true,
+            peak_rss: None,
+            allocations: None,
+            budget: None,
+            parts: vec![],
+        }];
+        assert_eq!(
+            "| Benchmark | Time | Change | Result |\n\
+             |-----------|------|--------|--------|\n\
+             | a | [1.0 ns 2.0 ns 3.0 ns] | +50.000% | Regressed (significant) |\n",
+            summary_table(&rows)
+        );
+    }
+
+    #[test]
+    fn test_summary_table_budget_column() {
+        let row = |name: &str, budget| SummaryRow {
+            name: name.to_owned(),
+            time: "[1.0 ns 2.0 ns 3.0 ns]".to_owned(),
+            change: String::new(),
+            result: "New",
+            significant: false,
+            peak_rss: None,
+            allocations: None,
+            budget,
+            parts: vec![],
+        };
+        let check = |limit_ns| BudgetCheck {
+            limit_ns,
+            measured_ns: 2.0,
+            statistic: crate::config::BudgetStatistic::Mean,
+        };
+        let table = summary_table(&[
+            row("a", Some(check(5.0))),
+            row("b", Some(check(1.0))),
+            row("c", None),
+        ]);
+        assert_eq!(
+            "| Benchmark | Time | Change | Result | Budget |\n\
+             |-----------|------|--------|--------|--------|\n\
+             | a | [1.0 ns 2.0 ns 3.0 ns] |  | New | 5.0000 ns (pass) |\n\
+             | b | [1.0 ns 2.0 ns 3.0 ns] |  | New | 1.0000 ns (fail) |\n\
+             | c | [1.0 ns 2.0 ns 3.0 ns] |  | New |  |\n",
+            table
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! An append-only log of CBOR records, used to store all of the measurements of a benchmark in a
+//! single file instead of one file per measurement.
+//!
+//! Each record is stored as its length in bytes (as a little-endian u64) followed by the CBOR
+//! encoding of the record. Records are identified by the byte offset at which they start.
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const LENGTH_PREFIX_SIZE: usize = std::mem::size_of::<u64>();
+
+/// Append a record to the log at the given path, creating it if necessary. Returns the offset of
+/// the new record.
+///
+/// If an earlier write was interrupted, the log ends with a truncated record. That record is
+/// removed first, since the new record would otherwise be read as the rest of it.
+pub fn append<T: Serialize>(path: &Path, record: &T) -> Result<u64> {
+    let bytes = serde_cbor::to_vec(record)
+        .with_context(|| format!("Failed to serialize record for measurement log {:?}", path))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open measurement log {:?}", path))?;
+    let offset = end_of_complete_records(&mut file)
+        .with_context(|| format!("Failed to read measurement log {:?}", path))?;
+    let len = file
+        .metadata()
+        .with_context(|| format!("Failed to read measurement log {:?}", path))?
+        .len();
+    if offset < len {
+        warn!(
+            "Measurement log {:?} ends with a truncated record at offset {}, which will be removed",
+            path, offset
+        );
+        file.set_len(offset)
+            .with_context(|| format!("Failed to truncate measurement log {:?}", path))?;
+    }
+
+    // Write the whole record with a single call so that an interrupted write can only ever leave
+    // a truncated record at the end of the log.
+    let mut buffer = Vec::with_capacity(LENGTH_PREFIX_SIZE + bytes.len());
+    buffer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    buffer.extend_from_slice(&bytes);
+    file.write_all(&buffer)
+        .with_context(|| format!("Failed to write to measurement log {:?}", path))?;
+    Ok(offset)
+}
+
+/// Read the record starting at the given offset in the log at the given path.
+pub fn read_at<T: DeserializeOwned>(path: &Path, offset: u64) -> Result<T> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open measurement log {:?}", path))?;
+    file.seek(SeekFrom::Start(offset))
+        .with_context(|| format!("Failed to seek in measurement log {:?}", path))?;
+
+    let mut length = [0u8; LENGTH_PREFIX_SIZE];
+    file.read_exact(&mut length).with_context(|| {
+        format!(
+            "Failed to read record at offset {} of measurement log {:?}",
+            offset, path
+        )
+    })?;
+    let mut bytes = vec![0u8; u64::from_le_bytes(length) as usize];
+    file.read_exact(&mut bytes).with_context(|| {
+        format!(
+            "Failed to read record at offset {} of measurement log {:?}",
+            offset, path
+        )
+    })?;
+    serde_cbor::from_slice(&bytes).with_context(|| {
+        format!(
+            "Failed to parse record at offset {} of measurement log {:?}",
+            offset, path
+        )
+    })
+}
+
+/// Read every record in the log at the given path, oldest first. A record which can't be parsed
+/// produces an error in its place. If the log ends with a truncated record, the last entry is an
+/// error; `append` removes such a record before writing, so it can't be followed by others.
+pub fn read_all<T: DeserializeOwned>(path: &Path) -> Vec<Result<T>> {
+    read_all_with_offsets(path)
+        .into_iter()
+        .map(|record| record.map(|(_, record)| record))
+        .collect()
+}
+
+/// Like `read_all`, but also returns the offset of each record.
+pub fn read_all_with_offsets<T: DeserializeOwned>(path: &Path) -> Vec<Result<(u64, T)>> {
+    let mut contents = Vec::new();
+    if let Err(e) = File::open(path).and_then(|mut file| file.read_to_end(&mut contents)) {
+        return vec![Err(e).with_context(|| format!("Failed to read measurement log {:?}", path))];
+    }
+
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while offset < contents.len() {
+        let body_start = offset + LENGTH_PREFIX_SIZE;
+        if body_start > contents.len() {
+            records.push(Err(truncated(path, offset)));
+            break;
+        }
+        let mut length = [0u8; LENGTH_PREFIX_SIZE];
+        length.copy_from_slice(&contents[offset..body_start]);
+        let length = u64::from_le_bytes(length) as usize;
+        let body_end = match body_start.checked_add(length) {
+            Some(end) if end <= contents.len() => end,
+            _ => {
+                records.push(Err(truncated(path, offset)));
+                break;
+            }
+        };
+
+        records.push(
+            serde_cbor::from_slice(&contents[body_start..body_end])
+                .map(|record| (offset as u64, record))
+                .with_context(|| {
+                    format!(
+                        "Failed to parse record at offset {} of measurement log {:?}",
+                        offset, path
+                    )
+                }),
+        );
+        offset = body_end;
+    }
+    records
+}
+
+/// Find the end of the last complete record in the log, by following the length prefixes of the
+/// records. This is the end of the file, unless the last record is truncated.
+fn end_of_complete_records(file: &mut File) -> std::io::Result<u64> {
+    let len = file.metadata()?.len();
+    let mut offset = 0;
+    loop {
+        let body_start = offset + LENGTH_PREFIX_SIZE as u64;
+        if body_start > len {
+            return Ok(offset);
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut length = [0u8; LENGTH_PREFIX_SIZE];
+        file.read_exact(&mut length)?;
+        match body_start.checked_add(u64::from_le_bytes(length)) {
+            Some(end) if end <= len => offset = end,
+            _ => return Ok(offset),
+        }
+    }
+}
+
+fn truncated(path: &Path, offset: usize) -> anyhow::Error {
+    anyhow!(
+        "Measurement log {:?} is truncated at the record starting at offset {}",
+        path,
+        offset
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read() {
+        let path = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-measurements.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let first = append(&path, &"first".to_owned()).unwrap();
+        let second = append(&path, &"second".to_owned()).unwrap();
+        assert_eq!(first, 0);
+        assert_eq!("second", read_at::<String>(&path, second).unwrap());
+        assert_eq!("first", read_at::<String>(&path, first).unwrap());
+        let offsets: Vec<u64> = read_all_with_offsets::<String>(&path)
+            .into_iter()
+            .map(|record| record.unwrap().0)
+            .collect();
+        assert_eq!(offsets, vec![first, second]);
+
+        // Simulate a write which was interrupted partway through.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&100u64.to_le_bytes()).unwrap();
+        file.write_all(b"partial").unwrap();
+
+        let records = read_all::<String>(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap(), "first");
+        assert_eq!(records[1].as_ref().unwrap(), "second");
+        assert!(records[2].is_err());
+    }
+
+    #[test]
+    fn test_append_after_truncated_record() {
+        let path = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-truncated.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let first = append(&path, &"first".to_owned()).unwrap();
+        let second = append(&path, &"second".to_owned()).unwrap();
+        // Cut the second record short, as an interrupted write would.
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+        assert!(read_all::<String>(&path)[1].is_err());
+
+        // The truncated record is replaced, so everything in the log can be read again.
+        let third = append(&path, &"third".to_owned()).unwrap();
+        assert_eq!(third, second);
+        let records: Vec<_> = read_all_with_offsets::<String>(&path)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            records,
+            vec![(first, "first".to_owned()), (third, "third".to_owned())]
+        );
+    }
+}
@@ -1,52 +1,133 @@
+use super::upload::Uploader;
 use crate::connection::Throughput as ThroughputEnum;
 use crate::estimate::Estimate;
-use crate::model::BenchmarkGroup;
+use crate::logger;
+use crate::model::{BenchmarkGroup, Model};
 use crate::report::{
-    compare_to_threshold, BenchmarkId, ComparisonResult, MeasurementData, Report, ReportContext,
+    compare_to_threshold, is_significant_change, is_std_dev_increase_significant, std_dev_change,
+    BenchmarkId, ComparisonResult, MeasurementData, Report, ReportContext,
 };
 use crate::value_formatter::ValueFormatter;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde_derive::Serialize;
 use serde_json::json;
+use std::cell::{Cell, RefCell};
+use std::fs::File;
 use std::io::{stdout, Write};
+use std::path::Path;
 
 trait Message: serde::ser::Serialize {
     fn reason() -> &'static str;
 }
 
+/// The versions of the JSON message format. Only the way the estimates are written differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatVersion {
+    /// `--message-format json`.
+    V1,
+    /// `--message-format json-v2`, which writes each estimate as a `{point, lower, upper, unit}`
+    /// object, and the relative changes as percentages rather than fractions.
+    V2,
+}
+
+/// An estimate and the bounds of its confidence interval, all in the given unit.
 #[derive(Serialize)]
-struct ConfidenceInterval {
-    estimate: f64,
-    lower_bound: f64,
-    upper_bound: f64,
-    unit: String,
+#[serde(untagged)]
+enum ConfidenceInterval {
+    V1 {
+        estimate: f64,
+        lower_bound: f64,
+        upper_bound: f64,
+        unit: String,
+    },
+    V2 {
+        point: f64,
+        lower: f64,
+        upper: f64,
+        unit: String,
+    },
 }
 impl ConfidenceInterval {
-    fn from_estimate(estimate: &Estimate, value_formatter: &ValueFormatter) -> ConfidenceInterval {
+    fn new(version: FormatVersion, [point, lower, upper]: [f64; 3], unit: String) -> Self {
+        match version {
+            FormatVersion::V1 => ConfidenceInterval::V1 {
+                estimate: point,
+                lower_bound: lower,
+                upper_bound: upper,
+                unit,
+            },
+            FormatVersion::V2 => ConfidenceInterval::V2 {
+                point,
+                lower,
+                upper,
+                unit,
+            },
+        }
+    }
+    fn from_estimate(
+        estimate: &Estimate,
+        value_formatter: &ValueFormatter,
+        version: FormatVersion,
+    ) -> ConfidenceInterval {
         let mut array = [
             estimate.point_estimate,
             estimate.confidence_interval.lower_bound,
             estimate.confidence_interval.upper_bound,
         ];
         let unit = value_formatter.scale_for_machines(&mut array);
-        let [estimate, lower_bound, upper_bound] = array;
-        ConfidenceInterval {
-            estimate,
-            lower_bound,
-            upper_bound,
-            unit,
-        }
+        ConfidenceInterval::new(version, array, unit)
+    }
+    /// The relative changes are estimated as fractions. Version 1 writes them as they are, even
+    /// though their unit is "%".
+    fn from_percent(estimate: &Estimate, version: FormatVersion) -> ConfidenceInterval {
+        let scale = percent_scale(version);
+        let array = [
+            estimate.point_estimate * scale,
+            estimate.confidence_interval.lower_bound * scale,
+            estimate.confidence_interval.upper_bound * scale,
+        ];
+        ConfidenceInterval::new(version, array, "%".to_owned())
     }
-    fn from_percent(estimate: &Estimate) -> ConfidenceInterval {
-        ConfidenceInterval {
-            estimate: estimate.point_estimate,
-            lower_bound: estimate.confidence_interval.lower_bound,
-            upper_bound: estimate.confidence_interval.upper_bound,
-            unit: "%".to_owned(),
+}
+
+/// The relative change in the standard deviation. Version 1 writes its unit in a separate field.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum StdDevChange {
+    V1 { std_dev: f64, std_dev_unit: String },
+    V2 { std_dev: Quantity },
+}
+impl StdDevChange {
+    fn from_fraction(fraction: f64, version: FormatVersion) -> StdDevChange {
+        let value = fraction * percent_scale(version);
+        let unit = "%".to_owned();
+        match version {
+            FormatVersion::V1 => StdDevChange::V1 {
+                std_dev: value,
+                std_dev_unit: unit,
+            },
+            FormatVersion::V2 => StdDevChange::V2 {
+                std_dev: Quantity { value, unit },
+            },
         }
     }
 }
 
+/// A single estimate without a confidence interval, in the given unit.
+#[derive(Serialize)]
+struct Quantity {
+    value: f64,
+    unit: String,
+}
+
+/// What the relative changes, which are estimated as fractions, are multiplied by to write them.
+fn percent_scale(version: FormatVersion) -> f64 {
+    match version {
+        FormatVersion::V1 => 1.0,
+        FormatVersion::V2 => 100.0,
+    }
+}
+
 #[derive(Serialize)]
 struct Throughput {
     per_iteration: u64,
@@ -63,6 +144,10 @@ impl From<&ThroughputEnum> for Throughput {
                 per_iteration: *elements,
                 unit: "elements".to_owned(),
             },
+            ThroughputEnum::Custom { unit, count } => Throughput {
+                per_iteration: *count,
+                unit: unit.clone(),
+            },
         }
     }
 }
@@ -80,6 +165,14 @@ struct ChangeDetails {
     median: ConfidenceInterval,
 
     change: ChangeType,
+    // True if the confidence intervals of the old and new typical times don't overlap.
+    significant: bool,
+    // The relative change in the standard deviation, and whether it grew by more than the
+    // confidence intervals of the old and new standard deviations allow for.
+    #[serde(flatten)]
+    std_dev: StdDevChange,
+    std_dev_increased: bool,
+    baseline_label: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -91,6 +184,23 @@ struct BenchmarkComplete {
     unit: String,
 
     throughput: Vec<Throughput>,
+    time_per_element: Option<ConfidenceInterval>,
+    peak_rss_bytes: Option<u64>,
+    instructions_per_iteration: Option<f64>,
+    allocations_per_iteration: Option<f64>,
+    allocated_bytes_per_iteration: Option<f64>,
+    criterion_version: Option<String>,
+    bench_version: Option<String>,
+    bench_seed: Option<u64>,
+    single_thread: bool,
+    async_runtime: Option<String>,
+    // The budget in nanoseconds per iteration and whether it was exceeded, if the benchmark has one.
+    budget_ns: Option<f64>,
+    budget_exceeded: Option<bool>,
+    // The number of usable samples, and whether that was fewer than `--min-samples`.
+    sample_count: usize,
+    low_confidence: bool,
+    min_samples_enforced: bool,
 
     typical: ConfidenceInterval,
     mean: ConfidenceInterval,
@@ -118,25 +228,165 @@ impl Message for BenchmarkGroupComplete {
     }
 }
 
-pub struct JsonMessageReport;
+#[derive(Serialize)]
+struct Diagnostic<'a> {
+    level: String,
+    kind: &'a str,
+    message: &'a str,
+}
+impl Message for Diagnostic<'_> {
+    fn reason() -> &'static str {
+        "diagnostic"
+    }
+}
+
+/// Sent for each group once all of the benchmarks have finished, with totals over the latest
+/// measurements of its benchmarks.
+#[derive(Serialize)]
+struct GroupSummary {
+    group_name: String,
+    benchmark_count: usize,
+    total_time: f64,
+    unit: String,
+    regressed_count: usize,
+    improved_count: usize,
+}
+impl Message for GroupSummary {
+    fn reason() -> &'static str {
+        "group-summary"
+    }
+}
+
+/// Sent once all of the benchmarks have finished.
+#[derive(Serialize)]
+struct RunComplete {
+    benchmarks_run: usize,
+    benchmarks_filtered_out: usize,
+}
+impl Message for RunComplete {
+    fn reason() -> &'static str {
+        "run-complete"
+    }
+}
+
+pub struct JsonMessageReport {
+    out: Output,
+    version: FormatVersion,
+}
+
+enum Output {
+    Stdout,
+    File(RefCell<File>),
+    // Each message is posted to a collector. Failures are counted rather than stopping the run.
+    Upload {
+        uploader: Uploader,
+        failures: Cell<usize>,
+    },
+}
 impl JsonMessageReport {
+    pub fn new(path: Option<&Path>, version: FormatVersion) -> Result<JsonMessageReport> {
+        let out = match path {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("Failed to create message file {:?}", path))?;
+                Output::File(RefCell::new(file))
+            }
+            None => Output::Stdout,
+        };
+        Ok(JsonMessageReport { out, version })
+    }
+
+    /// A report which posts each message to the collector instead of writing it out, in the
+    /// first version of the message format.
+    pub fn upload(uploader: Uploader) -> JsonMessageReport {
+        JsonMessageReport {
+            out: Output::Upload {
+                uploader,
+                failures: Cell::new(0),
+            },
+            version: FormatVersion::V1,
+        }
+    }
+
+    /// The number of messages which couldn't be posted to the collector.
+    pub fn failed_uploads(&self) -> usize {
+        match &self.out {
+            Output::Upload { failures, .. } => failures.get(),
+            _ => 0,
+        }
+    }
+
+    /// Send the message, preceded by any warnings or errors logged since the last message.
     fn send_message<M: Message>(&self, message: M) {
-        fn do_send<M: Message>(message: M) -> Result<()> {
-            // Format the message to string
-            let message_text = serde_json::to_string(&message)?;
-            assert!(message_text.starts_with('{'));
+        // The diagnostics can only be taken once, so they're left to the --message-format output.
+        if !matches!(self.out, Output::Upload { .. }) {
+            self.send_diagnostics();
+        }
+        self.write_message(message);
+    }
 
-            let reason = json!(M::reason());
+    fn send_diagnostics(&self) {
+        for diagnostic in logger::take_diagnostics() {
+            self.write_message(Diagnostic {
+                level: diagnostic.level.to_string().to_lowercase(),
+                kind: &diagnostic.kind,
+                message: &diagnostic.message,
+            });
+        }
+    }
 
-            // Concatenate that into the message
-            writeln!(stdout(), "{{\"reason\":{},{}", reason, &message_text[1..])?;
+    fn write_message<M: Message>(&self, message: M) {
+        fn do_send(out: &mut dyn Write, message_text: &str) -> Result<()> {
+            // Each line is flushed as soon as it's written so that anyone watching the output sees
+            // each benchmark as it completes.
+            writeln!(out, "{}", message_text)?;
+            out.flush()?;
             Ok(())
         }
-        if let Err(e) = do_send(message) {
+        let message_text = match format_message(message) {
+            Ok(message_text) => message_text,
+            Err(e) => {
+                error!("Unexpected error formatting JSON message: {:?}", e);
+                return;
+            }
+        };
+        let result = match &self.out {
+            Output::Stdout => do_send(&mut stdout(), &message_text),
+            Output::File(file) => do_send(&mut *file.borrow_mut(), &message_text),
+            Output::Upload { uploader, failures } => {
+                if let Err(e) = uploader.post(&message_text) {
+                    // Only the first failure is explained, so that an unreachable collector
+                    // doesn't bury the rest of the output.
+                    if failures.get() == 0 {
+                        warn!(
+                            target: "upload-failure",
+                            "Failed to upload the results to {}: {:#}",
+                            uploader.url(),
+                            e
+                        );
+                    }
+                    failures.set(failures.get() + 1);
+                }
+                return;
+            }
+        };
+        if let Err(e) = result {
             error!("Unexpected error writing JSON message: {:?}", e)
         }
     }
 }
+
+/// Format the message as a JSON object, with its reason as the first field.
+fn format_message<M: Message>(message: M) -> Result<String> {
+    let message_text = serde_json::to_string(&message)?;
+    assert!(message_text.starts_with('{'));
+
+    let reason = json!(M::reason());
+
+    // Concatenate that into the message.
+    Ok(format!("{{\"reason\":{},{}", reason, &message_text[1..]))
+}
+
 impl Report for JsonMessageReport {
     fn measurement_complete(
         &self,
@@ -145,6 +395,7 @@ impl Report for JsonMessageReport {
         measurements: &MeasurementData<'_>,
         formatter: &ValueFormatter,
     ) {
+        let version = self.version;
         let mut measured_values = measurements.sample_times().to_vec();
         let unit = formatter.scale_for_machines(&mut measured_values);
 
@@ -168,36 +419,61 @@ impl Report for JsonMessageReport {
                 .iter()
                 .map(Throughput::from)
                 .collect(),
+            time_per_element: measurements
+                .time_per_element()
+                .map(|estimate| ConfidenceInterval::from_estimate(&estimate, formatter, version)),
+            peak_rss_bytes: measurements.peak_rss,
+            instructions_per_iteration: measurements.instructions_per_iteration,
+            allocations_per_iteration: measurements
+                .allocations
+                .map(|allocations| allocations.allocations_per_iteration),
+            allocated_bytes_per_iteration: measurements
+                .allocations
+                .map(|allocations| allocations.bytes_per_iteration),
+            criterion_version: measurements.criterion_version.clone(),
+            bench_version: measurements.bench_version.clone(),
+            bench_seed: measurements.bench_seed,
+            single_thread: measurements.single_thread,
+            async_runtime: measurements.async_runtime.clone(),
+            budget_ns: measurements.budget.map(|budget| budget.limit_ns),
+            budget_exceeded: measurements.budget.map(|budget| budget.exceeded()),
+            sample_count: measurements.effective_sample_count,
+            low_confidence: measurements.low_confidence,
+            min_samples_enforced: measurements.min_samples_enforced,
 
             typical: ConfidenceInterval::from_estimate(
                 measurements.absolute_estimates.typical(),
                 formatter,
+                version,
             ),
             mean: ConfidenceInterval::from_estimate(
                 &measurements.absolute_estimates.mean,
                 formatter,
+                version,
             ),
             median: ConfidenceInterval::from_estimate(
                 &measurements.absolute_estimates.median,
                 formatter,
+                version,
             ),
             median_abs_dev: ConfidenceInterval::from_estimate(
                 &measurements.absolute_estimates.median_abs_dev,
                 formatter,
+                version,
             ),
             slope: measurements
                 .absolute_estimates
                 .slope
                 .as_ref()
-                .map(|slope| ConfidenceInterval::from_estimate(slope, formatter)),
+                .map(|slope| ConfidenceInterval::from_estimate(slope, formatter, version)),
             change: measurements.comparison.as_ref().map(|comparison| {
                 let different_mean = comparison.p_value < comparison.significance_threshold;
-                let mean_est = &comparison.relative_estimates.mean;
+                let mean_est = comparison.primary_change();
 
                 let change = if !different_mean {
                     ChangeType::NoChange
                 } else {
-                    let comparison = compare_to_threshold(&mean_est, comparison.noise_threshold);
+                    let comparison = compare_to_threshold(mean_est, comparison.noise_threshold);
                     match comparison {
                         ComparisonResult::Improved => ChangeType::Improved,
                         ComparisonResult::Regressed => ChangeType::Regressed,
@@ -206,9 +482,31 @@ impl Report for JsonMessageReport {
                 };
 
                 ChangeDetails {
-                    mean: ConfidenceInterval::from_percent(&comparison.relative_estimates.mean),
-                    median: ConfidenceInterval::from_percent(&comparison.relative_estimates.median),
+                    mean: ConfidenceInterval::from_percent(
+                        &comparison.relative_estimates.mean,
+                        version,
+                    ),
+                    median: ConfidenceInterval::from_percent(
+                        &comparison.relative_estimates.median,
+                        version,
+                    ),
                     change,
+                    significant: is_significant_change(
+                        &comparison.base_estimates,
+                        &measurements.absolute_estimates,
+                    ),
+                    std_dev: StdDevChange::from_fraction(
+                        std_dev_change(
+                            &comparison.base_estimates,
+                            &measurements.absolute_estimates,
+                        ),
+                        version,
+                    ),
+                    std_dev_increased: is_std_dev_increase_significant(
+                        &comparison.base_estimates,
+                        &measurements.absolute_estimates,
+                    ),
+                    baseline_label: comparison.baseline_label.clone(),
                 }
             }),
         };
@@ -240,4 +538,66 @@ impl Report for JsonMessageReport {
 
         self.send_message(message);
     }
+
+    fn final_summary(&self, _context: &ReportContext, model: &Model) {
+        for (group_name, group) in &model.groups {
+            let summary = group.summary();
+            self.send_message(GroupSummary {
+                group_name: group_name.clone(),
+                benchmark_count: summary.benchmark_count,
+                total_time: summary.total_time,
+                unit: "ns".to_owned(),
+                regressed_count: summary.regressed_count,
+                improved_count: summary.improved_count,
+            });
+        }
+        let counts = model.run_counts();
+        self.send_message(RunComplete {
+            benchmarks_run: counts.run,
+            benchmarks_filtered_out: counts.filtered_out,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::estimate::ConfidenceInterval as Interval;
+
+    #[test]
+    fn test_estimates_in_each_format_version() {
+        let estimate = Estimate {
+            confidence_interval: Interval {
+                confidence_level: 0.95,
+                lower_bound: 0.25,
+                upper_bound: 0.75,
+            },
+            point_estimate: 0.5,
+            standard_error: 0.1,
+        };
+        assert_eq!(
+            json!({"estimate": 0.5, "lower_bound": 0.25, "upper_bound": 0.75, "unit": "%"}),
+            serde_json::to_value(ConfidenceInterval::from_percent(
+                &estimate,
+                FormatVersion::V1
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            json!({"point": 50.0, "lower": 25.0, "upper": 75.0, "unit": "%"}),
+            serde_json::to_value(ConfidenceInterval::from_percent(
+                &estimate,
+                FormatVersion::V2
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            json!({"std_dev": -0.1, "std_dev_unit": "%"}),
+            serde_json::to_value(StdDevChange::from_fraction(-0.1, FormatVersion::V1)).unwrap()
+        );
+        assert_eq!(
+            json!({"std_dev": {"value": -10.0, "unit": "%"}}),
+            serde_json::to_value(StdDevChange::from_fraction(-0.1, FormatVersion::V2)).unwrap()
+        );
+    }
 }
@@ -1,11 +1,32 @@
 mod json;
+mod upload;
 use crate::config::{MessageFormat, SelfConfig};
 
-use self::json::JsonMessageReport;
+use self::json::FormatVersion;
+pub use self::json::JsonMessageReport;
+use self::upload::Uploader;
+pub use self::upload::{parse_header, UploadUrl};
+use anyhow::Result;
 
-pub fn create_machine_report(self_config: &SelfConfig) -> Option<JsonMessageReport> {
-    match self_config.message_format {
-        Some(MessageFormat::Json) => Some(JsonMessageReport),
-        None => None,
+pub fn create_machine_report(self_config: &SelfConfig) -> Result<Option<JsonMessageReport>> {
+    let version = match self_config.message_format {
+        Some(MessageFormat::Json) => FormatVersion::V1,
+        Some(MessageFormat::JsonV2) => FormatVersion::V2,
+        None => return Ok(None),
+    };
+    Ok(Some(JsonMessageReport::new(
+        self_config.message_path.as_deref(),
+        version,
+    )?))
+}
+
+/// Create the report which posts the results to the collector given with `--upload-url`.
+pub fn create_upload_report(self_config: &SelfConfig) -> Result<Option<JsonMessageReport>> {
+    match &self_config.upload_url {
+        Some(url) => {
+            let uploader = Uploader::new(url, self_config.upload_headers.clone())?;
+            Ok(Some(JsonMessageReport::upload(uploader)))
+        }
+        None => Ok(None),
     }
 }
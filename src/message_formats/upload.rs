@@ -0,0 +1,210 @@
+//! Posting the JSON messages to a remote collector over HTTP, for `--upload-url`.
+//!
+//! Each message is sent in its own `POST` request on a new connection. Only plain `http://` URLs
+//! are supported.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long connecting to the collector, sending a message or waiting for the response may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the messages are posted, parsed from an `http://HOST[:PORT][/PATH]` URL.
+#[derive(Debug, PartialEq)]
+pub struct UploadUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+impl UploadUrl {
+    pub fn parse(url: &str) -> Result<UploadUrl, String> {
+        let rest = if let Some(rest) = url.strip_prefix("http://") {
+            rest
+        } else if url.starts_with("https://") {
+            return Err(format!(
+                "'{}' is an https:// URL, but only http:// URLs are supported",
+                url
+            ));
+        } else {
+            return Err(format!("'{}' is not an http:// URL", url));
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        // IPv6 addresses are written in brackets, so that their colons aren't taken for the port.
+        let (host, port) = match authority.rfind(':') {
+            Some(index) if !authority[index..].contains(']') => {
+                let port = authority[index + 1..]
+                    .parse()
+                    .map_err(|_| format!("'{}' does not have a valid port", url))?;
+                (&authority[..index], port)
+            }
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() || host.contains('@') {
+            return Err(format!("'{}' does not have a valid host", url));
+        }
+        Ok(UploadUrl {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+}
+
+/// Parse an extra request header given as `NAME: VALUE`.
+pub fn parse_header(s: &str) -> Result<(String, String), String> {
+    let split = s
+        .find(':')
+        .ok_or_else(|| format!("'{}' is not of the form NAME: VALUE", s))?;
+    let (name, value) = (s[..split].trim(), s[split + 1..].trim());
+    if name.is_empty() || name.contains(char::is_whitespace) || s.contains(['\r', '\n']) {
+        return Err(format!("'{}' is not a valid header", s));
+    }
+    Ok((name.to_owned(), value.to_owned()))
+}
+
+pub struct Uploader {
+    url: String,
+    target: UploadUrl,
+    headers: Vec<(String, String)>,
+}
+impl Uploader {
+    pub fn new(url: &str, headers: Vec<(String, String)>) -> Result<Uploader> {
+        let target = UploadUrl::parse(url).map_err(anyhow::Error::msg)?;
+        Ok(Uploader {
+            url: url.to_owned(),
+            target,
+            headers,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Post the JSON message to the collector. Fails unless it responds with a 2xx status.
+    pub fn post(&self, body: &str) -> Result<()> {
+        let UploadUrl { host, port, path } = &self.target;
+        let mut stream = connect(host, *port)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut request = format!("POST {} HTTP/1.1\r\n", path);
+        if *port == 80 {
+            request.push_str(&format!("Host: {}\r\n", host));
+        } else {
+            request.push_str(&format!("Host: {}:{}\r\n", host, port));
+        }
+        request.push_str("Content-Type: application/json\r\n");
+        request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        request.push_str("Connection: close\r\n");
+        for (name, value) in &self.headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream
+            .write_all(request.as_bytes())
+            .context("Failed to send the request")?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream)
+            .read_line(&mut status_line)
+            .context("Failed to read the response")?;
+        let status_line = status_line.trim_end();
+        match status_line.split_whitespace().nth(1) {
+            Some(status) if status.starts_with('2') => Ok(()),
+            Some(_) => anyhow::bail!("The collector responded with '{}'", status_line),
+            None => anyhow::bail!("The collector sent an invalid response"),
+        }
+    }
+}
+
+fn connect(host: &str, port: u16) -> Result<TcpStream> {
+    let addresses = (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("Failed to resolve {}", host))?;
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(e) => Err(e).with_context(|| format!("Failed to connect to {}:{}", host, port)),
+        None => anyhow::bail!("{} has no addresses", host),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_url() {
+        let url = |host: &str, port, path: &str| UploadUrl {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        };
+        assert_eq!(
+            Ok(url("ci.example.com", 80, "/")),
+            UploadUrl::parse("http://ci.example.com")
+        );
+        assert_eq!(
+            Ok(url("localhost", 8080, "/results?run=1")),
+            UploadUrl::parse("http://localhost:8080/results?run=1")
+        );
+        assert_eq!(
+            Ok(url("::1", 8080, "/")),
+            UploadUrl::parse("http://[::1]:8080/")
+        );
+        assert_eq!(Ok(url("::1", 80, "/")), UploadUrl::parse("http://[::1]/"));
+        assert!(UploadUrl::parse("https://ci.example.com").is_err());
+        assert!(UploadUrl::parse("ci.example.com").is_err());
+        assert!(UploadUrl::parse("http://localhost:http/").is_err());
+    }
+
+    #[test]
+    fn test_post() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let collector = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in ["200 OK", "500 Internal Server Error"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                // The body is the last thing sent, and it ends with the closing brace.
+                while !request.ends_with(b"}") {
+                    let read = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..read]);
+                }
+                write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+                requests.push(String::from_utf8(request).unwrap());
+            }
+            requests
+        });
+
+        let url = format!("http://127.0.0.1:{}/results", port);
+        let headers = vec![parse_header("Authorization: Bearer abc").unwrap()];
+        let uploader = Uploader::new(&url, headers).unwrap();
+        uploader.post(r#"{"reason":"run-complete"}"#).unwrap();
+        let error = uploader.post("{}").unwrap_err();
+        assert!(error.to_string().contains("500 Internal Server Error"));
+
+        let requests = collector.join().unwrap();
+        assert!(requests[0].starts_with("POST /results HTTP/1.1\r\n"));
+        assert!(requests[0].contains("\r\nAuthorization: Bearer abc\r\n"));
+        assert!(requests[0].contains("\r\nContent-Length: 25\r\n"));
+        assert!(requests[0].ends_with("\r\n\r\n{\"reason\":\"run-complete\"}"));
+    }
+}
@@ -1,33 +1,75 @@
+use crate::baseline_file::{self, BaselineKey};
+use crate::config::{
+    pattern_matches, CompareAgainst, DirNameStrategy, RetentionPolicy, SampleStorage, SortOrder,
+    StorageFormat,
+};
 use crate::connection::Throughput;
-use crate::estimate::{ChangeEstimates, Estimates};
-use crate::report::{BenchmarkId, ComparisonData, MeasurementData};
-use anyhow::{Context, Result};
+#[cfg(feature = "sqlite")]
+use crate::database::Database;
+use crate::estimate::{ChangeEstimates, ConfidenceInterval, Estimate, Estimates};
+use crate::measurement_log;
+use crate::report::{make_filename_safe, BenchmarkId, ComparisonData, MeasurementData};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use linked_hash_map::LinkedHashMap;
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicUsize};
+use std::time::SystemTime;
 use walkdir::WalkDir;
 
+/// How many files loading or checking the stored data may keep open at once, unless
+/// `--max-open-files` is given.
+pub const DEFAULT_MAX_OPEN_FILES: usize = 10;
+
+static MAX_OPEN_FILES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_OPEN_FILES);
+
+/// Set how many files loading or checking the stored data may keep open at once. This applies to
+/// the whole process, like the limit on open files that it guards against.
+pub fn set_max_open_files(max_open_files: usize) {
+    MAX_OPEN_FILES.store(max_open_files, atomic::Ordering::Relaxed);
+}
+
+/// Walk the given directory. Every file is closed as soon as it has been read, so one file is
+/// open at a time, and the rest of the `--max-open-files` limit is left for the directories being
+/// walked. Deeper directories are read into memory instead of being kept open.
+fn walk_dir(directory: &Path) -> WalkDir {
+    let max_open_files = MAX_OPEN_FILES.load(atomic::Ordering::Relaxed);
+    WalkDir::new(directory).max_open(max_open_files.saturating_sub(1).max(1))
+}
+
 #[derive(Debug)]
 pub struct Benchmark {
+    // The latest measurement of this benchmark, without its raw samples. Use `load_latest` to
+    // read the samples from disk.
     pub latest_stats: SavedStatistics,
-    pub previous_stats: Option<SavedStatistics>,
+    // The directory that this benchmark's measurements are stored in, and where the latest one is.
+    directory: PathBuf,
+    latest: MeasurementLocation,
     pub target: Option<String>,
 }
 impl Benchmark {
-    fn new(stats: SavedStatistics) -> Self {
+    fn new(directory: PathBuf, latest: MeasurementLocation, stats: SavedStatistics) -> Self {
         Benchmark {
-            latest_stats: stats,
-            previous_stats: None,
+            latest_stats: stats.without_samples(),
+            directory,
+            latest,
             target: None,
         }
     }
 
-    fn add_stats(&mut self, stats: SavedStatistics) {
-        let previous_stats = std::mem::replace(&mut self.latest_stats, stats);
-        self.previous_stats = Some(previous_stats);
+    fn add_stats(&mut self, latest: MeasurementLocation, stats: SavedStatistics) {
+        self.latest_stats = stats.without_samples();
+        self.latest = latest;
+    }
+
+    /// Load the latest measurement of this benchmark from disk, including its raw samples.
+    pub fn load_latest(&self) -> Result<SavedStatistics> {
+        load_measurement_at(&self.directory, &self.latest)
     }
 }
 
@@ -44,10 +86,80 @@ impl Default for BenchmarkGroup {
         }
     }
 }
+impl BenchmarkGroup {
+    /// Add up the latest measurements of the benchmarks in this group, counting how many of them
+    /// changed from the measurement they were compared against.
+    pub fn summary(&self) -> GroupSummary {
+        let mut summary = GroupSummary::default();
+        for benchmark in self.benchmarks.values() {
+            let stats = &benchmark.latest_stats;
+            summary.benchmark_count += 1;
+            summary.total_time += stats.estimates.typical().point_estimate;
+            match stats.change_direction {
+                Some(ChangeDirection::Regressed) => summary.regressed_count += 1,
+                Some(ChangeDirection::Improved) => summary.improved_count += 1,
+                _ => {}
+            }
+        }
+        summary
+    }
+}
+
+/// Totals over the benchmarks in a group, for an overview of large benchmark suites.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GroupSummary {
+    pub benchmark_count: usize,
+    /// The sum of the typical times of the benchmarks.
+    pub total_time: f64,
+    pub regressed_count: usize,
+    pub improved_count: usize,
+}
+
+/// The number of benchmarks which were run, and which were skipped because they didn't match the
+/// filter.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RunCounts {
+    pub run: usize,
+    pub filtered_out: usize,
+}
+
+/// Keeps track of which benchmarks the targets ran or skipped. Benchmarks are identified by title,
+/// so a benchmark reported more than once is only counted once, and a benchmark which was run is
+/// never counted as filtered out.
+#[derive(Debug, Default)]
+struct BenchmarkTally {
+    run: HashSet<String>,
+    filtered_out: HashSet<String>,
+}
+impl BenchmarkTally {
+    fn record(&mut self, title: &str, ran: bool) {
+        if ran {
+            self.filtered_out.remove(title);
+            self.run.insert(title.to_owned());
+        } else if !self.run.contains(title) {
+            self.filtered_out.insert(title.to_owned());
+        }
+    }
+
+    fn counts(&self) -> RunCounts {
+        RunCounts {
+            run: self.run.len(),
+            filtered_out: self.filtered_out.len(),
+        }
+    }
+}
 
 /// The Model struct stores everything that we keep in-memory about the benchmarks and their
 /// performance. It's loaded from disk at the beginning of a run and updated as benchmarks
 /// are executed.
+///
+/// Only the estimates of each benchmark are kept in memory. The raw samples are read from disk
+/// when they're needed, and at most one benchmark's samples are held at a time: one comparison
+/// sample while benchmarking, and one benchmark while drawing a group's summary plots, which keep
+/// only the smoothed curve of each earlier benchmark. The index is written one group at a time.
+/// So memory use doesn't grow with the number of samples stored, but it still grows with the
+/// number of benchmarks, whose estimates and metadata are held for the whole run.
+/// `test_report_generation_peak_rss` measures this.
 #[derive(Debug)]
 pub struct Model {
     // Path to output directory
@@ -55,71 +167,437 @@ pub struct Model {
     // Track all of the unique benchmark titles and directories we've seen, so we can uniquify them.
     all_titles: HashSet<String>,
     all_directories: HashSet<PathBuf>,
+    // The benchmarks which have been run or skipped so far.
+    tally: BenchmarkTally,
+    // The titles of the benchmarks which were rejected for having no stored measurement.
+    rejected_new_benchmarks: Vec<String>,
+    // The titles of the benchmarks whose performance has regressed, in every repeat of the run.
+    regressed_benchmarks: Vec<String>,
+    // The titles of the benchmarks whose new measurement was over its budget.
+    over_budget_benchmarks: Vec<String>,
+    // Patterns matching the benchmark and group IDs which may be encountered more than once
+    // without a warning, and whether every duplicate ID should fail the run anyway.
+    allowed_duplicate_ids: Vec<String>,
+    deny_duplicate_ids: bool,
+    // The benchmark and group IDs which have been encountered more than once.
+    duplicate_ids: Vec<String>,
+    // The titles of the benchmarks in each group as of the group's previous run, and of the ones
+    // run or skipped in each group so far in this run.
+    previous_group_members: HashMap<String, Vec<String>>,
+    group_members: HashMap<String, Vec<String>>,
+    // Whether a group which has fewer benchmarks than in its previous run should fail the run.
+    deny_missing_benchmarks: bool,
+    // The titles of the benchmarks which were in their group's previous run, but not in this one.
+    missing_benchmarks: Vec<String>,
     // All of the known benchmark groups, stored in execution order (where possible).
     pub groups: LinkedHashMap<String, BenchmarkGroup>,
 
     history_id: Option<String>,
     history_description: Option<String>,
+
+    // Which stored measurement new measurements should be compared against.
+    compare_against: CompareAgainst,
+    // The measurements read from the baseline file, if new measurements are compared against one.
+    baseline_file: HashMap<BaselineKey, SavedStatistics>,
+    // If set, new measurements are also saved as the baseline with this name.
+    save_baseline: Option<String>,
+    // How new measurements are written to disk.
+    storage_format: StorageFormat,
+    // How much of each new measurement is written to disk.
+    sample_storage: SampleStorage,
+    // How the directories of benchmarks with no stored measurements are named.
+    dir_name_strategy: DirNameStrategy,
+    // If set, new measurements are only kept in memory, and the files they would have been
+    // written to are printed instead.
+    dry_run: bool,
+    // If set, stored measurements which don't record when they were taken are dated by the
+    // modification time of their files.
+    datetime_from_mtime: bool,
+    // If set, each new measurement is also inserted into this SQLite database.
+    #[cfg(feature = "sqlite")]
+    database: Option<Database>,
+    // The index of the stored benchmarks in the timeline, kept up to date as new measurements are
+    // saved. None if the model wasn't loaded from the whole timeline, or the index couldn't be
+    // built.
+    index: Option<HistoryIndex>,
+    // Whether the index has changed since it was last written. It's written when the model is
+    // dropped, rather than after every measurement.
+    index_changed: bool,
 }
 impl Model {
     /// Load the model from disk. The output directory is scanned for benchmark files. Any files
     /// found are loaded into the model so that we can include them in the reports even if this
     /// run doesn't execute that particular benchmark.
+    #[allow(clippy::too_many_arguments)]
     pub fn load(
-        criterion_home: PathBuf,
+        data_directory: PathBuf,
         timeline: PathBuf,
         history_id: Option<String>,
         history_description: Option<String>,
+        compare_against: CompareAgainst,
+        save_baseline: Option<String>,
+        storage_format: StorageFormat,
+        sample_storage: SampleStorage,
+        dir_name_strategy: DirNameStrategy,
+        dry_run: bool,
+        datetime_from_mtime: bool,
+    ) -> Model {
+        let mut model = Model::empty(
+            data_directory.join(timeline),
+            history_id,
+            history_description,
+            compare_against,
+            save_baseline,
+            storage_format,
+            sample_storage,
+            dir_name_strategy,
+            dry_run,
+            datetime_from_mtime,
+        );
+        let timeline_directory = model.data_directory.clone();
+        if model.datetime_from_mtime {
+            // The index records the dates stored in the measurements, not the adjusted ones.
+            model.load_stored_benchmarks(&timeline_directory, None);
+        } else if !model.load_index() {
+            model.index = Some(HistoryIndex::default());
+            model.load_stored_benchmarks(&timeline_directory, None);
+            if !model.dry_run {
+                model.save_index();
+            }
+        }
+        model.load_group_members();
+        model
+    }
+
+    /// Load only the stored benchmarks of one group from disk. Only the directories that the
+    /// group's benchmarks can be stored in are scanned, so this is much faster than `load` when
+    /// there are many groups. The model is meant for reporting; nothing is compared against it.
+    pub fn load_group(data_directory: PathBuf, timeline: PathBuf, group: &str) -> Model {
+        let mut model = Model::empty(
+            data_directory.join(timeline),
+            None,
+            None,
+            CompareAgainst::Nothing,
+            None,
+            StorageFormat::Files,
+            SampleStorage::Full,
+            DirNameStrategy::Readable,
+            false,
+            false,
+        );
+        for directory in group_directories(&model.data_directory, group) {
+            model.load_stored_benchmarks(&directory, Some(group));
+        }
+        model
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn empty(
+        data_directory: PathBuf,
+        history_id: Option<String>,
+        history_description: Option<String>,
+        compare_against: CompareAgainst,
+        save_baseline: Option<String>,
+        storage_format: StorageFormat,
+        sample_storage: SampleStorage,
+        dir_name_strategy: DirNameStrategy,
+        dry_run: bool,
+        datetime_from_mtime: bool,
     ) -> Model {
-        let mut model = Model {
-            data_directory: path!(criterion_home, "data", timeline),
+        Model {
+            data_directory,
             all_titles: HashSet::new(),
             all_directories: HashSet::new(),
+            tally: BenchmarkTally::default(),
+            rejected_new_benchmarks: Vec::new(),
+            regressed_benchmarks: Vec::new(),
+            over_budget_benchmarks: Vec::new(),
+            allowed_duplicate_ids: Vec::new(),
+            deny_duplicate_ids: false,
+            duplicate_ids: Vec::new(),
+            previous_group_members: HashMap::new(),
+            group_members: HashMap::new(),
+            deny_missing_benchmarks: false,
+            missing_benchmarks: Vec::new(),
             groups: LinkedHashMap::new(),
             history_id,
             history_description,
-        };
+            compare_against,
+            baseline_file: HashMap::new(),
+            save_baseline,
+            storage_format,
+            sample_storage,
+            dir_name_strategy,
+            dry_run,
+            datetime_from_mtime,
+            #[cfg(feature = "sqlite")]
+            database: None,
+            index: None,
+            index_changed: false,
+        }
+    }
 
-        for entry in WalkDir::new(&model.data_directory)
-            .into_iter()
-            // Ignore errors.
-            .filter_map(::std::result::Result::ok)
-            .filter(|entry| entry.file_name() == OsStr::new("benchmark.cbor"))
-        {
-            if let Err(e) = model.load_stored_benchmark(entry.path()) {
-                error!("Encountered error while loading stored data: {}", e)
-            }
+    /// Load the latest measurement of each benchmark, or of each benchmark in one group, from the
+    /// SQLite database at the given path instead of from the data directory. Only the estimates
+    /// are kept, so `Benchmark::load_latest` can't read the samples of the benchmarks loaded this
+    /// way; the model is meant for the commands which print the stored estimates.
+    #[cfg(feature = "sqlite")]
+    pub fn load_from_database(path: &Path, group: Option<&str>) -> Result<Model> {
+        if !path.is_file() {
+            return Err(anyhow!("No database was found at {:?}", path));
+        }
+        let database = Database::open(path)?;
+        let mut model = Model::empty(
+            PathBuf::new(),
+            None,
+            None,
+            CompareAgainst::Nothing,
+            None,
+            StorageFormat::Files,
+            SampleStorage::Full,
+            DirNameStrategy::Readable,
+            false,
+            false,
+        );
+        for (id, stats) in database.load_latest(group)? {
+            let location = MeasurementLocation {
+                file: PathBuf::new(),
+                offset: None,
+            };
+            model
+                .groups
+                .entry(id.group_id.clone())
+                .or_insert_with(Default::default)
+                .benchmarks
+                .insert(id, Benchmark::new(PathBuf::new(), location, stats));
         }
+        Ok(model)
+    }
 
-        model
+    #[cfg(not(feature = "sqlite"))]
+    pub fn load_from_database(_: &Path, _: Option<&str>) -> Result<Model> {
+        Err(anyhow!(SQLITE_DISABLED))
+    }
+
+    /// Insert each new measurement into the SQLite database at the given path, as well as saving
+    /// it in the data directory. The database is created if it doesn't exist.
+    #[cfg(feature = "sqlite")]
+    pub fn record_to_database(&mut self, path: &Path) -> Result<()> {
+        self.database = Some(Database::open(path)?);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn record_to_database(&mut self, _: &Path) -> Result<()> {
+        Err(anyhow!(SQLITE_DISABLED))
+    }
+
+    /// Load every stored benchmark below the given directory, or only those in the given group.
+    /// If the index is being built, the directories and benchmarks are added to it.
+    fn load_stored_benchmarks(&mut self, directory: &Path, group: Option<&str>) {
+        for entry in walk_dir(directory) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // There is no stored data yet.
+                Err(e)
+                    if e.io_error()
+                        .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+                {
+                    continue
+                }
+                // Directories which can't be read, eg. because too many files are open, would
+                // otherwise leave their benchmarks out of the model without a word.
+                Err(e) => {
+                    error!(
+                        target: "load-failure",
+                        "Encountered error while searching for stored data: {}",
+                        e
+                    );
+                    self.index = None;
+                    continue;
+                }
+            };
+            if entry.file_type().is_dir() {
+                // The time is recorded before the directory's contents are read, so that any
+                // change made while loading leaves the index out of date.
+                if let Some(index) = &mut self.index {
+                    let relative = entry.path().strip_prefix(&self.data_directory).ok();
+                    let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                    match (relative, modified) {
+                        (Some(relative), Some(modified)) => {
+                            index.directories.insert(relative.to_owned(), modified);
+                        }
+                        _ => self.index = None,
+                    }
+                }
+            } else if entry.file_name() == OsStr::new("benchmark.cbor") {
+                if let Err(e) = self.load_stored_benchmark(entry.path(), group) {
+                    error!(
+                        target: "load-failure",
+                        "Encountered error while loading stored data: {}",
+                        e
+                    );
+                    // Leave the benchmark to be reported again by the next load.
+                    self.index = None;
+                }
+            }
+        }
     }
 
-    fn load_stored_benchmark(&mut self, benchmark_path: &Path) -> Result<()> {
+    fn load_stored_benchmark(&mut self, benchmark_path: &Path, group: Option<&str>) -> Result<()> {
         if !benchmark_path.is_file() {
             return Ok(());
         }
-        let mut benchmark_file = File::open(&benchmark_path)
-            .with_context(|| format!("Failed to open benchmark file {:?}", benchmark_path))?;
-        let benchmark_record: BenchmarkRecord = serde_cbor::from_reader(&mut benchmark_file)
-            .with_context(|| format!("Failed to read benchmark file {:?}", benchmark_path))?;
+        let modified = match self.index {
+            Some(_) => Some(modified_time(benchmark_path)?),
+            None => None,
+        };
+        // The directory name may be a hash, so the ID always comes from the record rather than the
+        // path.
+        let benchmark_record = load_benchmark_record(benchmark_path)?;
+        // Different groups can share a directory if their names only differ in characters that
+        // can't be used in file names.
+        if matches!(group, Some(group) if group != benchmark_record.id.group_id) {
+            return Ok(());
+        }
+        let benchmark_dir = benchmark_path.parent().unwrap();
 
-        let measurement_path = benchmark_path.with_file_name(benchmark_record.latest_record);
-        if !measurement_path.is_file() {
+        let latest = benchmark_record.latest();
+        if !benchmark_dir.join(&latest.file).is_file() {
             return Ok(());
         }
-        let mut measurement_file = File::open(&measurement_path)
-            .with_context(|| format!("Failed to open measurement file {:?}", measurement_path))?;
-        let saved_stats: SavedStatistics = serde_cbor::from_reader(&mut measurement_file)
-            .with_context(|| format!("Failed to read measurement file {:?}", measurement_path))?;
+        let mut saved_stats = load_measurement_at(benchmark_dir, &latest)?;
+        if self.datetime_from_mtime {
+            use_mtime_if_undated(&mut saved_stats, &benchmark_dir.join(&latest.file));
+        }
 
+        let id: BenchmarkId = benchmark_record.id.into();
+        if let (Some(index), Some(modified)) = (&mut self.index, modified) {
+            index.benchmarks.insert(
+                benchmark_dir.strip_prefix(&self.data_directory)?.to_owned(),
+                IndexedBenchmark {
+                    modified,
+                    id: (&id).into(),
+                    latest: latest.clone(),
+                    stats: saved_stats.clone().without_samples(),
+                },
+            );
+        }
+        let benchmark = Benchmark::new(benchmark_dir.to_owned(), latest, saved_stats);
         self.groups
-            .entry(benchmark_record.id.group_id.clone())
+            .entry(id.group_id.clone())
             .or_insert_with(Default::default)
             .benchmarks
-            .insert(benchmark_record.id.into(), Benchmark::new(saved_stats));
+            .insert(id, benchmark);
         Ok(())
     }
 
+    /// Load the stored benchmarks from the index, if it's up to date. Returns false if the
+    /// directories have to be walked instead.
+    fn load_index(&mut self) -> bool {
+        let index_path = self.data_directory.join(HISTORY_INDEX);
+        let index: HistoryIndex = match File::open(&index_path)
+            .ok()
+            .and_then(|file| serde_cbor::from_reader(file).ok())
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        if !index.is_current(&self.data_directory) {
+            debug!("The index {:?} is out of date", index_path);
+            return false;
+        }
+        for (directory, benchmark) in &index.benchmarks {
+            let id: BenchmarkId = (&benchmark.id).into();
+            let loaded = Benchmark::new(
+                self.data_directory.join(directory),
+                benchmark.latest.clone(),
+                benchmark.stats.clone(),
+            );
+            self.groups
+                .entry(id.group_id.clone())
+                .or_insert_with(Default::default)
+                .benchmarks
+                .insert(id, loaded);
+        }
+        self.index = Some(index);
+        true
+    }
+
+    /// Write the index, if it's in use. Failing to write it only means that the next load has to
+    /// walk the directories, so it stops being used rather than failing the run.
+    fn save_index(&mut self) {
+        if let Some(index) = &mut self.index {
+            if let Err(e) = index.save(&self.data_directory) {
+                debug!("Failed to save the index of the stored data: {:#}", e);
+                self.index = None;
+            }
+        }
+    }
+
+    /// Read the benchmarks in each group as of its previous run. A missing or unreadable file only
+    /// means that no group is checked for missing benchmarks.
+    fn load_group_members(&mut self) {
+        let path = self.data_directory.join(GROUP_MEMBERS);
+        if let Some(members) = File::open(&path)
+            .ok()
+            .and_then(|file| serde_cbor::from_reader(file).ok())
+        {
+            self.previous_group_members = members;
+        }
+    }
+
+    fn save_group_members(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.data_directory)?;
+        let path = self.data_directory.join(GROUP_MEMBERS);
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create group file {:?}", path))?;
+        serde_cbor::to_writer(file, &self.previous_group_members)
+            .with_context(|| format!("Failed to write group file {:?}", path))
+    }
+
+    /// Select the stored measurement that new measurements of a benchmark should be compared
+    /// against, according to `compare_against`.
+    fn select_comparison_sample(
+        &self,
+        benchmark_dir: &Path,
+        benchmark_record: &BenchmarkRecord,
+    ) -> Result<Option<SavedStatistics>> {
+        let stats = match &self.compare_against {
+            // The baseline file is read instead of the stored measurements.
+            CompareAgainst::Nothing | CompareAgainst::File(_) => None,
+            CompareAgainst::Previous => {
+                let latest = benchmark_record.latest();
+                if benchmark_dir.join(&latest.file).is_file() {
+                    Some(load_measurement_at(benchmark_dir, &latest)?)
+                } else {
+                    None
+                }
+            }
+            CompareAgainst::Baseline(name) => match benchmark_record.baselines.get(name) {
+                Some(location) => Some(load_measurement_at(benchmark_dir, location)?),
+                None => None,
+            },
+            CompareAgainst::Datetime(datetime) => self
+                .load_history_from(benchmark_dir)
+                .into_iter()
+                .rev()
+                .find(|stats| stats.datetime <= *datetime),
+            CompareAgainst::Commit(commit) => self
+                .load_history_from(benchmark_dir)
+                .into_iter()
+                .rev()
+                .find(|stats| matches!(&stats.history_id, Some(id) if id.starts_with(commit.as_str()))),
+            CompareAgainst::Rolling(count) => {
+                // Measurements stored without their raw samples can't be pooled.
+                let mut history = self.load_history_from(benchmark_dir);
+                history.retain(SavedStatistics::has_samples);
+                pool_statistics(&history[history.len().saturating_sub(*count)..])
+            }
+        };
+        Ok(stats)
+    }
+
     pub fn add_benchmark_id(&mut self, target: &str, id: &mut BenchmarkId) {
         id.ensure_directory_name_unique(&self.all_directories);
         self.all_directories
@@ -134,14 +612,200 @@ impl Model {
             .or_insert_with(Default::default);
 
         if let Some(mut benchmark) = group.benchmarks.remove(id) {
-            if let Some(target) = &benchmark.target {
-                warn!("Benchmark ID {} encountered multiple times. Benchmark IDs must be unique. First seen in the benchmark target '{}'", id.as_title(), target);
-            } else {
+            let first_target = benchmark.target.clone();
+            if first_target.is_none() {
                 benchmark.target = Some(target.to_owned());
             }
 
             // Remove and re-insert to move the benchmark to the end of its list.
             group.benchmarks.insert(id.clone(), benchmark);
+
+            if let Some(first_target) = first_target {
+                if self.record_duplicate_id(id.as_title()) {
+                    warn!(target: "duplicate-benchmark-id", "Benchmark ID {} encountered multiple times. Benchmark IDs must be unique. First seen in the benchmark target '{}'", id.as_title(), first_target);
+                }
+            }
+        }
+    }
+
+    /// Allow the benchmark and group IDs matching the given patterns to be encountered more than
+    /// once without a warning. With `deny`, every duplicate ID is recorded to fail the run, even
+    /// one which matches a pattern.
+    pub fn allow_duplicate_ids(&mut self, patterns: Vec<String>, deny: bool) {
+        self.allowed_duplicate_ids = patterns;
+        self.deny_duplicate_ids = deny;
+    }
+
+    /// Record that the given benchmark or group ID was encountered again. Returns true if the
+    /// duplicate should be warned about.
+    fn record_duplicate_id(&mut self, id: &str) -> bool {
+        if self.deny_duplicate_ids {
+            if !self.duplicate_ids.iter().any(|other| other == id) {
+                self.duplicate_ids.push(id.to_owned());
+            }
+            return true;
+        }
+        let allowed =
+            (self.allowed_duplicate_ids.iter()).any(|pattern| pattern_matches(pattern, id));
+        if allowed {
+            debug!(
+                "ID {} encountered again, as allowed by allow_duplicate_ids",
+                id
+            );
+        }
+        !allowed
+    }
+
+    /// The benchmark and group IDs which were encountered more than once with
+    /// `--deny-duplicate-ids`.
+    pub fn duplicate_ids(&self) -> &[String] {
+        &self.duplicate_ids
+    }
+
+    /// Forget which benchmarks have been run so far, so that running them all again with
+    /// `--repeat` doesn't treat them as duplicates.
+    pub fn begin_repeat(&mut self) {
+        self.all_titles.clear();
+        self.all_directories.clear();
+        self.tally = BenchmarkTally::default();
+        self.rejected_new_benchmarks.clear();
+        self.group_members.clear();
+        for (_, group) in self.groups.iter_mut() {
+            for (_, benchmark) in group.benchmarks.iter_mut() {
+                benchmark.target = None;
+            }
+        }
+    }
+
+    /// Record that a benchmark target ran the given benchmark, or skipped it because it didn't
+    /// match the filter.
+    pub fn record_benchmark(&mut self, id: &BenchmarkId, ran: bool) {
+        self.tally.record(id.as_title(), ran);
+        let members = (self.group_members).entry(id.group_id.clone()).or_default();
+        if !members.iter().any(|title| title == id.as_title()) {
+            members.push(id.as_title().to_owned());
+        }
+    }
+
+    /// With `deny`, a group which has fewer benchmarks than in its previous run fails the run.
+    pub fn deny_missing_benchmarks(&mut self, deny: bool) {
+        self.deny_missing_benchmarks = deny;
+    }
+
+    /// Compare the benchmarks run or skipped in the given group with the ones in its previous run,
+    /// and record them for the next run to compare with. Returns the number of benchmarks in the
+    /// previous run and in this one, and the titles of the ones missing from this run, if the
+    /// group has fewer benchmarks than before.
+    ///
+    /// With `--no-missing-benchmarks`, a group which lost benchmarks keeps the previous ones on
+    /// record, so that every run fails until the option is left out once.
+    pub fn check_group_members(&mut self, group: &str) -> Option<(usize, usize, Vec<String>)> {
+        // If no benchmark in the group was reported at all, there's nothing to compare.
+        let members = self.group_members.get(group).cloned()?;
+        let shrunk = (self.previous_group_members.get(group))
+            .filter(|previous| previous.len() > members.len())
+            .map(|previous| {
+                let missing: Vec<String> = (previous.iter())
+                    .filter(|title| !members.contains(title))
+                    .cloned()
+                    .collect();
+                (previous.len(), members.len(), missing)
+            });
+        if let Some((_, _, missing)) = &shrunk {
+            for title in missing {
+                if !self.missing_benchmarks.contains(title) {
+                    self.missing_benchmarks.push(title.clone());
+                }
+            }
+            if self.deny_missing_benchmarks {
+                return shrunk;
+            }
+        }
+        if self.dry_run {
+            return shrunk;
+        }
+        if self.previous_group_members.get(group) != Some(&members) {
+            self.previous_group_members
+                .insert(group.to_owned(), members);
+            if let Err(e) = self.save_group_members() {
+                warn!(
+                    "Failed to record the benchmarks in group {}: {:#}",
+                    group, e
+                );
+            }
+        }
+        shrunk
+    }
+
+    /// The titles of the benchmarks found missing by `check_group_members` so far.
+    pub fn missing_benchmarks(&self) -> &[String] {
+        &self.missing_benchmarks
+    }
+
+    /// Record that the given benchmark's measurement was thrown away because it has no stored
+    /// measurement and `--no-new-benchmarks` was given.
+    pub fn reject_new_benchmark(&mut self, id: &BenchmarkId) {
+        self.rejected_new_benchmarks.push(id.as_title().to_owned());
+    }
+
+    /// The titles of the benchmarks rejected by `reject_new_benchmark` so far.
+    pub fn rejected_new_benchmarks(&self) -> &[String] {
+        &self.rejected_new_benchmarks
+    }
+
+    /// Record that the performance of the given benchmark has regressed.
+    pub fn record_regression(&mut self, id: &BenchmarkId) {
+        let title = id.as_title();
+        if !self.regressed_benchmarks.iter().any(|other| other == title) {
+            self.regressed_benchmarks.push(title.to_owned());
+        }
+    }
+
+    /// The titles of the benchmarks recorded by `record_regression` so far.
+    pub fn regressed_benchmarks(&self) -> &[String] {
+        &self.regressed_benchmarks
+    }
+
+    /// Record that the new measurement of the given benchmark was over its budget.
+    pub fn record_over_budget(&mut self, id: &BenchmarkId) {
+        let title = id.as_title();
+        if !self
+            .over_budget_benchmarks
+            .iter()
+            .any(|other| other == title)
+        {
+            self.over_budget_benchmarks.push(title.to_owned());
+        }
+    }
+
+    /// The titles of the benchmarks recorded by `record_over_budget` so far.
+    pub fn over_budget_benchmarks(&self) -> &[String] {
+        &self.over_budget_benchmarks
+    }
+
+    /// How many benchmarks have been run or filtered out so far.
+    pub fn run_counts(&self) -> RunCounts {
+        self.tally.counts()
+    }
+
+    /// The directory that the given benchmark's measurements are stored in. A benchmark which was
+    /// loaded from disk keeps its directory, whichever way it was named; otherwise the directory
+    /// is named by `dir_name_strategy`.
+    pub fn benchmark_directory(&self, id: &BenchmarkId) -> PathBuf {
+        let loaded = self
+            .groups
+            .get(&id.group_id)
+            .and_then(|group| group.benchmarks.get(id));
+        if let Some(benchmark) = loaded {
+            return benchmark.directory.clone();
+        }
+        match self.dir_name_strategy {
+            DirNameStrategy::Readable => path!(&self.data_directory, id.as_directory_name()),
+            DirNameStrategy::Hashed => path!(
+                &self.data_directory,
+                make_filename_safe(&id.group_id),
+                hashed_directory_name(id)
+            ),
         }
     }
 
@@ -149,17 +813,16 @@ impl Model {
         &mut self,
         id: &BenchmarkId,
         analysis_results: &MeasurementData,
+        features: &[String],
+        target: &str,
+        target_fingerprint: Option<&str>,
     ) -> Result<()> {
-        let dir = path!(&self.data_directory, id.as_directory_name());
-
-        std::fs::create_dir_all(&dir)
-            .with_context(|| format!("Failed to create directory {:?}", dir))?;
-
+        let dir = self.benchmark_directory(id);
         let measurement_name = chrono::Local::now()
             .format("measurement_%y%m%d%H%M%S.cbor")
             .to_string();
 
-        let saved_stats = SavedStatistics {
+        let mut saved_stats = SavedStatistics {
             datetime: chrono::Utc::now(),
             iterations: analysis_results.iter_counts().to_vec(),
             values: analysis_results.sample_times().to_vec(),
@@ -174,28 +837,143 @@ impl Model {
                 .comparison
                 .as_ref()
                 .map(get_change_direction),
+            p_value: analysis_results.comparison.as_ref().map(|c| c.p_value),
             history_id: self.history_id.clone(),
             history_description: self.history_description.clone(),
+            features: features.to_vec(),
+            peak_rss: analysis_results.peak_rss,
+            instructions_per_iteration: analysis_results.instructions_per_iteration,
+            allocations: analysis_results.allocations,
+            cpu_affinity: analysis_results.cpu_affinity.clone(),
+            criterion_version: analysis_results.criterion_version.clone(),
+            bench_version: analysis_results.bench_version.clone(),
+            bench_seed: analysis_results.bench_seed,
+            single_thread: analysis_results.single_thread,
+            min_samples_enforced: analysis_results.min_samples_enforced,
+            profile: analysis_results.profile.clone(),
+            async_runtime: analysis_results.async_runtime.clone(),
+            target: Some(target.to_owned()),
+            target_fingerprint: target_fingerprint.map(ToOwned::to_owned),
+            compile_time: analysis_results.compile_time,
+            metadata: analysis_results.metadata.clone(),
+            sub_measurements: BTreeMap::new(),
+        };
+        // The parts are saved under the benchmark, with its details but their own samples and
+        // estimates.
+        saved_stats.sub_measurements = (analysis_results.sub_measurements.iter())
+            .map(|(name, sub)| {
+                let sub_stats = SavedStatistics {
+                    iterations: sub.iter_counts().to_vec(),
+                    values: sub.sample_times().to_vec(),
+                    avg_values: sub.avg_times.to_vec(),
+                    estimates: sub.absolute_estimates.clone(),
+                    throughput: None,
+                    changes: (sub.comparison.as_ref()).map(|c| c.relative_estimates.clone()),
+                    change_direction: sub.comparison.as_ref().map(get_change_direction),
+                    p_value: sub.comparison.as_ref().map(|c| c.p_value),
+                    peak_rss: None,
+                    instructions_per_iteration: None,
+                    allocations: None,
+                    metadata: HashMap::new(),
+                    ..saved_stats.clone()
+                };
+                (name.clone(), sub_stats)
+            })
+            .collect();
+        if let SampleStorage::Summary = self.sample_storage {
+            saved_stats = saved_stats.without_samples();
+        }
+
+        if self.dry_run {
+            let file = match self.storage_format {
+                StorageFormat::Files => PathBuf::from(&measurement_name),
+                StorageFormat::Log => PathBuf::from(MEASUREMENT_LOG),
+            };
+            warn!(
+                target: "dry-run",
+                "Dry run: would save the measurement of {} to {:?}",
+                id.as_title(),
+                dir.join(&file)
+            );
+            #[cfg(feature = "sqlite")]
+            {
+                if let Some(database) = &self.database {
+                    warn!(
+                        target: "dry-run",
+                        "Dry run: would also insert it into {:?}",
+                        database.path()
+                    );
+                }
+            }
+            let location = MeasurementLocation { file, offset: None };
+            self.add_measurement(id, dir, location, saved_stats);
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory {:?}", dir))?;
+        let location = match self.storage_format {
+            StorageFormat::Files => {
+                save_measurement(&dir.join(&measurement_name), &saved_stats)?;
+                MeasurementLocation {
+                    file: PathBuf::from(&measurement_name),
+                    offset: None,
+                }
+            }
+            StorageFormat::Log => {
+                let offset = measurement_log::append(&dir.join(MEASUREMENT_LOG), &saved_stats)?;
+                MeasurementLocation {
+                    file: PathBuf::from(MEASUREMENT_LOG),
+                    offset: Some(offset),
+                }
+            }
         };
 
-        let measurement_path = dir.join(&measurement_name);
-        let mut measurement_file = File::create(&measurement_path)
-            .with_context(|| format!("Failed to create measurement file {:?}", measurement_path))?;
-        serde_cbor::to_writer(&mut measurement_file, &saved_stats).with_context(|| {
-            format!("Failed to save measurements to file {:?}", measurement_path)
-        })?;
+        let benchmark_path = dir.join("benchmark.cbor");
+
+        // Keep any named baselines recorded by earlier runs.
+        let mut baselines = File::open(&benchmark_path)
+            .ok()
+            .and_then(|mut file| serde_cbor::from_reader::<BenchmarkRecord, _>(&mut file).ok())
+            .map(|record| record.baselines)
+            .unwrap_or_default();
+        if let Some(name) = &self.save_baseline {
+            baselines.insert(name.clone(), location.clone());
+        }
 
         let record = BenchmarkRecord {
             id: id.into(),
-            latest_record: PathBuf::from(&measurement_name),
+            latest_record: location.file.clone(),
+            latest_offset: location.offset,
+            baselines,
         };
 
-        let benchmark_path = dir.join("benchmark.cbor");
-        let mut benchmark_file = File::create(&benchmark_path)
-            .with_context(|| format!("Failed to create benchmark file {:?}", benchmark_path))?;
-        serde_cbor::to_writer(&mut benchmark_file, &record)
-            .with_context(|| format!("Failed to save benchmark file {:?}", benchmark_path))?;
+        save_benchmark_record(&benchmark_path, &record)?;
+        if let Some(index) = &mut self.index {
+            if let Err(e) = index.update(&self.data_directory, &dir, id, &location, &saved_stats) {
+                debug!("Failed to update the index of the stored data: {:#}", e);
+                self.index = None;
+            }
+            self.index_changed = true;
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            if let Some(database) = &self.database {
+                database.insert(id, &saved_stats)?;
+            }
+        }
+        self.add_measurement(id, dir, location, saved_stats);
+        Ok(())
+    }
 
+    /// Make the given measurement the latest one of its benchmark in memory.
+    fn add_measurement(
+        &mut self,
+        id: &BenchmarkId,
+        dir: PathBuf,
+        location: MeasurementLocation,
+        saved_stats: SavedStatistics,
+    ) {
         let benchmark_entry = self
             .groups
             .get_mut(&id.group_id)
@@ -205,28 +983,179 @@ impl Model {
 
         match benchmark_entry {
             vacant @ linked_hash_map::Entry::Vacant(_) => {
-                vacant.or_insert(Benchmark::new(saved_stats));
+                vacant.or_insert(Benchmark::new(dir, location, saved_stats));
             }
             linked_hash_map::Entry::Occupied(mut occupied) => {
-                occupied.get_mut().add_stats(saved_stats)
+                occupied.get_mut().add_stats(location, saved_stats)
             }
         };
-        Ok(())
     }
 
+    /// Returns the benchmark groups and the IDs of the benchmarks in each, in the given order.
+    /// Groups are ordered by their slowest benchmark or biggest regression when sorting by time or
+    /// change. The sort is stable, so ties keep the order in which the benchmarks were run.
+    pub fn sorted_benchmarks(&self, order: SortOrder) -> Vec<(&str, Vec<&BenchmarkId>)> {
+        let mut groups: Vec<(&str, Vec<&BenchmarkId>)> = self
+            .groups
+            .iter()
+            .map(|(group_id, group)| {
+                let mut ids: Vec<&BenchmarkId> = group.benchmarks.keys().collect();
+                match order {
+                    SortOrder::Insertion => {}
+                    SortOrder::Name => ids.sort_by(|a, b| a.as_title().cmp(b.as_title())),
+                    SortOrder::Time | SortOrder::Change => ids.sort_by(|a, b| {
+                        let key = |id| sort_key(order, &group.benchmarks[id]);
+                        key(*b).partial_cmp(&key(*a)).unwrap_or(Ordering::Equal)
+                    }),
+                }
+                (group_id.as_str(), ids)
+            })
+            .collect();
+
+        match order {
+            SortOrder::Insertion => {}
+            SortOrder::Name => groups.sort_by(|a, b| a.0.cmp(b.0)),
+            SortOrder::Time | SortOrder::Change => {
+                // The benchmarks are already sorted, so the first one has the largest key.
+                let key = |(group_id, ids): &(&str, Vec<&BenchmarkId>)| {
+                    ids.first()
+                        .map(|id| sort_key(order, &self.groups[*group_id].benchmarks[*id]))
+                        .unwrap_or(f64::NEG_INFINITY)
+                };
+                groups.sort_by(|a, b| key(b).partial_cmp(&key(a)).unwrap_or(Ordering::Equal));
+            }
+        }
+        groups
+    }
+
+    /// Describes what the comparison samples are, if they are something other than a single
+    /// stored measurement.
+    pub fn comparison_label(&self) -> Option<String> {
+        match self.compare_against {
+            CompareAgainst::Rolling(count) => Some(format!("vs rolling avg of {}", count)),
+            _ => None,
+        }
+    }
+
+    /// Move the output written by a profiler hook while running with `--profile-time` from the
+    /// given directory into the data directory of each benchmark, next to its measurements. Each
+    /// file is assumed to belong to the benchmark named by the directory that it's in. Returns the
+    /// directories that the profiles were moved to.
+    pub fn save_profiles(&self, profile_directory: &Path) -> Result<Vec<PathBuf>> {
+        let profile_name = chrono::Local::now().format("%y%m%d%H%M%S").to_string();
+
+        let mut saved = HashSet::new();
+        for entry in walk_dir(profile_directory)
+            .into_iter()
+            // Ignore errors.
+            .filter_map(::std::result::Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let benchmark_dir = entry
+                .path()
+                .parent()
+                .and_then(|dir| dir.strip_prefix(profile_directory).ok())
+                .filter(|dir| dir.components().next().is_some());
+            let benchmark_dir = match benchmark_dir {
+                Some(dir) => dir,
+                None => continue,
+            };
+
+            let destination = path!(
+                &self.data_directory,
+                benchmark_dir,
+                "profile",
+                &profile_name
+            );
+            std::fs::create_dir_all(&destination)
+                .with_context(|| format!("Failed to create directory {:?}", destination))?;
+            let file_destination = destination.join(entry.file_name());
+            std::fs::rename(entry.path(), &file_destination).with_context(|| {
+                format!(
+                    "Failed to move profile {:?} to {:?}",
+                    entry.path(),
+                    file_destination
+                )
+            })?;
+            saved.insert(destination);
+        }
+
+        let mut saved: Vec<PathBuf> = saved.into_iter().collect();
+        saved.sort();
+        Ok(saved)
+    }
+
+    /// Returns the latest stored measurement of the given benchmark, without its raw samples.
     pub fn get_last_sample(&self, id: &BenchmarkId) -> Option<&SavedStatistics> {
-        self.groups
-            .get(&id.group_id)
-            .and_then(|g| g.benchmarks.get(id))
-            .map(|b| &b.latest_stats)
+        let group = self.groups.get(&id.group_id)?;
+        group
+            .benchmarks
+            .get(id)
+            .map(|benchmark| &benchmark.latest_stats)
     }
 
-    pub fn check_benchmark_group(&self, current_target: &str, group: &str) {
-        if let Some(benchmark_group) = self.groups.get(group) {
-            if let Some(target) = &benchmark_group.target {
-                if target != current_target {
-                    warn!("Benchmark group {} encountered again. Benchmark group IDs must be unique. First seen in the benchmark target '{}'", group, target);
+    /// Returns the stored statistics that a new measurement of the given benchmark should be
+    /// compared against, if any. These are loaded from disk each time they're requested.
+    pub fn get_comparison_sample(&self, id: &BenchmarkId) -> Option<SavedStatistics> {
+        match &self.compare_against {
+            CompareAgainst::Nothing => return None,
+            CompareAgainst::File(path) => {
+                let key = (
+                    id.group_id.clone(),
+                    id.function_id.clone(),
+                    id.value_str.clone(),
+                );
+                let stats = self.baseline_file.get(&key).cloned();
+                if stats.is_none() {
+                    warn!(
+                        target: "missing-comparison",
+                        "Benchmark {} is not in the baseline file {:?}; it will not be compared.",
+                        id.as_title(),
+                        path
+                    );
                 }
+                return stats;
+            }
+            _ => {}
+        }
+        let benchmark_dir = self.benchmark_directory(id);
+        let benchmark_path = benchmark_dir.join("benchmark.cbor");
+        if !benchmark_path.is_file() {
+            return None;
+        }
+
+        let stats = load_benchmark_record(&benchmark_path)
+            .and_then(|record| self.select_comparison_sample(&benchmark_dir, &record));
+        match stats {
+            Ok(Some(stats)) => Some(stats),
+            Ok(None) => {
+                warn!(
+                    target: "missing-comparison",
+                    "No stored measurement of benchmark {} matches {}; it will not be compared.",
+                    id.as_title(),
+                    self.compare_against
+                );
+                None
+            }
+            Err(e) => {
+                error!(
+                    target: "load-failure",
+                    "Failed to load the stored measurements of benchmark {}: {:?}",
+                    id.as_title(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn check_benchmark_group(&mut self, current_target: &str, group: &str) {
+        let first_target = (self.groups.get(group))
+            .and_then(|benchmark_group| benchmark_group.target.clone())
+            .filter(|target| target != current_target);
+        if let Some(target) = first_target {
+            if self.record_duplicate_id(group) {
+                warn!(target: "duplicate-benchmark-group", "Benchmark group {} encountered again. Benchmark group IDs must be unique. First seen in the benchmark target '{}'", group, target);
             }
         }
     }
@@ -239,54 +1168,888 @@ impl Model {
         self.groups.get(group_name).unwrap()
     }
 
+    /// Returns true if the latest stored measurement taken by the given benchmark target was taken
+    /// with an executable which had the given fingerprint.
+    pub fn is_target_unchanged(&self, target: &str, fingerprint: &str) -> bool {
+        self.groups
+            .values()
+            .flat_map(|group| group.benchmarks.values())
+            .map(|benchmark| &benchmark.latest_stats)
+            .filter(|stats| stats.target.as_deref() == Some(target))
+            .max_by_key(|stats| stats.datetime)
+            .and_then(|stats| stats.target_fingerprint.as_deref())
+            == Some(fingerprint)
+    }
+
+    /// Read the measurements to compare against from the baseline file given with
+    /// `--baseline-file`.
+    pub fn load_baseline_file(&mut self, path: &Path) -> Result<()> {
+        self.baseline_file = baseline_file::load(path)?;
+        Ok(())
+    }
+
     pub fn load_history(&self, id: &BenchmarkId) -> Result<Vec<SavedStatistics>> {
-        let dir = path!(&self.data_directory, id.as_directory_name());
+        let dir = self.benchmark_directory(id);
+        Ok(self.load_history_from(&dir))
+    }
 
-        fn load_from(measurement_path: &Path) -> Result<SavedStatistics> {
-            let mut measurement_file = File::open(&measurement_path).with_context(|| {
-                format!("Failed to open measurement file {:?}", measurement_path)
-            })?;
-            serde_cbor::from_reader(&mut measurement_file)
-                .with_context(|| format!("Failed to read measurement file {:?}", measurement_path))
-        }
+    fn load_history_from(&self, dir: &Path) -> Vec<SavedStatistics> {
+        load_history_from(dir, self.datetime_from_mtime)
+    }
 
-        let mut stats = Vec::new();
-        for entry in WalkDir::new(dir)
-            .max_depth(1)
-            .into_iter()
-            // Ignore errors.
-            .filter_map(::std::result::Result::ok)
-        {
-            let name_str = entry.file_name().to_string_lossy();
-            if name_str.starts_with("measurement_") && name_str.ends_with(".cbor") {
-                match load_from(entry.path()) {
-                    Ok(saved_stats) => stats.push(saved_stats),
-                    Err(e) => error!(
-                        "Unexpected error loading benchmark history from file {}: {:?}",
-                        entry.path().display(),
-                        e
-                    ),
-                }
-            }
+    /// Save the latest stored measurement of the given benchmark as the baseline with the given
+    /// name, replacing any earlier baseline with that name. Nothing is measured or analyzed.
+    pub fn save_latest_as_baseline(&self, id: &BenchmarkId, name: &str) -> Result<()> {
+        let benchmark_path = self.benchmark_directory(id).join("benchmark.cbor");
+        let mut record = load_benchmark_record(&benchmark_path)?;
+        if self.dry_run {
+            warn!(
+                target: "dry-run",
+                "Dry run: would save the latest measurement of {} as baseline {}",
+                id.as_title(),
+                name
+            );
+            return Ok(());
+        }
+        record.baselines.insert(name.to_owned(), record.latest());
+        save_benchmark_record(&benchmark_path, &record)
+    }
+}
+impl Drop for Model {
+    fn drop(&mut self) {
+        if self.index_changed {
+            self.save_index();
         }
+    }
+}
 
-        stats.sort_unstable_by_key(|st| st.datetime);
+/// The value to sort benchmarks by, largest first, when sorting by time or change.
+fn sort_key(order: SortOrder, benchmark: &Benchmark) -> f64 {
+    let stats = &benchmark.latest_stats;
+    match order {
+        SortOrder::Change => stats
+            .changes
+            .as_ref()
+            .map(|changes| changes.mean.point_estimate)
+            .unwrap_or(f64::NEG_INFINITY),
+        _ => stats.estimates.typical().point_estimate,
+    }
+}
 
-        Ok(stats)
+/// The name of the directory that a benchmark is stored in with `DirNameStrategy::Hashed`: a 64-bit
+/// FNV-1a hash of its group, function and value, in hex. This must never change, or the benchmarks
+/// stored by earlier versions would no longer be found.
+fn hashed_directory_name(id: &BenchmarkId) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let parts = [
+        Some(id.group_id.as_str()),
+        id.function_id.as_deref(),
+        id.value_str.as_deref(),
+    ];
+    for part in &parts {
+        // Separate the parts, and tell a missing part from an empty one.
+        let bytes = match part {
+            Some(part) => part.as_bytes(),
+            None => &[0xff],
+        };
+        for &byte in bytes.iter().chain(&[0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
     }
+    format!("{:016x}", hash)
 }
 
-// These structs are saved to disk and may be read by future versions of cargo-criterion, so
-// backwards compatibility is important.
+/// The directories in the timeline directory that the benchmarks of the given group can be stored
+/// in. These are named after the group, with a numeric suffix if another group's name was turned
+/// into the same directory name.
+fn group_directories(timeline_directory: &Path, group: &str) -> Vec<PathBuf> {
+    let name = make_filename_safe(group);
+    let entries = match std::fs::read_dir(timeline_directory) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(::std::result::Result::ok)
+        .filter(|entry| match entry.file_name().to_str() {
+            Some(file_name) => is_group_directory_name(file_name, &name),
+            None => false,
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct SavedBenchmarkId {
-    group_id: String,
-    function_id: Option<String>,
-    value_str: Option<String>,
-    throughput: Option<Throughput>,
+fn is_group_directory_name(file_name: &str, name: &str) -> bool {
+    if !file_name.starts_with(name) {
+        return false;
+    }
+    let suffix = &file_name[name.len()..];
+    suffix.is_empty() || (suffix.starts_with('_') && suffix[1..].parse::<u32>().is_ok())
 }
-impl From<BenchmarkId> for SavedBenchmarkId {
+
+/// Load a benchmark.cbor file.
+fn load_benchmark_record(benchmark_path: &Path) -> Result<BenchmarkRecord> {
+    let mut benchmark_file = File::open(benchmark_path)
+        .with_context(|| format!("Failed to open benchmark file {:?}", benchmark_path))?;
+    serde_cbor::from_reader(&mut benchmark_file)
+        .with_context(|| format!("Failed to read benchmark file {:?}", benchmark_path))
+}
+
+fn is_measurement_file(path: &Path) -> bool {
+    let name_str = path.file_name().unwrap_or_default().to_string_lossy();
+    name_str.starts_with("measurement_") && name_str.ends_with(".cbor")
+}
+
+/// A problem found in the stored data by `check_stored_data`.
+#[derive(Debug)]
+pub enum DataProblem {
+    /// A benchmark.cbor file could not be read.
+    UnreadableRecord(anyhow::Error),
+    /// A benchmark.cbor file refers to a measurement file which does not exist.
+    MissingMeasurement {
+        benchmark_path: PathBuf,
+        measurement_path: PathBuf,
+    },
+    /// A measurement file could not be read.
+    UnreadableMeasurement(anyhow::Error),
+    /// A measurement file is stored in a directory without a benchmark.cbor file, so it will never
+    /// be loaded.
+    Orphan(PathBuf),
+}
+impl DataProblem {
+    /// Does this problem mean that stored data has been lost or damaged?
+    pub fn is_corruption(&self) -> bool {
+        !matches!(self, DataProblem::Orphan(_))
+    }
+}
+impl std::fmt::Display for DataProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataProblem::UnreadableRecord(e) | DataProblem::UnreadableMeasurement(e) => {
+                write!(f, "{:#}", e)
+            }
+            DataProblem::MissingMeasurement {
+                benchmark_path,
+                measurement_path,
+            } => write!(
+                f,
+                "Benchmark file {:?} refers to missing measurement file {:?}",
+                benchmark_path, measurement_path
+            ),
+            DataProblem::Orphan(path) => write!(
+                f,
+                "Measurement file {:?} does not belong to any benchmark",
+                path
+            ),
+        }
+    }
+}
+
+/// The result of checking the stored data with `check_stored_data`.
+#[derive(Debug, Default)]
+pub struct DataHealth {
+    pub benchmark_count: usize,
+    pub measurement_count: usize,
+    pub problems: Vec<DataProblem>,
+}
+
+/// Check every benchmark and measurement file stored in any timeline under the given data
+/// directory. This performs the same loading as `Model::load`, but collects the problems it finds
+/// instead of skipping over them.
+pub fn check_stored_data(data_directory: &Path) -> DataHealth {
+    let mut health = DataHealth::default();
+
+    for entry in walk_dir(data_directory)
+        .into_iter()
+        // Ignore errors.
+        .filter_map(::std::result::Result::ok)
+    {
+        let path = entry.path();
+        if entry.file_name() == OsStr::new("benchmark.cbor") {
+            health.benchmark_count += 1;
+            let record = match load_benchmark_record(path) {
+                Ok(record) => record,
+                Err(e) => {
+                    health.problems.push(DataProblem::UnreadableRecord(e));
+                    continue;
+                }
+            };
+            let benchmark_dir = path.parent().unwrap();
+            for location in std::iter::once(&record.latest()).chain(record.baselines.values()) {
+                let measurement_path = benchmark_dir.join(&location.file);
+                let exists = match location.offset {
+                    Some(_) => load_measurement_at(benchmark_dir, location).is_ok(),
+                    None => measurement_path.is_file(),
+                };
+                if !exists {
+                    health.problems.push(DataProblem::MissingMeasurement {
+                        benchmark_path: path.to_owned(),
+                        measurement_path,
+                    });
+                }
+            }
+        } else if entry.file_name() == OsStr::new(MEASUREMENT_LOG) {
+            for record in measurement_log::read_all::<SavedStatistics>(path) {
+                match record {
+                    Ok(_) => health.measurement_count += 1,
+                    Err(e) => health.problems.push(DataProblem::UnreadableMeasurement(e)),
+                }
+            }
+            if !path.with_file_name("benchmark.cbor").is_file() {
+                health.problems.push(DataProblem::Orphan(path.to_owned()));
+            }
+        } else if is_measurement_file(path) {
+            health.measurement_count += 1;
+            if let Err(e) = load_measurement(path) {
+                health.problems.push(DataProblem::UnreadableMeasurement(e));
+            }
+            if !path.with_file_name("benchmark.cbor").is_file() {
+                health.problems.push(DataProblem::Orphan(path.to_owned()));
+            }
+        }
+    }
+    health
+}
+
+/// A lock on the stored data in a data directory, released when dropped. Benchmark runs and the
+/// commands which change the stored data hold it, so that they don't change the data at the same
+/// time as each other.
+pub struct DataLock {
+    path: PathBuf,
+}
+impl DataLock {
+    fn lock_path(data_directory: &Path) -> PathBuf {
+        data_directory.join(".lock")
+    }
+
+    /// Take the lock, failing if it's held by a process which is still running. The lock file
+    /// records the ID of the process holding it, so a lock left behind by an interrupted process
+    /// is taken over.
+    pub fn acquire(data_directory: &Path) -> Result<DataLock> {
+        let path = DataLock::lock_path(data_directory);
+        std::fs::create_dir_all(path.parent().unwrap())
+            .with_context(|| format!("Failed to create directory {:?}", path.parent()))?;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    write!(file, "{}", std::process::id())
+                        .with_context(|| format!("Failed to write lock file {:?}", path))?;
+                    return Ok(DataLock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create lock file {:?}", path))
+                }
+            }
+            let holder = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| contents.trim().parse::<u32>().ok());
+            if let Some(pid) = holder {
+                if process_is_running(pid) {
+                    return Err(anyhow!(
+                        "The stored data is locked by process {}, which is still running. If that \
+                         isn't cargo-criterion, the lock was left behind by an interrupted run and \
+                         {:?} can be deleted.",
+                        pid,
+                        path
+                    ));
+                }
+            }
+            info!(
+                "Taking over the lock {:?} left behind by a process which is no longer running",
+                path
+            );
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                // Another process took over the lock first; try again to see who holds it now.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove lock file {:?}", path))
+                }
+            }
+        }
+    }
+}
+impl Drop for DataLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Whether the process with the given ID is still running. If that can't be told, it's assumed to
+/// be running, so that a lock is never taken from a live process.
+fn process_is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else if cfg!(unix) {
+        std::process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(true)
+    } else if cfg!(windows) {
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .map(|output| {
+                let pid = pid.to_string();
+                String::from_utf8_lossy(&output.stdout)
+                    .split_whitespace()
+                    .any(|word| word == pid)
+            })
+            .unwrap_or(true)
+    } else {
+        true
+    }
+}
+
+/// The result of converting the stored data with `migrate_stored_data`.
+#[derive(Debug, Default)]
+pub struct MigrationSummary {
+    /// The number of benchmarks whose measurements were rewritten.
+    pub migrated_benchmarks: usize,
+    /// The number of benchmarks which were already stored in the requested format.
+    pub unchanged_benchmarks: usize,
+    /// The number of measurements converted from the other format.
+    pub converted_measurements: usize,
+}
+
+/// Rewrite the measurements of every benchmark in every timeline under the given data directory in
+/// the given storage format. The measurements themselves are copied unchanged, and
+/// the latest measurement and named baselines of each benchmark are updated to point at the
+/// copies. Benchmarks which are already stored in the given format are left alone, so migrating
+/// again does nothing. Fails without changing anything if a benchmark run holds the lock.
+///
+/// If `dry_run` is set, nothing is changed, and the summary describes what would have been
+/// converted.
+pub fn migrate_stored_data(
+    data_directory: &Path,
+    to: StorageFormat,
+    dry_run: bool,
+) -> Result<MigrationSummary> {
+    let _lock = if dry_run {
+        None
+    } else {
+        Some(DataLock::acquire(data_directory)?)
+    };
+
+    let benchmark_paths: Vec<PathBuf> = walk_dir(data_directory)
+        .into_iter()
+        // Ignore errors.
+        .filter_map(::std::result::Result::ok)
+        .filter(|entry| entry.file_name() == OsStr::new("benchmark.cbor"))
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+
+    let mut summary = MigrationSummary::default();
+    for benchmark_path in benchmark_paths {
+        let converted = migrate_benchmark(&benchmark_path, to, dry_run)
+            .with_context(|| format!("Failed to migrate benchmark {:?}", benchmark_path))?;
+        match converted {
+            Some(count) => {
+                summary.migrated_benchmarks += 1;
+                summary.converted_measurements += count;
+            }
+            None => summary.unchanged_benchmarks += 1,
+        }
+    }
+    Ok(summary)
+}
+
+/// Rewrite the measurements of one benchmark in the given storage format. Returns the number of
+/// measurements converted, or None if they were all stored in that format already. If `dry_run`
+/// is set, returns the number of measurements which would be converted without converting them.
+fn migrate_benchmark(
+    benchmark_path: &Path,
+    to: StorageFormat,
+    dry_run: bool,
+) -> Result<Option<usize>> {
+    let mut record = load_benchmark_record(benchmark_path)?;
+    let benchmark_dir = benchmark_path.parent().unwrap();
+    let log_path = benchmark_dir.join(MEASUREMENT_LOG);
+
+    let mut measurements = Vec::new();
+    if log_path.is_file() {
+        for entry in measurement_log::read_all_with_offsets::<SavedStatistics>(&log_path) {
+            let (offset, stats) = entry?;
+            let location = MeasurementLocation {
+                file: PathBuf::from(MEASUREMENT_LOG),
+                offset: Some(offset),
+            };
+            measurements.push((location, stats));
+        }
+    }
+    for entry in walk_dir(benchmark_dir)
+        .max_depth(1)
+        .into_iter()
+        // Ignore errors.
+        .filter_map(::std::result::Result::ok)
+        .filter(|entry| is_measurement_file(entry.path()))
+    {
+        let location = MeasurementLocation {
+            file: PathBuf::from(entry.file_name()),
+            offset: None,
+        };
+        measurements.push((location, load_measurement(entry.path())?));
+    }
+
+    let in_target_format = |location: &MeasurementLocation| match to {
+        StorageFormat::Files => location.offset.is_none(),
+        StorageFormat::Log => location.offset.is_some(),
+    };
+    if measurements
+        .iter()
+        .all(|(location, _)| in_target_format(location))
+    {
+        return Ok(None);
+    }
+    if dry_run {
+        // A measurement which already has a copy in the target format isn't converted again.
+        let already_converted: HashSet<DateTime<Utc>> = (measurements.iter())
+            .filter(|(location, _)| in_target_format(location))
+            .map(|(_, stats)| stats.datetime)
+            .collect();
+        let to_convert: HashSet<DateTime<Utc>> = (measurements.iter())
+            .map(|(_, stats)| stats.datetime)
+            .filter(|datetime| !already_converted.contains(datetime))
+            .collect();
+        warn!(
+            target: "dry-run",
+            "Dry run: would convert {} measurement(s) in {:?}",
+            to_convert.len(),
+            benchmark_dir
+        );
+        return Ok(Some(to_convert.len()));
+    }
+
+    // Rewrite the measurements oldest first. If an earlier migration was interrupted, some
+    // measurements are stored in both formats; those are identified by their datetime and only
+    // the copy in the target format is kept.
+    measurements.sort_by_key(|(location, stats)| (stats.datetime, !in_target_format(location)));
+    let new_log_path = benchmark_dir.join("measurements.log.new");
+    if new_log_path.exists() {
+        std::fs::remove_file(&new_log_path)
+            .with_context(|| format!("Failed to remove {:?}", new_log_path))?;
+    }
+    let mut moved = HashMap::new();
+    let mut converted = 0;
+    let mut previous: Option<(DateTime<Utc>, MeasurementLocation)> = None;
+    for (location, stats) in &measurements {
+        let new_location = match &previous {
+            Some((datetime, new_location)) if *datetime == stats.datetime => new_location.clone(),
+            _ => {
+                if !in_target_format(location) {
+                    converted += 1;
+                }
+                match to {
+                    StorageFormat::Files if location.offset.is_none() => location.clone(),
+                    StorageFormat::Files => {
+                        let file = unused_measurement_name(benchmark_dir, stats.datetime);
+                        save_measurement(&benchmark_dir.join(&file), stats)?;
+                        MeasurementLocation { file, offset: None }
+                    }
+                    StorageFormat::Log => MeasurementLocation {
+                        file: PathBuf::from(MEASUREMENT_LOG),
+                        offset: Some(measurement_log::append(&new_log_path, stats)?),
+                    },
+                }
+            }
+        };
+        previous = Some((stats.datetime, new_location.clone()));
+        moved.insert(location.clone(), new_location);
+    }
+
+    if let StorageFormat::Log = to {
+        std::fs::rename(&new_log_path, &log_path)
+            .with_context(|| format!("Failed to move {:?} to {:?}", new_log_path, log_path))?;
+    }
+
+    // Locations which don't refer to a stored measurement are left as they are.
+    let latest = record.latest();
+    let latest = moved.get(&latest).cloned().unwrap_or(latest);
+    record.latest_record = latest.file;
+    record.latest_offset = latest.offset;
+    for location in record.baselines.values_mut() {
+        if let Some(new_location) = moved.get(location) {
+            *location = new_location.clone();
+        }
+    }
+    save_benchmark_record(benchmark_path, &record)?;
+
+    // Only remove the old copies once nothing refers to them.
+    match to {
+        StorageFormat::Files => std::fs::remove_file(&log_path)
+            .with_context(|| format!("Failed to remove {:?}", log_path))?,
+        StorageFormat::Log => {
+            for (location, _) in &measurements {
+                if location.offset.is_none() {
+                    let path = benchmark_dir.join(&location.file);
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("Failed to remove {:?}", path))?;
+                }
+            }
+        }
+    }
+    Ok(Some(converted))
+}
+
+/// Pick a name for a measurement file taken at the given time, in the same form as the names given
+/// to new measurements, which isn't already used in the given directory.
+fn unused_measurement_name(benchmark_dir: &Path, datetime: DateTime<Utc>) -> PathBuf {
+    let stem = datetime
+        .with_timezone(&chrono::Local)
+        .format("measurement_%y%m%d%H%M%S")
+        .to_string();
+    let mut name = PathBuf::from(format!("{}.cbor", stem));
+    let mut counter = 1;
+    while benchmark_dir.join(&name).exists() {
+        counter += 1;
+        name = PathBuf::from(format!("{}_{}.cbor", stem, counter));
+    }
+    name
+}
+
+/// The result of deleting old measurements with `prune_stored_data`.
+#[derive(Debug, Default)]
+pub struct PruneSummary {
+    /// The number of measurement files which were deleted.
+    pub deleted_measurements: usize,
+    /// The total size of the deleted files, in bytes.
+    pub deleted_bytes: u64,
+    /// The total size of the files left in the data directory, in bytes.
+    pub remaining_bytes: u64,
+}
+
+/// Delete stored measurements according to the given retention policy. With a size budget, the
+/// sizes of all of the files in every timeline under the data directory are added up, and the
+/// oldest measurement files are deleted, whichever benchmark they belong to, until the total is
+/// within the budget. Measurement files are ordered by the time in their names.
+///
+/// The latest measurement and the named baselines of each benchmark are never deleted, and nor
+/// are measurements in measurement logs, which can't be deleted one at a time, or measurements
+/// whose benchmark.cbor file can't be read. The data can still be over budget afterwards.
+///
+/// If `dry_run` is set, the files which would be deleted are printed instead, and the summary
+/// describes what would have been deleted.
+pub fn prune_stored_data(
+    data_directory: &Path,
+    policy: RetentionPolicy,
+    dry_run: bool,
+) -> Result<PruneSummary> {
+    let budget = match policy {
+        RetentionPolicy::KeepAll => return Ok(PruneSummary::default()),
+        RetentionPolicy::MaxBytes(budget) => budget,
+    };
+
+    let mut total_bytes = 0;
+    let mut measurements = Vec::new();
+    let mut prunable_dirs = HashSet::new();
+    let mut kept = HashSet::new();
+    for entry in walk_dir(data_directory)
+        .into_iter()
+        // Ignore errors.
+        .filter_map(::std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        total_bytes += size;
+        let path = entry.path();
+        if entry.file_name() == OsStr::new("benchmark.cbor") {
+            if let Ok(record) = load_benchmark_record(path) {
+                let benchmark_dir = path.parent().unwrap();
+                prunable_dirs.insert(benchmark_dir.to_owned());
+                for location in std::iter::once(&record.latest()).chain(record.baselines.values()) {
+                    kept.insert(benchmark_dir.join(&location.file));
+                }
+            }
+        } else if is_measurement_file(path) {
+            measurements.push((entry.into_path(), size));
+        }
+    }
+
+    measurements
+        .retain(|(path, _)| prunable_dirs.contains(path.parent().unwrap()) && !kept.contains(path));
+    // The names begin with the time the measurement was taken, so they sort oldest first.
+    measurements.sort_by(|(a, _), (b, _)| a.file_name().cmp(&b.file_name()));
+
+    let mut summary = PruneSummary::default();
+    for (path, size) in measurements {
+        if total_bytes <= budget {
+            break;
+        }
+        if dry_run {
+            warn!(target: "dry-run", "Dry run: would delete {:?}", path);
+        } else {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete measurement file {:?}", path))?;
+        }
+        total_bytes -= size;
+        summary.deleted_measurements += 1;
+        summary.deleted_bytes += size;
+    }
+    summary.remaining_bytes = total_bytes;
+    Ok(summary)
+}
+
+/// Load the measurement stored at the given location in a benchmark directory.
+fn load_measurement_at(
+    benchmark_dir: &Path,
+    location: &MeasurementLocation,
+) -> Result<SavedStatistics> {
+    let path = benchmark_dir.join(&location.file);
+    match location.offset {
+        Some(offset) => measurement_log::read_at(&path, offset),
+        None => load_measurement(&path),
+    }
+}
+
+/// Write a single measurement file.
+fn save_measurement(measurement_path: &Path, stats: &SavedStatistics) -> Result<()> {
+    let mut measurement_file = File::create(measurement_path)
+        .with_context(|| format!("Failed to create measurement file {:?}", measurement_path))?;
+    serde_cbor::to_writer(&mut measurement_file, stats)
+        .with_context(|| format!("Failed to save measurements to file {:?}", measurement_path))
+}
+
+/// Write a benchmark.cbor file.
+fn save_benchmark_record(benchmark_path: &Path, record: &BenchmarkRecord) -> Result<()> {
+    let mut benchmark_file = File::create(benchmark_path)
+        .with_context(|| format!("Failed to create benchmark file {:?}", benchmark_path))?;
+    serde_cbor::to_writer(&mut benchmark_file, record)
+        .with_context(|| format!("Failed to save benchmark file {:?}", benchmark_path))
+}
+
+/// Load a single measurement file.
+pub fn load_measurement(measurement_path: &Path) -> Result<SavedStatistics> {
+    let mut measurement_file = File::open(measurement_path)
+        .with_context(|| format!("Failed to open measurement file {:?}", measurement_path))?;
+    serde_cbor::from_reader(&mut measurement_file)
+        .with_context(|| format!("Failed to read measurement file {:?}", measurement_path))
+}
+
+/// If the measurement doesn't record when it was taken, date it by the time that the file it's
+/// stored in was last modified instead.
+fn use_mtime_if_undated(stats: &mut SavedStatistics, path: &Path) {
+    if stats.datetime != unknown_datetime() {
+        return;
+    }
+    if let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        stats.datetime = modified.into();
+    }
+}
+
+/// The datetime of measurements which don't record when they were taken.
+fn unknown_datetime() -> DateTime<Utc> {
+    std::time::UNIX_EPOCH.into()
+}
+
+/// Load all of the measurements stored in the given benchmark directory, sorted from oldest to
+/// newest. Files which can't be loaded are logged and skipped. If `datetime_from_mtime` is set,
+/// measurements which don't record when they were taken are dated by their file's modification
+/// time.
+fn load_history_from(dir: &Path, datetime_from_mtime: bool) -> Vec<SavedStatistics> {
+    let mut stats = Vec::new();
+    for entry in walk_dir(dir)
+        .max_depth(1)
+        .into_iter()
+        // Ignore errors.
+        .filter_map(::std::result::Result::ok)
+    {
+        if is_measurement_file(entry.path()) {
+            match load_measurement(entry.path()) {
+                Ok(mut saved_stats) => {
+                    if datetime_from_mtime {
+                        use_mtime_if_undated(&mut saved_stats, entry.path());
+                    }
+                    stats.push(saved_stats)
+                }
+                Err(e) => error!(
+                    target: "load-failure",
+                    "Unexpected error loading benchmark history from file {}: {:?}",
+                    entry.path().display(),
+                    e
+                ),
+            }
+        } else if entry.file_name() == OsStr::new(MEASUREMENT_LOG) {
+            for record in measurement_log::read_all(entry.path()) {
+                match record {
+                    Ok(mut saved_stats) => {
+                        if datetime_from_mtime {
+                            use_mtime_if_undated(&mut saved_stats, entry.path());
+                        }
+                        stats.push(saved_stats)
+                    }
+                    Err(e) => error!(
+                        target: "load-failure",
+                        "Unexpected error loading benchmark history: {:?}",
+                        e
+                    ),
+                }
+            }
+        }
+    }
+
+    // Keep records with the same datetime in the order they were read, since those dated by the
+    // modification time of a measurement log were appended in order.
+    stats.sort_by_key(|st| st.datetime);
+    stats
+}
+
+/// Combine several stored measurements of a benchmark into one, for comparing against a rolling
+/// average. The samples are concatenated so that the comparison sees the pooled variance of all
+/// of the runs, and the estimates are averaged. Each part of the latest measurement is pooled with
+/// the same part of the others. Returns None if there are no measurements.
+fn pool_statistics(history: &[SavedStatistics]) -> Option<SavedStatistics> {
+    let latest = history.last()?;
+    if history.len() == 1 {
+        return Some(latest.clone());
+    }
+
+    let average = |get: &dyn Fn(&Estimates) -> Option<&Estimate>| -> Option<Estimate> {
+        let estimates = history
+            .iter()
+            .map(|stats| get(&stats.estimates))
+            .collect::<Option<Vec<_>>>()?;
+        let mean = |f: &dyn Fn(&Estimate) -> f64| {
+            estimates.iter().map(|e| f(e)).sum::<f64>() / estimates.len() as f64
+        };
+        Some(Estimate {
+            confidence_interval: ConfidenceInterval {
+                confidence_level: estimates[0].confidence_interval.confidence_level,
+                lower_bound: mean(&|e| e.confidence_interval.lower_bound),
+                upper_bound: mean(&|e| e.confidence_interval.upper_bound),
+            },
+            point_estimate: mean(&|e| e.point_estimate),
+            standard_error: mean(&|e| e.standard_error.powi(2)).sqrt(),
+        })
+    };
+
+    Some(SavedStatistics {
+        datetime: latest.datetime,
+        iterations: history
+            .iter()
+            .flat_map(|s| s.iterations.iter().copied())
+            .collect(),
+        values: history
+            .iter()
+            .flat_map(|s| s.values.iter().copied())
+            .collect(),
+        avg_values: history
+            .iter()
+            .flat_map(|s| s.avg_values.iter().copied())
+            .collect(),
+        estimates: Estimates {
+            mean: average(&|e| Some(&e.mean))?,
+            median: average(&|e| Some(&e.median))?,
+            median_abs_dev: average(&|e| Some(&e.median_abs_dev))?,
+            slope: average(&|e| e.slope.as_ref()),
+            std_dev: average(&|e| Some(&e.std_dev))?,
+        },
+        throughput: latest.throughput.clone(),
+        changes: None,
+        change_direction: None,
+        p_value: None,
+        history_id: None,
+        history_description: None,
+        features: latest.features.clone(),
+        peak_rss: None,
+        instructions_per_iteration: None,
+        allocations: None,
+        cpu_affinity: latest.cpu_affinity.clone(),
+        criterion_version: latest.criterion_version.clone(),
+        bench_version: latest.bench_version.clone(),
+        bench_seed: latest.bench_seed,
+        single_thread: latest.single_thread,
+        min_samples_enforced: latest.min_samples_enforced,
+        profile: latest.profile.clone(),
+        async_runtime: latest.async_runtime.clone(),
+        target: latest.target.clone(),
+        target_fingerprint: latest.target_fingerprint.clone(),
+        compile_time: latest.compile_time,
+        metadata: latest.metadata.clone(),
+        sub_measurements: (latest.sub_measurements.keys())
+            .filter_map(|name| {
+                let parts: Vec<SavedStatistics> = (history.iter())
+                    .filter_map(|stats| stats.sub_measurements.get(name).cloned())
+                    .collect();
+                Some((name.clone(), pool_statistics(&parts)?))
+            })
+            .collect(),
+    })
+}
+
+/// The raw samples of several stored measurements of a benchmark, concatenated so that they can be
+/// analyzed as a single measurement.
+#[derive(Debug)]
+pub struct PooledSamples {
+    pub iterations: Vec<f64>,
+    pub values: Vec<f64>,
+    pub avg_values: Vec<f64>,
+    /// Were all of the measurements taken with linear sampling? The slope can only be estimated
+    /// from the pooled samples if so.
+    pub linear: bool,
+}
+
+/// Concatenate the samples of the given measurements of a benchmark. Each sample keeps its own
+/// iteration count, and its time per iteration is worked out from its own total time, so samples
+/// taken with different iteration counts (for example by runs with different measurement times)
+/// can be pooled. Each sample counts once towards the estimates, however many iterations it ran.
+pub fn pool_samples(history: &[SavedStatistics]) -> Result<PooledSamples> {
+    let first = history
+        .first()
+        .ok_or_else(|| anyhow!("there are no measurements to pool"))?;
+    let mut pooled = PooledSamples {
+        iterations: Vec::new(),
+        values: Vec::new(),
+        avg_values: Vec::new(),
+        linear: true,
+    };
+    for stats in history {
+        if !stats.has_samples() {
+            return Err(anyhow!(
+                "the measurement taken at {} doesn't store its samples",
+                stats.datetime
+            ));
+        }
+        if stats.throughput != first.throughput {
+            return Err(anyhow!(
+                "the measurement taken at {} has a different throughput from the one taken at {}",
+                stats.datetime,
+                first.datetime
+            ));
+        }
+        if stats.iterations.len() != stats.values.len() {
+            return Err(anyhow!(
+                "the measurement taken at {} has {} iteration counts but {} samples",
+                stats.datetime,
+                stats.iterations.len(),
+                stats.values.len()
+            ));
+        }
+        pooled.linear &= stats.estimates.slope.is_some();
+        for (&iterations, &value) in stats.iterations.iter().zip(&stats.values) {
+            pooled.iterations.push(iterations);
+            pooled.values.push(value);
+            pooled.avg_values.push(value / iterations);
+        }
+    }
+    Ok(pooled)
+}
+
+// These structs are saved to disk and may be read by future versions of cargo-criterion, so
+// backwards compatibility is important.
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SavedBenchmarkId {
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    throughput: Option<Throughput>,
+}
+impl From<BenchmarkId> for SavedBenchmarkId {
     fn from(other: BenchmarkId) -> Self {
         SavedBenchmarkId {
             group_id: other.group_id,
@@ -313,7 +2076,12 @@ impl From<SavedBenchmarkId> for BenchmarkId {
 }
 impl From<&SavedBenchmarkId> for BenchmarkId {
     fn from(other: &SavedBenchmarkId) -> Self {
-        other.into()
+        BenchmarkId::new(
+            other.group_id.clone(),
+            other.function_id.clone(),
+            other.value_str.clone(),
+            other.throughput.clone(),
+        )
     }
 }
 
@@ -321,6 +2089,148 @@ impl From<&SavedBenchmarkId> for BenchmarkId {
 struct BenchmarkRecord {
     id: SavedBenchmarkId,
     latest_record: PathBuf,
+    // The offset of the latest measurement within latest_record, if it is a measurement log.
+    #[serde(default)]
+    latest_offset: Option<u64>,
+    // The measurements saved under each named baseline.
+    #[serde(default)]
+    baselines: HashMap<String, MeasurementLocation>,
+}
+impl BenchmarkRecord {
+    fn latest(&self) -> MeasurementLocation {
+        MeasurementLocation {
+            file: self.latest_record.clone(),
+            offset: self.latest_offset,
+        }
+    }
+}
+
+/// The error returned when `--db` is given but SQLite support wasn't compiled in.
+#[cfg(not(feature = "sqlite"))]
+const SQLITE_DISABLED: &str = "SQLite support is disabled. To use --db, install cargo-criterion \
+                               with the 'sqlite' feature enabled";
+
+/// The name of the file that measurements are appended to when using the log storage format.
+const MEASUREMENT_LOG: &str = "measurements.log";
+
+/// The name of the index file in the root of each timeline directory.
+const HISTORY_INDEX: &str = "index.cbor";
+/// The name of the file in the root of each timeline directory which lists the benchmarks in each
+/// group as of the group's latest run.
+const GROUP_MEMBERS: &str = "groups.cbor";
+/// The version of the index format. An index of any other version is ignored.
+const HISTORY_INDEX_VERSION: u32 = 2;
+
+// An index of the stored benchmarks in a timeline directory, which lets `Model::load` read one
+// file instead of walking the directories and reading every benchmark's files. It's only used
+// while every directory in the timeline, and every benchmark.cbor file, still has the
+// modification time recorded in it; adding, removing or rewriting any of them leaves it out of
+// date.
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryIndex {
+    version: u32,
+    // Every directory in the timeline, relative to the timeline directory.
+    directories: HashMap<PathBuf, SystemTime>,
+    // The benchmarks, by their directory relative to the timeline directory.
+    benchmarks: HashMap<PathBuf, IndexedBenchmark>,
+}
+impl Default for HistoryIndex {
+    fn default() -> Self {
+        HistoryIndex {
+            version: HISTORY_INDEX_VERSION,
+            directories: HashMap::new(),
+            benchmarks: HashMap::new(),
+        }
+    }
+}
+impl HistoryIndex {
+    fn is_current(&self, timeline_directory: &Path) -> bool {
+        let unchanged = |path: PathBuf, modified: &SystemTime| {
+            modified_time(&path).ok().as_ref() == Some(modified)
+        };
+        self.version == HISTORY_INDEX_VERSION
+            && (self.directories.iter()).all(|(directory, modified)| {
+                unchanged(timeline_directory.join(directory), modified)
+            })
+            && (self.benchmarks.iter()).all(|(directory, benchmark)| {
+                let path = path!(timeline_directory, directory, "benchmark.cbor");
+                unchanged(path, &benchmark.modified)
+            })
+    }
+
+    /// Make the given measurement the latest one of its benchmark in the index.
+    fn update(
+        &mut self,
+        timeline_directory: &Path,
+        benchmark_dir: &Path,
+        id: &BenchmarkId,
+        location: &MeasurementLocation,
+        stats: &SavedStatistics,
+    ) -> Result<()> {
+        let directory = benchmark_dir.strip_prefix(timeline_directory)?.to_owned();
+        let benchmark = IndexedBenchmark {
+            modified: modified_time(&benchmark_dir.join("benchmark.cbor"))?,
+            id: id.into(),
+            latest: location.clone(),
+            stats: stats.clone().without_samples(),
+        };
+        self.benchmarks.insert(directory.clone(), benchmark);
+        // Saving the measurement changed the benchmark's directory, and may have created the
+        // directories above it.
+        for directory in directory.ancestors() {
+            let modified = modified_time(&timeline_directory.join(directory))?;
+            self.directories.insert(directory.to_owned(), modified);
+        }
+        Ok(())
+    }
+
+    fn save(&mut self, timeline_directory: &Path) -> Result<()> {
+        // Nothing is stored yet, so there's nothing to index.
+        if !timeline_directory.is_dir() {
+            return Ok(());
+        }
+        let path = timeline_directory.join(HISTORY_INDEX);
+        let created = !path.is_file();
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create index file {:?}", path))?;
+        if created {
+            // Creating the file changed the modification time of the timeline directory. Later
+            // saves overwrite it in place, which doesn't.
+            self.directories
+                .insert(PathBuf::new(), modified_time(timeline_directory)?);
+        }
+        serde_cbor::to_writer(&mut file, self)
+            .with_context(|| format!("Failed to write index file {:?}", path))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedBenchmark {
+    // The modification time of the benchmark's benchmark.cbor file.
+    modified: SystemTime,
+    id: SavedBenchmarkId,
+    latest: MeasurementLocation,
+    // The latest measurement, without its raw samples.
+    stats: SavedStatistics,
+}
+
+fn modified_time(path: &Path) -> Result<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("Failed to read the modification time of {:?}", path))
+}
+
+// Where a measurement is stored, relative to its benchmark directory.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
+struct MeasurementLocation {
+    file: PathBuf,
+    // The offset of the record within the file, if the file is a measurement log. Otherwise the
+    // file holds just this one measurement.
+    offset: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -332,11 +2242,12 @@ pub enum ChangeDirection {
 }
 
 fn get_change_direction(comp: &ComparisonData) -> ChangeDirection {
-    if comp.p_value < comp.significance_threshold {
+    // A change is only significant if the p-value is below the threshold.
+    if comp.p_value >= comp.significance_threshold {
         return ChangeDirection::NoChange;
     }
 
-    let ci = &comp.relative_estimates.mean.confidence_interval;
+    let ci = &comp.primary_change().confidence_interval;
     let lb = ci.lower_bound;
     let ub = ci.upper_bound;
     let noise = comp.noise_threshold;
@@ -352,7 +2263,9 @@ fn get_change_direction(comp: &ComparisonData) -> ChangeDirection {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SavedStatistics {
-    // The timestamp of when these measurements were saved.
+    // The timestamp of when these measurements were saved. Files produced by other tools may not
+    // record this, in which case it's the Unix epoch.
+    #[serde(default = "unknown_datetime")]
     pub datetime: DateTime<Utc>,
     // The number of iterations in each sample
     pub iterations: Vec<f64>,
@@ -369,6 +2282,9 @@ pub struct SavedStatistics {
     pub changes: Option<ChangeEstimates>,
     // Was the change (if any) significant?
     pub change_direction: Option<ChangeDirection>,
+    // The p-value of the change, if there was a comparison. Older measurements don't record this.
+    #[serde(default)]
+    pub p_value: Option<f64>,
 
     // An optional user-provided identifier string. This might be a version control commit ID or
     // something custom
@@ -376,4 +2292,1310 @@ pub struct SavedStatistics {
     // An optional user-provided description. This might be a version control commit message or
     // something custom.
     pub history_description: Option<String>,
+    // The cargo features enabled when the benchmark was compiled, in sorted order. This is empty
+    // for measurements stored before the features were recorded, so an empty list means unknown.
+    #[serde(default)]
+    pub features: Vec<String>,
+    // The peak resident set size of the benchmark process in bytes, if it was measured.
+    #[serde(default)]
+    pub peak_rss: Option<u64>,
+    // The mean number of instructions executed per iteration, if they were counted.
+    #[serde(default)]
+    pub instructions_per_iteration: Option<f64>,
+    // The allocations made per iteration, if the benchmark reported them.
+    #[serde(default)]
+    pub allocations: Option<AllocStats>,
+    // The CPUs that the benchmark target was restricted to, or empty if it could run on any CPU.
+    #[serde(default)]
+    pub cpu_affinity: Vec<usize>,
+    // The version of Criterion.rs that the benchmark was built with, if known.
+    #[serde(default)]
+    pub criterion_version: Option<String>,
+    // The tag that the user gave to this version of the benchmark code with --bench-version.
+    #[serde(default)]
+    pub bench_version: Option<String>,
+    // The seed that the benchmark was given with --bench-seed to generate its inputs from.
+    #[serde(default)]
+    pub bench_seed: Option<u64>,
+    // Whether the benchmark's worker thread pools were limited to a single thread with
+    // --single-thread. Older measurements don't record this, and are read as unlimited.
+    #[serde(default)]
+    pub single_thread: bool,
+    // Whether the benchmark took more samples than it was configured to, to satisfy --min-samples.
+    #[serde(default)]
+    pub min_samples_enforced: bool,
+    // The Cargo profile that the benchmark was compiled with. Older measurements don't record this.
+    #[serde(default)]
+    pub profile: Option<String>,
+    // The async runtime that the benchmark was run on, or None for synchronous benchmarks and for
+    // measurements taken before this was recorded.
+    #[serde(default)]
+    pub async_runtime: Option<String>,
+    // The benchmark target which took these measurements, and the fingerprint of its executable.
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub target_fingerprint: Option<String>,
+    // How long Cargo took to compile the benchmark target, in seconds. This is only known if Cargo
+    // reported the timing of each target, which needs `-Zunstable-options --timings=json`.
+    #[serde(default)]
+    pub compile_time: Option<f64>,
+    // Key/value annotations attached to the benchmark by the benchmark code.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    // The statistics of the parts of the benchmark which it timed separately, by name.
+    #[serde(default)]
+    pub sub_measurements: BTreeMap<String, SavedStatistics>,
+}
+/// The allocations made by a benchmark, as reported by a counting allocator installed in the
+/// benchmark crate.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct AllocStats {
+    /// The mean number of allocations per iteration.
+    pub allocations_per_iteration: f64,
+    /// The mean number of bytes allocated per iteration.
+    pub bytes_per_iteration: f64,
+}
+impl AllocStats {
+    /// Average the totals reported over a measurement across its iterations.
+    pub fn per_iteration(allocations: u64, bytes: u64, iterations: f64) -> AllocStats {
+        AllocStats {
+            allocations_per_iteration: allocations as f64 / iterations,
+            bytes_per_iteration: bytes as f64 / iterations,
+        }
+    }
+}
+
+impl SavedStatistics {
+    /// Were the raw samples stored along with the estimates? They are omitted by `--store summary`.
+    pub fn has_samples(&self) -> bool {
+        !self.avg_values.is_empty()
+    }
+
+    /// Returns these statistics with the raw samples removed, keeping only the estimates.
+    pub fn without_samples(self) -> SavedStatistics {
+        SavedStatistics {
+            iterations: vec![],
+            values: vec![],
+            avg_values: vec![],
+            sub_measurements: (self.sub_measurements.into_iter())
+                .map(|(name, sub)| (name, sub.without_samples()))
+                .collect(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MEASUREMENT: &str = "measurement_200101000000.cbor";
+
+    /// A directory for a test to store its data in, with anything left in it by an earlier run
+    /// removed. It's named after the test and this process, so that tests running at the same time
+    /// don't share one.
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    /// The record of a benchmark in the group, with no function ID, value or baselines.
+    fn benchmark_record(group: &str, latest_record: &str) -> BenchmarkRecord {
+        BenchmarkRecord {
+            id: SavedBenchmarkId {
+                group_id: group.to_owned(),
+                function_id: None,
+                value_str: None,
+                throughput: None,
+            },
+            latest_record: PathBuf::from(latest_record),
+            latest_offset: None,
+            baselines: HashMap::new(),
+        }
+    }
+
+    /// Store `stats` in `benchmark_dir` as the latest measurement of a benchmark in the group.
+    fn write_benchmark(benchmark_dir: &Path, group: &str, stats: &SavedStatistics) {
+        std::fs::create_dir_all(benchmark_dir).unwrap();
+        save_measurement(&benchmark_dir.join(MEASUREMENT), stats).unwrap();
+        let record = benchmark_record(group, MEASUREMENT);
+        save_benchmark_record(&benchmark_dir.join("benchmark.cbor"), &record).unwrap();
+    }
+
+    /// Load the main timeline of the stored data with the default options.
+    fn load_model(data_directory: &Path) -> Model {
+        Model::load(
+            data_directory.to_owned(),
+            PathBuf::from("main"),
+            None,
+            None,
+            CompareAgainst::Nothing,
+            None,
+            StorageFormat::Files,
+            SampleStorage::Full,
+            DirNameStrategy::Readable,
+            false,
+            false,
+        )
+    }
+
+    /// An estimate of exactly `value`, with no uncertainty.
+    fn exact_estimate(value: f64) -> Estimate {
+        Estimate {
+            confidence_interval: ConfidenceInterval {
+                confidence_level: 0.95,
+                lower_bound: value,
+                upper_bound: value,
+            },
+            point_estimate: value,
+            standard_error: 0.0,
+        }
+    }
+
+    fn stats_with_change(time: f64, change_direction: Option<ChangeDirection>) -> SavedStatistics {
+        let estimate = exact_estimate(time);
+        SavedStatistics {
+            datetime: unknown_datetime(),
+            iterations: vec![],
+            values: vec![],
+            avg_values: vec![],
+            estimates: Estimates {
+                mean: estimate.clone(),
+                median: estimate.clone(),
+                median_abs_dev: estimate.clone(),
+                slope: None,
+                std_dev: estimate,
+            },
+            throughput: None,
+            changes: None,
+            change_direction,
+            p_value: None,
+            history_id: None,
+            history_description: None,
+            features: vec![],
+            peak_rss: None,
+            instructions_per_iteration: None,
+            allocations: None,
+            cpu_affinity: vec![],
+            criterion_version: None,
+            bench_version: None,
+            bench_seed: None,
+            single_thread: false,
+            min_samples_enforced: false,
+            profile: None,
+            async_runtime: None,
+            target: None,
+            target_fingerprint: None,
+            compile_time: None,
+            metadata: HashMap::new(),
+            sub_measurements: BTreeMap::new(),
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_database_round_trip() {
+        let directory = test_directory("database");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("measurements.db");
+        let sum = BenchmarkId::new("sum".to_owned(), None, Some("10".to_owned()), None);
+        let other = BenchmarkId::new(
+            "other".to_owned(),
+            Some("f".to_owned()),
+            None,
+            Some(Throughput::Bytes(64)),
+        );
+        {
+            let database = Database::open(&path).unwrap();
+            database
+                .insert(&sum, &stats_with_change(1.0, None))
+                .unwrap();
+            let mut other_stats = stats_with_change(5.0, None);
+            other_stats.throughput = Some(Throughput::Bytes(64));
+            database.insert(&other, &other_stats).unwrap();
+            let mut latest = stats_with_change(2.0, Some(ChangeDirection::Regressed));
+            latest.history_id = Some("abc123".to_owned());
+            database.insert(&sum, &latest).unwrap();
+        }
+
+        let model = Model::load_from_database(&path, None);
+        let group = Model::load_from_database(&path, Some("sum"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let model = model.unwrap();
+        assert_eq!(
+            model.groups.keys().collect::<Vec<_>>(),
+            vec!["other", "sum"]
+        );
+        let stats = &model.groups["sum"].benchmarks[&sum].latest_stats;
+        assert_eq!(stats.estimates.mean.point_estimate, 2.0);
+        assert_eq!(stats.history_id.as_deref(), Some("abc123"));
+        assert!(model.groups["other"].benchmarks.contains_key(&other));
+
+        let group = group.unwrap();
+        assert_eq!(group.groups.keys().collect::<Vec<_>>(), vec!["sum"]);
+        assert!(Model::load_from_database(&path, None).is_err());
+    }
+
+    #[test]
+    fn test_pool_samples_mixes_iteration_counts() {
+        let mut linear = stats_with_change(1.0, None);
+        linear.estimates.slope = Some(linear.estimates.mean.clone());
+        linear.iterations = vec![1.0, 2.0];
+        linear.values = vec![10.0, 20.0];
+        linear.avg_values = vec![10.0, 10.0];
+        let mut flat = stats_with_change(1.0, None);
+        flat.iterations = vec![5.0, 5.0];
+        flat.values = vec![60.0, 40.0];
+        flat.avg_values = vec![12.0, 8.0];
+
+        let pooled = pool_samples(&[linear.clone(), flat.clone()]).unwrap();
+        assert_eq!(pooled.iterations, [1.0, 2.0, 5.0, 5.0]);
+        assert_eq!(pooled.values, [10.0, 20.0, 60.0, 40.0]);
+        assert_eq!(pooled.avg_values, [10.0, 10.0, 12.0, 8.0]);
+        assert!(!pooled.linear);
+        assert!(
+            pool_samples(&[linear.clone(), linear.clone()])
+                .unwrap()
+                .linear
+        );
+
+        flat.throughput = Some(Throughput::Bytes(1024));
+        assert!(pool_samples(&[linear.clone(), flat]).is_err());
+        assert!(pool_samples(&[linear.without_samples()]).is_err());
+    }
+
+    #[test]
+    fn test_pool_statistics_combines_estimates() {
+        let measurement = |time: f64, standard_error: f64, samples: Vec<f64>| {
+            let mut stats = stats_with_change(time, None);
+            stats.estimates.mean = Estimate {
+                confidence_interval: ConfidenceInterval {
+                    confidence_level: 0.95,
+                    lower_bound: time * 0.8,
+                    upper_bound: time * 1.2,
+                },
+                point_estimate: time,
+                standard_error,
+            };
+            stats.iterations = vec![1.0; samples.len()];
+            stats.values = samples.clone();
+            stats.avg_values = samples;
+            stats
+        };
+        let mut first = measurement(10.0, 3.0, vec![9.0, 11.0]);
+        first.estimates.slope = Some(first.estimates.mean.clone());
+        first
+            .sub_measurements
+            .insert("cycles".to_owned(), measurement(100.0, 0.0, vec![100.0]));
+        let mut second = measurement(20.0, 4.0, vec![19.0, 21.0, 20.0]);
+        second.throughput = Some(Throughput::Bytes(64));
+        second.changes = Some(ChangeEstimates {
+            mean: exact_estimate(1.0),
+            median: exact_estimate(1.0),
+            slope: None,
+        });
+        second
+            .sub_measurements
+            .insert("cycles".to_owned(), measurement(300.0, 0.0, vec![300.0]));
+
+        let pooled = pool_statistics(&[first.clone(), second.clone()]).unwrap();
+        assert_eq!(pooled.values, [9.0, 11.0, 19.0, 21.0, 20.0]);
+        assert_eq!(pooled.avg_values, pooled.values);
+        assert_eq!(pooled.iterations, [1.0; 5]);
+        // The estimates are averaged, and their standard errors combined as independent errors.
+        let mean = &pooled.estimates.mean;
+        assert_eq!(mean.point_estimate, 15.0);
+        assert_eq!(mean.confidence_interval.lower_bound, 12.0);
+        assert_eq!(mean.confidence_interval.upper_bound, 18.0);
+        assert_eq!(mean.standard_error, 12.5f64.sqrt());
+        assert_eq!(pooled.estimates.median.point_estimate, 15.0);
+        // Only the first measurement has a slope, so there's none to pool.
+        assert!(pooled.estimates.slope.is_none());
+        // The rest is taken from the latest measurement, except for its comparison results.
+        assert_eq!(pooled.throughput, second.throughput);
+        assert!(pooled.changes.is_none());
+        let cycles = &pooled.sub_measurements["cycles"];
+        assert_eq!(cycles.estimates.mean.point_estimate, 200.0);
+        assert_eq!(cycles.values, [100.0, 300.0]);
+
+        let single = pool_statistics(&[second.clone()]).unwrap();
+        assert_eq!(single.values, second.values);
+        assert!(single.changes.is_some());
+        assert!(pool_statistics(&[]).is_none());
+    }
+
+    #[test]
+    fn test_change_direction_at_significance_threshold() {
+        let change = Estimate {
+            confidence_interval: ConfidenceInterval {
+                confidence_level: 0.95,
+                lower_bound: 0.4,
+                upper_bound: 0.6,
+            },
+            point_estimate: 0.5,
+            standard_error: 0.0,
+        };
+        let distribution = || crate::stats::Distribution::from(vec![0.5].into_boxed_slice());
+        let comparison = |p_value: f64| ComparisonData {
+            p_value,
+            t_distribution: distribution(),
+            t_value: 0.0,
+            relative_estimates: ChangeEstimates {
+                mean: change.clone(),
+                median: change.clone(),
+                slope: None,
+            },
+            relative_distributions: crate::estimate::ChangeDistributions {
+                mean: distribution(),
+                median: distribution(),
+                slope: None,
+            },
+            significance_threshold: 0.05,
+            noise_threshold: 0.01,
+            compare_metric: crate::config::CompareMetric::Mean,
+            base_iter_counts: vec![],
+            base_sample_times: vec![],
+            base_avg_times: vec![],
+            base_estimates: stats_with_change(1.0, None).estimates,
+            baseline_label: None,
+            base_criterion_version: None,
+            throughput_mismatch: None,
+        };
+        assert!(matches!(
+            get_change_direction(&comparison(0.01)),
+            ChangeDirection::Regressed
+        ));
+        assert!(matches!(
+            get_change_direction(&comparison(0.05)),
+            ChangeDirection::NoChange
+        ));
+        assert!(matches!(
+            get_change_direction(&comparison(0.5)),
+            ChangeDirection::NoChange
+        ));
+    }
+
+    #[test]
+    fn test_comparison_sample_selection() {
+        let data_directory = test_directory("comparison_sample");
+        let measurement = |time: f64, datetime: &str, history_id: &str| {
+            let mut stats = stats_with_change(time, None);
+            stats.datetime = DateTime::parse_from_rfc3339(datetime)
+                .unwrap()
+                .with_timezone(&Utc);
+            stats.history_id = Some(history_id.to_owned());
+            stats.iterations = vec![1.0];
+            stats.values = vec![time];
+            stats.avg_values = vec![time];
+            stats
+        };
+        // Two measurements in the main timeline, saved as different baselines, and one in a
+        // timeline named by --timeline-date.
+        let main_dir = data_directory.join("main").join("sum");
+        std::fs::create_dir_all(&main_dir).unwrap();
+        let first = "measurement_200101000000.cbor";
+        let stats = measurement(2.0, "2001-01-01T00:00:00Z", "aaa111");
+        save_measurement(&main_dir.join(first), &stats).unwrap();
+        let second = "measurement_200102000000.cbor";
+        let stats = measurement(4.0, "2001-01-02T00:00:00Z", "bbb222");
+        save_measurement(&main_dir.join(second), &stats).unwrap();
+        let mut record = benchmark_record("sum", second);
+        for (baseline, file) in [("before", first), ("after", second)] {
+            let location = MeasurementLocation {
+                file: PathBuf::from(file),
+                offset: None,
+            };
+            record.baselines.insert(baseline.to_owned(), location);
+        }
+        save_benchmark_record(&main_dir.join("benchmark.cbor"), &record).unwrap();
+        write_benchmark(
+            &data_directory.join("2001-01-03").join("sum"),
+            "sum",
+            &measurement(8.0, "2001-01-03T00:00:00Z", "ccc333"),
+        );
+
+        let id = BenchmarkId::new("sum".to_owned(), None, None, None);
+        let compared_time = |timeline: &str, compare_against: CompareAgainst| {
+            let model = Model::load(
+                data_directory.clone(),
+                PathBuf::from(timeline),
+                None,
+                None,
+                compare_against,
+                None,
+                StorageFormat::Files,
+                SampleStorage::Full,
+                DirNameStrategy::Readable,
+                false,
+                false,
+            );
+            let sample = model.get_comparison_sample(&id);
+            sample.map(|stats| stats.estimates.mean.point_estimate)
+        };
+        let before = |datetime: &str| {
+            let datetime = DateTime::parse_from_rfc3339(datetime).unwrap();
+            CompareAgainst::Datetime(datetime.with_timezone(&Utc))
+        };
+
+        // Each timeline is only compared against its own measurements.
+        assert_eq!(Some(4.0), compared_time("main", CompareAgainst::Previous));
+        assert_eq!(
+            Some(8.0),
+            compared_time("2001-01-03", CompareAgainst::Previous)
+        );
+        assert_eq!(None, compared_time("2001-01-04", CompareAgainst::Previous));
+        let baseline = |name: &str| CompareAgainst::Baseline(name.to_owned());
+        assert_eq!(Some(2.0), compared_time("main", baseline("before")));
+        assert_eq!(Some(4.0), compared_time("main", baseline("after")));
+        assert_eq!(None, compared_time("main", baseline("other")));
+        assert_eq!(None, compared_time("2001-01-03", baseline("before")));
+        assert_eq!(
+            Some(2.0),
+            compared_time("main", before("2001-01-01T12:00:00Z"))
+        );
+        assert_eq!(
+            Some(4.0),
+            compared_time("main", before("2001-01-05T00:00:00Z"))
+        );
+        assert_eq!(None, compared_time("main", before("2000-12-31T00:00:00Z")));
+        let commit = |id: &str| CompareAgainst::Commit(id.to_owned());
+        assert_eq!(Some(2.0), compared_time("main", commit("aaa")));
+        assert_eq!(None, compared_time("main", commit("ccc")));
+        assert_eq!(Some(3.0), compared_time("main", CompareAgainst::Rolling(2)));
+        assert_eq!(None, compared_time("main", CompareAgainst::Nothing));
+
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_group_summary() {
+        let mut group = BenchmarkGroup::default();
+        let changes = [
+            (10.0, Some(ChangeDirection::Regressed)),
+            (20.0, Some(ChangeDirection::Improved)),
+            (30.0, Some(ChangeDirection::NotSignificant)),
+            (40.0, None),
+        ];
+        for (i, (time, change_direction)) in changes.iter().enumerate() {
+            let id = BenchmarkId::new("group".to_owned(), Some(i.to_string()), None, None);
+            let location = MeasurementLocation {
+                file: PathBuf::from("measurement_200101000000.cbor"),
+                offset: None,
+            };
+            let stats = stats_with_change(*time, change_direction.clone());
+            let benchmark = Benchmark::new(PathBuf::from("group"), location, stats);
+            group.benchmarks.insert(id, benchmark);
+        }
+        let expected = GroupSummary {
+            benchmark_count: 4,
+            total_time: 100.0,
+            regressed_count: 1,
+            improved_count: 1,
+        };
+        assert_eq!(expected, group.summary());
+        assert_eq!(GroupSummary::default(), BenchmarkGroup::default().summary());
+    }
+
+    #[test]
+    fn test_duplicate_group_ids() {
+        let empty_model = || {
+            let data_directory = std::env::temp_dir().join("cargo-criterion-test-nonexistent");
+            Model::load_group(data_directory, PathBuf::from("main"), "none")
+        };
+        let mut model = empty_model();
+        model.allow_duplicate_ids(vec!["shared_*".to_owned()], false);
+        for group in ["shared_grp", "grp"] {
+            model.add_benchmark_group("first", group);
+            model.check_benchmark_group("second", group);
+        }
+        // Only the --deny-duplicate-ids mode records the duplicates to fail the run.
+        assert!(model.duplicate_ids().is_empty());
+        assert!(model.record_duplicate_id("grp"));
+        assert!(!model.record_duplicate_id("shared_grp"));
+
+        let mut model = empty_model();
+        model.allow_duplicate_ids(vec!["shared_*".to_owned()], true);
+        model.add_benchmark_group("first", "shared_grp");
+        model.check_benchmark_group("first", "shared_grp");
+        assert!(model.duplicate_ids().is_empty());
+        model.check_benchmark_group("second", "shared_grp");
+        model.check_benchmark_group("third", "shared_grp");
+        assert_eq!(["shared_grp"], model.duplicate_ids());
+    }
+
+    #[test]
+    fn test_sorted_benchmarks() {
+        let data_directory = std::env::temp_dir().join("cargo-criterion-test-nonexistent");
+        let mut model = Model::load_group(data_directory, PathBuf::from("main"), "none");
+        // (group, function, time, change), in the order they were run.
+        let benchmarks = [
+            ("zeta", "only", 20.0, Some(-0.2)),
+            ("alpha", "slow", 30.0, Some(0.1)),
+            ("alpha", "fast", 10.0, Some(0.5)),
+            ("alpha", "tie", 10.0, None),
+            ("beta", "only", 20.0, Some(-0.2)),
+        ];
+        for (group, function, time, change) in benchmarks {
+            let id = BenchmarkId::new(group.to_owned(), Some(function.to_owned()), None, None);
+            let location = MeasurementLocation {
+                file: PathBuf::from(MEASUREMENT),
+                offset: None,
+            };
+            let mut stats = stats_with_change(time, None);
+            stats.changes = change.map(|change| ChangeEstimates {
+                mean: exact_estimate(change),
+                median: exact_estimate(change),
+                slope: None,
+            });
+            let benchmark = Benchmark::new(PathBuf::from(group), location, stats);
+            model
+                .groups
+                .entry(group.to_owned())
+                .or_insert_with(BenchmarkGroup::default)
+                .benchmarks
+                .insert(id, benchmark);
+        }
+        let sorted = |order| {
+            model
+                .sorted_benchmarks(order)
+                .into_iter()
+                .map(|(group, ids)| {
+                    let ids: Vec<String> = ids.iter().map(|id| id.as_title().to_owned()).collect();
+                    (group.to_owned(), ids)
+                })
+                .collect::<Vec<_>>()
+        };
+        let expected = |groups: &[(&str, &[&str])]| {
+            groups
+                .iter()
+                .map(|(group, ids)| {
+                    let ids = ids.iter().map(|id| format!("{}/{}", group, id)).collect();
+                    (group.to_string(), ids)
+                })
+                .collect::<Vec<(String, Vec<String>)>>()
+        };
+
+        assert_eq!(
+            expected(&[
+                ("zeta", &["only"]),
+                ("alpha", &["slow", "fast", "tie"]),
+                ("beta", &["only"]),
+            ]),
+            sorted(SortOrder::Insertion)
+        );
+        assert_eq!(
+            expected(&[
+                ("alpha", &["fast", "slow", "tie"]),
+                ("beta", &["only"]),
+                ("zeta", &["only"]),
+            ]),
+            sorted(SortOrder::Name)
+        );
+        // Ties keep the order the benchmarks were run in.
+        assert_eq!(
+            expected(&[
+                ("alpha", &["slow", "fast", "tie"]),
+                ("zeta", &["only"]),
+                ("beta", &["only"]),
+            ]),
+            sorted(SortOrder::Time)
+        );
+        // Benchmarks without a change to sort by go last.
+        assert_eq!(
+            expected(&[
+                ("alpha", &["fast", "slow", "tie"]),
+                ("zeta", &["only"]),
+                ("beta", &["only"]),
+            ]),
+            sorted(SortOrder::Change)
+        );
+    }
+
+    #[test]
+    fn test_tally_counts() {
+        let mut tally = BenchmarkTally::default();
+        tally.record("grp/a/1", true);
+        tally.record("grp/a/10", false);
+        tally.record("grp/b/1", false);
+        tally.record("other", true);
+        assert_eq!(
+            RunCounts {
+                run: 2,
+                filtered_out: 2
+            },
+            tally.counts()
+        );
+    }
+
+    #[test]
+    fn test_tally_counts_each_benchmark_once() {
+        let mut tally = BenchmarkTally::default();
+        // Reported as skipped, then run anyway.
+        tally.record("grp/a", false);
+        tally.record("grp/a", true);
+        // Run, then reported as skipped again.
+        tally.record("grp/b", true);
+        tally.record("grp/b", false);
+        tally.record("grp/c", false);
+        tally.record("grp/c", false);
+        assert_eq!(
+            RunCounts {
+                run: 2,
+                filtered_out: 1
+            },
+            tally.counts()
+        );
+    }
+
+    #[test]
+    fn test_is_group_directory_name() {
+        assert!(is_group_directory_name("grp", "grp"));
+        assert!(is_group_directory_name("grp_2", "grp"));
+        assert!(is_group_directory_name("grp_13", "grp"));
+        assert!(!is_group_directory_name("grp_x", "grp"));
+        assert!(!is_group_directory_name("grp_", "grp"));
+        assert!(!is_group_directory_name("grp2", "grp"));
+        assert!(!is_group_directory_name("gr", "grp"));
+    }
+
+    #[test]
+    fn test_hashed_directory_name() {
+        let id = |function_id: Option<&str>, value_str: Option<&str>| {
+            BenchmarkId::new(
+                "grp".to_owned(),
+                function_id.map(ToOwned::to_owned),
+                value_str.map(ToOwned::to_owned),
+                None,
+            )
+        };
+        // Changing the hash would lose track of the benchmarks stored with it.
+        let name = hashed_directory_name(&id(Some("sum"), Some("10")));
+        assert_eq!("b462a8487e4f0084", name);
+        assert_ne!(name, hashed_directory_name(&id(Some("sum/10"), None)));
+        assert_ne!(
+            hashed_directory_name(&id(None, None)),
+            hashed_directory_name(&id(Some(""), None))
+        );
+    }
+
+    #[test]
+    fn test_prune_stored_data_deletes_oldest_measurements_first() {
+        let data_directory = test_directory("prune");
+        let write_benchmark = |name: &str, measurements: &[&str], baseline: Option<&str>| {
+            let benchmark_dir = data_directory.join("main").join(name);
+            std::fs::create_dir_all(&benchmark_dir).unwrap();
+            for measurement in measurements {
+                std::fs::write(benchmark_dir.join(measurement), [0u8; 100]).unwrap();
+            }
+            let mut record = benchmark_record(name, measurements.last().unwrap());
+            record.baselines = baseline
+                .map(|file| {
+                    let location = MeasurementLocation {
+                        file: PathBuf::from(file),
+                        offset: None,
+                    };
+                    ("old".to_owned(), location)
+                })
+                .into_iter()
+                .collect();
+            save_benchmark_record(&benchmark_dir.join("benchmark.cbor"), &record).unwrap();
+        };
+        write_benchmark(
+            "a",
+            &[
+                "measurement_200101000000.cbor",
+                "measurement_200103000000.cbor",
+                "measurement_200105000000.cbor",
+            ],
+            Some("measurement_200101000000.cbor"),
+        );
+        write_benchmark(
+            "b",
+            &[
+                "measurement_200102000000.cbor",
+                "measurement_200104000000.cbor",
+            ],
+            None,
+        );
+        let exists =
+            |name: &str, file: &str| data_directory.join("main").join(name).join(file).is_file();
+        let summary = prune_stored_data(&data_directory, RetentionPolicy::KeepAll, false).unwrap();
+        assert_eq!(0, summary.deleted_measurements);
+
+        // Only the oldest measurement which isn't a baseline needs to go to get under the budget.
+        let total_bytes =
+            prune_stored_data(&data_directory, RetentionPolicy::MaxBytes(u64::MAX), false)
+                .unwrap()
+                .remaining_bytes;
+
+        // A dry run only reports what it would delete.
+        let summary =
+            prune_stored_data(&data_directory, RetentionPolicy::MaxBytes(0), true).unwrap();
+        assert_eq!(2, summary.deleted_measurements);
+        assert_eq!(total_bytes, summary.remaining_bytes + summary.deleted_bytes);
+        assert!(exists("b", "measurement_200102000000.cbor"));
+        assert!(exists("a", "measurement_200103000000.cbor"));
+
+        let summary = prune_stored_data(
+            &data_directory,
+            RetentionPolicy::MaxBytes(total_bytes - 50),
+            false,
+        )
+        .unwrap();
+        assert_eq!(1, summary.deleted_measurements);
+        assert_eq!(100, summary.deleted_bytes);
+        assert!(exists("a", "measurement_200101000000.cbor"));
+        assert!(!exists("b", "measurement_200102000000.cbor"));
+        assert!(exists("a", "measurement_200103000000.cbor"));
+
+        // The latest measurements and baselines are kept even if that leaves the data over budget.
+        let summary =
+            prune_stored_data(&data_directory, RetentionPolicy::MaxBytes(0), false).unwrap();
+        assert_eq!(1, summary.deleted_measurements);
+        assert!(!exists("a", "measurement_200103000000.cbor"));
+        assert!(exists("a", "measurement_200101000000.cbor"));
+        assert!(exists("a", "measurement_200105000000.cbor"));
+        assert!(exists("b", "measurement_200104000000.cbor"));
+        assert!(summary.remaining_bytes > 0);
+
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_stored_data_between_formats() {
+        let data_directory = test_directory("migrate");
+        let benchmark_dir = path!(&data_directory, "main", "a");
+        std::fs::create_dir_all(&benchmark_dir).unwrap();
+        for (file, time, datetime) in [
+            ("measurement_200101000000.cbor", 1.0, "2020-01-01T00:00:00Z"),
+            ("measurement_200102000000.cbor", 2.0, "2020-01-02T00:00:00Z"),
+        ] {
+            let mut stats = stats_with_change(time, None);
+            stats.datetime = datetime.parse().unwrap();
+            save_measurement(&benchmark_dir.join(file), &stats).unwrap();
+        }
+        let benchmark_path = benchmark_dir.join("benchmark.cbor");
+        let record = benchmark_record("a", "measurement_200102000000.cbor");
+        save_benchmark_record(&benchmark_path, &record).unwrap();
+        let latest_mean = || {
+            let record = load_benchmark_record(&benchmark_path).unwrap();
+            let stats = load_measurement_at(&benchmark_dir, &record.latest()).unwrap();
+            (
+                record.latest_offset.is_some(),
+                stats.estimates.mean.point_estimate,
+            )
+        };
+
+        let summary = migrate_stored_data(&data_directory, StorageFormat::Log, false).unwrap();
+        assert_eq!(
+            (1, 2),
+            (summary.migrated_benchmarks, summary.converted_measurements)
+        );
+        assert!(benchmark_dir.join(MEASUREMENT_LOG).is_file());
+        assert!(!benchmark_dir.join("measurement_200101000000.cbor").exists());
+        assert_eq!((true, 2.0), latest_mean());
+
+        // Migrating again finds nothing to convert.
+        let summary = migrate_stored_data(&data_directory, StorageFormat::Log, false).unwrap();
+        assert_eq!(
+            (0, 1),
+            (summary.migrated_benchmarks, summary.unchanged_benchmarks)
+        );
+
+        let summary = migrate_stored_data(&data_directory, StorageFormat::Files, false).unwrap();
+        assert_eq!(2, summary.converted_measurements);
+        assert!(!benchmark_dir.join(MEASUREMENT_LOG).exists());
+        assert_eq!((false, 2.0), latest_mean());
+
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_data_lock_is_only_taken_over_from_a_dead_process() {
+        let data_directory = test_directory("lock");
+        std::fs::create_dir_all(&data_directory).unwrap();
+        let lock_path = DataLock::lock_path(&data_directory);
+
+        // This process is running, so its lock is respected.
+        std::fs::write(&lock_path, std::process::id().to_string()).unwrap();
+        assert!(DataLock::acquire(&data_directory).is_err());
+        assert!(migrate_stored_data(&data_directory, StorageFormat::Log, false).is_err());
+        assert!(lock_path.is_file());
+
+        // No process can have an ID this large.
+        std::fs::write(&lock_path, u32::MAX.to_string()).unwrap();
+        let lock = DataLock::acquire(&data_directory).unwrap();
+        assert_eq!(
+            std::process::id().to_string(),
+            std::fs::read_to_string(&lock_path).unwrap()
+        );
+        assert!(DataLock::acquire(&data_directory).is_err());
+        drop(lock);
+        assert!(!lock_path.exists());
+
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_load_uses_index_until_out_of_date() {
+        let data_directory = test_directory("index");
+        let write = |group: &str, time: f64| {
+            let benchmark_dir = path!(&data_directory, "main", group);
+            write_benchmark(&benchmark_dir, group, &stats_with_change(time, None));
+        };
+        let load = || load_model(&data_directory);
+        let mean = |model: &Model, group: &str| {
+            let benchmarks = &model.groups[group].benchmarks;
+            benchmarks
+                .values()
+                .next()
+                .unwrap()
+                .latest_stats
+                .estimates
+                .mean
+                .point_estimate
+        };
+        write("a", 1.0);
+        load();
+        let index_path = path!(&data_directory, "main", HISTORY_INDEX);
+        assert!(index_path.is_file());
+
+        // Change the estimate in the index alone, to tell whether the index was used.
+        let mut index: HistoryIndex =
+            serde_cbor::from_reader(File::open(&index_path).unwrap()).unwrap();
+        let benchmark = index.benchmarks.values_mut().next().unwrap();
+        benchmark.stats.estimates.mean.point_estimate = 5.0;
+        let file = OpenOptions::new().write(true).open(&index_path).unwrap();
+        file.set_len(0).unwrap();
+        serde_cbor::to_writer(file, &index).unwrap();
+        assert_eq!(5.0, mean(&load(), "a"));
+
+        // A new benchmark leaves the index out of date, so everything is loaded from the files
+        // again and the index is rebuilt.
+        write("b", 2.0);
+        let model = load();
+        assert_eq!(1.0, mean(&model, "a"));
+        assert_eq!(2.0, mean(&model, "b"));
+        let index: HistoryIndex =
+            serde_cbor::from_reader(File::open(&index_path).unwrap()).unwrap();
+        assert_eq!(2, index.benchmarks.len());
+        assert!(index.is_current(&data_directory.join("main")));
+
+        // So does rewriting a benchmark's record.
+        write("a", 3.0);
+        assert_eq!(3.0, mean(&load(), "a"));
+
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_check_stored_data() {
+        let data_directory = test_directory("doctor");
+        let file = PathBuf::from(MEASUREMENT);
+        let write_record = |group: &str| {
+            let benchmark_dir = path!(&data_directory, "main", group);
+            std::fs::create_dir_all(&benchmark_dir).unwrap();
+            let record = benchmark_record(group, MEASUREMENT);
+            save_benchmark_record(&benchmark_dir.join("benchmark.cbor"), &record).unwrap();
+            benchmark_dir
+        };
+        let healthy = write_record("healthy");
+        save_measurement(&healthy.join(&file), &stats_with_change(1.0, None)).unwrap();
+        write_record("missing");
+        let unreadable = write_record("unreadable");
+        std::fs::write(unreadable.join(&file), b"not cbor").unwrap();
+        let broken_record = path!(&data_directory, "main", "broken");
+        std::fs::create_dir_all(&broken_record).unwrap();
+        std::fs::write(broken_record.join("benchmark.cbor"), b"not cbor").unwrap();
+        let orphan = path!(&data_directory, "main", "orphan");
+        std::fs::create_dir_all(&orphan).unwrap();
+        save_measurement(&orphan.join(&file), &stats_with_change(1.0, None)).unwrap();
+
+        let health = check_stored_data(&data_directory);
+        std::fs::remove_dir_all(&data_directory).unwrap();
+        assert_eq!(4, health.benchmark_count);
+        assert_eq!(3, health.measurement_count);
+        let mut problems: Vec<_> = (health.problems.iter())
+            .map(|problem| match problem {
+                DataProblem::UnreadableRecord(_) => "unreadable record",
+                DataProblem::MissingMeasurement { .. } => "missing measurement",
+                DataProblem::UnreadableMeasurement(_) => "unreadable measurement",
+                DataProblem::Orphan(path) => {
+                    assert_eq!(&orphan.join(&file), path);
+                    "orphan"
+                }
+            })
+            .collect();
+        problems.sort_unstable();
+        assert_eq!(
+            vec![
+                "missing measurement",
+                "orphan",
+                "unreadable measurement",
+                "unreadable record"
+            ],
+            problems
+        );
+        let corrupt_count = (health.problems.iter())
+            .filter(|problem| problem.is_corruption())
+            .count();
+        assert_eq!(3, corrupt_count);
+    }
+
+    #[test]
+    fn test_summary_storage_drops_samples() {
+        let data_directory = test_directory("summary");
+        let iterations: Vec<f64> = (1..=10).map(f64::from).collect();
+        let values: Vec<f64> = iterations.iter().map(|&n| n * 100.0).collect();
+        let avg_values: Vec<f64> = iterations.iter().map(|_| 100.0).collect();
+        let config = crate::analysis::BenchmarkConfig {
+            nresamples: 100,
+            ..Default::default()
+        };
+        let measured_data = crate::analysis::analysis(
+            &config,
+            None,
+            crate::analysis::MeasuredValues {
+                iteration_count: &iterations,
+                sample_values: &values,
+                avg_values: &avg_values,
+            },
+            None,
+            crate::connection::SamplingMethod::Linear,
+        );
+
+        let stored = |sample_storage: SampleStorage| {
+            let mut model = load_model(&data_directory);
+            model.sample_storage = sample_storage;
+            let mut id = BenchmarkId::new(format!("{:?}", sample_storage), None, None, None);
+            model.add_benchmark_id("bench", &mut id);
+            model
+                .benchmark_complete(&id, &measured_data, &[], "bench", None)
+                .unwrap();
+            model.groups[&id.group_id].benchmarks[&id]
+                .load_latest()
+                .unwrap()
+        };
+        let full = stored(SampleStorage::Full);
+        let summary = stored(SampleStorage::Summary);
+        std::fs::remove_dir_all(&data_directory).unwrap();
+        assert_eq!(avg_values, full.avg_values);
+        assert!(!summary.has_samples());
+        assert!(summary.iterations.is_empty() && summary.values.is_empty());
+        assert_eq!(
+            full.estimates.mean.point_estimate,
+            summary.estimates.mean.point_estimate
+        );
+    }
+
+    #[test]
+    fn test_datetime_from_mtime() {
+        let data_directory = test_directory("mtime");
+        let benchmark_dir = path!(&data_directory, "main", "a");
+        std::fs::create_dir_all(&benchmark_dir).unwrap();
+        let undated = benchmark_dir.join(MEASUREMENT);
+        save_measurement(&undated, &stats_with_change(1.0, None)).unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        File::options()
+            .write(true)
+            .open(&undated)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let mut dated = stats_with_change(2.0, None);
+        dated.datetime =
+            (std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_500_000_000)).into();
+        save_measurement(&benchmark_dir.join("measurement_200102000000.cbor"), &dated).unwrap();
+
+        // Without the flag, the undated measurement sorts first, at the Unix epoch.
+        let history = load_history_from(&benchmark_dir, false);
+        assert_eq!(unknown_datetime(), history[0].datetime);
+        assert_eq!(dated.datetime, history[1].datetime);
+        // With it, the undated measurement takes its file's modification time, which is later.
+        let history = load_history_from(&benchmark_dir, true);
+        assert_eq!(dated.datetime, history[0].datetime);
+        assert_eq!(DateTime::<Utc>::from(modified), history[1].datetime);
+        assert_eq!(1.0, history[1].estimates.mean.point_estimate);
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_loaded_benchmarks_keep_only_estimates() {
+        let data_directory = test_directory("estimates");
+        let mut stats = stats_with_change(1.0, None);
+        stats.iterations = vec![1.0, 2.0, 3.0];
+        stats.values = vec![1.0, 2.0, 3.0];
+        stats.avg_values = vec![1.0, 1.0, 1.0];
+        write_benchmark(&path!(&data_directory, "main", "a"), "a", &stats);
+
+        let model = load_model(&data_directory);
+        let benchmark = model.groups["a"].benchmarks.values().next().unwrap();
+        assert!(!benchmark.latest_stats.has_samples());
+        assert_eq!(1.0, benchmark.latest_stats.estimates.mean.point_estimate);
+        let loaded = benchmark.load_latest().unwrap();
+        assert_eq!(stats.avg_values, loaded.avg_values);
+
+        drop(model);
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    #[test]
+    fn test_missing_benchmarks() {
+        let data_directory = test_directory("groups");
+        let run = |functions: &[&str], deny: bool| {
+            let mut model = load_model(&data_directory);
+            model.deny_missing_benchmarks(deny);
+            for (i, function) in functions.iter().enumerate() {
+                let id =
+                    BenchmarkId::new("grp".to_owned(), Some((*function).to_owned()), None, None);
+                // Filtered out benchmarks still count as part of the group.
+                model.record_benchmark(&id, i % 2 == 0);
+            }
+            let shrunk = model.check_group_members("grp");
+            assert_eq!(None, model.check_group_members("other"));
+            shrunk
+        };
+
+        assert_eq!(None, run(&["a", "b", "c"], false));
+        assert_eq!(None, run(&["c", "a", "b"], false));
+        let missing = |titles: &[&str]| titles.iter().map(|&title| title.to_owned()).collect();
+        assert_eq!(Some((3, 2, missing(&["grp/b"]))), run(&["a", "c"], true));
+        // With --no-missing-benchmarks, the previous benchmarks stay on record.
+        assert_eq!(
+            Some((3, 2, missing(&["grp/c", "grp/b"]))),
+            run(&["a", "d"], false)
+        );
+        // Without it, the new benchmarks replace them.
+        assert_eq!(None, run(&["a", "d"], false));
+        // A renamed benchmark leaves the group the same size.
+        assert_eq!(None, run(&["a", "e"], false));
+
+        std::fs::remove_dir_all(&data_directory).unwrap();
+    }
+
+    /// Load a tree of stored data with benchmarks nested much deeper than `--max-open-files`, with
+    /// a limit on open files which loading it with every directory kept open would exceed. The
+    /// limit applies to the whole process, so the loading is done by this test running again in a
+    /// child process.
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_load_with_low_open_file_limit() {
+        use std::os::raw::c_int;
+        use std::os::unix::process::CommandExt;
+
+        const DEPTH: usize = 40;
+        if let Some(data_directory) = std::env::var_os("CARGO_CRITERION_TEST_DATA") {
+            set_max_open_files(4);
+            let model = load_model(Path::new(&data_directory));
+            assert_eq!(DEPTH, model.groups.len());
+            return;
+        }
+
+        let data_directory = test_directory("fds");
+        let mut benchmark_dir = data_directory.join("main");
+        for depth in 0..DEPTH {
+            benchmark_dir.push(format!("d{}", depth));
+            let group = format!("group{}", depth);
+            write_benchmark(&benchmark_dir, &group, &stats_with_change(1.0, None));
+        }
+
+        #[repr(C)]
+        struct RLimit {
+            current: u64,
+            maximum: u64,
+        }
+        extern "C" {
+            fn setrlimit(resource: c_int, limit: *const RLimit) -> c_int;
+        }
+        const RLIMIT_NOFILE: c_int = 7;
+
+        let mut command = std::process::Command::new(std::env::current_exe().unwrap());
+        command
+            .args([
+                "model::test::test_load_with_low_open_file_limit",
+                "--exact",
+                "--test-threads=1",
+            ])
+            .env("CARGO_CRITERION_TEST_DATA", &data_directory);
+        unsafe {
+            command.pre_exec(|| {
+                let limit = RLimit {
+                    current: 24,
+                    maximum: 24,
+                };
+                if setrlimit(RLIMIT_NOFILE, &limit) == 0 {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error())
+                }
+            });
+        }
+        let output = command.output().unwrap();
+        std::fs::remove_dir_all(&data_directory).unwrap();
+        assert!(
+            output.status.success(),
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// The peak resident set size of this process in KiB, as the kernel reports it.
+    #[cfg(target_os = "linux")]
+    fn peak_rss_kib() -> u64 {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        let line = (status.lines())
+            .find(|line| line.starts_with("VmHWM:"))
+            .unwrap();
+        line.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    /// Generate the HTML reports of every group in the model, as a run does when each group's
+    /// benchmarks are complete, followed by the index.
+    #[cfg(feature = "plotters_backend")]
+    fn generate_reports(model: &Model, output_directory: &Path) {
+        use crate::connection::{AxisScale, Connection, PlotConfiguration};
+        use crate::report::{Report, ReportContext};
+        use std::io::Read;
+        use std::net::{TcpListener, TcpStream};
+
+        // The values are formatted in a fixed unit, so that the formatter doesn't need a real
+        // benchmark target to ask; it only has to get through the handshake.
+        crate::format::set_time_unit(crate::config::TimeUnit::Nanoseconds);
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let benchmark = std::thread::spawn(move || {
+            let mut socket = TcpStream::connect(address).unwrap();
+            socket
+                .write_all(b"Criterion\x00\x03\x06\x00\x01\x00\x01")
+                .unwrap();
+            let mut runner_hello = [0; 18];
+            socket.read_exact(&mut runner_hello).unwrap();
+        });
+        let mut connection = Connection::new(listener.accept().unwrap().0).unwrap();
+        benchmark.join().unwrap();
+        let formatter = crate::value_formatter::ValueFormatter::new(&mut connection);
+
+        let plotter = crate::plot::PlotGenerator {
+            backend: crate::plot::PlottersBackend::new(&crate::config::Colors::default()),
+        };
+        let html = crate::html::Html::new(
+            Some(Box::new(plotter)),
+            SortOrder::Insertion,
+            None,
+            None,
+            None,
+        );
+        let context = ReportContext {
+            output_directory: output_directory.to_owned(),
+            plot_config: PlotConfiguration {
+                summary_scale: AxisScale::Linear,
+            },
+        };
+        for (group_id, group) in &model.groups {
+            html.summarize(&context, group_id, group, &formatter);
+        }
+        html.final_summary(&context, model);
+    }
+
+    /// A benchmark of the memory used to generate the reports, rather than a test of what they
+    /// contain. The reports of a synthetic suite are generated twice, from few and from many stored
+    /// samples per benchmark, each in a child process so that its peak RSS can be measured on its
+    /// own. The samples are loaded one benchmark at a time, so the peak shouldn't grow by more
+    /// than the samples of a few benchmarks. Run it with
+    /// `cargo test --release test_report_generation_peak_rss -- --ignored --nocapture`.
+    #[cfg(all(target_os = "linux", feature = "plotters_backend"))]
+    #[test]
+    #[ignore]
+    fn test_report_generation_peak_rss() {
+        const GROUPS: usize = 10;
+        const BENCHMARKS_PER_GROUP: usize = 20;
+        const SAMPLE_COUNTS: [usize; 2] = [100, 5000];
+
+        if let Some(data_directory) = std::env::var_os("CARGO_CRITERION_TEST_DATA") {
+            let data_directory = Path::new(&data_directory);
+            let model = load_model(data_directory);
+            let loading = peak_rss_kib();
+            // Reset the peak, so that only generating the reports is measured.
+            std::fs::write("/proc/self/clear_refs", "5").unwrap();
+            generate_reports(&model, &data_directory.join("reports"));
+            println!("peak RSS in KiB: {} {}", loading, peak_rss_kib());
+            return;
+        }
+
+        let mut peaks = Vec::new();
+        for sample_count in SAMPLE_COUNTS {
+            let data_directory = test_directory(&format!("peak-rss-{}", sample_count));
+            for group in 0..GROUPS {
+                for function in 0..BENCHMARKS_PER_GROUP {
+                    let time = 1000.0 + 10.0 * function as f64;
+                    let (group, function) = (format!("group{}", group), format!("f{}", function));
+                    let mut stats = stats_with_change(time, None);
+                    stats.iterations = (1..=sample_count).map(|i| i as f64).collect();
+                    stats.avg_values = (0..sample_count)
+                        .map(|i| time * (1.0 + (i % 7) as f64 / 100.0))
+                        .collect();
+                    stats.values = (stats.iterations.iter().zip(&stats.avg_values))
+                        .map(|(iterations, value)| iterations * value)
+                        .collect();
+                    let benchmark_dir = data_directory.join("main").join(&group).join(&function);
+                    std::fs::create_dir_all(&benchmark_dir).unwrap();
+                    save_measurement(&benchmark_dir.join(MEASUREMENT), &stats).unwrap();
+                    let mut record = benchmark_record(&group, MEASUREMENT);
+                    record.id.function_id = Some(function);
+                    save_benchmark_record(&benchmark_dir.join("benchmark.cbor"), &record).unwrap();
+                }
+            }
+
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "model::test::test_report_generation_peak_rss",
+                    "--exact",
+                    "--ignored",
+                    "--nocapture",
+                    "--test-threads=1",
+                ])
+                .env("CARGO_CRITERION_TEST_DATA", &data_directory)
+                .output()
+                .unwrap();
+            std::fs::remove_dir_all(&data_directory).unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(
+                output.status.success(),
+                "{}{}",
+                stdout,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            // The line follows the test harness's own "test ... " on the same line.
+            let line = (stdout.lines())
+                .find_map(|line| line.split("peak RSS in KiB: ").nth(1))
+                .unwrap();
+            let peak: Vec<u64> = line.split(' ').map(|kib| kib.parse().unwrap()).collect();
+            println!(
+                "{} benchmarks with {} samples each: peak RSS {} KiB loading the model, {} KiB \
+                 generating the reports",
+                GROUPS * BENCHMARKS_PER_GROUP,
+                sample_count,
+                peak[0],
+                peak[1]
+            );
+            peaks.push(peak[1]);
+        }
+
+        // Each sample is stored as its iteration count, its total time and its time per iteration.
+        let extra_kib_per_benchmark = (SAMPLE_COUNTS[1] - SAMPLE_COUNTS[0]) as u64 * 3 * 8 / 1024;
+        let growth = peaks[1].saturating_sub(peaks[0]);
+        assert!(
+            growth < 5 * extra_kib_per_benchmark,
+            "The peak RSS grew by {} KiB with {} KiB more samples per benchmark",
+            growth,
+            extra_kib_per_benchmark
+        );
+    }
 }
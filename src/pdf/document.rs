@@ -0,0 +1,222 @@
+//! A minimal writer for PDF documents made of text, lines and filled rectangles. Only the standard
+//! PDF fonts are used, so no fonts need to be embedded, and every viewer can display the result.
+
+use std::fmt::Write;
+
+/// The width of an A4 page, in points.
+pub const PAGE_WIDTH: f64 = 595.0;
+/// The height of an A4 page, in points.
+pub const PAGE_HEIGHT: f64 = 842.0;
+
+/// The fonts that can be used on a page.
+#[derive(Debug, Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+    /// Every character is 0.6 times the font size wide, which makes it easy to line up columns.
+    Monospace,
+}
+impl Font {
+    const ALL: [Font; 3] = [Font::Regular, Font::Bold, Font::Monospace];
+
+    fn resource_name(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Monospace => "F3",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Monospace => "Courier",
+        }
+    }
+}
+
+/// A page of the document. Coordinates are in points, measured from the bottom-left corner.
+#[derive(Debug, Default)]
+pub struct Page {
+    content: String,
+}
+impl Page {
+    pub fn new() -> Page {
+        Page::default()
+    }
+
+    /// Write a line of text with its baseline starting at the given point.
+    pub fn text(&mut self, x: f64, y: f64, font: Font, size: f64, text: &str) {
+        writeln!(
+            self.content,
+            "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET",
+            font.resource_name(),
+            size,
+            x,
+            y,
+            escape_text(text)
+        )
+        .unwrap();
+    }
+
+    /// Draw a straight line. If `dashed` is set, the line is drawn as a series of short dashes.
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64), width: f64, dashed: bool) {
+        let dash = if dashed { "[3 3] 0 d" } else { "[] 0 d" };
+        writeln!(
+            self.content,
+            "{} {:.2} w {:.2} {:.2} m {:.2} {:.2} l S",
+            dash, width, from.0, from.1, to.0, to.1
+        )
+        .unwrap();
+    }
+
+    /// Fill a rectangle whose bottom-left corner is at the given point.
+    pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        writeln!(
+            self.content,
+            "{:.2} {:.2} {:.2} {:.2} re f",
+            x, y, width, height
+        )
+        .unwrap();
+    }
+
+    /// Set the color that following lines and rectangles are drawn in, from black (0.0) to white
+    /// (1.0).
+    pub fn set_gray(&mut self, level: f64) {
+        writeln!(self.content, "{:.2} G {:.2} g", level, level).unwrap();
+    }
+}
+
+/// Escape a string for use in a PDF string literal. The standard fonts can only be relied on for
+/// printable ASCII, so anything else is replaced with a question mark.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// A document made up of pages, which can be written out as a PDF file.
+#[derive(Debug, Default)]
+pub struct Document {
+    pages: Vec<Page>,
+}
+impl Document {
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    pub fn add_page(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    /// Write out the document in PDF format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Objects are numbered from one, in this order: the catalog, the page tree, the fonts and
+        // then each page followed by its content stream.
+        let first_page = 3 + Font::ALL.len();
+        let page_refs: Vec<String> = (0..self.pages.len())
+            .map(|i| format!("{} 0 R", first_page + 2 * i))
+            .collect();
+        let font_refs: Vec<String> = Font::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, font)| format!("/{} {} 0 R", font.resource_name(), 3 + i))
+            .collect();
+
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_refs.join(" "),
+                self.pages.len()
+            ),
+        ];
+        for font in &Font::ALL {
+            objects.push(format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font.base_font()
+            ));
+        }
+        for (i, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                font_refs.join(" "),
+                first_page + 2 * i + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.content.len(),
+                page.content
+            ));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object).unwrap();
+        }
+        let xref_offset = out.len();
+        write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).unwrap();
+        for offset in offsets {
+            writeln!(out, "{:010} 00000 n ", offset).unwrap();
+        }
+        write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        )
+        .unwrap();
+        out.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!("f\\(x\\) \\\\ 2", escape_text("f(x) \\ 2"));
+        assert_eq!("5 ?s", escape_text("5 µs"));
+    }
+
+    #[test]
+    fn test_cross_reference_offsets() {
+        let mut document = Document::new();
+        for title in &["first", "second"] {
+            let mut page = Page::new();
+            page.text(10.0, 20.0, Font::Bold, 12.0, title);
+            page.line((0.0, 0.0), (10.0, 10.0), 1.0, true);
+            document.add_page(page);
+        }
+        let pdf = String::from_utf8(document.to_bytes()).unwrap();
+
+        let startxref = pdf.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref_offset: usize = pdf[startxref..].lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref_offset..].starts_with("xref\n0 10\n"));
+
+        // Every entry of the cross-reference table must point at the object with its number.
+        let entries = pdf[xref_offset..].lines().skip(3).take(9);
+        for (i, entry) in entries.enumerate() {
+            assert_eq!(20, entry.len() + 1);
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.ends_with("%%EOF\n"));
+    }
+}
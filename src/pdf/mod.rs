@@ -0,0 +1,314 @@
+//! Module for rendering the stored benchmark results as a single PDF file, for archiving. The
+//! document starts with a summary of every benchmark, followed by a page for each benchmark with
+//! its estimates and a plot of its samples.
+
+mod document;
+
+use self::document::{Document, Font, Page, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::config::SortOrder;
+use crate::estimate::Statistic;
+use crate::format;
+use crate::model::{Model, SavedStatistics};
+use crate::report::{Report, ReportContext};
+use anyhow::Context;
+
+const MARGIN: f64 = 50.0;
+const SUMMARY_ROWS_PER_PAGE: usize = 50;
+const MAX_NAME_LEN: usize = 50;
+const MAX_DESCRIPTION_LINE_LEN: usize = 90;
+
+/// Writes `report.pdf` in the report directory once the benchmarks are complete.
+pub struct PdfReport {
+    sort_by: SortOrder,
+    change_ratio_above: Option<f64>,
+    title: Option<String>,
+    description: Option<String>,
+}
+impl PdfReport {
+    pub fn new(
+        sort_by: SortOrder,
+        change_ratio_above: Option<f64>,
+        title: Option<String>,
+        description: Option<String>,
+    ) -> PdfReport {
+        PdfReport {
+            sort_by,
+            change_ratio_above,
+            title,
+            description,
+        }
+    }
+
+    fn document(&self, model: &Model) -> Document {
+        let mut benchmarks = Vec::new();
+        for (group_id, ids) in model.sorted_benchmarks(self.sort_by) {
+            for id in ids {
+                benchmarks.push((id.as_title(), &model.groups[group_id].benchmarks[id]));
+            }
+        }
+
+        let mut pages = Vec::new();
+        let summary_page_count = benchmarks.chunks(SUMMARY_ROWS_PER_PAGE).len().max(1);
+        for page_number in 0..summary_page_count {
+            let mut page = Page::new();
+            let mut y = PAGE_HEIGHT - MARGIN - 18.0;
+            if page_number == 0 {
+                let title = self.title.as_deref().unwrap_or("Benchmark Report");
+                page.text(MARGIN, y, Font::Bold, 18.0, title);
+                y -= 20.0;
+                if let Some(description) = &self.description {
+                    for line in wrap(description, MAX_DESCRIPTION_LINE_LEN) {
+                        page.text(MARGIN, y, Font::Regular, 10.0, &line);
+                        y -= 13.0;
+                    }
+                    y -= 7.0;
+                }
+                let summary = format!(
+                    "{} benchmarks in {} groups",
+                    benchmarks.len(),
+                    model.groups.len()
+                );
+                page.text(MARGIN, y, Font::Regular, 10.0, &summary);
+                y -= 30.0;
+            }
+            let header = format!("{:<50} {:>10}  {}", "Benchmark", "Time", "Change");
+            page.text(MARGIN, y, Font::Monospace, 9.0, &header);
+            y -= 4.0;
+            page.line((MARGIN, y), (PAGE_WIDTH - MARGIN, y), 0.5, false);
+            y -= 12.0;
+
+            let rows = benchmarks
+                .iter()
+                .skip(page_number * SUMMARY_ROWS_PER_PAGE)
+                .take(SUMMARY_ROWS_PER_PAGE);
+            for (title, benchmark) in rows {
+                let stats = &benchmark.latest_stats;
+                let change = match &stats.changes {
+                    Some(changes) => {
+                        format::time_change(changes.mean.point_estimate, self.change_ratio_above)
+                    }
+                    None => String::new(),
+                };
+                let row = format!(
+                    "{:<50} {:>10}  {}",
+                    shorten(title),
+                    format::time(stats.estimates.typical().point_estimate),
+                    change
+                );
+                page.text(MARGIN, y, Font::Monospace, 9.0, &row);
+                y -= 13.0;
+            }
+            pages.push(page);
+        }
+
+        for (title, benchmark) in benchmarks {
+            // The samples are only needed to draw this page, so load them one at a time.
+            let stats = match benchmark.load_latest() {
+                Ok(stats) => stats,
+                Err(e) => {
+                    error!("{:?}", e);
+                    benchmark.latest_stats.clone()
+                }
+            };
+            pages.push(self.benchmark_page(title, &stats));
+        }
+
+        let page_count = pages.len();
+        let mut document = Document::new();
+        for (i, mut page) in pages.into_iter().enumerate() {
+            let footer = format!("Page {} of {}", i + 1, page_count);
+            page.text(MARGIN, MARGIN / 2.0, Font::Regular, 8.0, &footer);
+            document.add_page(page);
+        }
+        document
+    }
+
+    fn benchmark_page(&self, title: &str, stats: &SavedStatistics) -> Page {
+        let mut page = Page::new();
+        let mut y = PAGE_HEIGHT - MARGIN - 16.0;
+        page.text(MARGIN, y, Font::Bold, 16.0, title);
+        y -= 18.0;
+        let measured_at = format!(
+            "Measured at {}",
+            stats.datetime.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        page.text(MARGIN, y, Font::Regular, 10.0, &measured_at);
+        y -= 30.0;
+
+        let header = format!(
+            "{:<10} {:>12} {:>12} {:>12}",
+            "", "Lower bound", "Estimate", "Upper bound"
+        );
+        page.text(MARGIN, y, Font::Monospace, 10.0, &header);
+        y -= 14.0;
+        for &(statistic, name) in &[
+            (Statistic::Slope, "Slope"),
+            (Statistic::Mean, "Mean"),
+            (Statistic::Median, "Median"),
+            (Statistic::StdDev, "Std. Dev."),
+            (Statistic::MedianAbsDev, "MAD"),
+        ] {
+            if let Some(estimate) = stats.estimates.get(statistic) {
+                let row = format!(
+                    "{:<10} {:>12} {:>12} {:>12}",
+                    name,
+                    format::time(estimate.confidence_interval.lower_bound),
+                    format::time(estimate.point_estimate),
+                    format::time(estimate.confidence_interval.upper_bound),
+                );
+                page.text(MARGIN, y, Font::Monospace, 10.0, &row);
+                y -= 14.0;
+            }
+        }
+        if let Some(changes) = &stats.changes {
+            let mean = &changes.mean;
+            let row = format!(
+                "{:<10} {:>12} {:>12} {:>12}",
+                "Change",
+                format::time_change(
+                    mean.confidence_interval.lower_bound,
+                    self.change_ratio_above
+                ),
+                format::time_change(mean.point_estimate, self.change_ratio_above),
+                format::time_change(
+                    mean.confidence_interval.upper_bound,
+                    self.change_ratio_above
+                ),
+            );
+            page.text(MARGIN, y, Font::Monospace, 10.0, &row);
+            y -= 14.0;
+        }
+        y -= 20.0;
+
+        if stats.has_samples() {
+            page.text(
+                MARGIN,
+                y,
+                Font::Bold,
+                11.0,
+                "Average Time per Iteration of Each Sample",
+            );
+            draw_sample_plot(&mut page, stats, MARGIN, MARGIN + 30.0, y - 30.0);
+        } else {
+            page.text(
+                MARGIN,
+                y,
+                Font::Regular,
+                10.0,
+                "The raw samples of this measurement were not stored, so they can't be plotted.",
+            );
+        }
+        page
+    }
+}
+impl Report for PdfReport {
+    fn final_summary(&self, context: &ReportContext, model: &Model) {
+        let document = self.document(model);
+        let path = context.output_directory.join("report.pdf");
+        try_else_return!(std::fs::create_dir_all(&context.output_directory)
+            .with_context(|| format!("Failed to create directory {:?}", context.output_directory)));
+        try_else_return!(std::fs::write(&path, document.to_bytes())
+            .with_context(|| format!("Failed to write PDF report {:?}", path)));
+        info!("Wrote PDF report to {:?}", path);
+    }
+}
+
+/// Shorten a benchmark name to fit in the summary table.
+fn shorten(title: &str) -> String {
+    if title.chars().count() <= MAX_NAME_LEN {
+        title.to_owned()
+    } else {
+        let mut short: String = title.chars().take(MAX_NAME_LEN - 3).collect();
+        short.push_str("...");
+        short
+    }
+}
+
+/// Split text into lines of at most `width` characters, breaking at spaces where possible. Words
+/// longer than a line are split between lines.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            let line_len = line.chars().count();
+            if line_len > 0 && line_len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            while word.len() > width {
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Plot the average time per iteration of each sample, along with the typical estimate and its
+/// confidence interval. The plot fills the width of the page between `bottom` and `top`.
+fn draw_sample_plot(page: &mut Page, stats: &SavedStatistics, x: f64, bottom: f64, top: f64) {
+    let typical = stats.estimates.typical();
+    let interval = &typical.confidence_interval;
+    let values = &stats.avg_values;
+
+    let min = values.iter().copied().fold(interval.lower_bound, f64::min);
+    let max = values.iter().copied().fold(interval.upper_bound, f64::max);
+    let padding = ((max - min) * 0.05).max(max.abs() * 1e-3);
+    let (min, max) = (min - padding, max + padding);
+    let (unit_ns, unit) = format::time_unit(max);
+
+    // Leave room on the left for the labels of the time axis.
+    let left = x + 50.0;
+    let right = PAGE_WIDTH - MARGIN;
+    let to_x = |i: usize| left + (right - left) * (i as f64 + 0.5) / values.len() as f64;
+    let to_y = |ns: f64| bottom + (top - bottom) * (ns - min) / (max - min);
+
+    page.set_gray(0.0);
+    page.line((left, bottom), (left, top), 0.8, false);
+    page.line((left, bottom), (right, bottom), 0.8, false);
+    for i in 0..=4 {
+        let ns = min + (max - min) * f64::from(i) / 4.0;
+        let label = format!("{:>8} {}", format::short(ns / unit_ns), unit);
+        page.text(x - 10.0, to_y(ns) - 3.0, Font::Monospace, 8.0, &label);
+        page.line((left - 3.0, to_y(ns)), (left, to_y(ns)), 0.8, false);
+    }
+    let sample_label = format!("Sample (1 - {})", values.len());
+    page.text(left, bottom - 14.0, Font::Regular, 9.0, &sample_label);
+
+    page.set_gray(0.6);
+    page.line(
+        (left, to_y(typical.point_estimate)),
+        (right, to_y(typical.point_estimate)),
+        1.0,
+        false,
+    );
+    for &bound in &[interval.lower_bound, interval.upper_bound] {
+        page.line((left, to_y(bound)), (right, to_y(bound)), 0.6, true);
+    }
+
+    page.set_gray(0.0);
+    for (i, &value) in values.iter().enumerate() {
+        page.fill_rect(to_x(i) - 1.5, to_y(value) - 1.5, 3.0, 3.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("Release 1.2 on an\nARM laptop", 12),
+            ["Release 1.2", "on an", "ARM laptop"]
+        );
+        assert_eq!(wrap("a abcdefghij", 4), ["a", "abcd", "efgh", "ij"]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Counting the instructions executed by a benchmark target, using the Linux perf_event interface.
+//!
+//! Instruction counts are much less sensitive to other activity on the machine than wall-clock
+//! time, which makes them useful on shared CI machines. Only user-space instructions are counted,
+//! which is permitted for the user's own processes as long as the `kernel.perf_event_paranoid`
+//! sysctl is 2 or lower (the default on most distributions).
+
+use std::fs::File;
+use std::io;
+
+/// A hardware counter of the user-space instructions executed by another process since the counter
+/// was attached.
+///
+/// The count covers everything the process does, not just the benchmark's own code: it includes
+/// the overhead of Criterion.rs's measurement loop and of sending the results back, and the
+/// instructions of every thread or child process started after the counter was attached, such as
+/// worker threads started by the benchmark. No baseline is subtracted, so the count is mostly
+/// useful for comparing runs of the same benchmark rather than as an absolute cost.
+pub struct InstructionCounter {
+    file: File,
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sys {
+    use std::os::raw::c_long;
+
+    #[cfg(target_arch = "x86_64")]
+    pub const SYS_PERF_EVENT_OPEN: c_long = 298;
+    #[cfg(target_arch = "aarch64")]
+    pub const SYS_PERF_EVENT_OPEN: c_long = 241;
+
+    pub const PERF_TYPE_HARDWARE: u32 = 0;
+    pub const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    pub const PERF_FLAG_FD_CLOEXEC: c_long = 8;
+
+    // Bits of the flags field.
+    /// Also count the threads and child processes created after the counter is opened, adding
+    /// their instructions to the same count. Threads which already exist aren't counted, so the
+    /// counter should be attached before the target starts any.
+    pub const INHERIT: u64 = 1 << 1;
+    pub const EXCLUDE_KERNEL: u64 = 1 << 5;
+    pub const EXCLUDE_HV: u64 = 1 << 6;
+
+    /// The first version of `struct perf_event_attr` (PERF_ATTR_SIZE_VER5). None of the fields
+    /// after `flags` are needed, so they're left as zeroes.
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct PerfEventAttr {
+        pub type_: u32,
+        pub size: u32,
+        pub config: u64,
+        pub sample_period: u64,
+        pub sample_type: u64,
+        pub read_format: u64,
+        pub flags: u64,
+        pub rest: [u64; 8],
+    }
+
+    extern "C" {
+        pub fn syscall(number: c_long, ...) -> c_long;
+    }
+}
+
+impl InstructionCounter {
+    /// Start counting the instructions executed by the process with the given ID and by the
+    /// threads and processes it starts from now on. Attach it as soon as the process is spawned.
+    /// If it has already started other threads, the instructions of those aren't counted.
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    pub fn attach(pid: u32) -> io::Result<InstructionCounter> {
+        use std::os::raw::c_long;
+        use std::os::unix::io::FromRawFd;
+
+        let attr = sys::PerfEventAttr {
+            type_: sys::PERF_TYPE_HARDWARE,
+            size: std::mem::size_of::<sys::PerfEventAttr>() as u32,
+            config: sys::PERF_COUNT_HW_INSTRUCTIONS,
+            flags: sys::INHERIT | sys::EXCLUDE_KERNEL | sys::EXCLUDE_HV,
+            ..Default::default()
+        };
+        // Count on any CPU, without a group leader.
+        let fd = unsafe {
+            sys::syscall(
+                sys::SYS_PERF_EVENT_OPEN,
+                &attr as *const sys::PerfEventAttr,
+                pid as c_long,
+                -1 as c_long,
+                -1 as c_long,
+                sys::PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let file = unsafe { File::from_raw_fd(fd as i32) };
+        Ok(InstructionCounter { file })
+    }
+
+    #[cfg(not(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )))]
+    pub fn attach(_pid: u32) -> io::Result<InstructionCounter> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "counting instructions is only supported on Linux",
+        ))
+    }
+
+    /// The number of instructions executed since the counter was attached.
+    pub fn read(&self) -> io::Result<u64> {
+        use std::io::Read;
+
+        let mut count = [0u8; 8];
+        (&self.file).read_exact(&mut count)?;
+        Ok(u64::from_ne_bytes(count))
+    }
+}
@@ -1,8 +1,9 @@
-use crate::plot::gnuplot_backend::{gnuplot_escape, Colors, DEFAULT_FONT, LINEWIDTH};
+use crate::plot::gnuplot_backend::{gnuplot_escape, Colors, DEFAULT_FONT, LINEWIDTH, POINT_SIZE};
 use crate::plot::Size;
-use crate::plot::{FilledCurve as FilledArea, LineCurve};
+use crate::plot::{FilledCurve as FilledArea, HistoryExtremes, LineCurve};
 use criterion_plot::prelude::*;
 
+#[allow(clippy::too_many_arguments)]
 pub fn history_plot(
     colors: &Colors,
     title: &str,
@@ -10,6 +11,7 @@ pub fn history_plot(
     point_estimate: LineCurve,
     confidence_interval: FilledArea,
     ids: &[String],
+    extremes: HistoryExtremes,
     unit: &str,
 ) -> Figure {
     let mut figure = Figure::new();
@@ -55,5 +57,23 @@ pub fn history_plot(
                 .set(Label("Confidence Interval"))
         },
     );
+    let markers = [
+        (extremes.fastest, "Fastest mean", colors.mild_outlier),
+        (extremes.slowest, "Slowest mean", colors.severe_outlier),
+    ];
+    for &(index, label, color) in &markers {
+        figure.plot(
+            Points {
+                x: &point_estimate.xs[index..=index],
+                y: &point_estimate.ys[index..=index],
+            },
+            |c| {
+                c.set(color)
+                    .set(Label(format!("{} ({})", label, ids[index])))
+                    .set(PointType::FilledCircle)
+                    .set(POINT_SIZE)
+            },
+        );
+    }
     figure
 }
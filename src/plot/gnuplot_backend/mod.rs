@@ -3,8 +3,8 @@ use crate::estimate::Statistic;
 use crate::format;
 use crate::plot::Size;
 use crate::plot::{
-    FilledCurve as FilledArea, Line, LineCurve, PlottingBackend, Points as PointPlot, Rectangle,
-    VerticalLine,
+    FilledCurve as FilledArea, HistoryExtremes, Line, LineCurve, PlottingBackend,
+    Points as PointPlot, Rectangle, VerticalLine,
 };
 use crate::report::{BenchmarkId, ValueType};
 use criterion_plot::prelude::*;
@@ -103,16 +103,47 @@ impl From<Size> for criterion_plot::Size {
 }
 
 pub struct Gnuplot {
-    process_list: Vec<Child>,
+    // The running gnuplot processes, and the plot that each one is drawing.
+    process_list: Vec<(PathBuf, Child)>,
     colors: Colors,
+    // If set, a plot which gnuplot fails to draw is an error rather than a warning.
+    strict: bool,
+    // The plots which gnuplot failed to draw, if strict. The run fails once the report is done.
+    failed_plots: Vec<PathBuf>,
 }
 impl Gnuplot {
-    pub fn new(colors: &crate::config::Colors) -> Gnuplot {
+    pub fn new(colors: &crate::config::Colors, strict: bool) -> Gnuplot {
         Gnuplot {
             process_list: vec![],
             colors: colors.into(),
+            strict,
+            failed_plots: vec![],
         }
     }
+
+    /// Start a gnuplot process drawing the figure to the given path.
+    fn draw(&mut self, mut figure: Figure, path: PathBuf) {
+        match figure.set(Output(path.clone())).draw() {
+            Ok(child) => self.process_list.push((path, child)),
+            Err(e) => self.plot_failed(&path, &format!("Failed to run gnuplot: {}", e)),
+        }
+    }
+
+    /// Report that a plot couldn't be drawn. The plot is left out of the report and the rest of
+    /// the report is still generated; if strict plotting was requested, the runner then fails.
+    fn plot_failed(&mut self, path: &Path, message: &str) {
+        if self.strict {
+            error!("Failed to draw plot {:?}: {}", path, message);
+            self.failed_plots.push(path.to_owned());
+        } else {
+            warn!(target: "plot-failure",
+                "Failed to draw plot {:?}, so it will be missing from the report: {}",
+                path, message
+            );
+        }
+        // Don't leave a partially-written plot behind.
+        let _ = std::fs::remove_file(path);
+    }
 }
 impl PlottingBackend for Gnuplot {
     fn abs_distribution(
@@ -127,7 +158,7 @@ impl PlottingBackend for Gnuplot {
         bootstrap_area: FilledArea,
         point_estimate: Line,
     ) {
-        let mut figure = distributions::abs_distribution(
+        let figure = distributions::abs_distribution(
             &self.colors,
             id,
             statistic,
@@ -138,8 +169,7 @@ impl PlottingBackend for Gnuplot {
             point_estimate,
         );
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap());
+        self.draw(figure, path);
     }
 
     fn rel_distribution(
@@ -154,7 +184,7 @@ impl PlottingBackend for Gnuplot {
         point_estimate: Line,
         noise_threshold: Rectangle,
     ) {
-        let mut figure = distributions::rel_distribution(
+        let figure = distributions::rel_distribution(
             &self.colors,
             id,
             statistic,
@@ -166,8 +196,7 @@ impl PlottingBackend for Gnuplot {
         );
 
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn iteration_times(
@@ -181,7 +210,7 @@ impl PlottingBackend for Gnuplot {
         current_times: PointPlot,
         base_times: Option<PointPlot>,
     ) {
-        let mut figure = iteration_times::iteration_times(
+        let figure = iteration_times::iteration_times(
             &self.colors,
             id,
             size,
@@ -192,8 +221,7 @@ impl PlottingBackend for Gnuplot {
         );
 
         debug_script(&file_path, &figure);
-        self.process_list
-            .push(figure.set(Output(file_path)).draw().unwrap())
+        self.draw(figure, file_path);
     }
 
     fn regression(
@@ -209,7 +237,7 @@ impl PlottingBackend for Gnuplot {
         regression: Line,
         confidence_interval: FilledArea,
     ) {
-        let mut figure = regression::regression(
+        let figure = regression::regression(
             &self.colors,
             id,
             size,
@@ -223,8 +251,7 @@ impl PlottingBackend for Gnuplot {
         );
 
         debug_script(&file_path, &figure);
-        self.process_list
-            .push(figure.set(Output(file_path)).draw().unwrap())
+        self.draw(figure, file_path);
     }
 
     fn regression_comparison(
@@ -241,7 +268,7 @@ impl PlottingBackend for Gnuplot {
         base_regression: Line,
         base_confidence_interval: FilledArea,
     ) {
-        let mut figure = regression::regression_comparison(
+        let figure = regression::regression_comparison(
             &self.colors,
             id,
             size,
@@ -255,8 +282,7 @@ impl PlottingBackend for Gnuplot {
             base_confidence_interval,
         );
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn pdf_full(
@@ -273,7 +299,7 @@ impl PlottingBackend for Gnuplot {
         fences: (VerticalLine, VerticalLine, VerticalLine, VerticalLine),
         points: (PointPlot, PointPlot, PointPlot),
     ) {
-        let mut figure = pdf::pdf_full(
+        let figure = pdf::pdf_full(
             &self.colors,
             id,
             size,
@@ -288,8 +314,7 @@ impl PlottingBackend for Gnuplot {
         );
 
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn pdf_thumbnail(
@@ -300,10 +325,9 @@ impl PlottingBackend for Gnuplot {
         mean: Line,
         pdf: FilledArea,
     ) {
-        let mut figure = pdf::pdf_thumbnail(&self.colors, size, unit, mean, pdf);
+        let figure = pdf::pdf_thumbnail(&self.colors, size, unit, mean, pdf);
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn pdf_comparison(
@@ -318,7 +342,7 @@ impl PlottingBackend for Gnuplot {
         base_mean: Line,
         base_pdf: FilledArea,
     ) {
-        let mut figure = pdf::pdf_comparison(
+        let figure = pdf::pdf_comparison(
             &self.colors,
             id,
             size,
@@ -330,8 +354,7 @@ impl PlottingBackend for Gnuplot {
             base_pdf,
         );
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn t_test(
@@ -342,28 +365,26 @@ impl PlottingBackend for Gnuplot {
         t: VerticalLine,
         t_distribution: FilledArea,
     ) {
-        let mut figure = t_test::t_test(&self.colors, id, size, t, t_distribution);
+        let figure = t_test::t_test(&self.colors, id, size, t, t_distribution);
 
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn line_comparison(
         &mut self,
         path: PathBuf,
         title: &str,
-        unit: &str,
+        y_label: &str,
         value_type: ValueType,
         axis_scale: AxisScale,
         lines: &[(Option<&String>, LineCurve)],
     ) {
-        let mut figure =
-            summary::line_comparison(&self.colors, title, unit, value_type, axis_scale, lines);
+        let figure =
+            summary::line_comparison(&self.colors, title, y_label, value_type, axis_scale, lines);
 
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn violin(
@@ -374,10 +395,9 @@ impl PlottingBackend for Gnuplot {
         axis_scale: AxisScale,
         lines: &[(&str, LineCurve)],
     ) {
-        let mut figure = summary::violin(&self.colors, title, unit, axis_scale, lines);
+        let figure = summary::violin(&self.colors, title, unit, axis_scale, lines);
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn history_plot(
@@ -388,30 +408,48 @@ impl PlottingBackend for Gnuplot {
         point_estimate: LineCurve,
         confidence_interval: FilledArea,
         ids: &[String],
+        extremes: HistoryExtremes,
         unit: &str,
     ) {
-        let mut figure = history::history_plot(
+        let figure = history::history_plot(
             &self.colors,
             id.as_title(),
             size,
             point_estimate,
             confidence_interval,
             ids,
+            extremes,
             unit,
         );
         debug_script(&path, &figure);
-        self.process_list
-            .push(figure.set(Output(path)).draw().unwrap())
+        self.draw(figure, path);
     }
 
     fn wait(&mut self) {
         let start = std::time::Instant::now();
         let child_count = self.process_list.len();
-        for child in self.process_list.drain(..) {
+        for (path, child) in std::mem::take(&mut self.process_list) {
             match child.wait_with_output() {
-                Ok(ref out) if out.status.success() => {}
-                Ok(out) => error!("Error in Gnuplot: {}", String::from_utf8_lossy(&out.stderr)),
-                Err(e) => error!("Got IO error while waiting for Gnuplot to complete: {}", e),
+                Ok(ref out) if out.status.success() => {
+                    if !out.stderr.is_empty() {
+                        info!(
+                            "Gnuplot output while drawing {:?}: {}",
+                            path,
+                            String::from_utf8_lossy(&out.stderr).trim()
+                        );
+                    }
+                }
+                Ok(out) => self.plot_failed(
+                    &path,
+                    &format!(
+                        "Error in Gnuplot: {}",
+                        String::from_utf8_lossy(&out.stderr).trim()
+                    ),
+                ),
+                Err(e) => self.plot_failed(
+                    &path,
+                    &format!("Got IO error while waiting for Gnuplot to complete: {}", e),
+                ),
             }
         }
         let elapsed = &start.elapsed();
@@ -421,4 +459,8 @@ impl PlottingBackend for Gnuplot {
             format::time(crate::DurationExt::to_nanos(elapsed) as f64)
         );
     }
+
+    fn failed_plots(&self) -> Vec<PathBuf> {
+        self.failed_plots.clone()
+    }
 }
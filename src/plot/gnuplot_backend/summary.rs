@@ -10,7 +10,7 @@ use criterion_plot::prelude::*;
 pub fn line_comparison(
     colors: &Colors,
     title: &str,
-    unit: &str,
+    y_label: &str,
     value_type: ValueType,
     axis_scale: AxisScale,
     lines: &[(Option<&String>, LineCurve)],
@@ -31,22 +31,20 @@ pub fn line_comparison(
                 .set(Order::SampleText)
                 .set(Position::Outside(Vertical::Top, Horizontal::Right))
         })
-        .set(Title(format!("{}: Comparison", gnuplot_escape(title))))
+        .set(Title(gnuplot_escape(title)))
         .configure(Axis::BottomX, |a| {
             a.set(Label(format!("Input{}", input_suffix)))
                 .set(axis_scale.to_gnuplot())
         });
 
-    let mut i = 0;
-
     figure.configure(Axis::LeftY, |a| {
         a.configure(Grid::Major, |g| g.show())
             .configure(Grid::Minor, |g| g.hide())
-            .set(Label(format!("Average time ({})", unit)))
+            .set(Label(y_label.to_owned()))
             .set(axis_scale.to_gnuplot())
     });
 
-    for (name, curve) in lines {
+    for (i, (name, curve)) in lines.iter().enumerate() {
         let function_name = name.map(|string| gnuplot_escape(string));
 
         figure
@@ -75,8 +73,6 @@ pub fn line_comparison(
                         .set(colors.comparison_colors[i % colors.comparison_colors.len()])
                 },
             );
-
-        i += 1;
     }
 
     figure
@@ -91,6 +87,10 @@ pub fn violin(
 ) -> Figure {
     let tics = || (0..).map(|x| (f64::from(x)) + 0.5);
     let size: criterion_plot::Size = Size(1280, 200 + (25 * lines.len())).into();
+    let max = lines
+        .iter()
+        .flat_map(|(_, curve)| curve.xs.iter())
+        .fold(0.0f64, |max, &x| max.max(x));
     let mut figure = Figure::new();
     figure
         .set(Font(DEFAULT_FONT))
@@ -99,7 +99,7 @@ pub fn violin(
         .configure(Axis::BottomX, |a| {
             a.configure(Grid::Major, |g| g.show())
                 .configure(Grid::Minor, |g| g.hide())
-                .set(Range::Limits(0., max as f64 * one[0]))
+                .set(Range::Limits(0., max))
                 .set(Label(format!("Average time ({})", unit)))
                 .set(axis_scale.to_gnuplot())
         })
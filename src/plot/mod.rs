@@ -8,7 +8,7 @@ pub use gnuplot_backend::Gnuplot;
 #[cfg(feature = "plotters_backend")]
 pub use plotters_backend::PlottersBackend;
 
-use crate::connection::AxisScale;
+use crate::connection::{AxisScale, Throughput};
 use crate::estimate::Statistic;
 use crate::estimate::{ConfidenceInterval, Estimate};
 use crate::kde;
@@ -38,7 +38,6 @@ pub struct PlotContext<'a> {
     pub id: &'a BenchmarkId,
     pub context: &'a ReportContext,
     pub size: Option<Size>,
-    pub is_thumbnail: bool,
 }
 
 const KDE_POINTS: usize = 500;
@@ -55,6 +54,14 @@ impl<'a> PlotContext<'a> {
         )
     }
 
+    pub fn per_element_path(&self) -> PathBuf {
+        path!(
+            &self.context.output_directory,
+            self.id.as_directory_name(),
+            "per_element.svg"
+        )
+    }
+
     pub fn violin_path(&self) -> PathBuf {
         path!(
             &self.context.output_directory,
@@ -64,6 +71,15 @@ impl<'a> PlotContext<'a> {
     }
 }
 
+/// The number of elements processed per iteration, for benchmarks with an elements-based
+/// throughput.
+pub fn element_count(throughput: &Option<Throughput>) -> Option<u64> {
+    match throughput {
+        Some(Throughput::Elements(elements)) => Some(*elements),
+        _ => None,
+    }
+}
+
 pub trait Plotter {
     fn pdf(
         &mut self,
@@ -163,15 +179,27 @@ pub trait Plotter {
         value_type: ValueType,
     );
 
-    fn violin(
+    /// Plot the mean time per element against the number of elements, for each function in the
+    /// group. Benchmarks without an elements-based throughput are left out.
+    fn per_element_comparison(
         &mut self,
         ctx: PlotContext<'_>,
         formatter: &ValueFormatter,
         all_curves: &[(&BenchmarkId, &Benchmark)],
     );
 
+    /// Draw the distribution of the time per iteration of each benchmark. The samples of each
+    /// benchmark are taken from `all_samples` one at a time, and only its curve is kept.
+    fn violin(
+        &mut self,
+        ctx: PlotContext<'_>,
+        formatter: &ValueFormatter,
+        all_samples: &mut dyn Iterator<Item = (&BenchmarkId, Vec<f64>)>,
+    );
+
     fn t_test(&mut self, ctx: PlotContext<'_>, comparison: &ComparisonData);
 
+    #[allow(clippy::too_many_arguments)]
     fn history(
         &mut self,
         ctx: PlotContext<'_>,
@@ -179,10 +207,157 @@ pub trait Plotter {
         point_estimate: &[f64],
         lower_bound: &[f64],
         ids: &[String],
+        extremes: HistoryExtremes,
         unit: &str,
     );
 
     fn wait(&mut self);
+
+    /// The plots which couldn't be drawn and had to be treated as errors.
+    fn failed_plots(&self) -> Vec<PathBuf>;
+}
+
+/// A plotter which draws nothing. It stands in for a plotting backend when none is usable, so that
+/// the HTML reports can still be generated without their plots.
+pub struct NoPlots;
+impl Plotter for NoPlots {
+    fn pdf(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn pdf_thumbnail(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn pdf_comparison(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+        _comparison: &ComparisonData,
+    ) {
+    }
+    fn pdf_comparison_thumbnail(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+        _comparison: &ComparisonData,
+    ) {
+    }
+    fn iteration_times(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn iteration_times_thumbnail(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn iteration_times_comparison(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+        _comparison: &ComparisonData,
+    ) {
+    }
+    fn iteration_times_comparison_thumbnail(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+        _comparison: &ComparisonData,
+    ) {
+    }
+    fn regression(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn regression_thumbnail(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn regression_comparison(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+        _comparison: &ComparisonData,
+    ) {
+    }
+    fn regression_comparison_thumbnail(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+        _comparison: &ComparisonData,
+    ) {
+    }
+    fn abs_distributions(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _measurements: &MeasurementData<'_>,
+        _formatter: &ValueFormatter<'_>,
+    ) {
+    }
+    fn rel_distributions(&mut self, _ctx: PlotContext<'_>, _comparison: &ComparisonData) {}
+    fn line_comparison(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _formatter: &ValueFormatter,
+        _all_curves: &[(&BenchmarkId, &Benchmark)],
+        _value_type: ValueType,
+    ) {
+    }
+    fn per_element_comparison(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _formatter: &ValueFormatter,
+        _all_curves: &[(&BenchmarkId, &Benchmark)],
+    ) {
+    }
+    fn violin(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _formatter: &ValueFormatter,
+        _all_samples: &mut dyn Iterator<Item = (&BenchmarkId, Vec<f64>)>,
+    ) {
+    }
+    fn t_test(&mut self, _ctx: PlotContext<'_>, _comparison: &ComparisonData) {}
+    #[allow(clippy::too_many_arguments)]
+    fn history(
+        &mut self,
+        _ctx: PlotContext<'_>,
+        _upper_bound: &[f64],
+        _point_estimate: &[f64],
+        _lower_bound: &[f64],
+        _ids: &[String],
+        _extremes: HistoryExtremes,
+        _unit: &str,
+    ) {
+    }
+    fn wait(&mut self) {}
+    fn failed_plots(&self) -> Vec<PathBuf> {
+        vec![]
+    }
 }
 
 // Some types representing things we might want to draw
@@ -225,6 +400,14 @@ pub struct Rectangle {
     bottom: f64,
 }
 
+/// The entries of a benchmark's history with the lowest and highest mean time, which are marked
+/// on the history plot.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryExtremes {
+    pub fastest: usize,
+    pub slowest: usize,
+}
+
 pub trait PlottingBackend {
     fn abs_distribution(
         &mut self,
@@ -349,7 +532,7 @@ pub trait PlottingBackend {
         &mut self,
         path: PathBuf,
         title: &str,
-        unit: &str,
+        y_label: &str,
         value_type: ValueType,
         axis_scale: AxisScale,
         lines: &[(Option<&String>, LineCurve)],
@@ -373,10 +556,13 @@ pub trait PlottingBackend {
         point_estimate: LineCurve,
         confidence_interval: FilledCurve,
         ids: &[String],
+        extremes: HistoryExtremes,
         unit: &str,
     );
 
     fn wait(&mut self);
+
+    fn failed_plots(&self) -> Vec<PathBuf>;
 }
 
 pub struct PlotGenerator<B: PlottingBackend> {
@@ -431,8 +617,8 @@ impl<B: PlottingBackend> PlotGenerator<B> {
         let len = end - start;
 
         let distribution_curve = LineCurve {
-            xs: &*kde_xs,
-            ys: &*ys,
+            xs: &kde_xs,
+            ys: &ys,
         };
         let bootstrap_area = FilledCurve {
             xs: &kde_xs[start..end],
@@ -519,7 +705,7 @@ impl<B: PlottingBackend> PlotGenerator<B> {
             )
         };
 
-        let distribution_curve = LineCurve { xs: &*xs, ys: &*ys };
+        let distribution_curve = LineCurve { xs: &xs, ys: &ys };
         let confidence_interval = FilledCurve {
             xs: &xs[start..end],
             ys_1: &ys[start..end],
@@ -569,7 +755,7 @@ impl<B: PlottingBackend> PlotGenerator<B> {
 
         let points = Points {
             xs: &xs,
-            ys: &scaled_y,
+            ys: scaled_y,
         };
         self.backend.iteration_times(
             ctx.id,
@@ -609,11 +795,11 @@ impl<B: PlottingBackend> PlotGenerator<B> {
 
         let current_points = Points {
             xs: &current_xs,
-            ys: &scaled_current_y,
+            ys: scaled_current_y,
         };
         let base_points = Points {
             xs: &base_xs,
-            ys: &scaled_base_y,
+            ys: scaled_base_y,
         };
         self.backend.iteration_times(
             ctx.id,
@@ -817,15 +1003,15 @@ impl<B: PlottingBackend> PlotGenerator<B> {
             format!("Iterations (x 10^{})", exponent)
         };
 
-        let (xs, ys) = kde::sweep(&scaled_avg_times, KDE_POINTS, None);
+        let (xs, ys) = kde::sweep(scaled_avg_times, KDE_POINTS, None);
         let (lost, lomt, himt, hist) = avg_times.fences();
         let mut fences = [lost, lomt, himt, hist];
         let _ = formatter.scale_values(typical, &mut fences);
         let [lost, lomt, himt, hist] = fences;
 
         let pdf = FilledCurve {
-            xs: &*xs,
-            ys_1: &*ys,
+            xs: &xs,
+            ys_1: &ys,
             ys_2: &vec![0.0; ys.len()],
         };
         let mean = VerticalLine { x: mean };
@@ -920,8 +1106,8 @@ impl<B: PlottingBackend> PlotGenerator<B> {
             end: Point { x: mean, y: mean_y },
         };
         let pdf = FilledCurve {
-            xs: &*xs,
-            ys_1: &*ys,
+            xs: &xs,
+            ys_1: &ys,
             ys_2: &vec![0.0; ys.len()],
         };
 
@@ -970,8 +1156,8 @@ impl<B: PlottingBackend> PlotGenerator<B> {
             },
         };
         let base_pdf = FilledCurve {
-            xs: &*base_xs,
-            ys_1: &*base_ys,
+            xs: &base_xs,
+            ys_1: &base_ys,
             ys_2: &vec![0.0; base_ys.len()],
         };
 
@@ -986,8 +1172,8 @@ impl<B: PlottingBackend> PlotGenerator<B> {
             },
         };
         let current_pdf = FilledCurve {
-            xs: &*xs,
-            ys_1: &*ys,
+            xs: &xs,
+            ys_1: &ys,
             ys_2: &vec![0.0; base_ys.len()],
         };
 
@@ -1015,8 +1201,8 @@ impl<B: PlottingBackend> PlotGenerator<B> {
 
         let t = VerticalLine { x: t };
         let t_distribution = FilledCurve {
-            xs: &*xs,
-            ys_1: &*ys,
+            xs: &xs,
+            ys_1: &ys,
             ys_2: &vec![0.0; ys.len()],
         };
 
@@ -1032,6 +1218,7 @@ impl<B: PlottingBackend> PlotGenerator<B> {
         point_estimate: &[f64],
         lower_bound: &[f64],
         ids: &[String],
+        extremes: HistoryExtremes,
         file_path: PathBuf,
         unit: &str,
     ) {
@@ -1053,6 +1240,7 @@ impl<B: PlottingBackend> PlotGenerator<B> {
             point_estimate,
             confidence_interval,
             ids,
+            extremes,
             unit,
         );
     }
@@ -1295,7 +1483,7 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
         let max = all_curves
             .iter()
             .map(|(_, bench)| bench.latest_stats.estimates.typical().point_estimate)
-            .fold(::std::f64::NAN, f64::max);
+            .fold(f64::NAN, f64::max);
 
         let mut dummy = [1.0];
         let unit = formatter.scale_values(max, &mut dummy);
@@ -1332,34 +1520,78 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
 
         let lines: Vec<_> = series_data
             .iter()
-            .map(|(name, xs, ys)| (*name, LineCurve { xs: &*xs, ys: &*ys }))
+            .map(|(name, xs, ys)| (*name, LineCurve { xs, ys }))
             .collect();
 
         self.backend.line_comparison(
             ctx.line_comparison_path(),
-            ctx.id.as_title(),
-            &unit,
+            &format!("{}: Comparison", ctx.id.as_title()),
+            &format!("Average time ({})", unit),
             value_type,
             ctx.context.plot_config.summary_scale,
             &lines,
         );
     }
 
-    fn violin(
+    fn per_element_comparison(
         &mut self,
         ctx: PlotContext<'_>,
         formatter: &ValueFormatter,
         all_curves: &[(&BenchmarkId, &Benchmark)],
     ) {
-        let mut kdes = all_curves
+        let mut function_id_to_points = LinkedHashMap::new();
+        for (id, bench) in all_curves {
+            if let Some(elements) = element_count(&bench.latest_stats.throughput) {
+                let time_per_element =
+                    bench.latest_stats.estimates.typical().point_estimate / elements as f64;
+                function_id_to_points
+                    .entry(&id.function_id)
+                    .or_insert_with(Vec::new)
+                    .push((elements as f64, time_per_element));
+            }
+        }
+
+        let max = function_id_to_points
             .iter()
-            .rev()
-            .map(|(id, sample)| {
-                let (x, mut y) = kde::sweep(
-                    Sample::new(&sample.latest_stats.avg_values),
-                    KDE_POINTS,
-                    None,
-                );
+            .flat_map(|(_, points)| points.iter().map(|&(_, y)| y))
+            .fold(f64::NAN, f64::max);
+        let mut dummy = [1.0];
+        let unit = formatter.scale_values(max, &mut dummy);
+
+        let mut series_data = vec![];
+        for (key, mut points) in function_id_to_points {
+            points.sort_by(|&(ax, _), &(bx, _)| {
+                ax.partial_cmp(&bx).unwrap_or(std::cmp::Ordering::Less)
+            });
+            let (xs, mut ys): (Vec<_>, Vec<_>) = points.into_iter().unzip();
+            formatter.scale_values(max, &mut ys);
+            series_data.push((key.as_ref(), xs, ys));
+        }
+
+        let lines: Vec<_> = series_data
+            .iter()
+            .map(|(name, xs, ys)| (*name, LineCurve { xs, ys }))
+            .collect();
+
+        self.backend.line_comparison(
+            ctx.per_element_path(),
+            &format!("{}: Time per element", ctx.id.as_title()),
+            &format!("Average time per element ({})", unit),
+            ValueType::Elements,
+            ctx.context.plot_config.summary_scale,
+            &lines,
+        );
+    }
+
+    fn violin(
+        &mut self,
+        ctx: PlotContext<'_>,
+        formatter: &ValueFormatter,
+        all_samples: &mut dyn Iterator<Item = (&BenchmarkId, Vec<f64>)>,
+    ) {
+        let mut kdes = all_samples
+            .map(|(id, avg_values)| {
+                let (x, mut y) = kde::sweep(Sample::new(&avg_values), KDE_POINTS, None);
                 let y_max = Sample::new(&y).max();
                 for y in y.iter_mut() {
                     *y /= y_max;
@@ -1368,10 +1600,11 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
                 (id.as_title(), x, y)
             })
             .collect::<Vec<_>>();
+        kdes.reverse();
 
         let mut xs = kdes
             .iter()
-            .flat_map(|&(_, ref x, _)| x.iter())
+            .flat_map(|(_, x, _)| x.iter())
             .filter(|&&x| x > 0.);
         let (mut min, mut max) = {
             let &first = xs.next().unwrap();
@@ -1392,7 +1625,7 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
 
         let lines = kdes
             .iter()
-            .map(|(name, xs, ys)| (*name, LineCurve { xs: &*xs, ys: &*ys }))
+            .map(|(name, xs, ys)| (*name, LineCurve { xs, ys }))
             .collect::<Vec<_>>();
 
         self.backend.violin(
@@ -1419,6 +1652,7 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
         point_estimate: &[f64],
         lower_bound: &[f64],
         ids: &[String],
+        extremes: HistoryExtremes,
         unit: &str,
     ) {
         self.history_plot(
@@ -1428,6 +1662,7 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
             point_estimate,
             lower_bound,
             ids,
+            extremes,
             ctx.context.report_path(ctx.id, "history.svg"),
             unit,
         )
@@ -1436,4 +1671,8 @@ impl<B: PlottingBackend> Plotter for PlotGenerator<B> {
     fn wait(&mut self) {
         self.backend.wait();
     }
+
+    fn failed_plots(&self) -> Vec<PathBuf> {
+        self.backend.failed_plots()
+    }
 }
@@ -99,7 +99,7 @@ pub fn rel_distribution(
     point_estimate: Line,
     noise_threshold: RectangleArea,
 ) {
-    let xs_ = Sample::new(&distribution_curve.xs);
+    let xs_ = Sample::new(distribution_curve.xs);
     let x_min = xs_.min();
     let x_max = xs_.max();
 
@@ -1,9 +1,10 @@
-use crate::plot::plotters_backend::{Colors, DEFAULT_FONT};
-use crate::plot::{FilledCurve, LineCurve, Size};
+use crate::plot::plotters_backend::{Colors, DEFAULT_FONT, POINT_SIZE};
+use crate::plot::{FilledCurve, HistoryExtremes, LineCurve, Size};
 use plotters::data::float::pretty_print_float;
 use plotters::prelude::*;
 use std::path::PathBuf;
 
+#[allow(clippy::too_many_arguments)]
 pub fn history(
     colors: &Colors,
     title: &str,
@@ -12,11 +13,17 @@ pub fn history(
     point_estimate: LineCurve,
     confidence_interval: FilledCurve,
     ids: &[String],
+    extremes: HistoryExtremes,
     unit: &str,
 ) {
     let root_area = SVGBackend::new(&path, size.into()).into_drawing_area();
 
-    let x_range = plotters::data::fitting_range(point_estimate.xs.iter());
+    let mut x_range = plotters::data::fitting_range(point_estimate.xs.iter());
+    // With only one entry in the history, the range would be empty.
+    if x_range.start == x_range.end {
+        x_range.start -= 0.5;
+        x_range.end += 0.5;
+    }
     let mut y_range = plotters::data::fitting_range(
         confidence_interval
             .ys_1
@@ -40,7 +47,7 @@ pub fn history(
         .disable_mesh()
         .y_desc(format!("Average time ({})", unit))
         .x_desc("History")
-        .x_label_formatter(&|&v| ids[v as usize].clone())
+        .x_label_formatter(&|&v| ids.get(v.round() as usize).cloned().unwrap_or_default())
         .y_label_formatter(&|&v| pretty_print_float(v, true))
         .x_labels(ids.len())
         .draw()
@@ -84,6 +91,23 @@ pub fn history(
             )
         });
 
+    let markers = [
+        (extremes.fastest, "Fastest mean", &colors.mild_outlier),
+        (extremes.slowest, "Slowest mean", &colors.severe_outlier),
+    ];
+    for &(index, label, color) in &markers {
+        let point = (point_estimate.xs[index], point_estimate.ys[index]);
+        chart
+            .draw_series(std::iter::once(Circle::new(
+                point,
+                POINT_SIZE,
+                color.filled(),
+            )))
+            .unwrap()
+            .label(format!("{} ({})", label, ids[index]))
+            .legend(move |(x, y)| Circle::new((x + 10, y), POINT_SIZE, color.filled()));
+    }
+
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
@@ -1,8 +1,8 @@
 use crate::connection::AxisScale;
 use crate::estimate::Statistic;
 use crate::plot::{
-    FilledCurve, Line, LineCurve, PlottingBackend, Points, Rectangle as RectangleArea, Size,
-    VerticalLine,
+    FilledCurve, HistoryExtremes, Line, LineCurve, PlottingBackend, Points,
+    Rectangle as RectangleArea, Size, VerticalLine,
 };
 use crate::report::{BenchmarkId, ValueType};
 use plotters::prelude::*;
@@ -307,7 +307,7 @@ impl PlottingBackend for PlottersBackend {
         &mut self,
         path: PathBuf,
         title: &str,
-        unit: &str,
+        y_label: &str,
         value_type: ValueType,
         axis_scale: AxisScale,
         lines: &[(Option<&String>, LineCurve)],
@@ -316,7 +316,7 @@ impl PlottingBackend for PlottersBackend {
             &self.colors,
             path,
             title,
-            unit,
+            y_label,
             value_type,
             axis_scale,
             lines,
@@ -342,6 +342,7 @@ impl PlottingBackend for PlottersBackend {
         point_estimate: LineCurve,
         confidence_interval: FilledCurve,
         ids: &[String],
+        extremes: HistoryExtremes,
         unit: &str,
     ) {
         history::history(
@@ -352,9 +353,14 @@ impl PlottingBackend for PlottersBackend {
             point_estimate,
             confidence_interval,
             ids,
+            extremes,
             unit,
         );
     }
 
     fn wait(&mut self) {}
+
+    fn failed_plots(&self) -> Vec<PathBuf> {
+        vec![]
+    }
 }
@@ -10,7 +10,7 @@ pub fn line_comparison(
     colors: &Colors,
     path: PathBuf,
     title: &str,
-    unit: &str,
+    y_label: &str,
     value_type: ValueType,
     axis_scale: AxisScale,
     lines: &[(Option<&String>, LineCurve)],
@@ -21,17 +21,17 @@ pub fn line_comparison(
         plotters::data::fitting_range(lines.iter().flat_map(|(_, curve)| curve.ys.iter()));
     let root_area = SVGBackend::new(&path, SIZE.into())
         .into_drawing_area()
-        .titled(&format!("{}: Comparison", title), (DEFAULT_FONT, 20))
+        .titled(title, (DEFAULT_FONT, 20))
         .unwrap();
 
     match axis_scale {
         AxisScale::Linear => draw_line_comparison_figure(
-            colors, root_area, &unit, x_range, y_range, value_type, lines,
+            colors, root_area, y_label, x_range, y_range, value_type, lines,
         ),
         AxisScale::Logarithmic => draw_line_comparison_figure(
             colors,
             root_area,
-            &unit,
+            y_label,
             LogRange(x_range),
             LogRange(y_range),
             value_type,
@@ -43,7 +43,7 @@ pub fn line_comparison(
 fn draw_line_comparison_figure<XR: AsRangedCoord<Value = f64>, YR: AsRangedCoord<Value = f64>>(
     colors: &Colors,
     root_area: DrawingArea<SVGBackend, Shift>,
-    y_unit: &str,
+    y_label: &str,
     x_range: XR,
     y_range: YR,
     value_type: ValueType,
@@ -66,7 +66,7 @@ fn draw_line_comparison_figure<XR: AsRangedCoord<Value = f64>, YR: AsRangedCoord
         .configure_mesh()
         .disable_mesh()
         .x_desc(format!("Input{}", input_suffix))
-        .y_desc(format!("Average time ({})", y_unit))
+        .y_desc(y_label)
         .draw()
         .unwrap();
 
@@ -81,7 +81,7 @@ fn draw_line_comparison_figure<XR: AsRangedCoord<Value = f64>, YR: AsRangedCoord
             )
             .unwrap();
         if let Some(name) = name {
-            let name: &str = &*name;
+            let name: &str = name;
             series.label(name).legend(move |(x, y)| {
                 Rectangle::new(
                     [(x, y - 5), (x + 20, y + 5)],
@@ -119,9 +119,9 @@ pub fn violin(
         .unwrap();
 
     match axis_scale {
-        AxisScale::Linear => draw_violin_figure(colors, root_area, &unit, x_range, y_range, lines),
+        AxisScale::Linear => draw_violin_figure(colors, root_area, unit, x_range, y_range, lines),
         AxisScale::Logarithmic => {
-            draw_violin_figure(colors, root_area, &unit, LogRange(x_range), y_range, lines)
+            draw_violin_figure(colors, root_area, unit, LogRange(x_range), y_range, lines)
         }
     }
 }
@@ -0,0 +1,307 @@
+//! The checks run by `cargo criterion preflight`, which look for problems with the environment
+//! that would make a run fail or its measurements unreliable, without running anything.
+
+use crate::config::{cargo_program, PlottingBackend, RetentionPolicy, SelfConfig};
+use crate::format;
+use crate::runner;
+use anyhow::Result;
+use std::fmt;
+use std::io::{stdout, Write};
+use std::path::Path;
+use std::process::Command;
+
+/// Without a `--history-max-size`, less free space than this is warned about.
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Pass,
+    /// The run can go ahead, but something may affect it.
+    Warn,
+    /// The run would fail.
+    Fail,
+}
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => f.pad("pass"),
+            Status::Warn => f.pad("warn"),
+            Status::Fail => f.pad("fail"),
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    details: String,
+}
+impl Check {
+    fn new(name: &'static str, status: Status, details: String) -> Check {
+        Check {
+            name,
+            status,
+            details,
+        }
+    }
+}
+
+/// Run every check and print a table of the results. Fails if any check failed.
+pub fn preflight(config: &SelfConfig) -> Result<()> {
+    let checks = vec![
+        check_cargo(),
+        check_criterion_home(&config.criterion_home),
+        check_plotting_backend(config),
+        check_cpu_governor(),
+        check_disk_space(&config.data_directory, config.retention),
+    ];
+
+    let stdout = stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "{:<18}{:<8}Details", "Check", "Status")?;
+    for check in &checks {
+        writeln!(
+            out,
+            "{:<18}{:<8}{}",
+            check.name, check.status, check.details
+        )?;
+    }
+
+    let failed = (checks.iter())
+        .filter(|check| check.status == Status::Fail)
+        .count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} preflight checks failed", failed, checks.len());
+    }
+    Ok(())
+}
+
+fn check_cargo() -> Check {
+    let program = cargo_program();
+    let name = "cargo";
+    match Command::new(&program).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+            Check::new(name, Status::Pass, version)
+        }
+        Ok(output) => Check::new(
+            name,
+            Status::Fail,
+            format!("{:?} --version failed with {}", program, output.status),
+        ),
+        Err(e) => Check::new(
+            name,
+            Status::Fail,
+            format!("{:?} could not be run: {}", program, e),
+        ),
+    }
+}
+
+/// The criterion home must be writable to store the measurements and reports. It's created if it
+/// doesn't exist yet, as a run would.
+fn check_criterion_home(criterion_home: &Path) -> Check {
+    let name = "criterion home";
+    let probe = criterion_home.join(format!(".preflight-{}", std::process::id()));
+    let result = std::fs::create_dir_all(criterion_home)
+        .and_then(|()| std::fs::write(&probe, b""))
+        .and_then(|()| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::new(
+            name,
+            Status::Pass,
+            format!("{:?} is writable", criterion_home),
+        ),
+        Err(e) => Check::new(
+            name,
+            Status::Fail,
+            format!("{:?} is not writable: {}", criterion_home, e),
+        ),
+    }
+}
+
+/// An unusable plotting backend only fails the run with `--require-plots`; otherwise the reports
+/// are generated without plots.
+fn check_plotting_backend(config: &SelfConfig) -> Check {
+    let name = "plotting backend";
+    let result = match config.plotting_backend {
+        PlottingBackend::Gnuplot => runner::gnuplot_available().map(|()| "gnuplot"),
+        PlottingBackend::Plotters => runner::plotters_available().map(|()| "plotters"),
+        PlottingBackend::Auto => {
+            runner::gnuplot_available()
+                .map(|()| "gnuplot")
+                .or_else(|gnuplot_error| {
+                    runner::plotters_available()
+                        .map(|()| "plotters")
+                        .map_err(|plotters_error| {
+                            anyhow::anyhow!("{}, and {}", gnuplot_error, plotters_error)
+                        })
+                })
+        }
+        PlottingBackend::Disabled => {
+            return Check::new(name, Status::Pass, "plotting is disabled".to_owned())
+        }
+    };
+    match result {
+        Ok(backend) => Check::new(name, Status::Pass, format!("{} is usable", backend)),
+        Err(e) => Check::new(
+            name,
+            if config.require_plots {
+                Status::Fail
+            } else {
+                Status::Warn
+            },
+            format!("no backend is usable: {}", e),
+        ),
+    }
+}
+
+/// Any CPU frequency governor other than `performance` can change the clock speed in the middle of
+/// a measurement.
+fn check_cpu_governor() -> Check {
+    let governors = std::fs::read_dir("/sys/devices/system/cpu")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path().join("cpufreq/scaling_governor");
+            std::fs::read_to_string(path).ok()
+        })
+        .map(|governor| governor.trim().to_owned())
+        .collect();
+    governor_check(governors)
+}
+
+/// Check the governors used by each CPU.
+fn governor_check(mut governors: Vec<String>) -> Check {
+    let name = "cpu governor";
+    if governors.is_empty() {
+        return Check::new(
+            name,
+            Status::Pass,
+            "the CPU frequency governor is not exposed on this system".to_owned(),
+        );
+    }
+    let cpus = governors.len();
+    governors.retain(|governor| governor != "performance");
+    if governors.is_empty() {
+        return Check::new(
+            name,
+            Status::Pass,
+            format!("all {} CPUs use the performance governor", cpus),
+        );
+    }
+    let slow_cpus = governors.len();
+    governors.sort();
+    governors.dedup();
+    Check::new(
+        name,
+        Status::Warn,
+        format!(
+            "{} of {} CPUs use the {} governor, which can change the clock speed mid-measurement; \
+             use the performance governor for steadier timings",
+            slow_cpus,
+            cpus,
+            governors.join(", ")
+        ),
+    )
+}
+
+/// There should be room for the stored data to grow to its `--history-max-size`, or if it has no
+/// limit, at least `MIN_FREE_BYTES` free.
+fn check_disk_space(data_directory: &Path, retention: RetentionPolicy) -> Check {
+    let name = "disk space";
+    let available = match available_bytes(data_directory) {
+        Ok(available) => available,
+        Err(e) => {
+            return Check::new(
+                name,
+                Status::Warn,
+                format!("the free space could not be checked: {}", e),
+            )
+        }
+    };
+    let free = format::bytes(available).trim().to_owned();
+    match retention {
+        RetentionPolicy::MaxBytes(budget) => {
+            let stored = stored_bytes(data_directory);
+            let growth = budget.saturating_sub(stored);
+            if available < growth {
+                Check::new(
+                    name,
+                    Status::Warn,
+                    format!(
+                        "{} free, but --history-max-size lets the stored data grow by {}",
+                        free,
+                        format::bytes(growth).trim()
+                    ),
+                )
+            } else {
+                Check::new(
+                    name,
+                    Status::Pass,
+                    format!("{} free, enough for --history-max-size", free),
+                )
+            }
+        }
+        RetentionPolicy::KeepAll if available < MIN_FREE_BYTES => Check::new(
+            name,
+            Status::Warn,
+            format!(
+                "only {} free, and without --history-max-size the stored data grows with every run",
+                free
+            ),
+        ),
+        RetentionPolicy::KeepAll => Check::new(name, Status::Pass, format!("{} free", free)),
+    }
+}
+
+/// The free space on the filesystem holding the directory, or the nearest of its ancestors which
+/// exists, as reported by `df`.
+fn available_bytes(directory: &Path) -> Result<u64> {
+    let existing = (directory.ancestors())
+        .find(|path| path.exists())
+        .unwrap_or_else(|| Path::new("."));
+    let output = Command::new("df").arg("-Pk").arg(existing).output()?;
+    if !output.status.success() {
+        anyhow::bail!("df failed with {}", output.status);
+    }
+    // The second line describes the filesystem, and its fourth column is the available space in
+    // kibibytes.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available = (stdout.lines().nth(1))
+        .and_then(|line| line.split_whitespace().nth(3))
+        .and_then(|available| available.parse::<u64>().ok())
+        .ok_or_else(|| anyhow::anyhow!("df printed an unexpected result"))?;
+    Ok(available * 1024)
+}
+
+/// The total size of the files under the data directory.
+fn stored_bytes(data_directory: &Path) -> u64 {
+    walkdir::WalkDir::new(data_directory)
+        .into_iter()
+        .filter_map(::std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_governor_check() {
+        let governors = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect();
+        assert_eq!(Status::Pass, governor_check(vec![]).status);
+        assert_eq!(
+            Status::Pass,
+            governor_check(governors(&["performance", "performance"])).status
+        );
+
+        let check = governor_check(governors(&["powersave", "performance", "powersave"]));
+        assert_eq!(Status::Warn, check.status);
+        assert!(check
+            .details
+            .starts_with("2 of 3 CPUs use the powersave governor"));
+    }
+}
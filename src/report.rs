@@ -1,7 +1,8 @@
+use crate::config::{BudgetStatistic, CompareMetric, SortOrder};
 use crate::connection::{PlotConfiguration, Throughput};
 use crate::estimate::{ChangeDistributions, ChangeEstimates, Distributions, Estimate, Estimates};
 use crate::format;
-use crate::model::{BenchmarkGroup, Model, SavedStatistics};
+use crate::model::{AllocStats, BenchmarkGroup, Model, SavedStatistics};
 use crate::stats::bivariate::regression::Slope;
 use crate::stats::bivariate::Data;
 use crate::stats::univariate::outliers::tukey::LabeledSample;
@@ -10,10 +11,9 @@ use crate::stats::Distribution;
 use crate::value_formatter::ValueFormatter;
 use std::cell::Cell;
 use std::cmp;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::io::stderr;
-use std::io::Write;
+use std::io::{self, stderr, stdout, Write};
 use std::path::{Path, PathBuf};
 
 const MAX_DIRECTORY_NAME_LEN: usize = 64;
@@ -27,10 +27,56 @@ pub struct ComparisonData {
     pub relative_distributions: ChangeDistributions,
     pub significance_threshold: f64,
     pub noise_threshold: f64,
+    // The statistic whose change decides whether the benchmark improved or regressed.
+    pub compare_metric: CompareMetric,
     pub base_iter_counts: Vec<f64>,
     pub base_sample_times: Vec<f64>,
     pub base_avg_times: Vec<f64>,
     pub base_estimates: Estimates,
+    // Describes what the base samples are, if they are not a single stored measurement.
+    pub baseline_label: Option<String>,
+    // The version of Criterion.rs that the base samples were measured with, if known.
+    pub base_criterion_version: Option<String>,
+    // Set if only one of the base and new measurements has a throughput.
+    pub throughput_mismatch: Option<ThroughputMismatch>,
+}
+
+impl ComparisonData {
+    /// The change in the statistic chosen with `--compare-metric`.
+    pub fn primary_change(&self) -> &Estimate {
+        self.relative_estimates.compared(self.compare_metric)
+    }
+}
+
+/// Only one of two compared measurements of a benchmark has a throughput, because the benchmark
+/// gained or lost its throughput in between, so there's no change in throughput to report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThroughputMismatch {
+    /// The new measurement has a throughput, but the base measurement doesn't.
+    Gained,
+    /// The base measurement has a throughput, but the new measurement doesn't.
+    Lost,
+}
+impl ThroughputMismatch {
+    pub fn new(base: Option<&Throughput>, new: Option<&Throughput>) -> Option<ThroughputMismatch> {
+        match (base, new) {
+            (None, Some(_)) => Some(ThroughputMismatch::Gained),
+            (Some(_), None) => Some(ThroughputMismatch::Lost),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for ThroughputMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThroughputMismatch::Gained => f.write_str(
+                "has a throughput, but the measurement it is compared to was recorded without one",
+            ),
+            ThroughputMismatch::Lost => f.write_str(
+                "has no throughput, but the measurement it is compared to was recorded with one",
+            ),
+        }
+    }
 }
 
 pub struct MeasurementData<'a> {
@@ -40,6 +86,44 @@ pub struct MeasurementData<'a> {
     pub distributions: Distributions,
     pub comparison: Option<ComparisonData>,
     pub throughput: Option<Throughput>,
+    /// The peak resident set size of the benchmark process in bytes, if `--measure-memory` was
+    /// given. This is a process-wide high-water mark, so it includes any benchmarks run earlier by
+    /// the same target.
+    pub peak_rss: Option<u64>,
+    /// The mean number of instructions executed per iteration, if `--measure instructions` was
+    /// given. This includes the overhead of Criterion.rs' measurement loop.
+    pub instructions_per_iteration: Option<f64>,
+    /// The allocations made per iteration, if the benchmark installed a counting allocator.
+    pub allocations: Option<AllocStats>,
+    /// The CPUs that the benchmark target was restricted to by `--cpu-affinity`, if any.
+    pub cpu_affinity: Vec<usize>,
+    /// The version of Criterion.rs that the benchmark was built with.
+    pub criterion_version: Option<String>,
+    /// The tag given with `--bench-version`, if any.
+    pub bench_version: Option<String>,
+    /// The seed given with `--bench-seed`, if any.
+    pub bench_seed: Option<u64>,
+    /// Were the benchmark's worker thread pools limited to a single thread by `--single-thread`?
+    pub single_thread: bool,
+    /// The Cargo profile that the benchmark was compiled with, if known.
+    pub profile: Option<String>,
+    /// How long Cargo took to compile the benchmark target, in seconds, if Cargo reported it.
+    pub compile_time: Option<f64>,
+    /// The async runtime that the benchmark was run on, or None if it was a synchronous benchmark.
+    pub async_runtime: Option<String>,
+    /// The number of samples which took some time over at least one iteration.
+    pub effective_sample_count: usize,
+    /// Were the estimates based on fewer samples than `--min-samples`? Their confidence intervals
+    /// are too wide to trust.
+    pub low_confidence: bool,
+    /// Did the benchmark take more samples than it was configured to, to satisfy `--min-samples`?
+    pub min_samples_enforced: bool,
+    /// Key/value annotations attached to the benchmark by the benchmark code.
+    pub metadata: HashMap<String, String>,
+    /// The check of the benchmark's time against its `--budget`, or None if it has no budget.
+    pub budget: Option<BudgetCheck>,
+    /// The parts of the benchmark which it timed separately, in order of name.
+    pub sub_measurements: Vec<(String, MeasurementData<'a>)>,
 }
 impl<'a> MeasurementData<'a> {
     pub fn iter_counts(&self) -> &Sample<f64> {
@@ -49,6 +133,52 @@ impl<'a> MeasurementData<'a> {
     pub fn sample_times(&self) -> &Sample<f64> {
         self.data.y()
     }
+
+    /// Mean time taken to process a single element, for benchmarks with an elements-based
+    /// throughput.
+    pub fn time_per_element(&self) -> Option<Estimate> {
+        match self.throughput {
+            Some(Throughput::Elements(elements)) if elements > 0 => {
+                let elements = elements as f64;
+                let mut estimate = self.absolute_estimates.mean.clone();
+                estimate.point_estimate /= elements;
+                estimate.standard_error /= elements;
+                estimate.confidence_interval.lower_bound /= elements;
+                estimate.confidence_interval.upper_bound /= elements;
+                Some(estimate)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The result of checking a benchmark's time against its budget. Only the new measurement is
+/// checked; earlier measurements make no difference.
+#[derive(Debug, Clone, Copy)]
+pub struct BudgetCheck {
+    /// The budget, in nanoseconds per iteration.
+    pub limit_ns: f64,
+    /// The estimate that was checked against the budget, in nanoseconds per iteration.
+    pub measured_ns: f64,
+    /// Which estimate was checked.
+    pub statistic: BudgetStatistic,
+}
+impl BudgetCheck {
+    pub fn new(limit_ns: f64, estimates: &Estimates, statistic: BudgetStatistic) -> BudgetCheck {
+        let measured_ns = match statistic {
+            BudgetStatistic::Mean => estimates.mean.point_estimate,
+            BudgetStatistic::UpperBound => estimates.mean.confidence_interval.upper_bound,
+        };
+        BudgetCheck {
+            limit_ns,
+            measured_ns,
+            statistic,
+        }
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.measured_ns > self.limit_ns
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -113,10 +243,10 @@ impl BenchmarkId {
         throughput: Option<Throughput>,
     ) -> BenchmarkId {
         let full_id = match (&function_id, &value_str) {
-            (&Some(ref func), &Some(ref val)) => format!("{}/{}/{}", group_id, func, val),
-            (&Some(ref func), &None) => format!("{}/{}", group_id, func),
-            (&None, &Some(ref val)) => format!("{}/{}", group_id, val),
-            (&None, &None) => group_id.clone(),
+            (Some(func), Some(val)) => format!("{}/{}/{}", group_id, func, val),
+            (Some(func), None) => format!("{}/{}", group_id, func),
+            (None, Some(val)) => format!("{}/{}", group_id, val),
+            (None, None) => group_id.clone(),
         };
 
         let mut title = full_id.clone();
@@ -154,7 +284,9 @@ impl BenchmarkId {
 
     pub fn as_number(&self) -> Option<f64> {
         match self.throughput {
-            Some(Throughput::Bytes(n)) | Some(Throughput::Elements(n)) => Some(n as f64),
+            Some(Throughput::Bytes(n))
+            | Some(Throughput::Elements(n))
+            | Some(Throughput::Custom { count: n, .. }) => Some(n as f64),
             None => self
                 .value_str
                 .as_ref()
@@ -166,6 +298,7 @@ impl BenchmarkId {
         match self.throughput {
             Some(Throughput::Bytes(_)) => Some(ValueType::Bytes),
             Some(Throughput::Elements(_)) => Some(ValueType::Elements),
+            Some(Throughput::Custom { .. }) => Some(ValueType::Value),
             None => self
                 .value_str
                 .as_ref()
@@ -250,7 +383,6 @@ impl ReportContext {
 
 pub trait Report {
     fn benchmark_start(&self, _id: &BenchmarkId, _context: &ReportContext) {}
-    fn profile(&self, _id: &BenchmarkId, _context: &ReportContext, _profile_ns: f64) {}
     fn warmup(&self, _id: &BenchmarkId, _context: &ReportContext, _warmup_ns: f64) {}
     fn analysis(&self, _id: &BenchmarkId, _context: &ReportContext) {}
     fn measurement_start(
@@ -384,6 +516,8 @@ pub struct CliReport {
     pub enable_text_coloring: bool,
     pub verbose: bool,
     pub show_differences: bool,
+    /// Time changes at least this large a factor are shown as ratios rather than percentages.
+    pub change_ratio_above: Option<f64>,
 
     last_line_len: Cell<usize>,
 }
@@ -393,12 +527,14 @@ impl CliReport {
         enable_text_coloring: bool,
         show_differences: bool,
         verbose: bool,
+        change_ratio_above: Option<f64>,
     ) -> CliReport {
         CliReport {
             enable_text_overwrite,
             enable_text_coloring,
             show_differences,
             verbose,
+            change_ratio_above,
 
             last_line_len: Cell::new(0),
         }
@@ -415,7 +551,7 @@ impl CliReport {
     }
 
     //Passing a String is the common case here.
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::needless_pass_by_value))]
+    #[allow(clippy::needless_pass_by_value)]
     fn print_overwritable(&self, s: String) {
         if self.enable_text_overwrite {
             self.last_line_len.set(s.len());
@@ -498,6 +634,67 @@ impl CliReport {
         print(him, "high mild");
         print(his, "high severe");
     }
+
+    /// Print the time of a part of a benchmark which it timed separately, indented under the
+    /// benchmark, and its change if differences are shown.
+    fn sub_measurement(&self, name: &str, meas: &MeasurementData<'_>, formatter: &ValueFormatter) {
+        let typical_estimate = meas.absolute_estimates.typical();
+        let mut name = format!("  {}", name);
+        if name.len() > 23 {
+            eprintln!("{}", self.green(name.clone()));
+            name.clear();
+        }
+        let name_len = name.len();
+        eprintln!(
+            "{}{}time:   [{} {} {}]",
+            self.green(name),
+            " ".repeat(24 - name_len),
+            self.faint(formatter.format_value(typical_estimate.confidence_interval.lower_bound)),
+            self.bold(formatter.format_value(typical_estimate.point_estimate)),
+            self.faint(formatter.format_value(typical_estimate.confidence_interval.upper_bound))
+        );
+
+        let comp = match &meas.comparison {
+            Some(comp) if self.show_differences => comp,
+            _ => return,
+        };
+        let change = comp.primary_change();
+        let different = comp.p_value < comp.significance_threshold;
+        let mut point_estimate_str =
+            format::time_change(change.point_estimate, self.change_ratio_above);
+        let result = if !different {
+            "No change in performance detected.".to_owned()
+        } else {
+            match compare_to_threshold(change, comp.noise_threshold) {
+                ComparisonResult::Improved => {
+                    point_estimate_str = self.green(self.bold(point_estimate_str));
+                    format!("Performance has {}.", self.green("improved".to_owned()))
+                }
+                ComparisonResult::Regressed => {
+                    point_estimate_str = self.red(self.bold(point_estimate_str));
+                    format!("Performance has {}.", self.red("regressed".to_owned()))
+                }
+                ComparisonResult::NonSignificant => "Change within noise threshold.".to_owned(),
+            }
+        };
+        eprintln!(
+            "{}change: [{} {} {}] (p = {:.2} {} {:.2}) {}",
+            " ".repeat(24),
+            self.faint(format::time_change(
+                change.confidence_interval.lower_bound,
+                self.change_ratio_above
+            )),
+            point_estimate_str,
+            self.faint(format::time_change(
+                change.confidence_interval.upper_bound,
+                self.change_ratio_above
+            )),
+            comp.p_value,
+            if different { "<" } else { ">" },
+            comp.significance_threshold,
+            result
+        );
+    }
 }
 impl Report for CliReport {
     fn benchmark_start(&self, id: &BenchmarkId, _: &ReportContext) {
@@ -592,23 +789,88 @@ impl Report for CliReport {
             )
         }
 
+        if let Some(peak_rss) = meas.peak_rss {
+            eprintln!(
+                "{}mem:    [peak RSS {}]",
+                " ".repeat(24),
+                self.bold(format::bytes(peak_rss).trim_start().to_owned())
+            );
+        }
+
+        if let Some(instructions) = meas.instructions_per_iteration {
+            eprintln!(
+                "{}instr:  [{} per iteration]",
+                " ".repeat(24),
+                self.bold(format::instructions(instructions))
+            );
+        }
+
+        if let Some(allocations) = &meas.allocations {
+            eprintln!(
+                "{}alloc:  [{}, {} per iteration]",
+                " ".repeat(24),
+                self.bold(format::allocations(allocations.allocations_per_iteration)),
+                self.bold(
+                    format::bytes(allocations.bytes_per_iteration.round() as u64)
+                        .trim_start()
+                        .to_owned()
+                )
+            );
+        }
+
+        if let Some(async_runtime) = &meas.async_runtime {
+            eprintln!(
+                "{}async:  [{}]",
+                " ".repeat(24),
+                self.bold(async_runtime.clone())
+            );
+        }
+
+        if let Some(budget) = &meas.budget {
+            let result = if budget.exceeded() {
+                self.red("over budget".to_owned())
+            } else {
+                self.green("within budget".to_owned())
+            };
+            eprintln!(
+                "{}budget: [{} {} of {}, {}]",
+                " ".repeat(24),
+                budget.statistic,
+                self.bold(format::time(budget.measured_ns).trim().to_owned()),
+                format::time(budget.limit_ns).trim(),
+                result
+            );
+        }
+
+        if meas.low_confidence {
+            eprintln!(
+                "{}{}",
+                " ".repeat(24),
+                self.yellow(format!(
+                    "Low confidence: only {} usable samples were collected.",
+                    meas.effective_sample_count
+                ))
+            );
+        }
+
         if self.show_differences {
             if let Some(ref comp) = meas.comparison {
                 let different_mean = comp.p_value < comp.significance_threshold;
-                let mean_est = &comp.relative_estimates.mean;
+                let mean_est = comp.primary_change();
                 let point_estimate = mean_est.point_estimate;
-                let mut point_estimate_str = format::change(point_estimate, true);
+                let mut point_estimate_str =
+                    format::time_change(point_estimate, self.change_ratio_above);
                 // The change in throughput is related to the change in timing. Reducing the timing by
                 // 50% increases the througput by 100%.
                 let to_thrpt_estimate = |ratio: f64| 1.0 / (1.0 + ratio) - 1.0;
                 let mut thrpt_point_estimate_str =
                     format::change(to_thrpt_estimate(point_estimate), true);
-                let explanation_str: String;
+                let mut explanation_str: String;
 
                 if !different_mean {
                     explanation_str = "No change in performance detected.".to_owned();
                 } else {
-                    let comparison = compare_to_threshold(&mean_est, comp.noise_threshold);
+                    let comparison = compare_to_threshold(mean_est, comp.noise_threshold);
                     match comparison {
                         ComparisonResult::Improved => {
                             point_estimate_str = self.green(self.bold(point_estimate_str));
@@ -630,20 +892,21 @@ impl Report for CliReport {
                     }
                 }
 
-                if meas.throughput.is_some() {
+                // A change in throughput can only be shown if both measurements have one.
+                if meas.throughput.is_some() && comp.throughput_mismatch.is_none() {
                     eprintln!("{}change:", " ".repeat(17));
 
                     eprintln!(
                         "{}time:   [{} {} {}] (p = {:.2} {} {:.2})",
                         " ".repeat(24),
-                        self.faint(format::change(
+                        self.faint(format::time_change(
                             mean_est.confidence_interval.lower_bound,
-                            true
+                            self.change_ratio_above
                         )),
                         point_estimate_str,
-                        self.faint(format::change(
+                        self.faint(format::time_change(
                             mean_est.confidence_interval.upper_bound,
-                            true
+                            self.change_ratio_above
                         )),
                         comp.p_value,
                         if different_mean { "<" } else { ">" },
@@ -666,14 +929,14 @@ impl Report for CliReport {
                     eprintln!(
                         "{}change: [{} {} {}] (p = {:.2} {} {:.2})",
                         " ".repeat(24),
-                        self.faint(format::change(
+                        self.faint(format::time_change(
                             mean_est.confidence_interval.lower_bound,
-                            true
+                            self.change_ratio_above
                         )),
                         point_estimate_str,
-                        self.faint(format::change(
+                        self.faint(format::time_change(
                             mean_est.confidence_interval.upper_bound,
-                            true
+                            self.change_ratio_above
                         )),
                         comp.p_value,
                         if different_mean { "<" } else { ">" },
@@ -681,10 +944,46 @@ impl Report for CliReport {
                     );
                 }
 
-                eprintln!("{}{}", " ".repeat(24), explanation_str);
+                if is_significant_change(&comp.base_estimates, &meas.absolute_estimates) {
+                    explanation_str.push_str(" Confidence intervals do not overlap.");
+                }
+                match &comp.baseline_label {
+                    Some(label) => eprintln!(
+                        "{}{} {}",
+                        " ".repeat(24),
+                        explanation_str,
+                        self.faint(format!("({})", label))
+                    ),
+                    None => eprintln!("{}{}", " ".repeat(24), explanation_str),
+                }
+                if let Some(mismatch) = comp.throughput_mismatch {
+                    eprintln!(
+                        "{}{}",
+                        " ".repeat(24),
+                        self.yellow(format!(
+                            "The benchmark {}, so no change in throughput is shown.",
+                            mismatch
+                        ))
+                    );
+                }
+                if is_std_dev_increase_significant(&comp.base_estimates, &meas.absolute_estimates) {
+                    let change = std_dev_change(&comp.base_estimates, &meas.absolute_estimates);
+                    eprintln!(
+                        "{}{}",
+                        " ".repeat(24),
+                        self.yellow(format!(
+                            "Standard deviation has increased by {}; the benchmark may have become noisier.",
+                            format::change(change, false).trim()
+                        ))
+                    );
+                }
             }
         }
 
+        for (name, sub) in &meas.sub_measurements {
+            self.sub_measurement(name, sub, formatter);
+        }
+
         if self.verbose {
             self.outliers(&meas.avg_times);
 
@@ -768,6 +1067,72 @@ impl Report for BencherReport {
     }
 }
 
+/// Prints the latest estimates of every stored benchmark to stdout once the run is complete, in a
+/// form that can be checked into version control. The benchmarks are sorted by name, and nothing
+/// that differs between runs with the same results - timestamps, changes from the previous run,
+/// details of the machine - is printed, so the output only changes when the estimates do.
+pub struct StableTextReport;
+impl Report for StableTextReport {
+    fn final_summary(&self, _context: &ReportContext, model: &Model) {
+        let stdout = stdout();
+        let mut out = stdout.lock();
+        if let Err(e) =
+            write_stored_estimates(&mut out, &latest_stats(model, SortOrder::Name), false, None)
+        {
+            error!("Failed to print the benchmark results: {}", e);
+        }
+    }
+}
+
+/// The title and latest measurement of each benchmark in the model, in the given order.
+pub fn latest_stats(model: &Model, order: SortOrder) -> Vec<(&str, &SavedStatistics)> {
+    let mut benchmarks = Vec::new();
+    for (group_id, ids) in model.sorted_benchmarks(order) {
+        for id in ids {
+            let stats = &model.groups[group_id].benchmarks[id].latest_stats;
+            benchmarks.push((id.as_title(), stats));
+        }
+    }
+    benchmarks
+}
+
+/// Write the estimated time of each benchmark, and its change from the measurement before it if
+/// `show_changes` is set. The output only depends on the estimates, so the same estimates always
+/// produce the same bytes.
+pub fn write_stored_estimates(
+    out: &mut dyn Write,
+    benchmarks: &[(&str, &SavedStatistics)],
+    show_changes: bool,
+    change_ratio_above: Option<f64>,
+) -> io::Result<()> {
+    for (title, stats) in benchmarks {
+        let typical = stats.estimates.typical();
+        writeln!(
+            out,
+            "{:<24}time:   [{} {} {}]",
+            title,
+            format::time(typical.confidence_interval.lower_bound),
+            format::time(typical.point_estimate),
+            format::time(typical.confidence_interval.upper_bound),
+        )?;
+        match &stats.changes {
+            Some(changes) if show_changes => {
+                let mean = &changes.mean;
+                writeln!(
+                    out,
+                    "{}change: [{} {} {}]",
+                    " ".repeat(24),
+                    format::time_change(mean.confidence_interval.lower_bound, change_ratio_above),
+                    format::time_change(mean.point_estimate, change_ratio_above),
+                    format::time_change(mean.confidence_interval.upper_bound, change_ratio_above),
+                )?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 pub enum ComparisonResult {
     Improved,
     Regressed,
@@ -788,6 +1153,36 @@ pub fn compare_to_threshold(estimate: &Estimate, noise: f64) -> ComparisonResult
     }
 }
 
+/// Returns true if the comparison shows a statistically significant slowdown larger than the noise
+/// threshold, which is what the report calls a regression.
+pub fn has_regressed(comparison: &ComparisonData) -> bool {
+    comparison.p_value < comparison.significance_threshold
+        && matches!(
+            compare_to_threshold(comparison.primary_change(), comparison.noise_threshold),
+            ComparisonResult::Regressed
+        )
+}
+
+/// Returns true if the confidence intervals of the typical times of the two measurements don't
+/// overlap. A change that large can't be put down to measurement noise.
+pub fn is_significant_change(base: &Estimates, new: &Estimates) -> bool {
+    let base = &base.typical().confidence_interval;
+    let new = &new.typical().confidence_interval;
+    new.lower_bound > base.upper_bound || new.upper_bound < base.lower_bound
+}
+
+/// The relative change in the standard deviation between the two measurements.
+pub fn std_dev_change(base: &Estimates, new: &Estimates) -> f64 {
+    new.std_dev.point_estimate / base.std_dev.point_estimate - 1.0
+}
+
+/// Returns true if the standard deviation of the new measurement is larger than that of the base
+/// measurement and their confidence intervals don't overlap. A benchmark whose typical time is
+/// unchanged can still have picked up a new source of noise.
+pub fn is_std_dev_increase_significant(base: &Estimates, new: &Estimates) -> bool {
+    new.std_dev.confidence_interval.lower_bound > base.std_dev.confidence_interval.upper_bound
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -852,4 +1247,129 @@ mod test {
         new_id.ensure_directory_name_unique(&directories);
         assert_ne!(existing_id.as_directory_name(), new_id.as_directory_name());
     }
+
+    fn estimates_with_interval(lower_bound: f64, upper_bound: f64) -> Estimates {
+        let estimate = Estimate {
+            confidence_interval: crate::estimate::ConfidenceInterval {
+                confidence_level: 0.95,
+                lower_bound,
+                upper_bound,
+            },
+            point_estimate: (lower_bound + upper_bound) / 2.0,
+            standard_error: 0.0,
+        };
+        Estimates {
+            mean: estimate.clone(),
+            median: estimate.clone(),
+            median_abs_dev: estimate.clone(),
+            slope: None,
+            std_dev: estimate,
+        }
+    }
+
+    fn saved_statistics(estimates: Estimates, datetime: &str) -> SavedStatistics {
+        SavedStatistics {
+            datetime: datetime.parse().unwrap(),
+            iterations: vec![1.0, 2.0],
+            values: vec![10.0, 22.0],
+            avg_values: vec![10.0, 11.0],
+            estimates,
+            throughput: None,
+            changes: Some(ChangeEstimates {
+                mean: estimates_with_interval(0.1, 0.3).mean,
+                median: estimates_with_interval(0.1, 0.3).median,
+                slope: None,
+            }),
+            change_direction: None,
+            p_value: Some(0.01),
+            history_id: Some(datetime.to_owned()),
+            history_description: None,
+            features: vec![],
+            peak_rss: None,
+            instructions_per_iteration: None,
+            allocations: None,
+            cpu_affinity: vec![],
+            criterion_version: None,
+            bench_version: None,
+            bench_seed: None,
+            single_thread: false,
+            min_samples_enforced: false,
+            profile: None,
+            async_runtime: None,
+            target: None,
+            target_fingerprint: None,
+            compile_time: None,
+            metadata: HashMap::new(),
+            sub_measurements: std::collections::BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_write_stored_estimates_is_stable() {
+        let render = |datetime| {
+            let first = saved_statistics(estimates_with_interval(10.0, 12.0), datetime);
+            let second = saved_statistics(estimates_with_interval(1500.0, 2500.0), datetime);
+            let mut out = Vec::new();
+            write_stored_estimates(&mut out, &[("a/b", &first), ("c", &second)], false, None)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let expected = "\
+a/b                     time:   [10.000 ns 11.000 ns 12.000 ns]
+c                       time:   [1.5000 us 2.0000 us 2.5000 us]
+";
+        // Measurements taken at different times with the same estimates give the same output.
+        assert_eq!(expected, render("2020-01-01T00:00:00Z"));
+        assert_eq!(expected, render("2021-06-15T12:30:00Z"));
+    }
+
+    #[test]
+    fn test_is_significant_change_overlapping_intervals() {
+        let base = estimates_with_interval(10.0, 12.0);
+        let significant =
+            |lower, upper| is_significant_change(&base, &estimates_with_interval(lower, upper));
+        assert!(!significant(11.0, 13.0));
+        assert!(!significant(9.0, 10.0));
+        assert!(!significant(8.0, 14.0));
+    }
+
+    #[test]
+    fn test_is_significant_change_separate_intervals() {
+        let base = estimates_with_interval(10.0, 12.0);
+        let significant =
+            |lower, upper| is_significant_change(&base, &estimates_with_interval(lower, upper));
+        assert!(significant(12.5, 13.0));
+        assert!(significant(8.0, 9.5));
+    }
+
+    #[test]
+    fn test_throughput_mismatch() {
+        let throughput = Throughput::Elements(10);
+        let mismatch = |base, new| ThroughputMismatch::new(base, new);
+        assert_eq!(None, mismatch(None, None));
+        assert_eq!(
+            None,
+            mismatch(Some(&throughput), Some(&Throughput::Bytes(8)))
+        );
+        assert_eq!(
+            Some(ThroughputMismatch::Gained),
+            mismatch(None, Some(&throughput))
+        );
+        assert_eq!(
+            Some(ThroughputMismatch::Lost),
+            mismatch(Some(&throughput), None)
+        );
+    }
+
+    #[test]
+    fn test_std_dev_increase() {
+        let base = estimates_with_interval(1.0, 2.0);
+        let increased = |lower, upper| {
+            is_std_dev_increase_significant(&base, &estimates_with_interval(lower, upper))
+        };
+        assert!(increased(2.5, 3.5));
+        assert!(!increased(1.5, 3.5));
+        assert!(!increased(0.2, 0.5));
+        assert!((std_dev_change(&base, &estimates_with_interval(2.5, 3.5)) - 1.0).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,1041 @@
+//! Running the benchmarks: compiling them, executing each benchmark target and reporting the
+//! results, either for the `cargo criterion` command or for another crate through `Runner`.
+
+use crate::baseline_file;
+use crate::compile;
+use crate::config::{
+    self, Action, CompareAgainst, FullConfig, LogFormat, Measure, OutputFormat, PlottingBackend,
+    SelfConfig, TextColor,
+};
+use crate::connection::{AxisScale, PlotConfiguration};
+use crate::error::RunError;
+use crate::format;
+use crate::message_formats;
+use crate::model;
+use crate::plot::Plotter;
+use crate::report::{Report, ReportContext};
+use anyhow::Error;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Compiles and runs the benchmarks of a package and reports the results, as `cargo criterion`
+/// does, for build scripts and custom benchmark harnesses which don't want to run it as a separate
+/// command.
+///
+/// Anything which isn't set through the builder is configured as usual, by the Criterion.toml file
+/// and the environment. Warnings, errors and debugging output, including what a dry run would have
+/// done, are logged through the `log` crate, so they're only printed if the caller has set up a
+/// logger. The progress messages which `cargo criterion` prints, such as how long compiling took,
+/// are passed to the `on_message` function, or logged too if there isn't one. The results are
+/// printed according to the output format, so choose `OutputFormat::Quiet` to leave them out.
+///
+/// ```no_run
+/// use cargo_criterion::{OutputFormat, Runner};
+///
+/// let summary = Runner::new()
+///     .cargo_args(&["--bench", "parsing", "--features", "simd"])
+///     .criterion_home("target/parsing-benchmarks")
+///     .output_format(OutputFormat::Quiet)
+///     .run()?;
+/// println!("Ran {} benchmarks", summary.benchmark_count);
+/// # Ok::<(), cargo_criterion::RunError>(())
+/// ```
+#[derive(Default)]
+pub struct Runner {
+    args: Vec<OsString>,
+    criterion_home: Option<PathBuf>,
+    output_format: Option<OutputFormat>,
+    on_message: Option<OnMessage>,
+}
+impl std::fmt::Debug for Runner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Runner")
+            .field("args", &self.args)
+            .field("criterion_home", &self.criterion_home)
+            .field("output_format", &self.output_format)
+            .field("on_message", &self.on_message.is_some())
+            .finish()
+    }
+}
+impl Runner {
+    pub fn new() -> Runner {
+        Runner::default()
+    }
+
+    /// Add arguments written as they would be on the command line after `cargo criterion`. These
+    /// select what Cargo compiles (eg. `--bench NAME` or `--features FEATURES`), but any other
+    /// option of `cargo criterion` may be given too, as may a benchmark filter and arguments for
+    /// the benchmark targets after `--`. The subcommands can't be used.
+    pub fn cargo_args<I, S>(mut self, args: I) -> Runner
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Store the measurements and write the reports under this directory instead of
+    /// `target/criterion`. This takes precedence over `$CRITERION_HOME` and the Criterion.toml file.
+    pub fn criterion_home<P: Into<PathBuf>>(mut self, criterion_home: P) -> Runner {
+        self.criterion_home = Some(criterion_home.into());
+        self
+    }
+
+    /// Choose how the results are printed. This takes precedence over `--output-format` and the
+    /// Criterion.toml file.
+    pub fn output_format(mut self, output_format: OutputFormat) -> Runner {
+        self.output_format = Some(output_format);
+        self
+    }
+
+    /// Pass each progress message of the run to this function instead of logging it at the info
+    /// level. These are the messages which `cargo criterion` prints to stderr, such as which
+    /// benchmarks were filtered out.
+    pub fn on_message<F: FnMut(&str) + 'static>(mut self, on_message: F) -> Runner {
+        self.on_message = Some(Box::new(on_message));
+        self
+    }
+
+    /// Compile and run the benchmarks, then generate the reports.
+    pub fn run(self) -> Result<RunSummary, RunError> {
+        let mut args = vec![
+            OsString::from("cargo-criterion"),
+            OsString::from("criterion"),
+        ];
+        args.extend(self.args);
+        let mut configuration = config::try_configure(args, self.criterion_home)?;
+        if let Some(output_format) = self.output_format {
+            configuration.self_config.output_format = output_format;
+        }
+        if !matches!(configuration.action, Action::Run) {
+            return Err(anyhow::anyhow!(
+                "Runner can only run benchmarks, but the arguments ask for {:?}",
+                configuration.action
+            )
+            .into());
+        }
+        crate::format::set_time_unit(configuration.self_config.time_unit);
+        let mut on_message = self
+            .on_message
+            .unwrap_or_else(|| Box::new(|message| info!("{}", message)));
+        Ok(run(&configuration, &mut *on_message)?)
+    }
+}
+
+/// A function which the progress messages of a run are passed to.
+type OnMessage = Box<dyn FnMut(&str)>;
+
+/// What happened during a run of the benchmarks.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RunSummary {
+    /// How many benchmarks were run. Benchmarks run more than once by `--repeat` are counted once.
+    pub benchmark_count: usize,
+    /// How many benchmarks were skipped because they didn't match the benchmark filter.
+    pub filtered_out_count: usize,
+    /// The IDs of the benchmarks whose performance regressed, including any allowed by
+    /// `--ignore-regression`.
+    pub regressed: Vec<String>,
+    /// The directory that the reports were written to.
+    pub report_directory: PathBuf,
+    /// How long compiling the benchmarks took, or None if the compiled benchmarks were reused.
+    pub compile_time: Option<Duration>,
+}
+
+/// Compile and run the benchmarks, generate the reports and then run the post-run hook, if there
+/// is one. The progress messages of the run are passed to `on_message`.
+pub fn run(
+    configuration: &FullConfig,
+    on_message: &mut dyn FnMut(&str),
+) -> Result<RunSummary, Error> {
+    let self_config = &configuration.self_config;
+    let result = run_benchmarks(configuration, on_message);
+    match &self_config.post_run_hook {
+        Some(hook) => {
+            let hook_result = run_post_run_hook(hook, &self_config.criterion_home, result.is_ok());
+            match (result, hook_result) {
+                (Ok(summary), hook_result) => hook_result.map(|()| summary),
+                (Err(e), Ok(())) => Err(e),
+                (Err(e), Err(hook_error)) => {
+                    error!("{:#}", hook_error);
+                    Err(e)
+                }
+            }
+        }
+        None => result,
+    }
+}
+
+/// Compile and run the benchmarks, then generate the reports.
+fn run_benchmarks(
+    configuration: &FullConfig,
+    on_message: &mut dyn FnMut(&str),
+) -> Result<RunSummary, Error> {
+    let self_config = &configuration.self_config;
+    let mut summary = RunSummary {
+        report_directory: self_config.report_directory.clone(),
+        ..RunSummary::default()
+    };
+
+    // Launch cargo to compile the crate and produce a list of the benchmark targets to run.
+    let compile::CompiledBenchmarks {
+        mut targets,
+        library_paths,
+        compile_time,
+    } = compile_benchmarks(self_config, &configuration.cargo_args)?;
+    summary.compile_time = compile_time;
+    for bench in &targets {
+        if let Some(seconds) = bench.compile_time {
+            info!(
+                "Compiling {} took {}",
+                bench.name,
+                format::time(seconds * 1e9).trim()
+            );
+        }
+    }
+    if self_config.bench_only {
+        targets.retain(|bench| {
+            if !bench.is_bench() {
+                info!("Leaving out {}, which isn't a benchmark target", bench.name);
+            }
+            bench.is_bench()
+        });
+    }
+
+    if self_config.check && self_config.dry_run {
+        warn!(
+            target: "dry-run",
+            "Dry run: would clear {:?} before checking the benchmarks",
+            self_config.criterion_home.join("check")
+        );
+    } else if self_config.check {
+        // Start each check run from nothing, so that it isn't compared against the last one.
+        let check_home = self_config.criterion_home.join("check");
+        match std::fs::remove_dir_all(&check_home) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                let e =
+                    Error::from(e).context(format!("Failed to clear directory {:?}", check_home));
+                return Err(e);
+            }
+            _ => {}
+        }
+    }
+
+    // Load the saved measurements from the last run.
+    let mut run_model = crate::load_model(self_config, self_config.compare_against.clone());
+    if let Some(db) = &self_config.db {
+        run_model.record_to_database(db)?;
+    }
+    if let CompareAgainst::File(path) = &self_config.compare_against {
+        run_model.load_baseline_file(path)?;
+    }
+    run_model.allow_duplicate_ids(
+        self_config.allow_duplicate_ids.clone(),
+        self_config.deny_duplicate_ids,
+    );
+    run_model.deny_missing_benchmarks(self_config.no_missing_benchmarks);
+
+    // Set up the reports. These receive notifications as the benchmarks proceed and generate output for the user.
+    let cli_report = configure_cli_output(self_config);
+    let bencher_report = crate::report::BencherReport;
+    let stable_text_report = crate::report::StableTextReport;
+    // A dry run leaves out the reports which write files.
+    if self_config.dry_run {
+        warn!(
+            target: "dry-run",
+            "Dry run: no reports will be written to {:?}",
+            self_config.report_directory
+        );
+    }
+    let html_report = match self_config.plotting_backend {
+        _ if self_config.dry_run => None,
+        PlottingBackend::Disabled => None,
+        _ => Some(crate::html::Html::new(
+            get_plotter(self_config)?,
+            self_config.sort_by,
+            self_config.change_ratio_above,
+            self_config.report_title.clone(),
+            self_config.report_description.clone(),
+        )),
+    };
+    let machine_report = message_formats::create_machine_report(self_config)?;
+    let upload_report = match &self_config.upload_url {
+        Some(url) if self_config.dry_run => {
+            warn!(target: "dry-run", "Dry run: the results won't be uploaded to {}", url);
+            None
+        }
+        _ => message_formats::create_upload_report(self_config)?,
+    };
+    let pdf_report = match self_config.output_format {
+        OutputFormat::Pdf if !self_config.dry_run => Some(pdf_report(self_config)?),
+        _ => None,
+    };
+    let github_summary_report = match &self_config.github_summary {
+        Some(path) if self_config.dry_run => {
+            warn!(target: "dry-run", "Dry run: the job summary won't be appended to {:?}", path);
+            None
+        }
+        Some(path) => Some(crate::markdown::GithubSummaryReport::new(
+            path.clone(),
+            self_config.sort_by,
+            self_config.change_ratio_above,
+        )),
+        None => None,
+    };
+
+    let mut reports: Vec<&dyn crate::report::Report> = Vec::new();
+    match self_config.output_format {
+        OutputFormat::Bencher => reports.push(&bencher_report),
+        OutputFormat::StableText => reports.push(&stable_text_report),
+        OutputFormat::Criterion | OutputFormat::Quiet | OutputFormat::Verbose => {
+            reports.push(&cli_report)
+        }
+        OutputFormat::Pdf => reports.push(&cli_report),
+    }
+    if let Some(pdf_report) = &pdf_report {
+        reports.push(pdf_report.as_ref());
+    }
+    if let Some(html_report) = &html_report {
+        reports.push(html_report);
+    }
+    if let Some(machine_report) = &machine_report {
+        reports.push(machine_report);
+    }
+    if let Some(upload_report) = &upload_report {
+        reports.push(upload_report);
+    }
+    if let Some(github_summary_report) = &github_summary_report {
+        reports.push(github_summary_report);
+    }
+    let reports = crate::report::Reports::new(reports);
+
+    if self_config.do_run {
+        // Keep the stored data from being migrated while new measurements are added to it. A dry
+        // run doesn't add anything.
+        let _data_lock = if self_config.dry_run {
+            None
+        } else {
+            Some(model::DataLock::acquire(&self_config.data_directory)?)
+        };
+
+        // Decide which targets to run up front, so that later repeats don't skip the targets
+        // measured by earlier ones.
+        let mut targets_to_run = Vec::new();
+        for mut bench in targets {
+            bench.fingerprint = bench
+                .compute_fingerprint()
+                .map_err(|e| warn!("{:#}", e))
+                .ok();
+            if self_config.changed_only {
+                if let Some(fingerprint) = &bench.fingerprint {
+                    if run_model.is_target_unchanged(&bench.name, fingerprint) {
+                        on_message(&format!(
+                            "Skipping benchmark target {}, which hasn't changed since it was last run",
+                            bench.name
+                        ));
+                        continue;
+                    }
+                }
+            }
+            bench.benchmark_overrides = self_config.benchmark_overrides.clone();
+            bench.cpu_affinity = self_config.cpu_affinity.clone();
+            bench.working_directory = self_config
+                .bench_cwd
+                .clone()
+                .or_else(|| bench.package_directory.clone());
+            bench.bench_version = self_config.bench_version.clone();
+            bench.bench_seed = self_config.bench_seed;
+            bench.single_thread = self_config.single_thread;
+            bench.connect_port = self_config.connect_port;
+            bench.budgets = self_config.budgets.clone();
+            bench.budget_statistic = self_config.budget_statistic;
+            bench.profile = Some(self_config.profile.clone());
+            bench.min_samples = self_config.min_samples;
+            bench.significance_test = self_config.significance_test;
+            bench.compare_metric = self_config.compare_metric;
+            bench.reject_new_benchmarks = self_config.no_new_benchmarks;
+            bench.run_retries = self_config.run_retries;
+            targets_to_run.push(bench);
+        }
+
+        // Execute each benchmark target, updating the model as we go.
+        execute_targets(
+            &targets_to_run,
+            self_config.repeat,
+            self_config.take,
+            &mut run_model,
+            on_message,
+            |bench, run_model, remaining| {
+                info!("Executing {} - {:?}", bench.name, bench.executable);
+                let err = bench.execute(
+                    &self_config.criterion_home,
+                    &self_config.report_directory,
+                    &configuration.additional_args,
+                    &library_paths,
+                    &reports,
+                    run_model,
+                    // Output from the targets would interfere with machine-readable or stable text
+                    // output printed to stdout, but not with output written to a file.
+                    (self_config.message_format.is_some() && self_config.message_path.is_none())
+                        || matches!(self_config.output_format, OutputFormat::StableText),
+                    self_config.measure_memory,
+                    matches!(self_config.measure, Measure::Instructions),
+                    self_config.adaptive_sampling,
+                    remaining,
+                );
+
+                if let Err(err) = err {
+                    if self_config.do_fail_fast {
+                        return Err(err);
+                    } else {
+                        error!(
+                            "Failed to execute benchmark target {}:\n{}",
+                            bench.name, err
+                        );
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        let counts = run_model.run_counts();
+        summary.benchmark_count = counts.run;
+        summary.filtered_out_count = counts.filtered_out;
+        if counts.filtered_out > 0 {
+            on_message(&format!(
+                "Ran {} of {} benchmarks; {} filtered out",
+                counts.run,
+                counts.run + counts.filtered_out,
+                counts.filtered_out
+            ));
+        }
+        if let Some(compile_time) = summary.compile_time {
+            on_message(&format!(
+                "Compiled the benchmarks in {}",
+                format::time(compile_time.as_secs_f64() * 1e9).trim()
+            ));
+        }
+
+        if self_config.profile_time.is_some() {
+            // Nothing was measured, so there's nothing new to report.
+            match run_model.save_profiles(&self_config.criterion_home.join("profile")) {
+                Ok(profiles) => {
+                    for profile in profiles {
+                        on_message(&format!("Saved profile data to {}", profile.display()));
+                    }
+                }
+                Err(e) => error!("Failed to save profile data: {:?}", e),
+            }
+            return Ok(summary);
+        }
+
+        // Generate the overall summary report using all of the records in the model.
+        let final_context = ReportContext {
+            output_directory: self_config.report_directory.clone(),
+            plot_config: PlotConfiguration {
+                summary_scale: AxisScale::Linear,
+            },
+        };
+
+        reports.final_summary(&final_context, &run_model);
+
+        if let Some(html_report) = &html_report {
+            let failed = html_report.failed_plots();
+            if !failed.is_empty() {
+                anyhow::bail!(
+                    "gnuplot failed to draw {} plot(s), starting with {:?}, which are missing \
+                     from the report",
+                    failed.len(),
+                    failed[0]
+                );
+            }
+        }
+
+        if let Some(path) = &self_config.export_baseline {
+            if self_config.dry_run {
+                warn!(
+                    target: "dry-run",
+                    "Dry run: would export the latest measurements to baseline file {:?}",
+                    path
+                );
+            } else {
+                let count = baseline_file::export(path, &run_model)?;
+                on_message(&format!(
+                    "Exported the latest measurements of {} benchmark(s) to baseline file {:?}",
+                    count, path
+                ));
+            }
+        }
+
+        if let (Some(upload_report), Some(url)) = (&upload_report, &self_config.upload_url) {
+            let failed = upload_report.failed_uploads();
+            if failed > 0 && self_config.upload_required {
+                anyhow::bail!("{} result(s) couldn't be uploaded to {}", failed, url);
+            } else if failed > 0 {
+                warn!(
+                    target: "upload-failure",
+                    "{} result(s) couldn't be uploaded to {}. Use --upload-required to make this \
+                     an error.",
+                    failed,
+                    url
+                );
+            }
+        }
+
+        if let config::RetentionPolicy::MaxBytes(budget) = self_config.retention {
+            let summary = model::prune_stored_data(
+                &self_config.data_directory,
+                self_config.retention,
+                self_config.dry_run,
+            )?;
+            if summary.deleted_measurements > 0 {
+                on_message(&format!(
+                    "{} {} old measurement(s) ({}) to keep the stored data under --history-max-size",
+                    if self_config.dry_run { "Would delete" } else { "Deleted" },
+                    summary.deleted_measurements,
+                    format::bytes(summary.deleted_bytes).trim()
+                ));
+            }
+            if summary.remaining_bytes > budget {
+                warn!(
+                    "The stored data takes up {}, which is more than --history-max-size allows, \
+                     but only latest measurements, baselines and measurement logs are left.",
+                    format::bytes(summary.remaining_bytes).trim()
+                );
+            }
+        }
+
+        summary.regressed = run_model.regressed_benchmarks().to_vec();
+        let rejected = run_model.rejected_new_benchmarks();
+        if !rejected.is_empty() {
+            return Err(RunError::NewBenchmarks {
+                ids: rejected.to_vec(),
+            }
+            .into());
+        }
+
+        let over_budget = run_model.over_budget_benchmarks();
+        if !over_budget.is_empty() {
+            return Err(RunError::OverBudget {
+                ids: over_budget.to_vec(),
+            }
+            .into());
+        }
+
+        let duplicates = run_model.duplicate_ids();
+        if !duplicates.is_empty() {
+            return Err(RunError::DuplicateIds {
+                ids: duplicates.to_vec(),
+            }
+            .into());
+        }
+
+        let missing = run_model.missing_benchmarks();
+        if self_config.no_missing_benchmarks && !missing.is_empty() {
+            return Err(RunError::MissingBenchmarks {
+                ids: missing.to_vec(),
+            }
+            .into());
+        }
+
+        check_regressions(
+            run_model.regressed_benchmarks(),
+            self_config.fail_on_regression,
+            &self_config.ignore_regression,
+            on_message,
+        )?;
+    }
+    Ok(summary)
+}
+
+/// With `--fail-on-regression`, fail the run if any of the regressed benchmarks don't match an
+/// `--ignore-regression` pattern.
+fn check_regressions(
+    regressed_benchmarks: &[String],
+    fail_on_regression: bool,
+    ignore_regression: &[String],
+    on_message: &mut dyn FnMut(&str),
+) -> Result<(), RunError> {
+    if !fail_on_regression {
+        return Ok(());
+    }
+    let mut regressed = Vec::new();
+    for id in regressed_benchmarks {
+        let ignored =
+            (ignore_regression.iter()).any(|pattern| config::pattern_matches(pattern, id));
+        if ignored {
+            on_message(&format!(
+                "Ignoring the regression of benchmark {}, as allowed by --ignore-regression",
+                id
+            ));
+        } else {
+            regressed.push(id.clone());
+        }
+    }
+    if regressed.is_empty() {
+        Ok(())
+    } else {
+        Err(RunError::RegressionDetected { ids: regressed })
+    }
+}
+
+/// Execute the benchmark targets in order, `repeat` times over. `execute` is given the number of
+/// benchmarks that `--take` still allows in this repeat, and counts off each benchmark it runs; the
+/// targets after the count reaches zero aren't executed.
+fn execute_targets<T>(
+    targets: &[T],
+    repeat: usize,
+    take: Option<usize>,
+    run_model: &mut model::Model,
+    on_message: &mut dyn FnMut(&str),
+    mut execute: impl FnMut(&T, &mut model::Model, &mut Option<usize>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for repeat_number in 1..=repeat {
+        if repeat > 1 {
+            on_message(&format!("Run {} of {}", repeat_number, repeat));
+            run_model.begin_repeat();
+        }
+
+        let mut remaining = take;
+        for target in targets {
+            if remaining == Some(0) {
+                break;
+            }
+            execute(target, run_model, &mut remaining)?;
+        }
+
+        if let (Some(take), Some(0)) = (take, remaining) {
+            on_message(&format!(
+                "Ran the first {} benchmarks, as limited by --take",
+                take
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Run the `--post-run-hook` command through the shell, telling it where the results are and
+/// whether the run succeeded.
+fn run_post_run_hook(
+    hook: &str,
+    criterion_home: &std::path::Path,
+    succeeded: bool,
+) -> Result<(), Error> {
+    let mut command = if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command
+        .arg(hook)
+        .env("CARGO_CRITERION_HOME", criterion_home)
+        .env("CARGO_CRITERION_STATUS", if succeeded { "0" } else { "1" });
+
+    debug!("Running post-run hook '{:?}'", command);
+    let status = command
+        .status()
+        .map_err(|e| Error::from(e).context(format!("Failed to run post-run hook '{}'", hook)))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "Post-run hook '{}' failed with exit code {:?}",
+            hook,
+            status.code()
+        ));
+    }
+    Ok(())
+}
+
+/// Compile the benchmarks, or reuse the list of benchmarks from the last run if `--reuse-compile`
+/// was given and nothing has changed.
+fn compile_benchmarks(
+    self_config: &SelfConfig,
+    cargo_args: &[std::ffi::OsString],
+) -> Result<compile::CompiledBenchmarks, Error> {
+    let log_diagnostics = matches!(self_config.log_format, LogFormat::Json);
+    if !self_config.reuse_compile {
+        return compile::compile(self_config.debug_build, log_diagnostics, cargo_args);
+    }
+
+    let cache_path = self_config.criterion_home.join("compile_cache.cbor");
+    if let Some(benchmarks) = compile::load_cached(
+        &cache_path,
+        &self_config.manifest_directory,
+        self_config.debug_build,
+        cargo_args,
+    ) {
+        info!("Reusing compiled benchmarks from {:?}", cache_path);
+        return Ok(benchmarks);
+    }
+
+    let compiled_at = std::time::SystemTime::now();
+    let benchmarks = compile::compile(self_config.debug_build, log_diagnostics, cargo_args)?;
+    if self_config.dry_run {
+        warn!(
+            target: "dry-run",
+            "Dry run: would save the list of compiled benchmarks to {:?}",
+            cache_path
+        );
+    } else if let Err(e) = compile::save_cached(
+        &cache_path,
+        compiled_at,
+        self_config.debug_build,
+        cargo_args,
+        &benchmarks,
+    ) {
+        error!("Failed to save compile cache: {:?}", e);
+    }
+    Ok(benchmarks)
+}
+
+/// Configure and return a Report object that prints benchmark information to the command-line.
+fn configure_cli_output(self_config: &crate::config::SelfConfig) -> crate::report::CliReport {
+    let stderr_isatty = atty::is(atty::Stream::Stderr);
+    let mut enable_text_overwrite = stderr_isatty && !crate::debug_enabled();
+    let enable_text_coloring = match self_config.text_color {
+        TextColor::Auto => stderr_isatty,
+        TextColor::Never => {
+            enable_text_overwrite = false;
+            false
+        }
+        TextColor::Always => true,
+    };
+
+    let show_differences = match self_config.output_format {
+        OutputFormat::Criterion | OutputFormat::Verbose | OutputFormat::Pdf => true,
+        OutputFormat::Quiet | OutputFormat::Bencher | OutputFormat::StableText => false,
+    };
+    let verbose = match self_config.output_format {
+        OutputFormat::Verbose => true,
+        OutputFormat::Criterion
+        | OutputFormat::Quiet
+        | OutputFormat::Bencher
+        | OutputFormat::StableText
+        | OutputFormat::Pdf => crate::debug_enabled(),
+    };
+
+    crate::report::CliReport::new(
+        enable_text_overwrite,
+        enable_text_coloring,
+        show_differences,
+        verbose,
+        self_config.change_ratio_above,
+    )
+}
+
+/// Check that the gnuplot backend can be used, or explain why it can't.
+#[cfg(feature = "gnuplot_backend")]
+pub fn gnuplot_available() -> Result<(), Error> {
+    criterion_plot::version()
+        .map(|_| ())
+        .map_err(|_| anyhow::anyhow!("gnuplot was not found on PATH (install gnuplot to use it)"))
+}
+
+/// Gnuplot support was not compiled in, so the gnuplot backend is not available.
+#[cfg(not(feature = "gnuplot_backend"))]
+pub fn gnuplot_available() -> Result<(), Error> {
+    anyhow::bail!("the 'gnuplot_backend' feature is not enabled (reinstall cargo-criterion with `--features gnuplot_backend` to use gnuplot)")
+}
+
+/// Configure and return a Gnuplot plotting backend, if available.
+#[cfg(feature = "gnuplot_backend")]
+fn gnuplot_plotter(config: &SelfConfig) -> Result<Box<dyn Plotter>, Error> {
+    gnuplot_available()?;
+    let generator = crate::plot::PlotGenerator {
+        backend: crate::plot::Gnuplot::new(&config.colors, config.strict_plots),
+    };
+    Ok(Box::new(generator))
+}
+
+/// Gnuplot support was not compiled in, so this always fails with the reason.
+#[cfg(not(feature = "gnuplot_backend"))]
+fn gnuplot_plotter(_: &SelfConfig) -> Result<Box<dyn Plotter>, Error> {
+    gnuplot_available()?;
+    unreachable!()
+}
+
+/// Check that the plotters backend can be used, or explain why it can't.
+pub fn plotters_available() -> Result<(), Error> {
+    if cfg!(feature = "plotters_backend") {
+        Ok(())
+    } else {
+        anyhow::bail!("the 'plotters_backend' feature is not enabled (reinstall cargo-criterion with `--features plotters_backend` to use plotters)")
+    }
+}
+
+/// Configure and return a Plotters plotting backend.
+#[cfg(feature = "plotters_backend")]
+fn plotters_plotter(config: &SelfConfig) -> Result<Box<dyn Plotter>, Error> {
+    let generator = crate::plot::PlotGenerator {
+        backend: crate::plot::PlottersBackend::new(&config.colors),
+    };
+    Ok(Box::new(generator))
+}
+
+/// Plotters support was not compiled in, so this always fails with the reason.
+#[cfg(not(feature = "plotters_backend"))]
+fn plotters_plotter(_: &SelfConfig) -> Result<Box<dyn Plotter>, Error> {
+    plotters_available()?;
+    unreachable!()
+}
+
+/// Configure and return a plotting backend. If the chosen backend isn't usable (or, with the auto
+/// backend, neither is), explain what is missing and return None, so that the reports are
+/// generated without plots. With `--require-plots` this is an error instead.
+fn get_plotter(config: &SelfConfig) -> Result<Option<Box<dyn Plotter>>, Error> {
+    let plotter = match config.plotting_backend {
+        PlottingBackend::Gnuplot => gnuplot_plotter(config),
+        PlottingBackend::Plotters => plotters_plotter(config),
+        PlottingBackend::Auto => gnuplot_plotter(config).or_else(|gnuplot_error| {
+            plotters_plotter(config).map_err(|plotters_error| {
+                anyhow::anyhow!("{}, and {}", gnuplot_error, plotters_error)
+            })
+        }),
+        PlottingBackend::Disabled => return Ok(None),
+    };
+    match plotter {
+        Ok(plotter) => Ok(Some(plotter)),
+        Err(e) if config.require_plots => {
+            anyhow::bail!("No plotting backend is usable: {}.", e)
+        }
+        Err(e) => {
+            warn!(
+                "No plotting backend is usable: {}. The HTML report will be generated without \
+                 plots. Use --require-plots to make this an error.",
+                e
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Configure and return the PDF report.
+#[cfg(feature = "pdf_report")]
+fn pdf_report(config: &SelfConfig) -> Result<Box<dyn crate::report::Report>, Error> {
+    Ok(Box::new(crate::pdf::PdfReport::new(
+        config.sort_by,
+        config.change_ratio_above,
+        config.report_title.clone(),
+        config.report_description.clone(),
+    )))
+}
+
+/// PDF support was not compiled in, so PDF reports are not available.
+#[cfg(not(feature = "pdf_report"))]
+fn pdf_report(_: &SelfConfig) -> Result<Box<dyn crate::report::Report>, Error> {
+    pdf_available()?;
+    unreachable!()
+}
+
+/// Check that PDF reports can be written, or explain why they can't.
+pub fn pdf_available() -> Result<(), Error> {
+    if cfg!(feature = "pdf_report") {
+        Ok(())
+    } else {
+        anyhow::bail!("PDF reports are disabled. To use --output-format pdf, install cargo-criterion with the 'pdf_report' feature enabled")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_runner_only_runs_benchmarks() {
+        let error = Runner::new().cargo_args(["list-groups"]).run().unwrap_err();
+        assert_eq!(1, error.exit_code());
+        assert!(error
+            .to_string()
+            .starts_with("Runner can only run benchmarks"));
+    }
+
+    /// Run `execute_targets` over targets given as their names and how many benchmarks they have,
+    /// returning the targets executed, the progress messages and how many benchmarks were run.
+    fn execute_test_targets(
+        targets: &[(&str, usize)],
+        repeat: usize,
+        take: Option<usize>,
+    ) -> (Vec<String>, Vec<String>, usize) {
+        let data_directory = std::env::temp_dir().join("cargo-criterion-test-nonexistent");
+        let mut run_model = model::Model::load_group(data_directory, PathBuf::from("main"), "none");
+        let mut executed = Vec::new();
+        let mut messages = Vec::new();
+        execute_targets(
+            targets,
+            repeat,
+            take,
+            &mut run_model,
+            &mut |message| messages.push(message.to_owned()),
+            |(name, count), run_model, remaining| {
+                executed.push(name.to_string());
+                // Run benchmarks as a target does, stopping once --take's limit is reached.
+                for index in 0..*count {
+                    if *remaining == Some(0) {
+                        break;
+                    }
+                    let id = crate::report::BenchmarkId::new(
+                        name.to_string(),
+                        Some(index.to_string()),
+                        None,
+                        None,
+                    );
+                    run_model.record_benchmark(&id, true);
+                    if let Some(remaining) = remaining {
+                        *remaining -= 1;
+                    }
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+        (executed, messages, run_model.run_counts().run)
+    }
+
+    #[test]
+    fn test_take_limits_the_benchmarks_run() {
+        let targets = [("first", 2), ("second", 2), ("third", 1)];
+        let (executed, messages, run) = execute_test_targets(&targets, 1, Some(3));
+        assert_eq!(["first", "second"], executed.as_slice());
+        assert_eq!(
+            ["Ran the first 3 benchmarks, as limited by --take"],
+            messages.as_slice()
+        );
+        assert_eq!(3, run);
+
+        // With fewer benchmarks than --take allows, they're all run, and nothing was left out.
+        let (executed, messages, run) = execute_test_targets(&targets, 1, Some(10));
+        assert_eq!(["first", "second", "third"], executed.as_slice());
+        assert!(messages.is_empty(), "{:?}", messages);
+        assert_eq!(5, run);
+    }
+
+    #[test]
+    fn test_repeat_runs_every_target_each_time() {
+        let targets = [("first", 2), ("second", 1)];
+        let (executed, messages, run) = execute_test_targets(&targets, 3, None);
+        assert_eq!(
+            ["first", "second", "first", "second", "first", "second"],
+            executed.as_slice()
+        );
+        assert_eq!(
+            ["Run 1 of 3", "Run 2 of 3", "Run 3 of 3"],
+            messages.as_slice()
+        );
+        // Benchmarks run by each repeat are only counted once.
+        assert_eq!(3, run);
+
+        // --take limits each repeat, rather than the run as a whole.
+        let (executed, messages, run) = execute_test_targets(&targets, 2, Some(2));
+        assert_eq!(["first", "first"], executed.as_slice());
+        assert_eq!(
+            [
+                "Run 1 of 2",
+                "Ran the first 2 benchmarks, as limited by --take",
+                "Run 2 of 2",
+                "Ran the first 2 benchmarks, as limited by --take",
+            ],
+            messages.as_slice()
+        );
+        assert_eq!(2, run);
+    }
+
+    #[test]
+    fn test_ignore_regression_excludes_matching_benchmarks() {
+        let regressed = [
+            "parse/small".to_owned(),
+            "parse/large".to_owned(),
+            "flaky".to_owned(),
+        ];
+        let check = |fail_on_regression: bool, ignore_regression: &[&str]| {
+            let ignore_regression: Vec<String> =
+                ignore_regression.iter().map(|s| s.to_string()).collect();
+            let mut messages = Vec::new();
+            let result = check_regressions(
+                &regressed,
+                fail_on_regression,
+                &ignore_regression,
+                &mut |message| messages.push(message.to_owned()),
+            );
+            (result, messages)
+        };
+
+        // Without --fail-on-regression, regressions never fail the run.
+        let (result, messages) = check(false, &["flaky"]);
+        assert!(result.is_ok());
+        assert!(messages.is_empty());
+
+        let (result, messages) = check(true, &[]);
+        match result {
+            Err(RunError::RegressionDetected { ids }) => assert_eq!(regressed.to_vec(), ids),
+            other => panic!("{:?}", other),
+        }
+        assert!(messages.is_empty());
+
+        // The ignored regressions are still reported, but only the others fail the run.
+        let (result, messages) = check(true, &["flaky", "*/large"]);
+        match result {
+            Err(RunError::RegressionDetected { ids }) => {
+                assert_eq!(["parse/small"], ids.as_slice())
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(2, messages.len());
+        assert!(messages[0].contains("parse/large"), "{:?}", messages);
+        assert!(messages[1].contains("flaky"), "{:?}", messages);
+
+        let (result, messages) = check(true, &["parse/*", "flaky"]);
+        assert!(result.is_ok());
+        assert_eq!(3, messages.len());
+        assert!(check_regressions(&[], true, &[], &mut |_| panic!()).is_ok());
+    }
+
+    #[test]
+    fn test_runner_runs_a_package_without_benchmarks() {
+        let package = std::env::temp_dir().join(format!(
+            "cargo-criterion-test-{}-runner",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&package);
+        std::fs::create_dir_all(package.join("src")).unwrap();
+        std::fs::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"runner-test\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+             [lib]\nbench = false\n\n[workspace]\n",
+        )
+        .unwrap();
+        std::fs::write(package.join("src/lib.rs"), "").unwrap();
+
+        let messages = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sent_messages = std::rc::Rc::clone(&messages);
+        let summary = Runner::new()
+            .cargo_args([
+                OsStr::new("--manifest-path"),
+                package.join("Cargo.toml").as_os_str(),
+                OsStr::new("--plotting-backend"),
+                OsStr::new("disabled"),
+            ])
+            .criterion_home(package.join("criterion"))
+            .output_format(OutputFormat::Quiet)
+            .on_message(move |message| sent_messages.borrow_mut().push(message.to_owned()))
+            .run()
+            .unwrap();
+        assert_eq!(0, summary.benchmark_count);
+        // The progress messages are passed on rather than printed.
+        let messages = messages.borrow();
+        assert!(
+            (messages.iter()).any(|message| message.starts_with("Compiled the benchmarks in")),
+            "{:?}",
+            messages
+        );
+        assert!(summary.regressed.is_empty());
+        assert_eq!(package.join("criterion/reports"), summary.report_directory);
+
+        std::fs::remove_dir_all(&package).unwrap();
+    }
+}
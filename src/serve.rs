@@ -0,0 +1,228 @@
+//! A small, read-only HTTP server for browsing the generated reports from another machine.
+//!
+//! Each connection is handled on its own thread, and answers one request. Only `GET` and `HEAD`
+//! are supported, and only files below the served directory can be read.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// How long a client may take to send its request, or to receive each part of the response.
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// The most that is read of the request line and headers together. Browsers send far less.
+const MAX_REQUEST_HEAD: u64 = 16 * 1024;
+
+/// Serve the files in the given directory until the process is killed.
+pub fn serve(root: &Path, host: &str, port: u16) -> Result<()> {
+    if !root.is_dir() {
+        anyhow::bail!(
+            "Report directory {:?} does not exist. Run the benchmarks to generate the reports first.",
+            root
+        );
+    }
+    let listener = TcpListener::bind((host, port))
+        .with_context(|| format!("Unable to listen on {}:{}", host, port))?;
+    let address = listener.local_addr()?;
+    println!(
+        "Serving reports from {} at http://{}/ (press Ctrl-C to stop)",
+        root.display(),
+        address
+    );
+    accept_connections(listener, root);
+    Ok(())
+}
+
+/// Handle each connection to the listener on a new thread, so that a slow client doesn't hold up
+/// the others.
+fn accept_connections(listener: TcpListener, root: &Path) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let root = root.to_path_buf();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &root) {
+                warn!("Failed to handle request: {:#}", e);
+            }
+        });
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, root: &Path) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_HEAD);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers; none of them change the response.
+    let mut header = request_line.clone();
+    while header.ends_with('\n') && !header.trim_end().is_empty() {
+        header.clear();
+        reader.read_line(&mut header)?;
+    }
+    if !header.ends_with('\n') {
+        if reader.limit() == 0 {
+            return respond(
+                &mut stream,
+                "431 Request Header Fields Too Large",
+                "text/plain",
+                b"",
+            );
+        }
+        // The client closed the connection before finishing its request.
+        return Ok(());
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    debug!("{} {}", method, target);
+
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+    }
+    let path = match resolve_path(root, target) {
+        Some(path) => path,
+        None => return respond(&mut stream, "400 Bad Request", "text/plain", b""),
+    };
+    let path = if path.is_dir() {
+        path.join("index.html")
+    } else {
+        path
+    };
+
+    let mut contents = Vec::new();
+    if File::open(&path)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .is_err()
+    {
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not found");
+    }
+    if method == "HEAD" {
+        contents.clear();
+    }
+    respond(&mut stream, "200 OK", content_type(&path), &contents)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Map the target of a request to a path below the root. Returns None if the target is malformed
+/// or refers to something outside of the root.
+fn resolve_path(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#'].as_ref()).next()?;
+    if !path.starts_with('/') {
+        return None;
+    }
+    let path = percent_decode(path)?;
+
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(&path).components() {
+        match component {
+            Component::RootDir | Component::CurDir => {}
+            Component::Normal(name) => resolved.push(name),
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Decode the %XX escapes in the path of a URL.
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let high = (iter.next()? as char).to_digit(16)?;
+            let low = (iter.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_path() {
+        let root = Path::new("reports");
+        assert_eq!(Some(root.to_path_buf()), resolve_path(root, "/"));
+        assert_eq!(
+            Some(root.join("my group").join("index.html")),
+            resolve_path(root, "/my%20group/index.html?x=1")
+        );
+        assert_eq!(None, resolve_path(root, "/../data/benchmark.cbor"));
+        assert_eq!(None, resolve_path(root, "/%2e%2e/secret"));
+        assert_eq!(None, resolve_path(root, "/bad%zz"));
+        assert_eq!(None, resolve_path(root, "index.html"));
+    }
+
+    #[test]
+    fn test_slow_and_oversized_requests() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-criterion-test-{}-serve", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("index.html"), "<html></html>").unwrap();
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let server_root = root.clone();
+        std::thread::spawn(move || accept_connections(listener, &server_root));
+
+        let request = |request: &[u8]| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request).unwrap();
+            let mut status_line = String::new();
+            BufReader::new(stream).read_line(&mut status_line).unwrap();
+            status_line
+        };
+        // A client which connects but sends nothing doesn't keep the others waiting.
+        let _silent = TcpStream::connect(address).unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\n",
+            request(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        );
+
+        let mut oversized = b"GET / HTTP/1.1\r\nX-Padding: ".to_vec();
+        // Exactly as much as is read, so that none is left unread when the connection is closed.
+        oversized.resize(MAX_REQUEST_HEAD as usize, b'x');
+        assert_eq!(
+            "HTTP/1.1 431 Request Header Fields Too Large\r\n",
+            request(&oversized)
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -20,7 +20,7 @@ pub struct Data<'a, X, Y>(&'a [X], &'a [Y]);
 
 impl<'a, X, Y> Copy for Data<'a, X, Y> {}
 
-#[cfg_attr(feature = "cargo-clippy", allow(clippy::expl_impl_clone_on_copy))]
+#[allow(clippy::expl_impl_clone_on_copy)]
 impl<'a, X, Y> Clone for Data<'a, X, Y> {
     fn clone(&self) -> Data<'a, X, Y> {
         *self
@@ -97,12 +97,12 @@ where
 
     /// Returns a view into the `X` data
     pub fn x(&self) -> &'a Sample<X> {
-        Sample::new(&self.0)
+        Sample::new(self.0)
     }
 
     /// Returns a view into the `Y` data
     pub fn y(&self) -> &'a Sample<Y> {
-        Sample::new(&self.1)
+        Sample::new(self.1)
     }
 }
 
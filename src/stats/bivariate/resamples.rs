@@ -12,7 +12,7 @@ where
     stage: Option<(Vec<X>, Vec<Y>)>,
 }
 
-#[cfg_attr(feature = "cargo-clippy", allow(clippy::should_implement_trait))]
+#[allow(clippy::should_implement_trait)]
 impl<'a, X, Y> Resamples<'a, X, Y>
 where
     X: 'a + Float,
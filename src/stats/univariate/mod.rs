@@ -24,7 +24,7 @@ pub use self::sample::Sample;
 /// - Multithreaded
 /// - Time: `O(nresamples)`
 /// - Memory: `O(nresamples)`
-#[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_lossless))]
+#[allow(clippy::cast_lossless)]
 pub fn bootstrap<A, B, T, S>(
     a: &Sample<A>,
     b: &Sample<B>,
@@ -40,7 +40,7 @@ where
     T::Builder: Send,
 {
     let nresamples_sqrt = (nresamples as f64).sqrt().ceil() as usize;
-    let per_chunk = (nresamples + nresamples_sqrt - 1) / nresamples_sqrt;
+    let per_chunk = nresamples.div_ceil(nresamples_sqrt);
 
     (0..nresamples_sqrt)
         .into_par_iter()
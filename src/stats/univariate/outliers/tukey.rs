@@ -70,7 +70,7 @@ where
     /// Returns the number of data points per label
     ///
     /// - Time: `O(length)`
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::similar_names))]
+    #[allow(clippy::similar_names)]
     pub fn count(&self) -> (usize, usize, usize, usize, usize) {
         let (mut los, mut lom, mut noa, mut him, mut his) = (0, 0, 0, 0, 0);
 
@@ -129,7 +129,7 @@ where
 {
     type Output = Label;
 
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::similar_names))]
+    #[allow(clippy::similar_names)]
     fn index(&self, i: usize) -> &Label {
         static LOW_SEVERE: Label = LowSevere;
         static LOW_MILD: Label = LowMild;
@@ -154,7 +154,7 @@ where
     }
 }
 
-impl<'a, 'b, A> IntoIterator for &'b LabeledSample<'a, A>
+impl<'a, A> IntoIterator for &LabeledSample<'a, A>
 where
     A: Float,
 {
@@ -181,7 +181,7 @@ where
 {
     type Item = (A, Label);
 
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::similar_names))]
+    #[allow(clippy::similar_names)]
     fn next(&mut self) -> Option<(A, Label)> {
         self.iter.next().map(|&x| {
             let (lost, lomt, himt, hist) = self.fences;
@@ -225,42 +225,27 @@ pub enum Label {
 impl Label {
     /// Checks if the data point has an "unusually" high value
     pub fn is_high(&self) -> bool {
-        match *self {
-            HighMild | HighSevere => true,
-            _ => false,
-        }
+        matches!(*self, HighMild | HighSevere)
     }
 
     /// Checks if the data point is labeled as a "mild" outlier
     pub fn is_mild(&self) -> bool {
-        match *self {
-            HighMild | LowMild => true,
-            _ => false,
-        }
+        matches!(*self, HighMild | LowMild)
     }
 
     /// Checks if the data point has an "unusually" low value
     pub fn is_low(&self) -> bool {
-        match *self {
-            LowMild | LowSevere => true,
-            _ => false,
-        }
+        matches!(*self, LowMild | LowSevere)
     }
 
     /// Checks if the data point is labeled as an outlier
     pub fn is_outlier(&self) -> bool {
-        match *self {
-            NotAnOutlier => false,
-            _ => true,
-        }
+        !matches!(*self, NotAnOutlier)
     }
 
     /// Checks if the data point is labeled as a "severe" outlier
     pub fn is_severe(&self) -> bool {
-        match *self {
-            HighSevere | LowSevere => true,
-            _ => false,
-        }
+        matches!(*self, HighSevere | LowSevere)
     }
 }
 
@@ -1,11 +1,12 @@
 use crate::connection::{Connection, IncomingMessage, OutgoingMessage, Throughput};
+use crate::format;
 use std::cell::RefCell;
 
 pub struct ValueFormatter<'a> {
     connection: RefCell<&'a mut Connection>,
 }
 impl<'a> ValueFormatter<'a> {
-    pub fn new(conn: &mut Connection) -> ValueFormatter {
+    pub fn new(conn: &mut Connection) -> ValueFormatter<'_> {
         ValueFormatter {
             connection: RefCell::new(conn),
         }
@@ -13,6 +14,10 @@ impl<'a> ValueFormatter<'a> {
 }
 impl<'a> ValueFormatter<'a> {
     pub fn format_value(&self, value: f64) -> String {
+        // Criterion.rs always chooses a unit to suit the value, so fixed units are applied here.
+        if let Some((unit_ns, unit)) = format::fixed_time_unit() {
+            return format!("{} {}", format::short(value / unit_ns), unit);
+        }
         self.connection
             .borrow_mut()
             .send(&OutgoingMessage::FormatValue { value })
@@ -24,20 +29,24 @@ impl<'a> ValueFormatter<'a> {
     }
 
     pub fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        let (throughput, custom_unit) = to_criterion_throughput(throughput);
         self.connection
             .borrow_mut()
-            .send(&OutgoingMessage::FormatThroughput {
-                value,
-                throughput: throughput.clone(),
-            })
+            .send(&OutgoingMessage::FormatThroughput { value, throughput })
             .unwrap();
         match self.connection.borrow_mut().recv().unwrap().unwrap() {
-            IncomingMessage::FormattedValue { value } => value,
+            IncomingMessage::FormattedValue { value } => with_custom_unit(value, custom_unit),
             other => panic!("Unexpected message {:?}", other),
         }
     }
 
     pub fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> String {
+        if let Some((unit_ns, unit)) = format::fixed_time_unit() {
+            for value in values.iter_mut() {
+                *value /= unit_ns;
+            }
+            return unit.to_owned();
+        }
         self.connection
             .borrow_mut()
             .send(&OutgoingMessage::ScaleValues {
@@ -65,12 +74,13 @@ impl<'a> ValueFormatter<'a> {
         throughput: &Throughput,
         values: &mut [f64],
     ) -> String {
+        let (throughput, custom_unit) = to_criterion_throughput(throughput);
         self.connection
             .borrow_mut()
             .send(&OutgoingMessage::ScaleThroughputs {
                 typical_value,
                 values,
-                throughput: throughput.clone(),
+                throughput,
             })
             .unwrap();
         match self.connection.borrow_mut().recv().unwrap().unwrap() {
@@ -79,7 +89,7 @@ impl<'a> ValueFormatter<'a> {
                 unit,
             } => {
                 values.copy_from_slice(&scaled_values);
-                unit
+                with_custom_unit(unit, custom_unit)
             }
             other => panic!("Unexpected message {:?}", other),
         }
@@ -110,3 +120,21 @@ impl<'a> Drop for ValueFormatter<'a> {
             .send(&OutgoingMessage::Continue);
     }
 }
+
+/// Criterion.rs only knows how to format byte and element throughputs, so custom throughputs are
+/// sent to it as elements. Returns the throughput to send, and the custom unit if there is one.
+fn to_criterion_throughput(throughput: &Throughput) -> (Throughput, Option<&str>) {
+    match throughput {
+        Throughput::Custom { unit, count } => (Throughput::Elements(*count), Some(unit)),
+        other => (other.clone(), None),
+    }
+}
+
+/// Replace the element unit in a value formatted by Criterion.rs (eg. "Kelem/s") with the custom
+/// unit, if there is one.
+fn with_custom_unit(formatted: String, custom_unit: Option<&str>) -> String {
+    match custom_unit {
+        Some(unit) => formatted.replace("elem", unit),
+        None => formatted,
+    }
+}